@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use rusty_mahjong::game::rules::RedFives;
+use rusty_mahjong::hand::hand_arrangement::HandArrangement;
+use rusty_mahjong::hand::shanten::{shanten, waits};
+use rusty_mahjong::tile::tile_counts::TileCounts;
+use rusty_mahjong::tile::notation::parse_tiles;
+
+/// Tenpai hands with few and many possible arrangements
+const HANDS : [(&str, &str); 4] = [
+    ("pinfu", "34m45556678p345s"),
+    ("chiitoitsu", "1133m5577p99s112z"),
+    ("chuuren", "1112345678999m"),
+    ("mixed honors", "123m456p789s1112z"),
+];
+
+fn bench_consume(c : &mut Criterion) {
+    for (name, text) in HANDS.iter() {
+        let mut tiles = parse_tiles(text, RedFives::none()).unwrap();
+        tiles.sort();
+        c.bench_function(&format!("consume {}", name), |b| {
+            b.iter(|| HandArrangement::empty().consume(black_box(&tiles)))
+        });
+    }
+}
+
+fn bench_shanten(c : &mut Criterion) {
+    let hands : Vec<TileCounts> = HANDS.iter()
+        .map(|(_, text)| TileCounts::from_tiles(&parse_tiles(text, RedFives::none()).unwrap()))
+        .collect();
+    // Builds the lookup tables before measuring
+    shanten(&hands[0], 0);
+    c.bench_function("shanten", |b| {
+        b.iter(|| hands.iter().map(|counts| shanten(black_box(counts), 0)).sum::<i8>())
+    });
+    c.bench_function("waits", |b| {
+        b.iter(|| hands.iter().map(|counts| waits(black_box(counts), 0).len()).sum::<usize>())
+    });
+}
+
+criterion_group!(benches, bench_consume, bench_shanten);
+criterion_main!(benches);
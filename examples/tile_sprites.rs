@@ -0,0 +1,183 @@
+//! Draws the tile sprites bundled in `assets/tiles`, used by `rusty_mahjong::render`.
+//!
+//! Run with `cargo run --example tile_sprites --features render [output directory]`.
+
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, Rect, Transform};
+
+const WIDTH : u32 = 48;
+const HEIGHT : u32 = 64;
+
+const FACE : (u8, u8, u8) = (245, 240, 225);
+const BORDER : (u8, u8, u8) = (90, 90, 90);
+const BACK : (u8, u8, u8) = (230, 140, 40);
+const BLACK : (u8, u8, u8) = (30, 30, 30);
+const BLUE : (u8, u8, u8) = (31, 78, 156);
+const GREEN : (u8, u8, u8) = (29, 122, 58);
+const RED : (u8, u8, u8) = (208, 32, 46);
+
+/// 5x7 bitmap glyphs of the characters written on the tiles
+fn glyph(character : char) -> [&'static str; 7] {
+    match character {
+        '1' => ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."],
+        '2' => [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"],
+        '3' => ["####.", "....#", "....#", ".###.", "....#", "....#", "####."],
+        '4' => ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."],
+        '5' => ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."],
+        '6' => [".###.", "#....", "#....", "####.", "#...#", "#...#", ".###."],
+        '7' => ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."],
+        '8' => [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."],
+        '9' => [".###.", "#...#", "#...#", ".####", "....#", "....#", ".###."],
+        'E' => ["#####", "#....", "#....", "####.", "#....", "#....", "#####"],
+        'S' => [".####", "#....", "#....", ".###.", "....#", "....#", "####."],
+        'W' => ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "##.##", "#...#"],
+        'N' => ["#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#", "#...#"],
+        'M' => ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"],
+        'F' => ["#####", "#....", "#....", "####.", "#....", "#....", "#...."],
+        'C' => [".####", "#....", "#....", "#....", "#....", "#....", ".####"],
+        _ => panic!("no glyph for {}", character),
+    }
+}
+
+fn paint(color : (u8, u8, u8)) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(Color::from_rgba8(color.0, color.1, color.2, 255));
+    paint.anti_alias = true;
+    paint
+}
+
+fn rounded_rect(x : f32, y : f32, width : f32, height : f32, radius : f32) -> tiny_skia::Path {
+    let mut builder = PathBuilder::new();
+    builder.move_to(x + radius, y);
+    builder.line_to(x + width - radius, y);
+    builder.quad_to(x + width, y, x + width, y + radius);
+    builder.line_to(x + width, y + height - radius);
+    builder.quad_to(x + width, y + height, x + width - radius, y + height);
+    builder.line_to(x + radius, y + height);
+    builder.quad_to(x, y + height, x, y + height - radius);
+    builder.line_to(x, y + radius);
+    builder.quad_to(x, y, x + radius, y);
+    builder.close();
+    builder.finish().unwrap()
+}
+
+/// A blank tile, face up or face down
+fn blank(face : (u8, u8, u8)) -> Pixmap {
+    let mut pixmap = Pixmap::new(WIDTH, HEIGHT).unwrap();
+    let (width, height) = (WIDTH as f32, HEIGHT as f32);
+    pixmap.fill_path(&rounded_rect(0.0, 0.0, width, height, 6.0), &paint(BORDER), FillRule::Winding, Transform::identity(), None);
+    pixmap.fill_path(&rounded_rect(2.0, 2.0, width - 4.0, height - 4.0, 5.0), &paint(face), FillRule::Winding, Transform::identity(), None);
+    pixmap
+}
+
+/// Writes a character with square pixels of `scale`, centered on `(x, y)`
+fn write(pixmap : &mut Pixmap, character : char, x : f32, y : f32, scale : f32, color : (u8, u8, u8)) {
+    let (left, top) = (x - 2.5 * scale, y - 3.5 * scale);
+    for (row, line) in glyph(character).iter().enumerate() {
+        for (column, pixel) in line.chars().enumerate() {
+            if pixel == '#' {
+                let rect = Rect::from_xywh(left + column as f32 * scale, top + row as f32 * scale, scale, scale).unwrap();
+                pixmap.fill_rect(rect, &paint(color), Transform::identity(), None);
+            }
+        }
+    }
+}
+
+/// Position of the pips of a number tile, relative to the face of the tile
+fn layout(number : u8) -> Vec<(f32, f32)> {
+    let grid = |columns : &[f32], rows : &[f32]| rows.iter().flat_map(|y| columns.iter().map(move |x| (*x, *y))).collect::<Vec<_>>();
+    match number {
+        1 => vec![(0.5, 0.5)],
+        2 => vec![(0.5, 0.25), (0.5, 0.75)],
+        3 => vec![(0.25, 0.2), (0.5, 0.5), (0.75, 0.8)],
+        4 => grid(&[0.3, 0.7], &[0.28, 0.72]),
+        5 => {
+            let mut pips = grid(&[0.27, 0.73], &[0.22, 0.78]);
+            pips.push((0.5, 0.5));
+            pips
+        },
+        6 => grid(&[0.3, 0.7], &[0.2, 0.52, 0.84]),
+        7 => {
+            let mut pips = vec![(0.22, 0.14), (0.5, 0.26), (0.78, 0.38)];
+            pips.extend(grid(&[0.3, 0.7], &[0.62, 0.86]));
+            pips
+        },
+        8 => grid(&[0.3, 0.7], &[0.14, 0.38, 0.62, 0.86]),
+        _ => grid(&[0.2, 0.5, 0.8], &[0.18, 0.5, 0.82]),
+    }
+}
+
+/// Converts a position relative to the face of a tile into pixels
+fn on_face((x, y) : (f32, f32)) -> (f32, f32) {
+    (6.0 + x * (WIDTH as f32 - 12.0), 7.0 + y * (HEIGHT as f32 - 14.0))
+}
+
+fn pin(number : u8, red : bool) -> Pixmap {
+    let mut pixmap = blank(FACE);
+    let radius = match number { 1 => 13.0, 2..=5 => 7.0, _ => 5.5 };
+    for (index, position) in layout(number).into_iter().enumerate() {
+        let color = if red || number == 1 || (number == 5 && index == 4) {RED} else if index % 2 == 0 {BLUE} else {GREEN};
+        let (x, y) = on_face(position);
+        let circle = PathBuilder::from_circle(x, y, radius).unwrap();
+        pixmap.fill_path(&circle, &paint(color), FillRule::Winding, Transform::identity(), None);
+        let inner = PathBuilder::from_circle(x, y, radius * 0.45).unwrap();
+        pixmap.fill_path(&inner, &paint(FACE), FillRule::Winding, Transform::identity(), None);
+    }
+    pixmap
+}
+
+fn sou(number : u8, red : bool) -> Pixmap {
+    let mut pixmap = blank(FACE);
+    let (width, height) = match number { 1 => (10.0, 36.0), 2..=6 => (6.0, 16.0), _ => (5.0, 10.0) };
+    for (index, position) in layout(number).into_iter().enumerate() {
+        let color = if red || number == 1 || (number == 5 && index == 4) {RED} else {GREEN};
+        let (x, y) = on_face(position);
+        let stick = rounded_rect(x - width / 2.0, y - height / 2.0, width, height, width / 2.0);
+        pixmap.fill_path(&stick, &paint(color), FillRule::Winding, Transform::identity(), None);
+    }
+    pixmap
+}
+
+fn man(number : u8, red : bool) -> Pixmap {
+    let mut pixmap = blank(FACE);
+    let digit = char::from(b'0' + number);
+    write(&mut pixmap, digit, WIDTH as f32 / 2.0, 20.0, 4.0, if red {RED} else {BLACK});
+    write(&mut pixmap, 'M', WIDTH as f32 / 2.0, 47.0, 3.0, RED);
+    pixmap
+}
+
+fn honor(index : u8) -> Pixmap {
+    let mut pixmap = blank(FACE);
+    let (x, y) = (WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0);
+    match index {
+        1..=4 => write(&mut pixmap, ['E', 'S', 'W', 'N'][index as usize - 1], x, y, 6.0, BLACK),
+        5 => {
+            let frame = rounded_rect(9.0, 11.0, WIDTH as f32 - 18.0, HEIGHT as f32 - 22.0, 3.0);
+            let inner = rounded_rect(13.0, 15.0, WIDTH as f32 - 26.0, HEIGHT as f32 - 30.0, 2.0);
+            pixmap.fill_path(&frame, &paint(BLUE), FillRule::Winding, Transform::identity(), None);
+            pixmap.fill_path(&inner, &paint(FACE), FillRule::Winding, Transform::identity(), None);
+        },
+        6 => write(&mut pixmap, 'F', x, y, 6.0, GREEN),
+        _ => write(&mut pixmap, 'C', x, y, 6.0, RED),
+    }
+    pixmap
+}
+
+fn main() {
+    let directory = std::env::args().nth(1).unwrap_or_else(|| "assets/tiles".to_string());
+    std::fs::create_dir_all(&directory).unwrap();
+    let mut sprites = vec![("back".to_string(), blank(BACK))];
+    for number in 1..=9 {
+        sprites.push((format!("{}m", number), man(number, false)));
+        sprites.push((format!("{}p", number), pin(number, false)));
+        sprites.push((format!("{}s", number), sou(number, false)));
+    }
+    sprites.push(("0m".to_string(), man(5, true)));
+    sprites.push(("0p".to_string(), pin(5, true)));
+    sprites.push(("0s".to_string(), sou(5, true)));
+    for index in 1..=7 {
+        sprites.push((format!("{}z", index), honor(index)));
+    }
+    for (name, sprite) in sprites {
+        sprite.save_png(format!("{}/{}.png", directory, name)).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+//! Discord bot hosting riichi mahjong tables, see `rusty_mahjong::discord`.
+//! The token of the bot is read from the `DISCORD_TOKEN` environment variable.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serenity::all::{
+    async_trait, Client, Context, EventHandler, GatewayIntents, Ready,
+    Command as SlashCommand, CommandDataOption, CommandDataOptionValue, CommandOptionType,
+    CreateCommand, CreateCommandOption, CreateActionRow, CreateButton, CreateMessage,
+    CreateInteractionResponse, CreateInteractionResponseMessage, ChannelId, Interaction as DiscordInteraction, Http,
+};
+use serenity::model::application::ButtonStyle as DiscordButtonStyle;
+
+use rusty_mahjong::discord::{Bot, Gateway, Message, Interaction, Command, User, ButtonStyle};
+
+/// Collects what the bot sends, to be sent once the bot is unlocked
+#[derive(Default)]
+struct Outbox {
+    posts : Vec<(u64, Message)>,
+    response : Option<(Message, bool)>,
+}
+
+impl Gateway for Outbox {
+    fn post(&mut self, channel : u64, message : Message) {
+        self.posts.push((channel, message));
+    }
+
+    fn respond(&mut self, _interaction : u64, message : Message, ephemeral : bool) {
+        self.response = Some((message, ephemeral));
+    }
+}
+
+fn components(message : &Message) -> Vec<CreateActionRow> {
+    message.buttons.chunks(5).map(|row| CreateActionRow::Buttons(row.iter().map(|button| {
+        let style = match button.style {
+            ButtonStyle::Primary => DiscordButtonStyle::Primary,
+            ButtonStyle::Secondary => DiscordButtonStyle::Secondary,
+            ButtonStyle::Success => DiscordButtonStyle::Success,
+            ButtonStyle::Danger => DiscordButtonStyle::Danger,
+        };
+        CreateButton::new(&button.custom_id).label(&button.label).style(style)
+    }).collect())).collect()
+}
+
+/// Reads `/mahjong <subcommand> [rules]`
+fn parse_command(options : &[CommandDataOption]) -> Option<Command> {
+    let subcommand = options.first()?;
+    let rules = match &subcommand.value {
+        CommandDataOptionValue::SubCommand(options) => options.iter()
+            .find(|option| option.name == "rules")
+            .and_then(|option| option.value.as_str()),
+        _ => None,
+    };
+    Command::parse(&subcommand.name, rules)
+}
+
+fn slash_command() -> CreateCommand {
+    let subcommand = |name : &str, description : &str| CreateCommandOption::new(CommandOptionType::SubCommand, name, description);
+    let rules = CreateCommandOption::new(CommandOptionType::String, "rules", "Rules of the table")
+        .required(false)
+        .add_string_choice("Mahjong Soul", "mahjongsoul")
+        .add_string_choice("Tenhou", "tenhou")
+        .add_string_choice("Mahjong Soul sanma", "sanma")
+        .add_string_choice("WRC", "wrc")
+        .add_string_choice("EMA", "ema");
+    CreateCommand::new("mahjong")
+        .description("Play riichi mahjong")
+        .add_option(subcommand("create", "Open a table in this channel").add_sub_option(rules))
+        .add_option(subcommand("join", "Take a seat at the table"))
+        .add_option(subcommand("leave", "Leave the table before the game starts"))
+        .add_option(subcommand("addbot", "Fill a seat with a bot"))
+        .add_option(subcommand("start", "Start the game once every seat is taken"))
+        .add_option(subcommand("hand", "Show your hand"))
+        .add_option(subcommand("stop", "Close the table"))
+}
+
+/// Posts the messages of the bot in their channels
+async fn send_posts(http : &Http, posts : Vec<(u64, Message)>) {
+    for (channel, message) in posts {
+        let post = CreateMessage::new().content(&message.content).components(components(&message));
+        if let Err(error) = ChannelId::new(channel).send_message(http, post).await {
+            eprintln!("Could not post a message : {:?}", error);
+        }
+    }
+}
+
+struct Handler {
+    bot : Arc<Mutex<Bot>>,
+    /// Whether the deadlines of the tables are already checked every second
+    ticking : AtomicBool,
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx : Context, ready : Ready) {
+        match SlashCommand::create_global_command(&ctx.http, slash_command()).await {
+            Ok(_) => println!("{} is connected", ready.user.name),
+            Err(error) => eprintln!("Could not register the slash command : {:?}", error),
+        }
+        // `ready` is called again on reconnections
+        if !self.ticking.swap(true, Ordering::SeqCst) {
+            let bot = self.bot.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    let mut outbox = Outbox::default();
+                    bot.lock().unwrap().tick(&mut outbox);
+                    send_posts(&ctx.http, outbox.posts).await;
+                }
+            });
+        }
+    }
+
+    async fn interaction_create(&self, ctx : Context, interaction : DiscordInteraction) {
+        let converted = match &interaction {
+            DiscordInteraction::Command(command) if command.data.name == "mahjong" => {
+                parse_command(&command.data.options).map(|parsed| Interaction::Command{
+                    id : command.id.get(),
+                    channel : command.channel_id.get(),
+                    user : User{id : command.user.id.get(), name : command.user.name.clone()},
+                    command : parsed,
+                })
+            },
+            DiscordInteraction::Component(component) => Some(Interaction::Button{
+                id : component.id.get(),
+                channel : component.channel_id.get(),
+                user : User{id : component.user.id.get(), name : component.user.name.clone()},
+                custom_id : component.data.custom_id.clone(),
+            }),
+            _ => None,
+        };
+        let converted = match converted {
+            Some(converted) => converted,
+            None => return,
+        };
+
+        let mut outbox = Outbox::default();
+        self.bot.lock().unwrap().handle(converted, &mut outbox);
+
+        if let Some((message, ephemeral)) = outbox.response {
+            let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                .content(&message.content)
+                .ephemeral(ephemeral)
+                .components(components(&message)));
+            let result = match &interaction {
+                DiscordInteraction::Command(command) => command.create_response(&ctx.http, response).await,
+                DiscordInteraction::Component(component) => component.create_response(&ctx.http, response).await,
+                _ => Ok(()),
+            };
+            if let Err(error) = result {
+                eprintln!("Could not answer an interaction : {:?}", error);
+            }
+        }
+        send_posts(&ctx.http, outbox.posts).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let token = std::env::var("DISCORD_TOKEN").expect("the DISCORD_TOKEN environment variable should hold the token of the bot");
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as u64).unwrap_or_default();
+    let handler = Handler{bot : Arc::new(Mutex::new(Bot::new(seed))), ticking : AtomicBool::new(false)};
+    let mut client = Client::builder(token, GatewayIntents::GUILDS)
+        .event_handler(handler)
+        .await
+        .expect("the client should be created");
+    if let Err(error) = client.start().await {
+        eprintln!("The bot stopped : {:?}", error);
+    }
+}
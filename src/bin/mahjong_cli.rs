@@ -0,0 +1,433 @@
+//! Analyzes and scores hands written in the MPSZ notation.
+//!
+//! Usage :
+//! - `mahjong-cli shanten 123m456p789s1122z` : shanten toward each form of hand
+//! - `mahjong-cli waits 123m456p789s112z` : waits of a tenpai hand, or the tiles that improve it
+//! - `mahjong-cli analyze 123m456p789s1122z` : arrangements of the hand, and the discards of a hand of 14 tiles
+//! - `mahjong-cli score 123m456p789s1122z --tsumo --riichi --dora 3m --seat e --round s` : yaku, fu and points
+//!
+//! Called melds are given with `--meld 555z` (chii, pon or open kan) and closed kans with `--ankan 9999p`.
+//! When scoring, the last tile of the hand is the winning tile.
+
+use std::collections::BTreeSet;
+
+use rusty_mahjong::tile::Tile;
+use rusty_mahjong::tile::tile_value::TileValue;
+use rusty_mahjong::tile::notation::{parse_mpsz, tiles_to_mpsz, to_mpsz, TileAllocator, NotationError};
+use rusty_mahjong::hand::Hand;
+use rusty_mahjong::hand::group::Group;
+use rusty_mahjong::hand::meld::Meld;
+use rusty_mahjong::hand::hand_arrangement::HandArrangement;
+use rusty_mahjong::hand::shanten::{shanten, regular_shanten, chiitoitsu_shanten, kokushi_shanten, waits};
+use rusty_mahjong::game::wind::Wind;
+use rusty_mahjong::game::rules::{RuleSet, RedFives};
+use rusty_mahjong::scoring::yaku::WinContext;
+use rusty_mahjong::scoring::hand_value::evaluate;
+use rusty_mahjong::scoring::payment::{ron_payment, tsumo_payments};
+
+const USAGE : &str = "Usage : mahjong-cli <analyze|waits|shanten|score> <hand> [--meld TILES]... [--ankan TILES]...
+Options of score : [--tsumo] [--riichi] [--double-riichi] [--ippatsu] [--haitei] [--rinshan] [--chankan]
+                   [--dora INDICATORS] [--ura INDICATORS] [--seat e|s|w|n] [--round e|s|w|n] [--honba N] [--rules NAME]";
+
+/// What the command line asks for
+struct Options {
+    command : String,
+    hand : String,
+    melds : Vec<String>,
+    closed_kans : Vec<String>,
+    dora : String,
+    ura : String,
+    seat : Wind,
+    round : Wind,
+    honba : u8,
+    rules : RuleSet,
+    flags : Vec<String>,
+}
+
+fn fail(message : &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn parse_wind(text : &str) -> Result<Wind, String> {
+    match text {
+        "e" | "east" => Ok(Wind::East),
+        "s" | "south" => Ok(Wind::South),
+        "w" | "west" => Ok(Wind::West),
+        "n" | "north" => Ok(Wind::North),
+        _ => Err(format!("Unknown wind {}, use e, s, w or n", text)),
+    }
+}
+
+/// Reads the arguments of the command line, without the name of the program
+fn parse_options(args : impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or(USAGE)?;
+    let mut options = Options {
+        command,
+        hand : String::new(),
+        melds : Vec::new(),
+        closed_kans : Vec::new(),
+        dora : String::new(),
+        ura : String::new(),
+        seat : Wind::East,
+        round : Wind::East,
+        honba : 0,
+        rules : RuleSet::mahjong_soul(),
+        flags : Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--meld" => options.melds.push(value()?),
+            "--ankan" => options.closed_kans.push(value()?),
+            "--dora" => options.dora = value()?,
+            "--ura" => options.ura = value()?,
+            "--seat" => options.seat = parse_wind(&value()?)?,
+            "--round" => options.round = parse_wind(&value()?)?,
+            "--honba" => options.honba = value()?.parse().map_err(|_| "The honba should be a number")?,
+            "--rules" => {
+                let name = value()?;
+                options.rules = RuleSet::preset(&name).ok_or_else(|| {
+                    format!("Unknown rules {}, use one of {}", name, RuleSet::PRESETS.join(", "))
+                })?;
+            },
+            "--tsumo" | "--riichi" | "--double-riichi" | "--ippatsu" | "--haitei" | "--rinshan" | "--chankan" => {
+                options.flags.push(arg.clone());
+            },
+            _ if arg.starts_with("--") || !options.hand.is_empty() => return Err(USAGE.to_string()),
+            _ => options.hand = arg.clone(),
+        }
+    }
+    if options.hand.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    Ok(options)
+}
+
+/// The tiles of the command line, each tile getting its own id
+struct Tiles {
+    hidden : Vec<Tile>,
+    melds : Vec<Vec<Tile>>,
+    closed_kans : Vec<Vec<Tile>>,
+    dora : Vec<Tile>,
+    ura : Vec<Tile>,
+}
+
+fn parse_tiles(options : &Options) -> Result<Tiles, NotationError> {
+    let mut allocator = TileAllocator::new(options.rules.red_fives);
+    let mut allocate = |text : &str| -> Result<Vec<Tile>, NotationError> {
+        parse_mpsz(text)?.into_iter().map(|(value, red)| allocator.allocate(value, red)).collect()
+    };
+    Ok(Tiles {
+        hidden : allocate(&options.hand)?,
+        melds : options.melds.iter().map(|meld| allocate(meld)).collect::<Result<_, _>>()?,
+        closed_kans : options.closed_kans.iter().map(|kan| allocate(kan)).collect::<Result<_, _>>()?,
+        dora : allocate(&options.dora)?,
+        ura : allocate(&options.ura)?,
+    })
+}
+
+/// The tiles in the MPSZ notation, in the order of their values : the honors are written in the 1234567z order
+fn mpsz(tiles : &[Tile], red_fives : RedFives) -> String {
+    let mut tiles = tiles.to_vec();
+    tiles.sort_by_key(|tile| tile.value().index());
+    tiles_to_mpsz(&tiles, red_fives)
+}
+
+/// The group made by the tiles of a meld, if they make one
+fn group(tiles : &[Tile]) -> Option<Group> {
+    let mut tiles = tiles.to_vec();
+    tiles.sort();
+    let same = tiles.iter().all(|tile| tile.value() == tiles[0].value());
+    match tiles[..] {
+        [a, b, c] if same => Some(Group::Pon(a, b, c)),
+        [a, b, c] if a.followed_by(b) && b.followed_by(c) => Some(Group::Chii(a, b, c)),
+        [a, b, c, d] if same => Some(Group::Kan(a, b, c, d)),
+        _ => None,
+    }
+}
+
+fn build_hand(options : &Options, tiles : &Tiles) -> Result<Hand, String> {
+    let mut hand = Hand::new(tiles.hidden.clone());
+    for meld in &tiles.melds {
+        let group = group(meld).ok_or_else(|| format!("{} is not a chii, a pon or a kan", mpsz(meld, options.rules.red_fives)))?;
+        // Who discarded the called tile does not change the value of the hand
+        hand.add_meld(Meld::new(group, meld[0], options.seat.next_seat(options.rules.players.count())));
+    }
+    for kan in &tiles.closed_kans {
+        match group(kan) {
+            Some(kan @ Group::Kan(..)) => hand.add_closed_kan(kan),
+            _ => return Err(format!("{} is not a kan", mpsz(kan, options.rules.red_fives))),
+        }
+    }
+    let size = hand.hidden_tiles().len() + 3 * (hand.melds().len() + hand.closed_kans().len());
+    if size != 13 && size != 14 {
+        return Err(format!("A hand holds 13 or 14 tiles, besides the fourth tile of its kans, not {}", size));
+    }
+    Ok(hand)
+}
+
+fn called(hand : &Hand) -> usize {
+    hand.melds().len() + hand.closed_kans().len()
+}
+
+/// Copies of a tile value not in the hand nor among its kans and melds
+fn unseen(hand : &Hand, value : TileValue) -> usize {
+    4 - hand.tiles().iter().filter(|tile| tile.value() == value).count()
+}
+
+/// The tiles that lower the shanten of a hand of 3n + 1 tiles, and how many of them are left
+fn ukeire(hand : &Hand) -> (Vec<TileValue>, usize) {
+    let counts = hand.tile_counts();
+    let current = shanten(&counts, called(hand));
+    let tiles : Vec<TileValue> = TileValue::all()
+        .filter(|value| unseen(hand, *value) > 0)
+        .filter(|value| {
+            let mut counts = counts;
+            counts.add(*value);
+            shanten(&counts, called(hand)) < current
+        })
+        .collect();
+    let count = tiles.iter().map(|value| unseen(hand, *value)).sum();
+    (tiles, count)
+}
+
+fn describe_shanten(value : i8) -> String {
+    match value {
+        -1 => "complete".to_string(),
+        0 => "tenpai".to_string(),
+        _ => format!("{}-shanten", value),
+    }
+}
+
+fn shanten_lines(hand : &Hand) -> Vec<String> {
+    let counts = hand.tile_counts();
+    let mut lines = vec![
+        format!("Shanten : {}", describe_shanten(shanten(&counts, called(hand)))),
+        format!("  regular : {}", regular_shanten(&counts, called(hand))),
+    ];
+    if called(hand) == 0 {
+        lines.push(format!("  seven pairs : {}", chiitoitsu_shanten(&counts)));
+        lines.push(format!("  thirteen orphans : {}", kokushi_shanten(&counts)));
+    }
+    lines
+}
+
+/// The arrangements of a complete or tenpai hand
+fn arrangement_lines(hand : &Hand, red_fives : RedFives) -> Vec<String> {
+    let arrangements = HandArrangement::empty().consume(hand.hidden_tiles());
+    if arrangements.is_empty() {
+        return Vec::new();
+    }
+    let complete = shanten(&hand.tile_counts(), called(hand)) == -1;
+    let mut lines = BTreeSet::new();
+    for arrangement in &arrangements {
+        let mut parts : Vec<String> = arrangement.groups().iter()
+            .map(|group| mpsz(&group.tiles().into_iter().cloned().collect::<Vec<Tile>>(), red_fives))
+            .collect();
+        // The pair of a complete hand is found as a wait on its own tiles
+        match arrangement.wait() {
+            Some(wait) if complete => parts.push(mpsz(wait.tiles(), red_fives)),
+            Some(wait) => parts.push(format!("[{}] waiting on {} ({:?})", mpsz(wait.tiles(), red_fives), to_mpsz(wait.wait_symbols()), wait.kind())),
+            None => (),
+        }
+        lines.insert(format!("  {}", parts.join(" ")));
+    }
+    std::iter::once("Arrangements :".to_string()).chain(lines).collect()
+}
+
+fn waits_line(hand : &Hand) -> String {
+    let counts = hand.tile_counts();
+    if shanten(&counts, called(hand)) == 0 {
+        let waits = waits(&counts, called(hand));
+        let left : usize = waits.iter().map(|value| unseen(hand, *value)).sum();
+        format!("Waits : {} ({} tiles left)", to_mpsz(&waits), left)
+    } else {
+        let (tiles, count) = ukeire(hand);
+        format!("Improving tiles : {} ({} tiles left)", to_mpsz(&tiles), count)
+    }
+}
+
+fn analyze(hand : &Hand, rules : &RuleSet) -> Vec<String> {
+    let mut lines = vec![format!("Hand : {}", mpsz(hand.hidden_tiles(), rules.red_fives))];
+    lines.extend(shanten_lines(hand));
+    let complete = shanten(&hand.tile_counts(), called(hand)) == -1;
+    if complete {
+        lines.extend(arrangement_lines(hand, rules.red_fives));
+        return lines;
+    }
+    if hand.hidden_tiles().len() % 3 == 1 {
+        lines.push(waits_line(hand));
+        lines.extend(arrangement_lines(hand, rules.red_fives));
+        return lines;
+    }
+    // The discards from the best ones, by shanten then by number of improving tiles
+    let mut discards = Vec::new();
+    let mut seen = BTreeSet::new();
+    for tile in hand.hidden_tiles() {
+        if !seen.insert(tile.value()) {
+            continue;
+        }
+        let mut after = hand.clone();
+        after.remove_tile(*tile);
+        let (tiles, count) = ukeire(&after);
+        discards.push((shanten(&after.tile_counts(), called(hand)), std::cmp::Reverse(count), tile.value(), tiles));
+    }
+    discards.sort();
+    lines.push("Discards :".to_string());
+    for (shanten, std::cmp::Reverse(count), value, tiles) in discards {
+        let label = if shanten == 0 {"waits on"} else {"improved by"};
+        lines.push(format!("  {} : {}, {} {} ({} tiles)", value, describe_shanten(shanten), label, to_mpsz(&tiles), count));
+    }
+    lines
+}
+
+fn score(options : &Options, hand : &Hand, tiles : &Tiles) -> Result<Vec<String>, String> {
+    let rules = &options.rules;
+    let flag = |name : &str| options.flags.iter().any(|flag| flag == name);
+    let winning_tile = *tiles.hidden.last().ok_or("The hand has no winning tile")?;
+    let mut context = WinContext::new(winning_tile, flag("--tsumo"), options.seat, options.round);
+    context.riichi = flag("--riichi");
+    context.double_riichi = flag("--double-riichi");
+    context.ippatsu = flag("--ippatsu");
+    context.last_tile = flag("--haitei");
+    context.rinshan = flag("--rinshan");
+    context.chankan = flag("--chankan");
+
+    let value = match evaluate(hand, &context, &tiles.dora, &tiles.ura, rules) {
+        Some(value) => value,
+        None => return Ok(vec!["No win : the hand is not complete or has no yaku".to_string()]),
+    };
+    let mut lines = vec![format!("Hand : {} winning on {}", mpsz(hand.hidden_tiles(), rules.red_fives), mpsz(&[winning_tile], rules.red_fives))];
+    for (name, han) in value.yaku_list() {
+        lines.push(format!("  {} : {} han", name, han));
+    }
+    if value.is_yakuman() {
+        lines.push(format!("{} yakuman", value.han / 13));
+    } else {
+        lines.push(format!("{} han {} fu", value.han, value.fu));
+    }
+    let dealer = options.seat == Wind::East;
+    if context.tsumo {
+        let payments = tsumo_payments(value.base_points, options.seat, options.honba, rules);
+        let total : u32 = payments.iter().map(|(_, points)| points).sum();
+        let shares : BTreeSet<u32> = payments.iter().map(|(_, points)| *points).collect();
+        let shares : Vec<String> = shares.into_iter().rev().map(|points| points.to_string()).collect();
+        lines.push(format!("Tsumo : {} points ({})", total, shares.join(" / ")));
+    } else {
+        lines.push(format!("Ron : {} points", ron_payment(value.base_points, dealer, options.honba, rules)));
+    }
+    Ok(lines)
+}
+
+/// What the command line prints, or why it fails
+fn run(args : impl IntoIterator<Item = String>) -> Result<Vec<String>, String> {
+    let options = parse_options(args)?;
+    let tiles = parse_tiles(&options).map_err(|error| error.to_string())?;
+    let hand = build_hand(&options, &tiles)?;
+    match options.command.as_str() {
+        "analyze" => Ok(analyze(&hand, &options.rules)),
+        "shanten" => Ok(shanten_lines(&hand)),
+        "waits" => {
+            if hand.hidden_tiles().len() % 3 != 1 {
+                return Err("waits needs a hand of 13 tiles, besides its melds".to_string());
+            }
+            let mut lines = vec![waits_line(&hand)];
+            lines.extend(arrangement_lines(&hand, options.rules.red_fives));
+            Ok(lines)
+        },
+        "score" => score(&options, &hand, &tiles),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    match run(std::env::args().skip(1)) {
+        Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+        Err(error) => fail(&error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_mahjong::tile::notation::{parse_mpsz, to_mpsz, TileAllocator};
+    use rusty_mahjong::tile::Tile;
+    use rusty_mahjong::hand::Hand;
+    use rusty_mahjong::hand::group::Group;
+    use rusty_mahjong::game::wind::Wind;
+    use rusty_mahjong::game::rules::RedFives;
+
+    use super::{parse_options, group, ukeire, run, USAGE};
+
+    fn args(line : &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn tiles(mpsz : &str) -> Vec<Tile> {
+        let mut allocator = TileAllocator::new(RedFives::one_each());
+        parse_mpsz(mpsz).unwrap().into_iter().map(|(value, red)| allocator.allocate(value, red).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_options() {
+        let options = parse_options(args("score 123m456p789s1122z --tsumo --meld 555z --seat s --round w --honba 2 --rules tenhou")).unwrap();
+        assert_eq!("score", options.command);
+        assert_eq!("123m456p789s1122z", options.hand);
+        assert_eq!(vec!["555z".to_string()], options.melds);
+        assert_eq!((Wind::South, Wind::West, 2), (options.seat, options.round, options.honba));
+        assert_eq!(vec!["--tsumo".to_string()], options.flags);
+
+        assert_eq!(Err(USAGE.to_string()), parse_options(args("")).map(|_| ()));
+        assert_eq!(Err(USAGE.to_string()), parse_options(args("shanten")).map(|_| ()));
+        assert_eq!(Err(USAGE.to_string()), parse_options(args("shanten 1m 2m")).map(|_| ()));
+        assert_eq!(Err("--dora needs a value".to_string()), parse_options(args("score 1m --dora")).map(|_| ()));
+        assert_eq!(Err("Unknown wind x, use e, s, w or n".to_string()), parse_options(args("score 1m --seat x")).map(|_| ()));
+        assert_eq!(Err("The honba should be a number".to_string()), parse_options(args("score 1m --honba two")).map(|_| ()));
+        assert!(parse_options(args("score 1m --rules chess")).map(|_| ()).unwrap_err().starts_with("Unknown rules chess"));
+    }
+
+    #[test]
+    fn test_group() {
+        assert!(matches!(group(&tiles("555z")), Some(Group::Pon(..))));
+        assert!(matches!(group(&tiles("312m")), Some(Group::Chii(..))));
+        assert!(matches!(group(&tiles("9999p")), Some(Group::Kan(..))));
+        assert!(group(&tiles("135m")).is_none());
+        assert!(group(&tiles("123z")).is_none());
+        assert!(group(&tiles("55z")).is_none());
+    }
+
+    #[test]
+    fn test_ukeire() {
+        // 1-shanten : the open wait and the pairs improve the hand, the copies in the hand being left out of the count
+        let (values, count) = ukeire(&Hand::new(tiles("123m456p78s1122z")));
+        assert_eq!("69s12z", to_mpsz(&values));
+        assert_eq!(4 + 4 + 2 + 2, count);
+    }
+
+    #[test]
+    fn test_run() {
+        let lines = run(args("score 19m19p19s1234567z1m")).unwrap();
+        assert_eq!("Hand : 119m19p19s1234567z winning on 1m", lines[0]);
+        assert_eq!("Ron : 96000 points", lines[3]);
+
+        let lines = run(args("score 234m456p678s22z55z5z --tsumo --seat e --round e")).unwrap();
+        assert_eq!(vec!["Hand : 234m456p678s22555z winning on 5z", "  Menzen tsumo : 1 han", "  Yakuhai haku : 1 han", "2 han 30 fu", "Tsumo : 3000 points (1000)"], lines);
+        assert_eq!(vec!["No win : the hand is not complete or has no yaku"], run(args("score 234m456p678s22z99m9m")).unwrap());
+
+        let lines = run(args("waits 123m456p789s1122z")).unwrap();
+        assert_eq!("Waits : 12z (4 tiles left)", lines[0]);
+        assert_eq!("Arrangements :", lines[1]);
+        assert_eq!(4, lines.len());
+
+        let lines = run(args("analyze 123m456p789s11227z")).unwrap();
+        assert!(lines.contains(&"Discards :".to_string()));
+        assert!(lines.contains(&"  7z : tenpai, waits on 12z (4 tiles)".to_string()));
+        assert_eq!(vec!["Shanten : complete", "  regular : -1", "  seven pairs : 4", "  thirteen orphans : 8"], run(args("shanten 123m456p789s11222z")).unwrap());
+
+        assert_eq!(Err("waits needs a hand of 13 tiles, besides its melds".to_string()), run(args("waits 123m456p789s11227z")));
+        assert_eq!(Err("123z is not a chii, a pon or a kan".to_string()), run(args("analyze 123m456p789s1z --meld 123z")));
+        assert_eq!(Err("A hand holds 13 or 14 tiles, besides the fourth tile of its kans, not 3".to_string()), run(args("shanten 123m")));
+        assert_eq!(Err(USAGE.to_string()), run(args("solve 123m456p789s1122z")));
+    }
+}
@@ -0,0 +1,119 @@
+//! Plays riichi mahjong through the mjai protocol.
+//!
+//! Usage :
+//! - `mjai server [--port N] [--ais N] [--seed N] [--rules mahjongsoul|tenhou|sanma|wrc|ema]` waits for `--ais` AIs
+//!   to connect, fills the other seats with bots and plays a game.
+//! - `mjai bot [--connect HOST:PORT] [--name NAME] [--room ROOM] [--rules ...]` plays with the `RuleBot` on an mjai server,
+//!   through the standard input and output when no address is given.
+
+use std::net::TcpListener;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusty_mahjong::game::rules::RuleSet;
+use rusty_mahjong::game::table::Table;
+use rusty_mahjong::game::host::{TableHost, PlayerInterface};
+use rusty_mahjong::bot::RuleBot;
+use rusty_mahjong::mjai::connection::Connection;
+use rusty_mahjong::mjai::server::{MjaiPlayer, greet};
+use rusty_mahjong::mjai::client::run_client;
+use rusty_mahjong::mjai::agent::PlayerAgent;
+
+const USAGE : &str = "Usage : mjai server [--port N] [--ais N] [--seed N] [--rules RULES]\n        mjai bot [--connect HOST:PORT] [--name NAME] [--room ROOM] [--rules RULES]";
+
+fn fail(message : &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn number(value : &str) -> u64 {
+    value.parse().unwrap_or_else(|_| fail(&format!("{} is not a number", value)))
+}
+
+fn serve(port : u16, ais : usize, seed : u64, rules : RuleSet) {
+    let seats = rules.players.count() as usize;
+    if ais == 0 || ais > seats {
+        fail(&format!("The table has {} seats, {} AIs were asked for", seats, ais));
+    }
+    let listener = TcpListener::bind(("0.0.0.0", port)).unwrap_or_else(|error| fail(&format!("Cannot listen on port {} : {}", port, error)));
+    println!("Waiting for {} AIs on port {}", ais, port);
+    let mut connections = Vec::new();
+    let mut names = Vec::new();
+    while connections.len() < ais {
+        let mut connection = match Connection::accept(&listener) {
+            Ok(connection) => connection,
+            Err(error) => fail(&format!("Cannot accept a connection : {}", error)),
+        };
+        match greet(&mut connection) {
+            Ok(name) => {
+                println!("{} joined", name);
+                names.push(name);
+                connections.push(connection);
+            },
+            Err(error) => eprintln!("An AI could not join : {}", error),
+        }
+    }
+    names.extend((ais..seats).map(|seat| format!("Bot {}", seat + 1)));
+
+    let table = Table::new(seed, names.clone(), rules.clone()).unwrap_or_else(|error| fail(&format!("The game cannot start : {}", error)));
+    let mut players : Vec<Box<dyn PlayerInterface>> = connections.into_iter().enumerate()
+        .map(|(seat, connection)| Box::new(MjaiPlayer::new(connection, seat, rules.clone(), names.clone())) as Box<dyn PlayerInterface>)
+        .collect();
+    players.extend((ais..seats).map(|_| Box::new(RuleBot::new()) as Box<dyn PlayerInterface>));
+    let mut host = TableHost::new(table, players);
+    host.set_replacement(|_| Box::new(RuleBot::new()));
+    match host.run() {
+        Ok(record) => {
+            let scores : Vec<String> = record.final_scores().iter().zip(record.placement_points()).zip(names.iter())
+                .map(|((score, points), name)| format!("{} {} ({:+.1})", name, score, points as f64 / 1000.0))
+                .collect();
+            println!("Final scores : {}", scores.join(", "));
+        },
+        Err(error) => {
+            eprintln!("The game stopped : {}", error);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn play(address : Option<String>, name : &str, room : &str, rules : RuleSet) {
+    let mut connection = match address {
+        Some(address) => Connection::connect(address.as_str()).unwrap_or_else(|error| fail(&format!("Cannot connect to {} : {}", address, error))),
+        None => Connection::stdio(),
+    };
+    let mut agent = PlayerAgent::new(RuleBot::new(), rules);
+    if let Err(error) = run_client(&mut connection, name, room, &mut agent) {
+        eprintln!("The game stopped : {}", error);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mode = args.next().unwrap_or_else(|| fail(USAGE));
+    let mut rules = RuleSet::mahjong_soul();
+    let mut port = 11600;
+    let mut ais = 1;
+    let mut seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default();
+    let mut address = None;
+    let mut name = "rusty_mahjong".to_string();
+    let mut room = "default".to_string();
+    while let Some(arg) = args.next() {
+        match (mode.as_str(), arg.as_str(), args.next()) {
+            (_, "--rules", Some(preset)) => rules = RuleSet::preset(&preset).unwrap_or_else(|| {
+                fail(&format!("Unknown rules {}, use one of {}", preset, RuleSet::PRESETS.join(", ")))
+            }),
+            ("server", "--port", Some(value)) => port = value.parse().unwrap_or_else(|_| fail(&format!("{} is not a port between 0 and 65535", value))),
+            ("server", "--ais", Some(value)) => ais = number(&value) as usize,
+            ("server", "--seed", Some(value)) => seed = number(&value),
+            ("bot", "--connect", Some(value)) => address = Some(value),
+            ("bot", "--name", Some(value)) => name = value,
+            ("bot", "--room", Some(value)) => room = value,
+            _ => fail(USAGE),
+        }
+    }
+    match mode.as_str() {
+        "server" => serve(port, ais, seed, rules),
+        "bot" => play(address, &name, &room, rules),
+        _ => fail(USAGE),
+    }
+}
@@ -0,0 +1,81 @@
+//! Plays games between bots and reports their statistics.
+//!
+//! Usage : `simulate [--games N] [--seed N] [--rules mahjongsoul|tenhou|sanma|wrc|ema] [--threads N] [--bots rule,rule,rule,tsumogiri]`
+//!
+//! The bots are `rule`, the `RuleBot`, and `tsumogiri`, which discards what it draws and never wins, as a baseline.
+
+use std::time::{Duration, Instant};
+
+use rusty_mahjong::game::rules::RuleSet;
+use rusty_mahjong::game::host::{PlayerInterface, Observation};
+use rusty_mahjong::bot::RuleBot;
+use rusty_mahjong::bot::simulation::Simulation;
+
+fn fail(message : &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn number(value : &str) -> u64 {
+    value.parse().unwrap_or_else(|_| fail(&format!("{} is not a number", value)))
+}
+
+fn bot(name : &str) -> Box<dyn PlayerInterface> {
+    match name {
+        "rule" => Box::new(RuleBot::new()),
+        _ => Box::new(|observation : &Observation, _ : Duration| observation.default_action()),
+    }
+}
+
+fn main() {
+    let mut games = 100;
+    let mut seed = 0;
+    let mut rules = RuleSet::mahjong_soul();
+    let mut threads = None;
+    let mut bots = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--games", Some(value)) => games = number(&value) as usize,
+            ("--seed", Some(value)) => seed = number(&value),
+            ("--threads", Some(value)) => threads = Some(number(&value) as usize),
+            ("--rules", Some(name)) => rules = RuleSet::preset(&name).unwrap_or_else(|| {
+                fail(&format!("Unknown rules {}, use one of {}", name, RuleSet::PRESETS.join(", ")))
+            }),
+            ("--bots", Some(names)) => bots = Some(names.split(',').map(str::to_string).collect::<Vec<String>>()),
+            _ => fail(&format!(
+                "Usage : simulate [--games N] [--seed N] [--rules {}] [--threads N] [--bots rule,tsumogiri,...]",
+                RuleSet::PRESETS.join("|"),
+            )),
+        }
+    }
+
+    let seats = rules.players.count() as usize;
+    let bots = bots.unwrap_or_else(|| vec!["rule".to_string(); seats]);
+    if bots.len() != seats {
+        fail(&format!("The table has {} seats, {} bots were given", seats, bots.len()));
+    }
+    let mut simulation = Simulation::new(rules, seed, games);
+    if let Some(threads) = threads {
+        simulation.set_threads(threads);
+    }
+    for (seat, name) in bots.iter().enumerate() {
+        if name != "rule" && name != "tsumogiri" {
+            fail(&format!("Unknown bot {}, use rule or tsumogiri", name));
+        }
+        let kind = name.clone();
+        simulation.add_player(format!("{} {}", name, seat + 1), move || bot(&kind));
+    }
+
+    let start = Instant::now();
+    match simulation.run() {
+        Ok(statistics) => {
+            print!("{}", statistics);
+            println!("Played in {:.1}s", start.elapsed().as_secs_f64());
+        },
+        Err(error) => {
+            eprintln!("A game stopped : {}", error);
+            std::process::exit(1);
+        },
+    }
+}
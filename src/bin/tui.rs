@@ -0,0 +1,380 @@
+//! Plays a game of riichi mahjong in the terminal, against bots.
+//!
+//! Usage : `tui [--rules mahjongsoul|tenhou|sanma|wrc|ema] [--seed N]`
+//!
+//! When it is your turn, type the index of a tile to discard it, `r` and an index to declare riichi with
+//! this discard, or the letter of another action. An empty line discards the drawn tile, or passes on a call.
+//! `help` shows how dangerous each tile of the hand is against each opponent.
+
+use std::io::{BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusty_mahjong::tile::Tile;
+use rusty_mahjong::tile::notation::tiles_to_mpsz;
+use rusty_mahjong::game::wind::Wind;
+use rusty_mahjong::game::rules::RuleSet;
+use rusty_mahjong::game::event::Event;
+use rusty_mahjong::game::action::Action;
+use rusty_mahjong::game::clock::{SystemClock, TimeControl};
+use rusty_mahjong::game::table::{Table, Update};
+use rusty_mahjong::game::host::{TableHost, PlayerInterface, Observation};
+use rusty_mahjong::record::RoundResult;
+use rusty_mahjong::bot::RuleBot;
+use rusty_mahjong::bot::danger::{danger_maps, TileDanger, MAX_DANGER};
+
+/// Seat of the human player
+const HUMAN : usize = 0;
+
+/// Why a tile is safer against an opponent
+fn protection(danger : &TileDanger) -> &'static str {
+    if danger.genbutsu {
+        "genbutsu"
+    } else if danger.suji {
+        "suji"
+    } else if danger.kabe {
+        "kabe"
+    } else if danger.one_chance {
+        "one-chance"
+    } else {
+        ""
+    }
+}
+
+fn wind_name(wind : Wind) -> &'static str {
+    match wind {
+        Wind::East => "East",
+        Wind::South => "South",
+        Wind::West => "West",
+        Wind::North => "North",
+    }
+}
+
+/// The human player, reading their actions from the standard input
+struct Terminal {
+    names : Vec<String>,
+    rules : RuleSet,
+}
+
+impl Terminal {
+
+    fn tiles(&self, tiles : &[Tile]) -> String {
+        tiles_to_mpsz(tiles, self.rules.red_fives)
+    }
+
+    fn name(&self, seat : usize) -> &str {
+        &self.names[seat]
+    }
+
+    fn label(&self, action : &Action) -> String {
+        match action {
+            Action::Discard { tile, riichi : false } => format!("Discard {}", self.tiles(&[*tile])),
+            Action::Discard { tile, riichi : true } => format!("Riichi {}", self.tiles(&[*tile])),
+            Action::Chii { tiles } => format!("Chii with {}", self.tiles(tiles)),
+            Action::Pon { tiles } => format!("Pon with {}", self.tiles(tiles)),
+            Action::OpenKan { tiles } => format!("Kan with {}", self.tiles(tiles)),
+            Action::ClosedKan { tiles } => format!("Closed kan {}", self.tiles(tiles)),
+            Action::AddedKan { tile } => format!("Add {} to the pon", self.tiles(&[*tile])),
+            Action::Nuki { tile } => format!("Nuki {}", self.tiles(&[*tile])),
+            Action::Tsumo => "Tsumo".to_string(),
+            Action::Ron => "Ron".to_string(),
+            Action::NineTerminals => "Nine terminals (abortive draw)".to_string(),
+            Action::Pass => "Pass".to_string(),
+        }
+    }
+
+    fn describe(&self, update : &Update) -> Option<String> {
+        let text = match update {
+            Update::RoundStarted { round_wind, dealer, honba, riichi_sticks, .. } => format!(
+                "\n===== {} {} - {} honba, {} riichi sticks, {} deals =====",
+                wind_name(*round_wind), dealer + 1, honba, riichi_sticks, self.name(*dealer),
+            ),
+            Update::Event(event) => match *event {
+                Event::Draw { player, tile } if player == HUMAN => format!("You draw {}", self.tiles(&[tile])),
+                Event::Draw { .. } => return None,
+                Event::Discard { player, tile, riichi, tsumogiri } => format!(
+                    "{} discards {}{}{}",
+                    self.name(player), self.tiles(&[tile]), if tsumogiri {" (drawn tile)"} else {""}, if riichi {" and declares riichi!"} else {""},
+                ),
+                Event::Chii { player, called, tiles, .. } => format!("{} calls chii : {} with {}", self.name(player), self.tiles(&[called]), self.tiles(&tiles)),
+                Event::Pon { player, called, .. } => format!("{} calls pon on {}", self.name(player), self.tiles(&[called])),
+                Event::OpenKan { player, called, .. } => format!("{} calls kan on {}", self.name(player), self.tiles(&[called])),
+                Event::ClosedKan { player, tiles } => format!("{} declares a closed kan of {}", self.name(player), self.tiles(&tiles)),
+                Event::AddedKan { player, tile } => format!("{} adds {} to their pon", self.name(player), self.tiles(&[tile])),
+                Event::Nuki { player, .. } => format!("{} sets a north aside", self.name(player)),
+                Event::NewDora { indicator } => format!("New dora indicator : {}", self.tiles(&[indicator])),
+            },
+            Update::HiddenDraw { .. } | Update::RiichiAccepted { .. } => return None,
+            Update::RoundEnded { result, scores } => {
+                let mut lines = Vec::new();
+                match result {
+                    RoundResult::Win(wins) => for win in wins {
+                        let how = match win.from {
+                            Some(from) => format!("ron from {}", self.name(from)),
+                            None => "tsumo".to_string(),
+                        };
+                        lines.push(format!("*** {} wins by {} ***", self.name(win.winner), how));
+                        for (yaku, han) in win.yaku.iter() {
+                            lines.push(format!("    {:<24}{:>3} han", yaku, han));
+                        }
+                        lines.push(format!("    {} han {} fu : {} points", win.han, win.fu, win.deltas[win.winner]));
+                    },
+                    RoundResult::ExhaustiveDraw { deltas } | RoundResult::NagashiMangan { deltas } => {
+                        let kind = if matches!(result, RoundResult::ExhaustiveDraw { .. }) {"Exhaustive draw"} else {"Nagashi mangan"};
+                        let changes : Vec<String> = deltas.iter().enumerate().map(|(seat, delta)| format!("{} {:+}", self.name(seat), delta)).collect();
+                        lines.push(format!("*** {} *** {}", kind, changes.join(", ")));
+                    },
+                    RoundResult::AbortiveDraw(abort) => lines.push(format!("*** Abortive draw : {:?} ***", abort)),
+                }
+                lines.push(self.scores(scores));
+                lines.join("\n")
+            },
+            Update::GameEnded { scores } => format!("\n===== The game is over =====\n{}", self.scores(scores)),
+        };
+        Some(text)
+    }
+
+    fn scores(&self, scores : &[i32]) -> String {
+        let scores : Vec<String> = scores.iter().enumerate().map(|(seat, score)| format!("{} {}", self.name(seat), score)).collect();
+        format!("Scores : {}", scores.join(" | "))
+    }
+
+    /// Prints the table and returns the hand in display order, the drawn tile last
+    fn show(&self, observation : &Observation) -> Vec<Tile> {
+        let round = match &observation.round {
+            Some(round) => round,
+            None => return Vec::new(),
+        };
+        println!();
+        println!("Dora indicators : {}   Wall : {}   {}", self.tiles(&round.dora_indicators), round.remaining, self.scores(&observation.scores));
+        for (seat, player) in round.players.iter().enumerate() {
+            let pond : Vec<String> = player.pond.discards().iter()
+                .filter(|discard| !discard.called)
+                .map(|discard| format!("{}{}", self.tiles(&[discard.tile]), if discard.riichi {"*"} else {""}))
+                .collect();
+            let melds : Vec<String> = player.melds.iter()
+                .map(|meld| meld.tiles().into_iter().cloned().collect::<Vec<Tile>>())
+                .chain(player.closed_kans.iter().map(|kan| kan.tiles().into_iter().cloned().collect()))
+                .map(|tiles| self.tiles(&tiles))
+                .collect();
+            println!(
+                "  {:<6}{} pond : {:<60} melds : {}",
+                self.name(seat), if player.riichi {"(R)"} else {"   "}, pond.join(" "), melds.join(" "),
+            );
+        }
+        let mut hand = round.hand.hidden_tiles().clone();
+        if let Some(position) = round.drawn.and_then(|drawn| hand.iter().position(|tile| *tile == drawn)) {
+            let drawn = hand.remove(position);
+            hand.push(drawn);
+        }
+        let indices : Vec<String> = (0..hand.len()).map(|index| format!("{:>3}", index)).collect();
+        let tiles : Vec<String> = hand.iter().map(|tile| format!("{:>3}", self.tiles(&[*tile]))).collect();
+        println!("Your hand ({} seat) :", wind_name(round.seat_wind));
+        println!("  {}", indices.join(" "));
+        println!("  {}", tiles.join(" "));
+        hand
+    }
+
+    /// Prints the danger of each tile of the hand against each opponent
+    fn help(&self, observation : &Observation, hand : &[Tile]) {
+        let round = match &observation.round {
+            Some(round) => round,
+            None => return,
+        };
+        let maps = danger_maps(round, observation.seat);
+        let header : Vec<String> = maps.iter()
+            .map(|map| format!("{:<14}", format!("{}{}", self.name(map.opponent()), if map.riichi() {" (R)"} else {""})))
+            .collect();
+        println!("Danger of your tiles, from 0 (safe) to {} :", MAX_DANGER);
+        println!("        {}", header.join(""));
+        let mut shown = Vec::new();
+        for tile in hand {
+            if shown.contains(&tile.value()) {
+                continue;
+            }
+            shown.push(tile.value());
+            let cells : Vec<String> = maps.iter().map(|map| {
+                let danger = map.get(tile.value());
+                format!("{:<14}", format!("{:>2} {}", danger.score, protection(danger)))
+            }).collect();
+            println!("  {:<6}{}", self.tiles(&[*tile]), cells.join(""));
+        }
+    }
+
+    /// The legal action playing the same tiles as `action`, whichever of their copies the table holds
+    fn legal(&self, action : &Action, observation : &Observation) -> Option<Action> {
+        observation.legal_actions.iter().find(|legal| legal.is_equivalent(action, self.rules.red_fives)).cloned()
+    }
+
+    /// Reads an action : a tile index, `r` and a tile index, a letter, or nothing for the default action
+    fn parse(&self, line : &str, hand : &[Tile], others : &[Action], observation : &Observation) -> Result<Action, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return observation.default_action().ok_or_else(|| "nothing to do".to_string());
+        }
+        let discard = |text : &str, riichi : bool| -> Result<Action, String> {
+            let index : usize = text.trim().parse().map_err(|_| format!("{} is not a tile index", text))?;
+            let tile = *hand.get(index).ok_or_else(|| format!("there is no tile {}", index))?;
+            let action = Action::Discard{tile, riichi};
+            self.legal(&action, observation).ok_or_else(|| format!("{} is not allowed", self.label(&action)))
+        };
+        if let Some(index) = line.strip_prefix('r') {
+            return discard(index, true);
+        }
+        if line.chars().all(|character| character.is_ascii_digit()) {
+            return discard(line, false);
+        }
+        let letter = line.chars().next().unwrap_or_default();
+        let index = (letter as usize).wrapping_sub('a' as usize);
+        others.get(index).cloned().ok_or_else(|| format!("{} is not an action", line))
+    }
+}
+
+impl PlayerInterface for Terminal {
+    fn observe(&mut self, updates : &[Update]) {
+        for text in updates.iter().filter_map(|update| self.describe(update)) {
+            println!("{}", text);
+        }
+    }
+
+    fn act(&mut self, observation : &Observation, _timeout : Duration) -> Option<Action> {
+        let hand = self.show(observation);
+        let others : Vec<Action> = observation.legal_actions.iter()
+            .filter(|action| !matches!(action, Action::Discard { .. }))
+            .cloned()
+            .collect();
+        let riichi : Vec<String> = hand.iter().enumerate()
+            .filter(|(_, tile)| self.legal(&Action::Discard{tile : **tile, riichi : true}, observation).is_some())
+            .map(|(index, _)| format!("r{}", index))
+            .collect();
+        if observation.legal_actions.iter().any(|action| matches!(action, Action::Discard { riichi : false, .. })) {
+            println!("Type the index of the tile to discard (empty : the drawn tile, help : the danger of your tiles)");
+        }
+        if !riichi.is_empty() {
+            println!("Riichi : {}", riichi.join(" "));
+        }
+        for (index, action) in others.iter().enumerate() {
+            println!("  {}) {}", char::from(b'a' + index as u8), self.label(action));
+        }
+        let stdin = std::io::stdin();
+        loop {
+            print!("> ");
+            std::io::stdout().flush().ok()?;
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).ok()? == 0 {
+                // The input is closed, the host plays for us
+                return None;
+            }
+            if line.trim() == "help" {
+                self.help(observation, &hand);
+                continue;
+            }
+            match self.parse(&line, &hand, &others, observation) {
+                Ok(action) => return Some(action),
+                Err(error) => println!("{}", error),
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut rules = RuleSet::mahjong_soul();
+    let mut seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--rules", Some(name)) => rules = RuleSet::preset(&name).unwrap_or_else(|| {
+                eprintln!("Unknown rules {}, use one of {}", name, RuleSet::PRESETS.join(", "));
+                std::process::exit(2);
+            }),
+            ("--seed", Some(value)) => seed = value.parse().unwrap_or_else(|_| {
+                eprintln!("The seed should be a number");
+                std::process::exit(2);
+            }),
+            _ => {
+                eprintln!("Usage : tui [--rules {}] [--seed N]", RuleSet::PRESETS.join("|"));
+                std::process::exit(2);
+            },
+        }
+    }
+
+    let players = rules.players.count() as usize;
+    let names : Vec<String> = (0..players).map(|seat| if seat == HUMAN {"You".to_string()} else {format!("Bot {}", seat)}).collect();
+    println!("Seed {}", seed);
+    let table = match Table::new(seed, names.clone(), rules.clone()) {
+        Ok(table) => table,
+        Err(error) => {
+            eprintln!("The game cannot start : {}", error);
+            std::process::exit(1);
+        },
+    };
+    let mut interfaces : Vec<Box<dyn PlayerInterface>> = vec![Box::new(Terminal{names, rules})];
+    interfaces.extend((1..players).map(|_| Box::new(RuleBot::new()) as Box<dyn PlayerInterface>));
+    // A human playing alone is never in a hurry
+    let day = Duration::from_secs(24 * 3600);
+    let host = TableHost::with_clock(table, interfaces, Box::new(SystemClock::new()), TimeControl::new(day, Duration::from_secs(0)));
+    if let Err(error) = host.run() {
+        eprintln!("The game stopped : {}", error);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_mahjong::tile::notation::TileAllocator;
+    use rusty_mahjong::tile::suit::Suit;
+    use rusty_mahjong::tile::tile_value::TileValue;
+    use rusty_mahjong::game::rules::RuleSet;
+    use rusty_mahjong::game::event::Event;
+    use rusty_mahjong::game::action::Action;
+    use rusty_mahjong::game::table::{Table, Update};
+    use rusty_mahjong::game::host::Observation;
+    use rusty_mahjong::record::RoundResult;
+
+    use super::{Terminal, HUMAN};
+
+    #[test]
+    fn test_describe() {
+        let rules = RuleSet::tenhou();
+        let names : Vec<String> = vec!["You".to_string(), "Bot 1".to_string(), "Bot 2".to_string(), "Bot 3".to_string()];
+        let mut table = Table::new(1, names.clone(), rules.clone()).unwrap();
+        let terminal = Terminal{names, rules : rules.clone()};
+        let updates = table.take_updates();
+        assert_eq!(Some("\n===== East 1 - 0 honba, 0 riichi sticks, You deals ====="), terminal.describe(&updates[0]).as_deref());
+
+        let mut allocator = TileAllocator::new(rules.red_fives);
+        let tile = allocator.allocate(TileValue::new_suited(Suit::Man, 5), true).unwrap();
+        let event = |event : Event| terminal.describe(&Update::Event(event));
+        assert_eq!(Some("You draw 0m".to_string()), event(Event::Draw{player : HUMAN, tile}));
+        assert_eq!(None, event(Event::Draw{player : 1, tile}));
+        assert_eq!(None, terminal.describe(&Update::HiddenDraw{player : 1}));
+        assert_eq!(Some("Bot 2 discards 0m (drawn tile) and declares riichi!".to_string()), event(Event::Discard{player : 2, tile, tsumogiri : true, riichi : true}));
+        assert_eq!("Riichi 0m", terminal.label(&Action::Discard{tile, riichi : true}));
+
+        let draw = Update::RoundEnded{result : RoundResult::ExhaustiveDraw{deltas : vec![1500, -1500, 1500, -1500]}, scores : vec![26500, 23500, 26500, 23500]};
+        assert_eq!(
+            Some("*** Exhaustive draw *** You +1500, Bot 1 -1500, Bot 2 +1500, Bot 3 -1500\nScores : You 26500 | Bot 1 23500 | Bot 2 26500 | Bot 3 23500"),
+            terminal.describe(&draw).as_deref(),
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let rules = RuleSet::tenhou();
+        let names : Vec<String> = (0..4).map(|seat| format!("Player {}", seat)).collect();
+        let table = Table::new(1, names.clone(), rules.clone()).unwrap();
+        let terminal = Terminal{names, rules};
+        let observation = Observation::new(&table, HUMAN);
+        let hand = terminal.show(&observation);
+
+        // Every tile of the hand can be discarded by its index, as the legal discard of the same tile
+        for (index, tile) in hand.iter().enumerate() {
+            let action = terminal.parse(&index.to_string(), &hand, &[], &observation).unwrap();
+            assert!(observation.legal_actions.contains(&action));
+            assert!(matches!(action, Action::Discard { tile : discarded, riichi : false } if discarded.value() == tile.value()));
+        }
+        assert_eq!(Ok(observation.default_action().unwrap()), terminal.parse("", &hand, &[], &observation));
+        assert!(terminal.parse("14", &hand, &[], &observation).is_err());
+        assert!(terminal.parse("r0", &hand, &[], &observation).unwrap_err().ends_with("is not allowed"));
+        assert!(terminal.parse("a", &hand, &[], &observation).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! How likely a discard is to deal into the hand of an opponent, from what the player can see.
+//!
+//! A tile is **genbutsu** when the opponent discarded it, or when anyone discarded it after their riichi :
+//! they cannot win on it. Most winning hands wait on
+//! two sides (ryanmen), so a tile is **suji** when the opponent discarded the other side of every two-sided wait
+//! on it, like 1m and 7m for 4m, such waits being furiten. A two-sided wait is impossible when every copy of one
+//! of its tiles is visible (**kabe**, the wall), and unlikely when only one copy is left (**one-chance**).
+//! The remaining waits on a suji or kabe tile, like pairs and single waits, are less frequent.
+
+use crate::tile::tile_value::TileValue;
+use crate::tile::tile_counts::TileCounts;
+use crate::game::host::RoundView;
+
+/// Highest danger score, of a middle tile without any protection
+pub const MAX_DANGER : u8 = 12;
+
+/// What protects a tile against an opponent, and its resulting danger
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileDanger {
+    pub value : TileValue,
+    /// The opponent discarded this tile, or let it pass after their riichi
+    pub genbutsu : bool,
+    /// The opponent discarded the other side of every two-sided wait on this tile
+    pub suji : bool,
+    /// Every two-sided wait on this tile needs a tile whose four copies are visible, or is suji
+    pub kabe : bool,
+    /// Every two-sided wait on this tile needs a tile with at most one unseen copy
+    pub one_chance : bool,
+    /// Heuristic danger from 0 (safe) to `MAX_DANGER`
+    pub score : u8,
+}
+
+/// The danger of every tile value against one opponent
+#[derive(Clone, Debug)]
+pub struct DangerMap {
+    opponent : usize,
+    riichi : bool,
+    tiles : Vec<TileDanger>,
+}
+
+impl DangerMap {
+
+    // Constructors
+
+    /// The danger of each tile against the player at `opponent`, from what the owner of `round` sees
+    pub fn new(round : &RoundView, opponent : usize) -> DangerMap {
+        let visible = round.visible_counts();
+        let state = &round.players[opponent];
+        let tiles = TileValue::all()
+            .map(|value| tile_danger(value, &visible, &|value| state.pond.contains(value) || state.passed.contains(&value)))
+            .collect();
+        DangerMap{opponent, riichi : state.riichi, tiles}
+    }
+
+    // Immutable getters
+
+    pub fn opponent(&self) -> usize {
+        self.opponent
+    }
+
+    /// Whether the opponent is in riichi
+    pub fn riichi(&self) -> bool {
+        self.riichi
+    }
+
+    /// The danger of each tile value, indexed by `TileValue::index`
+    pub fn tiles(&self) -> &Vec<TileDanger> {
+        &self.tiles
+    }
+
+    pub fn get(&self, value : TileValue) -> &TileDanger {
+        &self.tiles[value.index() as usize]
+    }
+}
+
+/// The danger maps of the other players than `seat`
+pub fn danger_maps(round : &RoundView, seat : usize) -> Vec<DangerMap> {
+    (0..round.players.len()).filter(|player| *player != seat).map(|player| DangerMap::new(round, player)).collect()
+}
+
+/// The highest danger of a tile against the opponents in riichi, 0 when nobody is
+pub fn riichi_danger(maps : &[DangerMap], value : TileValue) -> u8 {
+    maps.iter().filter(|map| map.riichi()).map(|map| map.get(value).score).max().unwrap_or_default()
+}
+
+/// The two-sided waits on a number with the other tile they wait on, like 56 waiting on 7 and 23 waiting on 1 for 4
+fn ryanmen_shapes(value : TileValue) -> Vec<(TileValue, TileValue, Option<TileValue>)> {
+    let shift = |offset : i8| (0..offset.abs()).try_fold(value, |value, _| if offset > 0 {value.next()} else {value.prev()});
+    let number = match value {
+        TileValue::Suited(suited) => suited.value(),
+        TileValue::Honor(_) => return Vec::new(),
+    };
+    let mut shapes = Vec::new();
+    if number <= 6 {
+        if let (Some(first), Some(second)) = (shift(1), shift(2)) {
+            shapes.push((first, second, shift(3)));
+        }
+    }
+    if number >= 4 {
+        if let (Some(first), Some(second)) = (shift(-2), shift(-1)) {
+            shapes.push((first, second, shift(-3)));
+        }
+    }
+    shapes
+}
+
+fn tile_danger(value : TileValue, visible : &TileCounts, discarded : &dyn Fn(TileValue) -> bool) -> TileDanger {
+    let shapes = ryanmen_shapes(value);
+    let furiten = |(_, _, other) : &(TileValue, TileValue, Option<TileValue>)| other.is_some_and(discarded);
+    let blocked = |shape : &(TileValue, TileValue, Option<TileValue>), copies : u8| visible[shape.0] >= copies || visible[shape.1] >= copies;
+    let genbutsu = discarded(value);
+    let suji = !value.is_honor() && shapes.iter().all(furiten);
+    let kabe = !value.is_honor() && !suji && shapes.iter().all(|shape| furiten(shape) || blocked(shape, 4));
+    let one_chance = !value.is_honor() && !suji && !kabe && shapes.iter().all(|shape| furiten(shape) || blocked(shape, 3));
+
+    let number = match value {
+        TileValue::Suited(suited) => suited.value(),
+        TileValue::Honor(_) => 0,
+    };
+    let unseen = 4 - visible[value].min(4);
+    let score = if genbutsu || unseen == 0 {
+        0
+    } else if value.is_honor() {
+        // Only a pair or a single wait : the fewer copies left, the less likely
+        match unseen {
+            1 => 1,
+            2 => 3,
+            _ => 6,
+        }
+    } else if suji || kabe {
+        match number {
+            1 | 9 => 2,
+            2 | 8 => 3,
+            _ => 4,
+        }
+    } else if one_chance {
+        match number {
+            1 | 9 => 4,
+            2 | 8 => 5,
+            _ => 7,
+        }
+    } else {
+        match number {
+            1 | 9 => 6,
+            2 | 8 => 8,
+            3 | 7 => 10,
+            _ => MAX_DANGER,
+        }
+    };
+    TileDanger{value, genbutsu, suji, kabe, one_chance, score}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tile_danger, DangerMap};
+    use crate::tile::tile_value::TileValue;
+    use crate::tile::tile_counts::TileCounts;
+    use crate::tile::notation::{parse_mpsz, parse_tiles};
+    use crate::hand::Hand;
+    use crate::game::wind::Wind;
+    use crate::game::rules::RedFives;
+    use crate::game::pond::{Pond, Discard};
+    use crate::game::host::{RoundView, PublicState};
+
+    fn values(text : &str) -> Vec<TileValue> {
+        parse_mpsz(text).unwrap().into_iter().map(|(value, _)| value).collect()
+    }
+
+    fn danger(tile : &str, pond : &str, visible : &str) -> super::TileDanger {
+        let pond = values(pond);
+        let visible : TileCounts = values(visible).into_iter().chain(pond.iter().cloned()).collect();
+        tile_danger(values(tile)[0], &visible, &|value| pond.contains(&value))
+    }
+
+    #[test]
+    fn test_genbutsu_and_suji() {
+        assert!(danger("5p", "5p", "").genbutsu);
+        assert_eq!(0, danger("5p", "5p", "").score);
+        // 4m is only suji when both 1m and 7m were discarded
+        assert!(!danger("4m", "1m", "").suji);
+        assert!(danger("4m", "17m", "").suji);
+        assert!(danger("1m", "4m", "").suji);
+        assert!(danger("9s", "6s", "").suji);
+        assert!(danger("4m", "17m", "").score < danger("4m", "1m", "").score);
+        assert!(danger("1m", "4m", "").score < danger("1m", "", "").score);
+    }
+
+    #[test]
+    fn test_kabe() {
+        // With every 8p visible, nobody waits on 9p with 78p
+        let kabe = danger("9p", "", "8888p");
+        assert!(kabe.kabe && !kabe.suji);
+        // 6p can still be waited on with 45p, unless 3p was discarded
+        assert!(!danger("6p", "", "8888p").kabe);
+        assert!(danger("6p", "3p", "8888p").kabe);
+        let one_chance = danger("9p", "", "888p");
+        assert!(one_chance.one_chance && !one_chance.kabe);
+        assert!(kabe.score < one_chance.score && one_chance.score < danger("9p", "", "").score);
+    }
+
+    #[test]
+    fn test_honors() {
+        assert_eq!(1, danger("1z", "", "111z").score);
+        assert_eq!(6, danger("5z", "", "").score);
+        assert_eq!(0, danger("5z", "", "5555z").score);
+        assert!(!danger("5z", "", "5555z").kabe);
+    }
+
+    #[test]
+    fn test_passed_after_riichi() {
+        let public = |pond : &str, riichi : bool, passed : &str| {
+            let mut discards = Pond::new();
+            for tile in parse_tiles(pond, RedFives::none()).unwrap() {
+                discards.push(Discard{tile, tsumogiri : false, riichi : false, called : false});
+            }
+            PublicState{pond : discards, melds : Vec::new(), closed_kans : Vec::new(), nuki : Vec::new(), riichi, passed : values(passed)}
+        };
+        // South declared riichi, then west discarded 5p, which they let pass
+        let round = RoundView {
+            round_wind : Wind::East,
+            seat_wind : Wind::East,
+            dealer : 0,
+            honba : 0,
+            riichi_sticks : 1,
+            remaining : 50,
+            dora_indicators : parse_tiles("1z", RedFives::none()).unwrap(),
+            hand : Hand::new(parse_tiles("123456789m5p1122z", RedFives::none()).unwrap()),
+            drawn : None,
+            players : vec![public("9m", false, ""), public("19s", true, "5p"), public("5p", false, ""), public("", false, "")],
+        };
+        let map = DangerMap::new(&round, 1);
+        let five = values("5p")[0];
+        assert!(map.get(five).genbutsu);
+        assert_eq!(0, map.get(five).score);
+        // The tiles the others discarded are only safe against a player in riichi who let them pass
+        assert!(!DangerMap::new(&round, 3).get(five).genbutsu);
+        assert!(map.get(values("5m")[0]).score > 0);
+    }
+}
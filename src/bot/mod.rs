@@ -0,0 +1,235 @@
+//! Bots taking the seats that no human takes, to fill a table or to test the engine at scale.
+
+pub mod danger;
+pub mod simulation;
+
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use crate::tile::tile_value::TileValue;
+use crate::tile::tile_counts::TileCounts;
+use crate::tile::honor::HonorTile;
+use crate::hand::shanten::{shanten, kokushi_shanten};
+use crate::game::action::Action;
+use crate::game::host::{PlayerInterface, Observation, RoundView};
+use crate::bot::danger::{DangerMap, danger_maps, riichi_danger};
+
+/// A baseline player following a few rules :
+/// - wins whenever possible
+/// - discards the tile leaving the lowest shanten and the most tiles to improve the hand (ukeire)
+/// - calls the pons of yakuhai, and nothing else
+/// - declares riichi when tenpai with at least `RIICHI_WAITS` tiles left to win on
+/// - folds when another player is in riichi and its hand is not tenpai, discarding the safest tiles (see `danger`)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RuleBot;
+
+/// Number of unseen winning tiles for a wait to be worth a riichi
+pub const RIICHI_WAITS : usize = 4;
+
+/// A discard the bot considers, with what it leaves in the hand
+struct Candidate {
+    value : TileValue,
+    shanten : i8,
+    ukeire : usize,
+    danger : u8,
+}
+
+impl RuleBot {
+
+    pub fn new() -> RuleBot {
+        RuleBot
+    }
+
+    /// The action of the bot, one of the legal actions of the observation
+    pub fn choose(&self, observation : &Observation) -> Option<Action> {
+        let actions = &observation.legal_actions;
+        let round = match &observation.round {
+            Some(round) => round,
+            None => return observation.default_action(),
+        };
+        let find = |matches : &dyn Fn(&Action) -> bool| actions.iter().find(|action| matches(action)).cloned();
+        if let Some(win) = find(&|action| matches!(action, Action::Tsumo | Action::Ron)) {
+            return Some(win);
+        }
+        if let Some(nuki) = find(&|action| matches!(action, Action::Nuki { .. })) {
+            return Some(nuki);
+        }
+        let counts = round.hand.tile_counts();
+        if kokushi_shanten(&counts) > 3 {
+            if let Some(abort) = find(&|action| *action == Action::NineTerminals) {
+                return Some(abort);
+            }
+        }
+
+        let melds = round.hand.melds().len() + round.hand.closed_kans().len();
+        let maps = danger_maps(round, observation.seat);
+        let threatened = maps.iter().any(DangerMap::riichi);
+        if actions.contains(&Action::Pass) {
+            // Calling on a discard : the hand holds 3n + 1 tiles
+            let folding = threatened && shanten(&counts, melds) > 0;
+            let pon = find(&|action| matches!(action, Action::Pon { tiles } if is_yakuhai(tiles[0].value(), round)));
+            return match pon {
+                Some(pon) if !folding && shanten(&counts, melds) > 0 => Some(pon),
+                _ => Some(Action::Pass),
+            };
+        }
+
+        let visible = round.visible_counts();
+        let candidates : Vec<Candidate> = actions.iter()
+            .filter_map(|action| match action {
+                Action::Discard { tile, riichi : false } => Some(tile.value()),
+                _ => None,
+            })
+            .map(|value| {
+                let mut after = counts;
+                after.remove(value);
+                let (shanten, ukeire) = ukeire(&after, melds, &visible);
+                Candidate{value, shanten, ukeire, danger : riichi_danger(&maps, value)}
+            })
+            .collect();
+        let best_shanten = candidates.iter().map(|candidate| candidate.shanten).min()?;
+        let folding = threatened && best_shanten > 0;
+        let best = if folding {
+            candidates.iter().min_by_key(|candidate| (candidate.danger, candidate.shanten, Reverse(candidate.ukeire)))
+        } else {
+            candidates.iter().min_by_key(|candidate| (candidate.shanten, Reverse(candidate.ukeire), !candidate.value.is_yaochuu()))
+        }?;
+
+        let riichi = find(&|action| matches!(action, Action::Discard { tile, riichi : true } if tile.value() == best.value));
+        match riichi {
+            Some(riichi) if best.shanten == 0 && best.ukeire >= RIICHI_WAITS => Some(riichi),
+            _ => find(&|action| matches!(action, Action::Discard { tile, riichi : false } if tile.value() == best.value)),
+        }
+    }
+}
+
+impl PlayerInterface for RuleBot {
+    fn act(&mut self, observation : &Observation, _timeout : Duration) -> Option<Action> {
+        self.choose(observation)
+    }
+}
+
+/// Whether a pon of this value is a yaku : a dragon, the seat wind or the round wind
+fn is_yakuhai(value : TileValue, round : &RoundView) -> bool {
+    match value {
+        TileValue::Honor(HonorTile::Dragon(_)) => true,
+        TileValue::Honor(HonorTile::Wind(wind)) => wind == round.seat_wind || wind == round.round_wind,
+        TileValue::Suited(_) => false,
+    }
+}
+
+/// Shanten of a hand of 3n + 1 tiles, and the number of unseen tiles that would lower it
+fn ukeire(counts : &TileCounts, melds : usize, visible : &TileCounts) -> (i8, usize) {
+    let current = shanten(counts, melds);
+    let ukeire = TileValue::all()
+        .filter(|value| visible[*value] < 4 && {
+            let mut counts = *counts;
+            counts.add(*value);
+            shanten(&counts, melds) < current
+        })
+        .map(|value| 4 - visible[value] as usize)
+        .sum();
+    (current, ukeire)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RuleBot;
+    use crate::tile::Tile;
+    use crate::tile::notation::parse_tiles;
+    use crate::hand::Hand;
+    use crate::game::wind::Wind;
+    use crate::game::pond::{Pond, Discard};
+    use crate::game::rules::{RuleSet, RedFives};
+    use crate::game::action::Action;
+    use crate::game::table::Table;
+    use crate::game::host::{TableHost, PlayerInterface, Observation, RoundView, PublicState};
+
+    fn tiles(text : &str) -> Vec<Tile> {
+        parse_tiles(text, RedFives::none()).unwrap()
+    }
+
+    fn public(pond : &str, riichi : bool) -> PublicState {
+        let mut discards = Pond::new();
+        for tile in tiles(pond) {
+            discards.push(Discard{tile, tsumogiri : false, riichi : false, called : false});
+        }
+        PublicState{pond : discards, melds : Vec::new(), closed_kans : Vec::new(), nuki : Vec::new(), riichi, passed : Vec::new()}
+    }
+
+    /// The observation of the east player holding `hand`, the south player having discarded `riichi_pond`
+    /// and declared riichi if it is given, with a discard of each tile as legal actions besides `actions`
+    fn observation(hand : &str, riichi_pond : Option<&str>, actions : Vec<Action>) -> Observation {
+        let hand = Hand::new(tiles(hand));
+        let mut legal_actions = actions;
+        if hand.hidden_tiles().len() % 3 == 2 {
+            legal_actions.extend(hand.hidden_tiles().iter().map(|tile| Action::Discard{tile : *tile, riichi : false}));
+        }
+        let round = RoundView {
+            round_wind : Wind::East,
+            seat_wind : Wind::East,
+            dealer : 0,
+            honba : 0,
+            riichi_sticks : 0,
+            remaining : 50,
+            dora_indicators : Vec::new(),
+            hand,
+            drawn : None,
+            players : vec![public("", false), public(riichi_pond.unwrap_or(""), riichi_pond.is_some()), public("", false), public("", false)],
+        };
+        Observation{seat : 0, scores : vec![25000; 4], round : Some(round), legal_actions}
+    }
+
+    fn discarded(action : Option<Action>) -> String {
+        match action {
+            Some(Action::Discard { tile, .. }) => format!("{}", tile.value()),
+            other => panic!("expected a discard, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_discard() {
+        // The isolated north wind is the only tile that does not help
+        let bot = RuleBot::new();
+        assert_eq!("4z", discarded(bot.choose(&observation("123456m456p78s114z", None, Vec::new()))));
+        // Keeping both 35m and 78s as shapes waiting for a pair beats keeping the lone 9p
+        assert_eq!("9p", discarded(bot.choose(&observation("35m1234569p78s111z", None, Vec::new()))));
+    }
+
+    #[test]
+    fn test_riichi() {
+        let hand = tiles("123456m456p78s114z");
+        let north = *hand.last().unwrap();
+        let riichi = Action::Discard{tile : north, riichi : true};
+        assert_eq!(Some(riichi), RuleBot::new().choose(&observation("123456m456p78s114z", None, vec![riichi])));
+        assert_eq!(Some(Action::Tsumo), RuleBot::new().choose(&observation("123456m456p78s114z", None, vec![Action::Tsumo, riichi])));
+    }
+
+    #[test]
+    fn test_fold() {
+        // Far from tenpai, the bot drops its tiles safe against the riichi : its discards first, then their suji
+        let bot = RuleBot::new();
+        assert_eq!("9s", discarded(bot.choose(&observation("1479m258p369s1235z", Some("9s"), Vec::new()))));
+        assert_eq!("1m", discarded(bot.choose(&observation("139m258p369s12345z", Some("4m"), Vec::new()))));
+        // Tenpai, it pushes
+        assert_eq!("4z", discarded(bot.choose(&observation("123456m456p78s114z", Some("9s"), Vec::new()))));
+    }
+
+    #[test]
+    fn test_calls() {
+        let hand = tiles("139m258p369s5577z");
+        let pon = |first : usize| Action::Pon{tiles : [hand[first], hand[first + 1]]};
+        let bot = RuleBot::new();
+        assert_eq!(Some(pon(9)), bot.choose(&observation("139m258p369s5577z", None, vec![pon(9), Action::Pass])));
+        assert_eq!(Some(Action::Pass), bot.choose(&observation("139m258p369s5577z", Some("1z"), vec![pon(9), Action::Pass])));
+        assert_eq!(Some(Action::Ron), bot.choose(&observation("139m258p369s5577z", None, vec![Action::Ron, Action::Pass])));
+    }
+
+    #[test]
+    fn test_game() {
+        let players = (0..4).map(|_| Box::new(RuleBot::new()) as Box<dyn PlayerInterface>).collect();
+        let table = Table::new(11, (0..4).map(|seat| format!("Bot {}", seat)).collect(), RuleSet::tenhou()).unwrap();
+        let record = TableHost::new(table, players).run().unwrap();
+        assert!(record.verify().is_ok());
+    }
+}
@@ -0,0 +1,351 @@
+//! Plays many games between bots without any front-end, spread over several threads, and gathers statistics
+//! to compare their strategies. Each game is determined by its seed, so a simulation gives the same
+//! statistics with any number of threads.
+
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::game::rules::RuleSet;
+use crate::game::rng::Rng;
+use crate::game::event::Event;
+use crate::game::table::{Table, TableError};
+use crate::game::host::{TableHost, PlayerInterface};
+use crate::game::clock::{SystemClock, TimeControl};
+use crate::record::RoundResult;
+use crate::record::game::GameRecord;
+
+/// Builds a bot for each game it plays
+pub type BotFactory = Box<dyn Fn() -> Box<dyn PlayerInterface> + Send + Sync>;
+
+/// What a player of a simulation did, over every game
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlayerStats {
+    pub name : String,
+    pub rounds : usize,
+    pub wins : usize,
+    /// Rounds where the player dealt into a ron
+    pub deal_ins : usize,
+    /// Points gained by the wins, honba and riichi sticks included
+    pub win_points : i64,
+    /// Rounds where the player declared riichi
+    pub riichi : usize,
+    /// Number of games finished at each rank, from the first
+    pub placements : Vec<usize>,
+    /// Sum of the results of the games, with the uma and the oka of the rules
+    pub points : i64,
+}
+
+impl PlayerStats {
+
+    fn rate(count : usize, total : usize) -> f64 {
+        if total == 0 {0.0} else {count as f64 / total as f64}
+    }
+
+    /// Part of the rounds won
+    pub fn win_rate(&self) -> f64 {
+        PlayerStats::rate(self.wins, self.rounds)
+    }
+
+    /// Part of the rounds where the player dealt into a ron
+    pub fn deal_in_rate(&self) -> f64 {
+        PlayerStats::rate(self.deal_ins, self.rounds)
+    }
+
+    pub fn riichi_rate(&self) -> f64 {
+        PlayerStats::rate(self.riichi, self.rounds)
+    }
+
+    /// Average points gained by a win
+    pub fn average_win(&self) -> f64 {
+        if self.wins == 0 {0.0} else {self.win_points as f64 / self.wins as f64}
+    }
+
+    /// Average rank, from 1 for the first
+    pub fn average_placement(&self) -> f64 {
+        let games : usize = self.placements.iter().sum();
+        let total : usize = self.placements.iter().enumerate().map(|(rank, count)| (rank + 1) * count).sum();
+        PlayerStats::rate(total, games)
+    }
+
+    /// Average result of a game, with the uma and the oka
+    pub fn average_points(&self) -> f64 {
+        let games : usize = self.placements.iter().sum();
+        if games == 0 {0.0} else {self.points as f64 / games as f64}
+    }
+}
+
+/// Statistics of a simulation
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub games : usize,
+    pub rounds : usize,
+    /// Rounds ended without a win : exhaustive draws, nagashi mangan and abortive draws
+    pub draws : usize,
+    /// The statistics of each player, in the order they were added to the simulation
+    pub players : Vec<PlayerStats>,
+}
+
+impl Statistics {
+
+    fn new(names : &[String]) -> Statistics {
+        let players = names.iter()
+            .map(|name| PlayerStats{name : name.clone(), placements : vec![0; names.len()], ..PlayerStats::default()})
+            .collect();
+        Statistics{games : 0, rounds : 0, draws : 0, players}
+    }
+
+    /// Part of the rounds ended without a win (ryuukyoku)
+    pub fn draw_rate(&self) -> f64 {
+        PlayerStats::rate(self.draws, self.rounds)
+    }
+
+    /// Adds a game whose seat `seat` was taken by the player `seating[seat]`
+    fn add_game(&mut self, record : &GameRecord, seating : &[usize]) {
+        self.games += 1;
+        for round in &record.rounds {
+            self.rounds += 1;
+            for player in seating {
+                self.players[*player].rounds += 1;
+            }
+            for event in &round.events {
+                if let Event::Discard { player, riichi : true, .. } = event {
+                    self.players[seating[*player]].riichi += 1;
+                }
+            }
+            match &round.result {
+                RoundResult::Win(wins) => {
+                    for win in wins {
+                        let winner = &mut self.players[seating[win.winner]];
+                        winner.wins += 1;
+                        winner.win_points += win.deltas[win.winner] as i64;
+                    }
+                    // A double ron is a single deal-in
+                    if let Some(from) = wins.first().and_then(|win| win.from) {
+                        self.players[seating[from]].deal_ins += 1;
+                    }
+                },
+                _ => self.draws += 1,
+            }
+        }
+        for (seat, rank) in record.placements().into_iter().enumerate() {
+            self.players[seating[seat]].placements[rank] += 1;
+        }
+        for (seat, points) in record.placement_points().into_iter().enumerate() {
+            self.players[seating[seat]].points += points as i64;
+        }
+    }
+
+    fn merge(&mut self, other : Statistics) {
+        self.games += other.games;
+        self.rounds += other.rounds;
+        self.draws += other.draws;
+        for (stats, other) in self.players.iter_mut().zip(other.players) {
+            stats.rounds += other.rounds;
+            stats.wins += other.wins;
+            stats.deal_ins += other.deal_ins;
+            stats.win_points += other.win_points;
+            stats.riichi += other.riichi;
+            stats.placements.iter_mut().zip(other.placements).for_each(|(count, other)| *count += other);
+            stats.points += other.points;
+        }
+    }
+}
+
+/// A report of the statistics, a line per player
+impl Display for Statistics {
+    fn fmt(&self, f : &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} games, {} rounds, {:.1}% draws", self.games, self.rounds, 100.0 * self.draw_rate())?;
+        writeln!(f, "{:<16}{:>8}{:>9}{:>9}{:>10}{:>8}{:>9}  placements", "player", "wins", "deal-in", "riichi", "avg win", "rank", "points")?;
+        for stats in &self.players {
+            let placements : Vec<String> = stats.placements.iter().map(usize::to_string).collect();
+            writeln!(
+                f, "{:<16}{:>7.1}%{:>8.1}%{:>8.1}%{:>10.0}{:>8.2}{:>+9.1}  {}",
+                stats.name, 100.0 * stats.win_rate(), 100.0 * stats.deal_in_rate(), 100.0 * stats.riichi_rate(),
+                stats.average_win(), stats.average_placement(), stats.average_points() / 1000.0, placements.join(" / "),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Matches between bots, one for each seat of the table.
+/// The players change seats from a game to the next, so that each of them deals first as often as the others.
+pub struct Simulation {
+    rules : RuleSet,
+    seed : u64,
+    games : usize,
+    threads : usize,
+    players : Vec<(String, BotFactory)>,
+}
+
+impl Simulation {
+
+    // Constructors
+
+    /// A simulation of `games` games, whose seeds are drawn from `seed`, running on every available core
+    pub fn new(rules : RuleSet, seed : u64, games : usize) -> Simulation {
+        let threads = std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+        Simulation{rules, seed, games, threads, players : Vec::new()}
+    }
+
+    // Mutators
+
+    /// Adds a player. The simulation needs as many players as there are seats at the table
+    pub fn add_player(&mut self, name : impl Into<String>, factory : impl Fn() -> Box<dyn PlayerInterface> + Send + Sync + 'static) {
+        self.players.push((name.into(), Box::new(factory)));
+    }
+
+    pub fn set_threads(&mut self, threads : usize) {
+        self.threads = threads.max(1);
+    }
+
+    // Other
+
+    /// Plays every game, stopping at the first error of a table
+    pub fn run(&self) -> Result<Statistics, TableError> {
+        let seats = self.rules.players.count() as usize;
+        assert_eq!(seats, self.players.len(), "a simulation needs a player for each seat");
+        let names : Vec<String> = self.players.iter().map(|(name, _)| name.clone()).collect();
+        let mut rng = Rng::new(self.seed);
+        let seeds : Vec<u64> = (0..self.games).map(|_| rng.next_u64()).collect();
+
+        let next = AtomicUsize::new(0);
+        let error = Mutex::new(None);
+        let results : Vec<Statistics> = std::thread::scope(|scope| {
+            let workers : Vec<_> = (0..self.threads.min(self.games.max(1))).map(|_| scope.spawn(|| {
+                let mut statistics = Statistics::new(&names);
+                loop {
+                    let game = next.fetch_add(1, Ordering::Relaxed);
+                    let seed = match seeds.get(game) {
+                        Some(seed) => seed,
+                        None => break,
+                    };
+                    let seating : Vec<usize> = (0..seats).map(|seat| (seat + game) % seats).collect();
+                    match self.play(*seed, &seating) {
+                        Ok(record) => statistics.add_game(&record, &seating),
+                        Err(table_error) => {
+                            error.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_or_insert(table_error);
+                            next.store(seeds.len(), Ordering::Relaxed);
+                        },
+                    }
+                }
+                statistics
+            })).collect();
+            workers.into_iter().map(|worker| worker.join().expect("a simulation thread panicked")).collect()
+        });
+        if let Some(error) = error.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+            return Err(error);
+        }
+        let mut statistics = Statistics::new(&names);
+        results.into_iter().for_each(|result| statistics.merge(result));
+        Ok(statistics)
+    }
+
+    /// Plays a game, `seating` giving the player at each seat
+    fn play(&self, seed : u64, seating : &[usize]) -> Result<GameRecord, TableError> {
+        let names = seating.iter().map(|player| self.players[*player].0.clone()).collect();
+        let table = Table::new(seed, names, self.rules.clone())?;
+        let players = seating.iter().map(|player| (self.players[*player].1)()).collect();
+        // Bots answer at once, the time control is never reached
+        let time_control = TimeControl::new(Duration::from_secs(3600), Duration::from_secs(0));
+        TableHost::with_clock(table, players, Box::new(SystemClock::new()), time_control).run()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::rc::Rc;
+    use std::cell::RefCell;
+
+    use super::{Simulation, Statistics};
+    use crate::bot::RuleBot;
+    use crate::game::rules::{RuleSet, RoundLength};
+    use crate::game::event::Event;
+    use crate::game::table::{Table, Update};
+    use crate::game::host::{Observation, PlayerInterface, TableHost};
+    use crate::game::action::Action;
+
+    /// A `RuleBot` keeping the scores it is told at the end of the game
+    struct Scorer {
+        bot : RuleBot,
+        scores : Rc<RefCell<Vec<i32>>>,
+    }
+
+    impl PlayerInterface for Scorer {
+        fn observe(&mut self, updates : &[Update]) {
+            for update in updates {
+                if let Update::GameEnded { scores } = update {
+                    *self.scores.borrow_mut() = scores.clone();
+                }
+            }
+        }
+
+        fn act(&mut self, observation : &Observation, timeout : Duration) -> Option<Action> {
+            self.bot.act(observation, timeout)
+        }
+    }
+
+    fn simulation(games : usize) -> Simulation {
+        let mut rules = RuleSet::tenhou();
+        rules.round_length = RoundLength::Tonpuusen;
+        let mut simulation = Simulation::new(rules, 3, games);
+        for index in 0..3 {
+            simulation.add_player(format!("Rule {}", index), || Box::new(RuleBot::new()) as Box<dyn PlayerInterface>);
+        }
+        // Discards what it draws and never wins
+        simulation.add_player("Tsumogiri", || Box::new(|observation : &Observation, _ : Duration| observation.default_action()) as Box<dyn PlayerInterface>);
+        simulation
+    }
+
+    #[test]
+    fn test_simulation() {
+        let mut simulation = simulation(4);
+        simulation.set_threads(2);
+        let statistics = simulation.run().unwrap();
+        assert_eq!(4, statistics.games);
+        assert!(statistics.players.iter().all(|stats| stats.rounds == statistics.rounds));
+        assert!(statistics.players.iter().all(|stats| stats.placements.iter().sum::<usize>() == 4));
+        // The uma and the oka only move points between the players
+        assert_eq!(0, statistics.players.iter().map(|stats| stats.points).sum::<i64>());
+        let wins : usize = statistics.players.iter().map(|stats| stats.wins).sum();
+        assert!(wins + statistics.draws >= statistics.rounds);
+        assert_eq!(0, statistics.players[3].wins);
+        assert_eq!(0, statistics.players[3].riichi);
+
+        // The statistics do not depend on the threads
+        simulation.set_threads(1);
+        assert_eq!(statistics, simulation.run().unwrap());
+        assert!(statistics.to_string().contains("Tsumogiri"));
+    }
+
+    #[test]
+    fn test_placements() {
+        let names : Vec<String> = (0..4).map(|seat| format!("Rule {}", seat)).collect();
+        let mut rules = RuleSet::tenhou();
+        rules.round_length = RoundLength::Tonpuusen;
+        let mut riichi_last = false;
+        for seed in 0..3 {
+            let scores = Rc::new(RefCell::new(Vec::new()));
+            let table = Table::new(seed, names.clone(), rules.clone()).unwrap();
+            let players = (0..4).map(|_| Box::new(Scorer{bot : RuleBot::new(), scores : scores.clone()}) as Box<dyn PlayerInterface>).collect();
+            let record = TableHost::new(table, players).run().unwrap();
+            let last = record.rounds.last().unwrap();
+            riichi_last |= last.events.iter().any(|event| matches!(event, Event::Discard { riichi : true, .. }));
+
+            // The players are placed by the scores the table announced at the end, the first seat winning ties
+            let scores = scores.borrow().clone();
+            let mut ranking : Vec<usize> = (0..4).collect();
+            ranking.sort_by_key(|seat| -scores[*seat]);
+            let mut statistics = Statistics::new(&names);
+            statistics.add_game(&record, &[0, 1, 2, 3]);
+            for (rank, seat) in ranking.into_iter().enumerate() {
+                assert_eq!(1, statistics.players[seat].placements[rank]);
+            }
+        }
+        assert!(riichi_last);
+    }
+}
@@ -0,0 +1,1072 @@
+//! A Discord front-end hosting riichi mahjong tables in text channels.
+//!
+//! Players use the `/mahjong` slash command : `create` opens a table in the channel, `join` and `leave` take
+//! and free a seat, `addbot` lets the host fill a seat with a `RuleBot`, `start` deals the first round once every
+//! seat is taken, `hand` shows the hand and `stop` closes the table. The game is announced in the channel, while each player sees their hand and a button
+//! for each of their legal actions in an ephemeral answer, that only they can see. Each player has a `TimeControl` :
+//! those who do not act in time play the default action, the deadlines being checked on each interaction and on each `Bot::tick`.
+//! A player who leaves during the game, or who runs out of time `MAX_TIMEOUTS` times in a row, is replaced by a `RuleBot`.
+//!
+//! The bot does not know about the network : it receives `Interaction`s and answers through a `Gateway`,
+//! which is implemented over the Discord API by the `discord-bot` binary and can be mocked in tests.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Debug};
+use std::time::Duration;
+
+use crate::tile::Tile;
+use crate::tile::notation::tiles_to_mpsz;
+use crate::tile::display::TileStyle;
+use crate::game::wind::Wind;
+use crate::game::rules::RuleSet;
+use crate::game::rng::Rng;
+use crate::game::event::Event;
+use crate::game::action::Action;
+use crate::game::table::{Table, TableError, Update};
+use crate::game::host::{Observation, PlayerInterface, visible_updates};
+use crate::game::clock::{Clock, SystemClock, TimeControl};
+use crate::record::RoundResult;
+use crate::bot::RuleBot;
+
+/// Maximum number of buttons of a message, in 5 rows of 5 buttons
+pub const MAX_BUTTONS : usize = 25;
+
+/// Id of the users standing for bots : Discord never gives it to a real user
+pub const BOT_ID : u64 = 0;
+
+/// Number of timeouts in a row after which a bot takes the seat of a player
+pub const MAX_TIMEOUTS : usize = 3;
+
+/// A Discord user
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct User {
+    pub id : u64,
+    pub name : String,
+}
+
+impl User {
+
+    /// A seat taken by a bot
+    pub fn bot(name : impl Into<String>) -> User {
+        User{id : BOT_ID, name : name.into()}
+    }
+
+    pub fn is_bot(&self) -> bool {
+        self.id == BOT_ID
+    }
+
+    /// Text mentioning the user in a message
+    pub fn mention(&self) -> String {
+        format!("<@{}>", self.id)
+    }
+}
+
+/// A subcommand of the `/mahjong` slash command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Opens a table in the channel, with the rules of a preset (see `RuleSet::preset`)
+    Create { rules : Option<String> },
+    Join,
+    /// Frees the seat of the player, a bot taking it once the game has started
+    Leave,
+    /// Seats a bot, only allowed to the host
+    AddBot,
+    Start,
+    /// Shows the hand of the player and their legal actions
+    Hand,
+    /// Closes the table of the channel, only allowed to the host
+    Stop,
+}
+
+impl Command {
+
+    /// Reads a subcommand from its name and its `rules` option
+    pub fn parse(name : &str, rules : Option<&str>) -> Option<Command> {
+        match name {
+            "create" => Some(Command::Create{rules : rules.map(str::to_string)}),
+            "join" => Some(Command::Join),
+            "leave" => Some(Command::Leave),
+            "addbot" => Some(Command::AddBot),
+            "start" => Some(Command::Start),
+            "hand" => Some(Command::Hand),
+            "stop" => Some(Command::Stop),
+            _ => None,
+        }
+    }
+}
+
+/// Something a user did that the bot has to answer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Interaction {
+    /// The user used a slash command
+    Command { id : u64, channel : u64, user : User, command : Command },
+    /// The user clicked a button of a message of the bot
+    Button { id : u64, channel : u64, user : User, custom_id : String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Success,
+    Danger,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Button {
+    /// Identifier sent back by Discord when the button is clicked
+    pub custom_id : String,
+    pub label : String,
+    pub style : ButtonStyle,
+}
+
+/// A message of the bot, with up to `MAX_BUTTONS` buttons
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub content : String,
+    pub buttons : Vec<Button>,
+}
+
+impl Message {
+
+    pub fn text(content : impl Into<String>) -> Message {
+        Message{content : content.into(), buttons : Vec::new()}
+    }
+
+    pub fn with_button(mut self, custom_id : impl Into<String>, label : impl Into<String>, style : ButtonStyle) -> Message {
+        self.buttons.push(Button{custom_id : custom_id.into(), label : label.into(), style});
+        self
+    }
+}
+
+/// The way the bot talks to Discord
+pub trait Gateway {
+    /// Posts a message that everyone in the channel can see
+    fn post(&mut self, channel : u64, message : Message);
+
+    /// Answers an interaction. An ephemeral answer is only seen by the user of the interaction
+    fn respond(&mut self, interaction : u64, message : Message, ephemeral : bool);
+}
+
+/// Reason why the bot refuses an interaction, told to the user in an ephemeral answer
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BotError {
+    NoTable,
+    TableExists,
+    UnknownRules,
+    AlreadySeated,
+    TableFull,
+    NotSeated,
+    /// The table needs this number of players to start
+    MissingPlayers(usize),
+    AlreadyStarted,
+    NotStarted,
+    NotHost,
+    /// The button belongs to a hand view that is out of date
+    Expired,
+    Table(TableError),
+}
+
+impl Debug for BotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BotError::NoTable => write!(f, "There is no table in this channel, create one with `/mahjong create`."),
+            BotError::TableExists => write!(f, "There is already a table in this channel."),
+            BotError::UnknownRules => write!(f, "Unknown rules, use tenhou, mahjongsoul, sanma, wrc or ema."),
+            BotError::AlreadySeated => write!(f, "You already have a seat at this table."),
+            BotError::TableFull => write!(f, "Every seat of this table is taken."),
+            BotError::NotSeated => write!(f, "You do not have a seat at this table."),
+            BotError::MissingPlayers(count) => write!(f, "The table needs {} players to start.", count),
+            BotError::AlreadyStarted => write!(f, "The game has already started."),
+            BotError::NotStarted => write!(f, "The game has not started yet."),
+            BotError::NotHost => write!(f, "Only the host of the table can do this."),
+            BotError::Expired => write!(f, "This hand view is out of date, use `/mahjong hand` to see your hand."),
+            BotError::Table(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Display for BotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<TableError> for BotError {
+    fn from(error : TableError) -> Self {
+        BotError::Table(error)
+    }
+}
+
+/// The seat of a human, who chooses their action with the buttons of their hand view.
+/// A click comes long after the table asks for the action, and `PlayerInterface::act` cannot wait for it
+/// without blocking every other table : the choice is kept until the table asks for it instead
+#[derive(Default)]
+struct Buttons {
+    choice : Option<Action>,
+}
+
+impl PlayerInterface for Buttons {
+    fn act(&mut self, observation : &Observation, _timeout : Duration) -> Option<Action> {
+        self.choice.take().filter(|action| observation.legal_actions.contains(action))
+    }
+}
+
+/// Who plays a seat of a table
+enum Player {
+    Human(Buttons),
+    Bot(Box<dyn PlayerInterface + Send>),
+}
+
+impl Player {
+
+    fn new(user : &User) -> Player {
+        if user.is_bot() {
+            Player::Bot(Box::new(RuleBot::new()))
+        } else {
+            Player::Human(Buttons::default())
+        }
+    }
+
+    fn interface(&mut self) -> &mut dyn PlayerInterface {
+        match self {
+            Player::Human(buttons) => buttons,
+            Player::Bot(bot) => bot.as_mut(),
+        }
+    }
+
+    /// The action of the player, a bot who does not answer or answers an illegal action playing the default action
+    fn act(&mut self, observation : &Observation) -> Option<Action> {
+        let action = self.interface().act(observation, Duration::ZERO)
+            .filter(|action| observation.legal_actions.contains(action));
+        match self {
+            Player::Bot(_) => action.or_else(|| observation.default_action()),
+            Player::Human(_) => action,
+        }
+    }
+}
+
+/// A table of a channel, the host being the first human seated
+enum Game {
+    Lobby { rules : RuleSet, seats : Vec<User> },
+    /// `view` changes whenever the game goes on, which invalidates the buttons of the older hand views.
+    /// The table asked for the actions of the current view at `asked`, on the clock of the bot.
+    /// Each player has `banks` left beyond the base time, and `timeouts` counts the timeouts in a row of each player
+    Playing {
+        table : Box<Table>,
+        seats : Vec<User>,
+        players : Vec<Player>,
+        view : u64,
+        asked : Duration,
+        banks : Vec<Duration>,
+        timeouts : Vec<usize>,
+    },
+}
+
+impl Game {
+
+    fn seats(&self) -> &Vec<User> {
+        match self {
+            Game::Lobby { seats, .. } | Game::Playing { seats, .. } => seats,
+        }
+    }
+
+    fn is_host(&self, user : &User) -> bool {
+        self.seats().iter().find(|seated| !seated.is_bot()).map(|host| host.id) == Some(user.id)
+    }
+
+    /// When the first of the humans the table waits for runs out of time, `None` in the lobby
+    fn deadline(&self, time_control : TimeControl) -> Option<Duration> {
+        match self {
+            Game::Playing { table, seats, asked, banks, .. } => table.waiting_for().into_iter()
+                .filter(|seat| !seats[*seat].is_bot())
+                .map(|seat| *asked + time_control.base + banks[seat])
+                .min(),
+            Game::Lobby { .. } => None,
+        }
+    }
+
+    fn seat(&self, user : &User) -> Result<usize, BotError> {
+        self.seats().iter().position(|seated| seated.id == user.id).ok_or(BotError::NotSeated)
+    }
+}
+
+/// Hosts a table in each channel
+pub struct Bot {
+    games : HashMap<u64, Game>,
+    rng : Rng,
+    /// How the tiles of the hand views are written
+    style : TileStyle,
+    clock : Box<dyn Clock + Send>,
+    /// The time given to the players for each action and for the whole game
+    time_control : TimeControl,
+}
+
+impl Bot {
+
+    // Constructors
+
+    /// The seeds of the games are drawn from `seed`, the players have 30 seconds for each action and a bank of 2 minutes
+    pub fn new(seed : u64) -> Bot {
+        let time_control = TimeControl::new(Duration::from_secs(30), Duration::from_secs(120));
+        Bot::with_clock(seed, Box::new(SystemClock::new()), time_control)
+    }
+
+    /// The players have the time of `time_control`, measured by `clock`
+    pub fn with_clock(seed : u64, clock : Box<dyn Clock + Send>, time_control : TimeControl) -> Bot {
+        Bot{games : HashMap::new(), rng : Rng::new(seed), style : TileStyle::Mpsz, clock, time_control}
+    }
+
+    // Immutable getters
+
+    /// The table of the channel, once the game has started
+    pub fn table(&self, channel : u64) -> Option<&Table> {
+        match self.games.get(&channel) {
+            Some(Game::Playing { table, .. }) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// The users seated at the table of the channel, by seat
+    pub fn seats(&self, channel : u64) -> Option<&Vec<User>> {
+        self.games.get(&channel).map(Game::seats)
+    }
+
+    /// The time left in the bank of each player of the channel, once the game has started
+    pub fn banks(&self, channel : u64) -> Option<&Vec<Duration>> {
+        match self.games.get(&channel) {
+            Some(Game::Playing { banks, .. }) => Some(banks),
+            _ => None,
+        }
+    }
+
+    // Mutators
+
+    /// Writes the tiles of the hand views with `style`, like the custom emoji of the server
+    pub fn set_tile_style(&mut self, style : TileStyle) {
+        self.style = style;
+    }
+
+    // Other
+
+    /// Plays the default action for the players who did not act in time
+    pub fn tick(&mut self, gateway : &mut impl Gateway) {
+        let now = self.clock.now();
+        let time_control = self.time_control;
+        let mut late : Vec<u64> = self.games.iter()
+            .filter(|(_, game)| matches!(game.deadline(time_control), Some(deadline) if deadline <= now))
+            .map(|(channel, _)| *channel)
+            .collect();
+        late.sort_unstable();
+        for channel in late {
+            if let Err(error) = self.time_out(channel, gateway) {
+                gateway.post(channel, Message::text(error.to_string()));
+            }
+        }
+    }
+
+    /// Answers an interaction, refusals being answered privately. The deadlines are checked first
+    pub fn handle(&mut self, interaction : Interaction, gateway : &mut impl Gateway) {
+        self.tick(gateway);
+        let id = match &interaction {
+            Interaction::Command { id, .. } | Interaction::Button { id, .. } => *id,
+        };
+        let result = match interaction {
+            Interaction::Command { id, channel, user, command } => self.command(id, channel, user, command, gateway),
+            Interaction::Button { id, channel, user, custom_id } => self.button(id, channel, user, &custom_id, gateway),
+        };
+        if let Err(error) = result {
+            gateway.respond(id, Message::text(error.to_string()), true);
+        }
+    }
+
+    fn command(&mut self, id : u64, channel : u64, user : User, command : Command, gateway : &mut impl Gateway) -> Result<(), BotError> {
+        match command {
+            Command::Create { rules } => {
+                if self.games.contains_key(&channel) {
+                    return Err(BotError::TableExists);
+                }
+                let name = rules.unwrap_or_else(|| "mahjongsoul".to_string());
+                let rules = RuleSet::preset(&name).ok_or(BotError::UnknownRules)?;
+                let players = rules.players.count();
+                let text = format!("{} opens a table with the {} rules (1/{}). Use `/mahjong join` to take a seat.", user.name, name, players);
+                self.games.insert(channel, Game::Lobby{rules, seats : vec![user]});
+                gateway.respond(id, Message::text(text), false);
+            },
+            Command::Join => {
+                let (rules, seats) = self.lobby(channel)?;
+                if seats.iter().any(|seated| seated.id == user.id) {
+                    return Err(BotError::AlreadySeated);
+                }
+                let players = rules.players.count() as usize;
+                if seats.len() >= players {
+                    return Err(BotError::TableFull);
+                }
+                seats.push(user.clone());
+                let text = format!("{} takes a seat ({}/{}).", user.name, seats.len(), players);
+                gateway.respond(id, Message::text(text), false);
+            },
+            Command::Leave => {
+                let game = self.games.get_mut(&channel).ok_or(BotError::NoTable)?;
+                let seat = game.seat(&user)?;
+                match game {
+                    Game::Lobby { seats, .. } => {
+                        seats.remove(seat);
+                        let empty = seats.iter().all(User::is_bot);
+                        if empty {
+                            self.games.remove(&channel);
+                        }
+                        gateway.respond(id, Message::text(format!("{} leaves the table.", user.name)), false);
+                    },
+                    Game::Playing { seats, players, .. } => {
+                        replace(seats, players, seat);
+                        gateway.respond(id, Message::text(format!("{} leaves the table, a bot takes their seat.", user.name)), false);
+                        self.resume(channel, Vec::new(), gateway)?;
+                    },
+                }
+            },
+            Command::AddBot => {
+                let host = self.games.get(&channel).is_some_and(|game| game.is_host(&user));
+                let (rules, seats) = self.lobby(channel)?;
+                if !host {
+                    return Err(BotError::NotHost);
+                }
+                let players = rules.players.count() as usize;
+                if seats.len() >= players {
+                    return Err(BotError::TableFull);
+                }
+                let name = format!("Bot {}", seats.iter().filter(|seated| seated.is_bot()).count() + 1);
+                let text = format!("{} takes a seat ({}/{}).", name, seats.len() + 1, players);
+                seats.push(User::bot(name));
+                gateway.respond(id, Message::text(text), false);
+            },
+            Command::Start => {
+                let seed = self.rng.next_u64();
+                let (rules, seats) = self.lobby(channel)?;
+                if !seats.iter().any(|seated| seated.id == user.id) {
+                    return Err(BotError::NotSeated);
+                }
+                let players = rules.players.count() as usize;
+                if seats.len() < players {
+                    return Err(BotError::MissingPlayers(players));
+                }
+                let seats = seats.clone();
+                let names = seats.iter().map(|seated| seated.name.clone()).collect();
+                let mut table = Table::new(seed, names, rules.clone())?;
+                let mut players : Vec<Player> = seats.iter().map(Player::new).collect();
+                let mut updates = table.take_updates();
+                play(&mut table, &mut players, &mut updates)?;
+                gateway.respond(id, Message::text("The game starts!"), false);
+                let asked = self.clock.now();
+                let banks = vec![self.time_control.bank; seats.len()];
+                let timeouts = vec![0; seats.len()];
+                self.games.insert(channel, Game::Playing{table : Box::new(table), seats, players, view : 0, asked, banks, timeouts});
+                self.announce(channel, &updates, gateway);
+            },
+            Command::Hand => {
+                let game = self.games.get(&channel).ok_or(BotError::NoTable)?;
+                let seat = game.seat(&user)?;
+                match game {
+                    Game::Playing { table, view, .. } => gateway.respond(id, hand_view(table, seat, *view, false, &self.style), true),
+                    Game::Lobby { .. } => return Err(BotError::NotStarted),
+                }
+            },
+            Command::Stop => {
+                let game = self.games.get(&channel).ok_or(BotError::NoTable)?;
+                if !game.is_host(&user) {
+                    return Err(BotError::NotHost);
+                }
+                self.games.remove(&channel);
+                gateway.respond(id, Message::text(format!("{} closes the table.", user.name)), false);
+            },
+        }
+        Ok(())
+    }
+
+    fn lobby(&mut self, channel : u64) -> Result<(&RuleSet, &mut Vec<User>), BotError> {
+        match self.games.get_mut(&channel) {
+            Some(Game::Lobby { rules, seats }) => Ok((rules, seats)),
+            Some(Game::Playing { .. }) => Err(BotError::AlreadyStarted),
+            None => Err(BotError::NoTable),
+        }
+    }
+
+    /// Handles the buttons of the hand views : `hand`, `riichi:<view>` and `act:<view>:<index of the action>`
+    fn button(&mut self, id : u64, channel : u64, user : User, custom_id : &str, gateway : &mut impl Gateway) -> Result<(), BotError> {
+        let now = self.clock.now();
+        let base = self.time_control.base;
+        let game = self.games.get_mut(&channel).ok_or(BotError::NoTable)?;
+        let seat = game.seat(&user)?;
+        let (table, players, view, asked, banks, timeouts) = match game {
+            Game::Playing { table, players, view, asked, banks, timeouts, .. } => (table, players, view, asked, banks, timeouts),
+            Game::Lobby { .. } => return Err(BotError::NotStarted),
+        };
+        let parts : Vec<&str> = custom_id.split(':').collect();
+        let current = |part : Option<&&str>| part.and_then(|part| part.parse::<u64>().ok()) == Some(*view);
+        match parts.first() {
+            Some(&"hand") => gateway.respond(id, hand_view(table, seat, *view, false, &self.style), true),
+            Some(&"riichi") if current(parts.get(1)) => gateway.respond(id, hand_view(table, seat, *view, true, &self.style), true),
+            Some(&"act") if current(parts.get(1)) => {
+                let actions = table.legal_actions(seat);
+                let action = parts.get(2)
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| actions.get(index).cloned())
+                    .ok_or(BotError::Expired)?;
+                let rules = table.rules().clone();
+                if let Player::Human(buttons) = &mut players[seat] {
+                    buttons.choice = Some(action);
+                }
+                // The time spent beyond the base time is taken from the bank, as in `TableHost`
+                banks[seat] = banks[seat].saturating_sub((now - *asked).saturating_sub(base));
+                timeouts[seat] = 0;
+                let mut updates = Vec::new();
+                play(table, players, &mut updates)?;
+                if !updates.is_empty() {
+                    *view += 1;
+                    *asked = now;
+                }
+                gateway.respond(id, Message::text(format!("You chose : {}", action_label(&action, &rules))), true);
+                self.announce(channel, &updates, gateway);
+            },
+            _ => return Err(BotError::Expired),
+        }
+        Ok(())
+    }
+
+    /// Plays the default action for the humans the table of the channel waits for who ran out of time,
+    /// a bot taking the seat of those who ran out of time `MAX_TIMEOUTS` times in a row
+    fn time_out(&mut self, channel : u64, gateway : &mut impl Gateway) -> Result<(), BotError> {
+        let now = self.clock.now();
+        let time_control = self.time_control;
+        let (table, seats, players, asked, banks, timeouts) = match self.games.get_mut(&channel) {
+            Some(Game::Playing { table, seats, players, asked, banks, timeouts, .. }) => (table, seats, players, asked, banks, timeouts),
+            _ => return Ok(()),
+        };
+        let mut late = Vec::new();
+        for seat in table.waiting_for() {
+            if let Player::Human(buttons) = &mut players[seat] {
+                if *asked + time_control.base + banks[seat] <= now {
+                    buttons.choice = Observation::new(table, seat).default_action();
+                    banks[seat] = Duration::ZERO;
+                    timeouts[seat] += 1;
+                    late.push(seat);
+                }
+            }
+        }
+        if late.is_empty() {
+            return Ok(());
+        }
+        let names : Vec<String> = late.iter().map(|seat| seats[*seat].name.clone()).collect();
+        gateway.post(channel, Message::text(format!("Time is up for {}.", names.join(", "))));
+        for seat in late {
+            if timeouts[seat] >= MAX_TIMEOUTS {
+                replace(seats, players, seat);
+                gateway.post(channel, Message::text(format!("{} keeps running out of time, a bot takes their seat.", seats[seat].name)));
+            }
+        }
+        self.resume(channel, Vec::new(), gateway)
+    }
+
+    /// Lets the players of the channel act after a change of their seats, and announces what happened.
+    /// The table is closed once only bots are left
+    fn resume(&mut self, channel : u64, mut updates : Vec<Update>, gateway : &mut impl Gateway) -> Result<(), BotError> {
+        let now = self.clock.now();
+        let (table, seats, players, view, asked) = match self.games.get_mut(&channel) {
+            Some(Game::Playing { table, seats, players, view, asked, .. }) => (table, seats, players, view, asked),
+            _ => return Ok(()),
+        };
+        if seats.iter().all(User::is_bot) {
+            self.games.remove(&channel);
+            gateway.post(channel, Message::text("Every player left, the table is closed."));
+            return Ok(());
+        }
+        play(table, players, &mut updates)?;
+        if !updates.is_empty() {
+            *view += 1;
+            *asked = now;
+        }
+        self.announce(channel, &updates, gateway);
+        Ok(())
+    }
+
+    /// Posts what happened at the table of the channel and asks the players who have to act to look at their hand
+    fn announce(&mut self, channel : u64, updates : &[Update], gateway : &mut impl Gateway) {
+        let (table, seats) = match self.games.get(&channel) {
+            Some(Game::Playing { table, seats, .. }) => (table, seats),
+            _ => return,
+        };
+        let lines : Vec<String> = updates.iter().filter_map(|update| update_text(update, seats, table.rules())).collect();
+        if !lines.is_empty() {
+            gateway.post(channel, Message::text(lines.join("\n")));
+        }
+        if table.is_over() {
+            self.games.remove(&channel);
+            return;
+        }
+        let waiting = table.waiting_for();
+        if !waiting.is_empty() {
+            let mentions : Vec<String> = waiting.iter().filter(|seat| !seats[**seat].is_bot()).map(|seat| seats[*seat].mention()).collect();
+            let text = format!("{}, your move.", mentions.join(" "));
+            gateway.post(channel, Message::text(text).with_button("hand", "Show my hand", ButtonStyle::Primary));
+        }
+    }
+}
+
+/// Puts a `RuleBot` in the seat of a player, who keeps their name in the announcements
+fn replace(seats : &mut [User], players : &mut [Player], seat : usize) {
+    seats[seat] = User::bot(seats[seat].name.clone());
+    players[seat] = Player::new(&seats[seat]);
+}
+
+/// Asks the players the table waits for to act, until it waits for humans who have not chosen yet.
+/// Every player observes `updates` and the updates that follow, which are added to them
+fn play(table : &mut Table, players : &mut [Player], updates : &mut Vec<Update>) -> Result<(), TableError> {
+    let mut observed = 0;
+    loop {
+        if observed < updates.len() {
+            for (seat, player) in players.iter_mut().enumerate() {
+                player.interface().observe(&visible_updates(&updates[observed..], seat));
+            }
+            observed = updates.len();
+        }
+        let answer = table.waiting_for().into_iter()
+            .find_map(|seat| players[seat].act(&Observation::new(table, seat)).map(|action| (seat, action)));
+        match answer {
+            Some((seat, action)) => updates.extend(table.act(seat, action)?),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// A tile in the MPSZ notation, a red five being written as a 0
+pub fn tile_name(tile : Tile, rules : &RuleSet) -> String {
+    tiles_text(&[tile], rules)
+}
+
+/// Tiles in the MPSZ notation, red fives being written as a 0
+pub fn tiles_text(tiles : &[Tile], rules : &RuleSet) -> String {
+    tiles_to_mpsz(tiles, rules.red_fives)
+}
+
+fn wind_name(wind : Wind) -> &'static str {
+    match wind {
+        Wind::East => "East",
+        Wind::South => "South",
+        Wind::West => "West",
+        Wind::North => "North",
+    }
+}
+
+/// Label of the button of an action
+pub fn action_label(action : &Action, rules : &RuleSet) -> String {
+    let name = |tile : Tile| tile_name(tile, rules);
+    match *action {
+        Action::Discard { tile, riichi : false } => name(tile),
+        Action::Discard { tile, riichi : true } => format!("Riichi {}", name(tile)),
+        Action::Chii { tiles } => format!("Chii {}", tiles_text(&tiles, rules)),
+        Action::Pon { tiles } => format!("Pon {}", tiles_text(&tiles, rules)),
+        Action::OpenKan { tiles } => format!("Kan {}", tiles_text(&tiles, rules)),
+        Action::ClosedKan { tiles } => format!("Kan {}", tiles_text(&tiles, rules)),
+        Action::AddedKan { tile } => format!("Kan {}", name(tile)),
+        Action::Nuki { tile } => format!("Nuki {}", name(tile)),
+        Action::Tsumo => "Tsumo".to_string(),
+        Action::Ron => "Ron".to_string(),
+        Action::NineTerminals => "Nine terminals".to_string(),
+        Action::Pass => "Pass".to_string(),
+    }
+}
+
+fn action_style(action : &Action) -> ButtonStyle {
+    match action {
+        Action::Tsumo | Action::Ron => ButtonStyle::Success,
+        Action::Discard { riichi : true, .. } => ButtonStyle::Danger,
+        Action::Discard { .. } => ButtonStyle::Secondary,
+        Action::Pass => ButtonStyle::Secondary,
+        _ => ButtonStyle::Primary,
+    }
+}
+
+/// What a player sees of their hand, with a button for each legal action.
+/// The riichi discards are shown instead of the other actions when `riichi` is set,
+/// so that every action fits in the buttons of a message.
+fn hand_view(table : &Table, seat : usize, view : u64, riichi : bool, style : &TileStyle) -> Message {
+    let rules = table.rules();
+    let round = match table.round() {
+        Some(round) => round,
+        None => return Message::text("The game is over."),
+    };
+    let state = round.player(seat);
+    let hand = state.hand();
+    let mut hidden = hand.hidden_tiles().clone();
+    if let Some(drawn) = state.drawn() {
+        if let Some(position) = hidden.iter().position(|tile| *tile == drawn) {
+            hidden.remove(position);
+        }
+    }
+    let mut lines = vec![format!(
+        "**Your hand** ({} seat, {} points)",
+        wind_name(round.seat_wind(seat)),
+        table.scores()[seat],
+    )];
+    let mut tiles = style.apply(&hidden[..], rules.red_fives).to_string();
+    if let Some(drawn) = state.drawn() {
+        tiles.push_str(&format!(" + {}", style.apply(&drawn, rules.red_fives)));
+    }
+    lines.push(tiles);
+    let melds : Vec<String> = hand.melds().iter()
+        .map(|meld| meld.tiles().into_iter().cloned().collect::<Vec<Tile>>())
+        .chain(hand.closed_kans().iter().map(|kan| kan.tiles().into_iter().cloned().collect()))
+        .map(|tiles| style.apply(&tiles[..], rules.red_fives).to_string())
+        .collect();
+    if !melds.is_empty() {
+        lines.push(format!("Melds : {}", melds.join(" ")));
+    }
+    lines.push(format!("Dora indicators : {}", style.apply(&round.dora_indicators()[..], rules.red_fives)));
+
+    let actions = table.legal_actions(seat);
+    let mut message = Message::text("");
+    let riichi_discards = actions.iter().any(|action| matches!(action, Action::Discard { riichi : true, .. }));
+    for (index, action) in actions.iter().enumerate() {
+        let shown = matches!(action, Action::Discard { riichi : true, .. }) == riichi;
+        if shown && message.buttons.len() < MAX_BUTTONS {
+            let custom_id = format!("act:{}:{}", view, index);
+            message = message.with_button(custom_id, action_label(action, rules), action_style(action));
+        }
+    }
+    if riichi_discards && !riichi && message.buttons.len() < MAX_BUTTONS {
+        message = message.with_button(format!("riichi:{}", view), "Riichi...", ButtonStyle::Danger);
+    }
+    if actions.is_empty() {
+        lines.push("Waiting for the other players.".to_string());
+    }
+    message.content = lines.join("\n");
+    message
+}
+
+/// Public announcement of an update, `None` for the updates that are private
+fn update_text(update : &Update, seats : &[User], rules : &RuleSet) -> Option<String> {
+    let name = |seat : usize| seats[seat].name.clone();
+    let tile = |tile : Tile| tile_name(tile, rules);
+    let scores = |scores : &[i32]| {
+        scores.iter().enumerate().map(|(seat, score)| format!("{} {}", name(seat), score)).collect::<Vec<String>>().join(", ")
+    };
+    let text = match update {
+        Update::RoundStarted { round_wind, dealer, honba, riichi_sticks, scores : start, .. } => format!(
+            "**{} {}**, {} honba, {} riichi sticks. Dealer : {}. Scores : {}",
+            wind_name(*round_wind), dealer + 1, honba, riichi_sticks, name(*dealer), scores(start),
+        ),
+        Update::Event(event) => match *event {
+            Event::Draw { .. } => return None,
+            Event::Discard { player, tile : discarded, riichi, .. } => {
+                let riichi = if riichi {" and declares riichi"} else {""};
+                format!("{} discards {}{}", name(player), tile(discarded), riichi)
+            },
+            Event::Chii { player, called, .. } => format!("{} calls chii on {}", name(player), tile(called)),
+            Event::Pon { player, called, .. } => format!("{} calls pon on {}", name(player), tile(called)),
+            Event::OpenKan { player, called, .. } => format!("{} calls kan on {}", name(player), tile(called)),
+            Event::ClosedKan { player, tiles } => format!("{} declares a closed kan of {}", name(player), tile(tiles[0])),
+            Event::AddedKan { player, tile : added } => format!("{} adds {} to their pon", name(player), tile(added)),
+            Event::Nuki { player, .. } => format!("{} sets a north wind aside", name(player)),
+            Event::NewDora { indicator } => format!("New dora indicator : {}", tile(indicator)),
+        },
+        Update::HiddenDraw { .. } | Update::RiichiAccepted { .. } => return None,
+        Update::RoundEnded { result, scores : end } => {
+            let mut lines = match result {
+                RoundResult::Win(wins) => wins.iter().map(|win| {
+                    let how = match win.from {
+                        Some(from) => format!("ron from {}", name(from)),
+                        None => "tsumo".to_string(),
+                    };
+                    let yaku : Vec<String> = win.yaku.iter().map(|(yaku, han)| format!("{} {}", yaku, han)).collect();
+                    format!(
+                        "**{} wins by {}** : {} ({} han {} fu, +{})",
+                        name(win.winner), how, yaku.join(", "), win.han, win.fu, win.deltas[win.winner],
+                    )
+                }).collect(),
+                RoundResult::ExhaustiveDraw { .. } => vec!["**Exhaustive draw**".to_string()],
+                RoundResult::NagashiMangan { .. } => vec!["**Nagashi mangan**".to_string()],
+                RoundResult::AbortiveDraw(abort) => vec![format!("**Abortive draw** ({:?})", abort)],
+            };
+            lines.push(format!("Scores : {}", scores(end)));
+            lines.join("\n")
+        },
+        Update::GameEnded { scores : end } => format!("**The game is over.** Final scores : {}", scores(end)),
+    };
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Bot, Gateway, Message, Interaction, Command, User, ButtonStyle, MAX_TIMEOUTS};
+    use crate::tile::display::TileStyle;
+    use crate::game::clock::{ManualClock, TimeControl};
+
+    const CHANNEL : u64 = 7;
+
+    /// Records what the bot sends instead of talking to Discord
+    #[derive(Default)]
+    struct MockGateway {
+        posts : Vec<(u64, Message)>,
+        responses : Vec<(u64, Message, bool)>,
+    }
+
+    impl Gateway for MockGateway {
+        fn post(&mut self, channel : u64, message : Message) {
+            self.posts.push((channel, message));
+        }
+
+        fn respond(&mut self, interaction : u64, message : Message, ephemeral : bool) {
+            self.responses.push((interaction, message, ephemeral));
+        }
+    }
+
+    fn user(id : u64) -> User {
+        User{id, name : format!("player{}", id)}
+    }
+
+    fn command(bot : &mut Bot, gateway : &mut MockGateway, user_id : u64, command : Command) -> (Message, bool) {
+        bot.handle(Interaction::Command{id : gateway.responses.len() as u64, channel : CHANNEL, user : user(user_id), command}, gateway);
+        let (_, message, ephemeral) = gateway.responses.last().unwrap().clone();
+        (message, ephemeral)
+    }
+
+    fn click(bot : &mut Bot, gateway : &mut MockGateway, user_id : u64, custom_id : &str) -> (Message, bool) {
+        let custom_id = custom_id.to_string();
+        bot.handle(Interaction::Button{id : gateway.responses.len() as u64, channel : CHANNEL, user : user(user_id), custom_id}, gateway);
+        let (_, message, ephemeral) = gateway.responses.last().unwrap().clone();
+        (message, ephemeral)
+    }
+
+    fn started(gateway : &mut MockGateway) -> Bot {
+        start(Bot::new(1), gateway)
+    }
+
+    fn start(mut bot : Bot, gateway : &mut MockGateway) -> Bot {
+        command(&mut bot, gateway, 10, Command::Create{rules : Some("tenhou".to_string())});
+        for id in 11..14 {
+            command(&mut bot, gateway, id, Command::Join);
+        }
+        command(&mut bot, gateway, 10, Command::Start);
+        bot
+    }
+
+    #[test]
+    fn test_lobby() {
+        let mut bot = Bot::new(1);
+        let mut gateway = MockGateway::default();
+        assert!(command(&mut bot, &mut gateway, 1, Command::Join).1);
+        let (message, ephemeral) = command(&mut bot, &mut gateway, 1, Command::Create{rules : Some("chess".to_string())});
+        assert!(ephemeral && message.content.starts_with("Unknown rules"));
+
+        let (message, ephemeral) = command(&mut bot, &mut gateway, 1, Command::Create{rules : Some("sanma".to_string())});
+        assert!(!ephemeral && message.content.contains("(1/3)"));
+        assert!(command(&mut bot, &mut gateway, 1, Command::Join).1);
+        assert!(command(&mut bot, &mut gateway, 2, Command::Start).1);
+        let (message, _) = command(&mut bot, &mut gateway, 1, Command::Start);
+        assert_eq!("The table needs 3 players to start.", message.content);
+
+        command(&mut bot, &mut gateway, 2, Command::Join);
+        command(&mut bot, &mut gateway, 2, Command::Leave);
+        command(&mut bot, &mut gateway, 2, Command::Join);
+        command(&mut bot, &mut gateway, 3, Command::Join);
+        assert_eq!("Every seat of this table is taken.", command(&mut bot, &mut gateway, 4, Command::Join).0.content);
+        assert_eq!(3, bot.seats(CHANNEL).unwrap().len());
+        assert!(!command(&mut bot, &mut gateway, 3, Command::Start).1);
+        assert!(bot.table(CHANNEL).is_some());
+        assert!(command(&mut bot, &mut gateway, 2, Command::Stop).1);
+        assert!(!command(&mut bot, &mut gateway, 1, Command::Stop).1);
+        assert!(bot.seats(CHANNEL).is_none());
+    }
+
+    #[test]
+    fn test_hand_view() {
+        let mut gateway = MockGateway::default();
+        let mut bot = started(&mut gateway);
+        let (_, prompt) = gateway.posts.last().unwrap().clone();
+        assert!(prompt.content.starts_with("<@10>"));
+        assert_eq!("hand", prompt.buttons[0].custom_id);
+
+        // The dealer sees their 14 tiles and a button for each discard
+        let (view, ephemeral) = click(&mut bot, &mut gateway, 10, "hand");
+        assert!(ephemeral && view.content.starts_with("**Your hand** (East seat, 25000 points)"));
+        assert!(view.buttons.iter().all(|button| button.custom_id.starts_with("act:0:") || button.custom_id == "riichi:0"));
+        let discard = view.buttons.iter().find(|button| button.style == ButtonStyle::Secondary).unwrap().clone();
+
+        // Another player has nothing to do, and a stranger cannot look
+        let (view, _) = click(&mut bot, &mut gateway, 11, "hand");
+        assert!(view.buttons.is_empty());
+        assert!(click(&mut bot, &mut gateway, 99, "hand").0.content.starts_with("You do not have a seat"));
+
+        let posts = gateway.posts.len();
+        let (answer, _) = click(&mut bot, &mut gateway, 10, &discard.custom_id);
+        assert_eq!(format!("You chose : {}", discard.label), answer.content);
+        assert!(gateway.posts[posts].1.content.starts_with(&format!("player10 discards {}", discard.label)));
+        assert!(click(&mut bot, &mut gateway, 10, &discard.custom_id).0.content.starts_with("This hand view is out of date"));
+
+        bot.set_tile_style(TileStyle::Unicode);
+        let (view, _) = click(&mut bot, &mut gateway, 11, "hand");
+        assert!(view.content.lines().nth(1).unwrap().chars().all(|tile| ('\u{1F000}'..='\u{1F021}').contains(&tile) || tile == ' ' || tile == '+'));
+    }
+
+    #[test]
+    fn test_full_game() {
+        let mut gateway = MockGateway::default();
+        let mut bot = started(&mut gateway);
+        let mut clicks = 0;
+        while let Some(table) = bot.table(CHANNEL) {
+            let seat = table.waiting_for()[0];
+            let id = bot.seats(CHANNEL).unwrap()[seat].id;
+            let (view, _) = click(&mut bot, &mut gateway, id, "hand");
+            // Wins when possible, passes on calls and otherwise discards the last tile of the hand
+            let button = view.buttons.iter()
+                .find(|button| button.label == "Tsumo" || button.label == "Ron" || button.label == "Pass")
+                .or_else(|| view.buttons.iter().rev().find(|button| button.style == ButtonStyle::Secondary))
+                .unwrap()
+                .clone();
+            click(&mut bot, &mut gateway, id, &button.custom_id);
+            clicks += 1;
+            assert!(clicks < 100_000);
+        }
+        let (_, last) = gateway.posts.last().unwrap();
+        assert!(last.content.contains("**The game is over.**"));
+        assert!(gateway.posts.iter().all(|(channel, _)| *channel == CHANNEL));
+    }
+
+    #[test]
+    fn test_bots() {
+        let mut gateway = MockGateway::default();
+        let mut bot = Bot::new(2);
+        command(&mut bot, &mut gateway, 10, Command::Create{rules : Some("sanma".to_string())});
+        command(&mut bot, &mut gateway, 11, Command::Join);
+        assert_eq!("Only the host of the table can do this.", command(&mut bot, &mut gateway, 11, Command::AddBot).0.content);
+        let (message, ephemeral) = command(&mut bot, &mut gateway, 10, Command::AddBot);
+        assert!(!ephemeral && message.content.starts_with("Bot 1 takes a seat (3/3)"));
+        assert!(command(&mut bot, &mut gateway, 10, Command::AddBot).1);
+        command(&mut bot, &mut gateway, 10, Command::Start);
+
+        // The bot plays on its own, only the humans are asked to act
+        let mut clicks = 0;
+        while let Some(table) = bot.table(CHANNEL) {
+            let seat = table.waiting_for()[0];
+            let user = &bot.seats(CHANNEL).unwrap()[seat];
+            assert!(!user.is_bot());
+            let id = user.id;
+            let (view, _) = click(&mut bot, &mut gateway, id, "hand");
+            let button = view.buttons.iter()
+                .find(|button| button.label == "Tsumo" || button.label == "Ron" || button.label == "Pass")
+                .or_else(|| view.buttons.iter().rev().find(|button| button.style == ButtonStyle::Secondary))
+                .unwrap()
+                .clone();
+            click(&mut bot, &mut gateway, id, &button.custom_id);
+            clicks += 1;
+            assert!(clicks < 100_000);
+        }
+        assert!(gateway.posts.iter().any(|(_, post)| post.content.contains("Bot 1 discards")));
+        assert!(gateway.posts.iter().all(|(_, post)| !post.content.contains("<@0>")));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let mut gateway = MockGateway::default();
+        let clock = ManualClock::new();
+        let time_control = TimeControl::new(Duration::from_secs(10), Duration::from_secs(20));
+        let mut bot = start(Bot::with_clock(1, Box::new(clock.clone()), time_control), &mut gateway);
+        let (view, _) = click(&mut bot, &mut gateway, 10, "hand");
+        let discard = view.buttons.iter().find(|button| button.style == ButtonStyle::Secondary).unwrap().clone();
+
+        // Nothing happens before the base time and the bank run out
+        clock.advance(Duration::from_secs(29));
+        let posts = gateway.posts.len();
+        bot.tick(&mut gateway);
+        assert_eq!(posts, gateway.posts.len());
+
+        // The dealer runs out of time and discards their drawn tile, which expires their hand view
+        clock.advance(Duration::from_secs(1));
+        bot.tick(&mut gateway);
+        assert_eq!("Time is up for player10.", gateway.posts[posts].1.content);
+        assert!(gateway.posts[posts + 1].1.content.starts_with("player10 discards"));
+        assert!(bot.table(CHANNEL).unwrap().waiting_for() != vec![0]);
+        assert!(click(&mut bot, &mut gateway, 10, &discard.custom_id).0.content.starts_with("This hand view is out of date"));
+        assert_eq!(&vec![Duration::ZERO, time_control.bank, time_control.bank, time_control.bank], bot.banks(CHANNEL).unwrap());
+
+        // The next deadline is checked when someone interacts with the bot
+        clock.advance(Duration::from_secs(30));
+        let posts = gateway.posts.len();
+        click(&mut bot, &mut gateway, 12, "hand");
+        assert!(gateway.posts[posts].1.content.starts_with("Time is up for"));
+    }
+
+    #[test]
+    fn test_time_bank() {
+        let mut gateway = MockGateway::default();
+        let clock = ManualClock::new();
+        let time_control = TimeControl::new(Duration::from_secs(10), Duration::from_secs(20));
+        let mut bot = start(Bot::with_clock(1, Box::new(clock.clone()), time_control), &mut gateway);
+
+        // The dealer answers after 15 seconds, 5 of them being taken from their bank
+        clock.advance(Duration::from_secs(15));
+        let (view, _) = click(&mut bot, &mut gateway, 10, "hand");
+        let discard = view.buttons.iter().find(|button| button.style == ButtonStyle::Secondary).unwrap().clone();
+        click(&mut bot, &mut gateway, 10, &discard.custom_id);
+        assert_eq!(Duration::from_secs(15), bot.banks(CHANNEL).unwrap()[0]);
+
+        // An answer within the base time leaves the bank as it is
+        let seat = bot.table(CHANNEL).unwrap().waiting_for()[0];
+        clock.advance(Duration::from_secs(10));
+        let (view, _) = click(&mut bot, &mut gateway, 10 + seat as u64, "hand");
+        let button = view.buttons.iter()
+            .find(|button| button.label == "Pass")
+            .or_else(|| view.buttons.iter().find(|button| button.style == ButtonStyle::Secondary))
+            .unwrap()
+            .clone();
+        click(&mut bot, &mut gateway, 10 + seat as u64, &button.custom_id);
+        assert_eq!(time_control.bank, bot.banks(CHANNEL).unwrap()[seat]);
+
+        // The next player has their whole bank
+        clock.advance(time_control.base + time_control.bank - Duration::from_secs(1));
+        let posts = gateway.posts.len();
+        bot.tick(&mut gateway);
+        assert_eq!(posts, gateway.posts.len());
+    }
+
+    #[test]
+    fn test_replacement() {
+        let mut gateway = MockGateway::default();
+        let clock = ManualClock::new();
+        let time_control = TimeControl::new(Duration::from_secs(10), Duration::from_secs(20));
+        let mut bot = start(Bot::with_clock(1, Box::new(clock.clone()), time_control), &mut gateway);
+
+        // A player who leaves during the game is replaced by a bot that keeps their name
+        let (message, ephemeral) = command(&mut bot, &mut gateway, 11, Command::Leave);
+        assert!(!ephemeral && message.content.starts_with("player11 leaves the table, a bot takes their seat"));
+        let seats = bot.seats(CHANNEL).unwrap();
+        assert!(seats[1].is_bot() && seats[1].name == "player11");
+        assert!(command(&mut bot, &mut gateway, 11, Command::Hand).1);
+
+        // The dealer, who is also the host, keeps running out of time until a bot takes their seat
+        let mut ticks = 0;
+        while !bot.seats(CHANNEL).unwrap()[0].is_bot() {
+            clock.advance(time_control.base + time_control.bank);
+            bot.tick(&mut gateway);
+            ticks += 1;
+            assert!(ticks < 100);
+        }
+        let timeouts = gateway.posts.iter().filter(|(_, post)| post.content.starts_with("Time is up") && post.content.contains("player10")).count();
+        assert_eq!(MAX_TIMEOUTS, timeouts);
+        assert!(gateway.posts.iter().any(|(_, post)| post.content == "player10 keeps running out of time, a bot takes their seat."));
+        assert!(bot.table(CHANNEL).unwrap().waiting_for().iter().all(|seat| *seat >= 2));
+
+        // The first human left is the host, and the table closes once only bots are left
+        assert!(!command(&mut bot, &mut gateway, 12, Command::Stop).1);
+        let mut bot = start(Bot::with_clock(2, Box::new(clock.clone()), time_control), &mut gateway);
+        for id in 10..14 {
+            command(&mut bot, &mut gateway, id, Command::Leave);
+        }
+        assert!(bot.seats(CHANNEL).is_none());
+        assert_eq!("Every player left, the table is closed.", gateway.posts.last().unwrap().1.content);
+    }
+}
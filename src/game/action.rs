@@ -0,0 +1,75 @@
+use crate::tile::Tile;
+use crate::game::rules::RedFives;
+
+/// A decision taken by a player, either on their turn or when another player discards.
+/// The tiles given for a call are the ones taken from the hand, the called tile being the last discard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    /// Discards a tile, possibly declaring riichi
+    Discard { tile : Tile, riichi : bool },
+    Chii { tiles : [Tile; 2] },
+    Pon { tiles : [Tile; 2] },
+    OpenKan { tiles : [Tile; 3] },
+    ClosedKan { tiles : [Tile; 4] },
+    AddedKan { tile : Tile },
+    /// In a three player game, sets a north wind aside as dora
+    Nuki { tile : Tile },
+    /// Wins on the drawn tile
+    Tsumo,
+    /// Wins on the last discard
+    Ron,
+    /// Aborts the round with nine different terminals and honors on the first draw
+    NineTerminals,
+    /// Does not call the last discard
+    Pass,
+}
+
+impl Action {
+
+    /// The tiles of the hand used by the action
+    pub fn tiles(&self) -> Vec<Tile> {
+        match self {
+            Action::Discard { tile, .. } | Action::AddedKan { tile } | Action::Nuki { tile } => vec![*tile],
+            Action::Chii { tiles } | Action::Pon { tiles } => tiles.to_vec(),
+            Action::OpenKan { tiles } => tiles.to_vec(),
+            Action::ClosedKan { tiles } => tiles.to_vec(),
+            Action::Tsumo | Action::Ron | Action::NineTerminals | Action::Pass => Vec::new(),
+        }
+    }
+
+    /// Whether the two actions only differ by the copies of the tiles they use, red fives being told apart.
+    /// The legal actions of a table only hold one copy of each tile, which may not be the one a player picked.
+    pub fn is_equivalent(&self, other : &Action, red_fives : RedFives) -> bool {
+        let riichi = |action : &Action| matches!(action, Action::Discard { riichi : true, .. });
+        let pais = |action : &Action| {
+            let mut pais : Vec<_> = action.tiles().iter().map(|tile| (tile.value(), tile.is_red(red_fives))).collect();
+            pais.sort();
+            pais
+        };
+        std::mem::discriminant(self) == std::mem::discriminant(other) && riichi(self) == riichi(other) && pais(self) == pais(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Action;
+    use crate::tile::Tile;
+    use crate::tile::suit::Suit::{Man, Pin};
+    use crate::game::rules::RedFives;
+
+    #[test]
+    fn test_equivalent() {
+        let discard = |tile, riichi| Action::Discard{tile, riichi};
+        let red_fives = RedFives::one_each();
+        assert!(discard(Tile::new_suited(Man, 3, 0), false).is_equivalent(&discard(Tile::new_suited(Man, 3, 2), false), red_fives));
+        assert!(!discard(Tile::new_suited(Man, 3, 0), false).is_equivalent(&discard(Tile::new_suited(Man, 3, 0), true), red_fives));
+        assert!(!discard(Tile::new_suited(Man, 3, 0), false).is_equivalent(&discard(Tile::new_suited(Pin, 3, 0), false), red_fives));
+        let pon = |id| Action::Pon{tiles : [Tile::new_suited(Pin, 5, id), Tile::new_suited(Pin, 5, 3)]};
+        // The copy 0 of the five is the red one
+        assert!(!pon(0).is_equivalent(&pon(1), red_fives));
+        assert!(pon(1).is_equivalent(&pon(2), red_fives));
+        assert!(Action::Pass.is_equivalent(&Action::Pass, red_fives));
+        assert!(!Action::Tsumo.is_equivalent(&Action::Ron, red_fives));
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Measures the time the players take to act
+pub trait Clock {
+    /// Time elapsed since an arbitrary origin, which never goes backwards
+    fn now(&self) -> Duration;
+}
+
+/// The real time, measured from the creation of the clock
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    start : Instant,
+}
+
+impl SystemClock {
+
+    pub fn new() -> SystemClock {
+        SystemClock{start : Instant::now()}
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to, its clones sharing the same time, even across threads
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    time : Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, duration : Duration) {
+        *self.time.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.time.lock().unwrap()
+    }
+}
+
+/// The time allowed to each player, as in online clients : every action may take `base`,
+/// and the time spent beyond it is taken from a bank of `bank` that lasts for the whole game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeControl {
+    pub base : Duration,
+    pub bank : Duration,
+}
+
+impl TimeControl {
+
+    pub fn new(base : Duration, bank : Duration) -> TimeControl {
+        TimeControl{base, bank}
+    }
+
+    /// 5 seconds for each action and a bank of 20 seconds
+    pub fn online() -> TimeControl {
+        TimeControl::new(Duration::from_secs(5), Duration::from_secs(20))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Clock, ManualClock, SystemClock};
+
+    #[test]
+    fn test_clocks() {
+        let clock = ManualClock::new();
+        let shared = clock.clone();
+        shared.advance(Duration::from_secs(3));
+        assert_eq!(Duration::from_secs(3), clock.now());
+
+        let system = SystemClock::new();
+        let before = system.now();
+        assert!(system.now() >= before);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::tile::Tile;
+
+/// Something that happens during a round.
+/// Players are designated by their index at the table, the first dealer being the player 0.
+/// A round can be replayed by applying its events in order, see `Round::apply`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    /// The player draws a tile, either from the live wall or from the dead wall after a kan or a nukidora
+    Draw { player : usize, tile : Tile },
+    /// The player discards a tile, possibly to declare riichi
+    Discard { player : usize, tile : Tile, tsumogiri : bool, riichi : bool },
+    /// The player calls the last discard of the player `from` to complete a sequence
+    Chii { player : usize, from : usize, called : Tile, tiles : [Tile; 2] },
+    /// The player calls the last discard of the player `from` to complete a triplet
+    Pon { player : usize, from : usize, called : Tile, tiles : [Tile; 2] },
+    /// The player calls the last discard of the player `from` to complete a quad (daiminkan)
+    OpenKan { player : usize, from : usize, called : Tile, tiles : [Tile; 3] },
+    /// The player declares a quad with four tiles of their closed hand (ankan)
+    ClosedKan { player : usize, tiles : [Tile; 4] },
+    /// The player adds a tile to one of their pons (shouminkan)
+    AddedKan { player : usize, tile : Tile },
+    /// In a three player game, the player sets a north wind aside as dora
+    Nuki { player : usize, tile : Tile },
+    /// A new dora indicator is revealed after a kan
+    NewDora { indicator : Tile },
+}
+
+impl Event {
+
+    /// The player doing the action, if any
+    pub fn player(&self) -> Option<usize> {
+        match self {
+            | Event::Draw { player, .. }
+            | Event::Discard { player, .. }
+            | Event::Chii { player, .. }
+            | Event::Pon { player, .. }
+            | Event::OpenKan { player, .. }
+            | Event::ClosedKan { player, .. }
+            | Event::AddedKan { player, .. }
+            | Event::Nuki { player, .. } => Some(*player),
+            Event::NewDora { .. } => None,
+        }
+    }
+}
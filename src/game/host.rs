@@ -0,0 +1,466 @@
+use std::time::Duration;
+
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::tile::tile_counts::TileCounts;
+use crate::hand::Hand;
+use crate::hand::meld::Meld;
+use crate::hand::group::Group;
+use crate::game::wind::Wind;
+use crate::game::pond::Pond;
+use crate::game::event::Event;
+use crate::game::action::Action;
+use crate::game::table::{Table, TableError, Update};
+use crate::game::clock::{Clock, SystemClock, TimeControl};
+use crate::record::game::GameRecord;
+
+/// What everyone can see of a player during a round
+#[derive(Clone, Debug)]
+pub struct PublicState {
+    pub pond : Pond,
+    pub melds : Vec<Meld>,
+    pub closed_kans : Vec<Group>,
+    pub nuki : Vec<Tile>,
+    pub riichi : bool,
+    /// The tiles the other players discarded since the riichi of the player
+    pub passed : Vec<TileValue>,
+}
+
+/// What a player sees of the round being played
+#[derive(Clone, Debug)]
+pub struct RoundView {
+    pub round_wind : Wind,
+    pub seat_wind : Wind,
+    pub dealer : usize,
+    pub honba : u8,
+    pub riichi_sticks : u8,
+    /// Number of tiles left to draw in the wall
+    pub remaining : usize,
+    pub dora_indicators : Vec<Tile>,
+    /// The hand of the player, with the drawn tile
+    pub hand : Hand,
+    pub drawn : Option<Tile>,
+    /// The public state of each player, by seat
+    pub players : Vec<PublicState>,
+}
+
+impl RoundView {
+
+    /// Copies of each tile value the player can see : their hand, the ponds, the melds,
+    /// the closed kans, the nukidora and the dora indicators
+    pub fn visible_counts(&self) -> TileCounts {
+        let mut counts = TileCounts::from_tiles(self.hand.hidden_tiles());
+        let mut tiles : Vec<Tile> = self.dora_indicators.clone();
+        for player in &self.players {
+            // A called discard is counted in the meld that took it
+            tiles.extend(player.pond.discards().iter().filter(|discard| !discard.called).map(|discard| discard.tile));
+            tiles.extend(player.melds.iter().flat_map(|meld| meld.tiles().into_iter().cloned()));
+            tiles.extend(player.closed_kans.iter().flat_map(|kan| kan.tiles().into_iter().cloned()));
+            tiles.extend(player.nuki.iter().cloned());
+        }
+        tiles.iter().for_each(|tile| counts.add(tile.value()));
+        counts
+    }
+}
+
+/// What a player knows when they have to decide an action
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub seat : usize,
+    pub scores : Vec<i32>,
+    /// The round being played, `None` once the game is over
+    pub round : Option<RoundView>,
+    /// The actions the player can choose from
+    pub legal_actions : Vec<Action>,
+}
+
+impl Observation {
+
+    // Constructors
+
+    /// What the player at `seat` sees of the table
+    pub fn new(table : &Table, seat : usize) -> Observation {
+        let round = table.round().map(|round| {
+            let state = round.player(seat);
+            RoundView {
+                round_wind : round.round_wind(),
+                seat_wind : round.seat_wind(seat),
+                dealer : round.dealer(),
+                honba : round.honba(),
+                riichi_sticks : round.riichi_sticks(),
+                remaining : round.remaining(),
+                dora_indicators : round.dora_indicators().clone(),
+                hand : state.hand().clone(),
+                drawn : state.drawn(),
+                players : (0..round.player_count()).map(|player| {
+                    let state = round.player(player);
+                    PublicState {
+                        pond : state.pond().clone(),
+                        melds : state.hand().melds().clone(),
+                        closed_kans : state.hand().closed_kans().clone(),
+                        nuki : state.hand().nuki().clone(),
+                        riichi : state.is_riichi(),
+                        passed : state.passed().clone(),
+                    }
+                }).collect(),
+            }
+        });
+        Observation{seat, scores : table.scores().clone(), round, legal_actions : table.legal_actions(seat)}
+    }
+
+    // Other
+
+    /// The action played for a player who does not answer : passing on calls, otherwise discarding
+    /// the drawn tile, or the last tile of the hand after a call
+    pub fn default_action(&self) -> Option<Action> {
+        let drawn = self.round.as_ref().and_then(|round| round.drawn);
+        let discards = || self.legal_actions.iter().filter(|action| matches!(action, Action::Discard { riichi : false, .. }));
+        self.legal_actions.iter().find(|action| **action == Action::Pass)
+            .or_else(|| discards().find(|action| matches!(action, Action::Discard { tile, .. } if Some(*tile) == drawn)))
+            .or_else(|| discards().next_back())
+            .or_else(|| self.legal_actions.first())
+            .cloned()
+    }
+}
+
+/// A player of a table run by a `TableHost`, whether a human behind a chat platform or a terminal, or a bot
+pub trait PlayerInterface {
+    /// Receives what happened at the table, as seen from the seat of the player.
+    /// Every update reaches every player once, in order, before they are asked to act on it.
+    fn observe(&mut self, _updates : &[Update]) {}
+
+    /// Chooses one of the legal actions of the observation, `None` meaning that the player did not answer within `timeout`.
+    /// Implementors must return by `timeout` : the host cannot interrupt a player, and the whole table waits for them
+    fn act(&mut self, observation : &Observation, timeout : Duration) -> Option<Action>;
+
+    /// Whether the player can still answer. A disconnected player is not asked to act anymore
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+impl<F> PlayerInterface for F where F : FnMut(&Observation, Duration) -> Option<Action> {
+    fn act(&mut self, observation : &Observation, timeout : Duration) -> Option<Action> {
+        self(observation, timeout)
+    }
+}
+
+/// The updates a player is allowed to see : the hands dealt to the other players and the tiles they draw are hidden
+pub fn visible_updates(updates : &[Update], seat : usize) -> Vec<Update> {
+    updates.iter()
+        .map(|update| match update {
+            Update::RoundStarted { round_wind, dealer, honba, riichi_sticks, scores, dora_indicator, hands } => Update::RoundStarted {
+                round_wind : *round_wind,
+                dealer : *dealer,
+                honba : *honba,
+                riichi_sticks : *riichi_sticks,
+                scores : scores.clone(),
+                dora_indicator : *dora_indicator,
+                hands : hands.iter().enumerate().map(|(player, hand)| if player == seat {hand.clone()} else {Vec::new()}).collect(),
+            },
+            Update::Event(Event::Draw { player, .. }) if *player != seat => Update::HiddenDraw{player : *player},
+            update => update.clone(),
+        })
+        .collect()
+}
+
+/// Builds a player to take the seat of a disconnected one
+pub type Replacement = Box<dyn FnMut(usize) -> Box<dyn PlayerInterface>>;
+
+/// Runs a game at a table, asking its players for their actions and telling them what happens.
+/// A player who does not answer within their time, or who is disconnected, gets the default action
+/// of their observation, unless a replacement takes their seat.
+pub struct TableHost {
+    table : Table,
+    players : Vec<Box<dyn PlayerInterface>>,
+    clock : Box<dyn Clock>,
+    time_control : TimeControl,
+    /// The time left in the bank of each player
+    banks : Vec<Duration>,
+    replacement : Option<Replacement>,
+}
+
+impl TableHost {
+
+    // Constructors
+
+    /// Hosts a table with a player for each of its seats, timed with the system clock and `TimeControl::online`
+    pub fn new(table : Table, players : Vec<Box<dyn PlayerInterface>>) -> TableHost {
+        TableHost::with_clock(table, players, Box::new(SystemClock::new()), TimeControl::online())
+    }
+
+    pub fn with_clock(table : Table, players : Vec<Box<dyn PlayerInterface>>, clock : Box<dyn Clock>, time_control : TimeControl) -> TableHost {
+        assert_eq!(table.player_count(), players.len(), "a table host needs a player for each seat");
+        let banks = vec![time_control.bank; players.len()];
+        TableHost{table, players, clock, time_control, banks, replacement : None}
+    }
+
+    // Immutable getters
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    /// The time left in the bank of each player
+    pub fn banks(&self) -> &Vec<Duration> {
+        &self.banks
+    }
+
+    // Mutators
+
+    /// Replaces the players who disconnect by the players built by `replacement` from their seat
+    pub fn set_replacement(&mut self, replacement : impl FnMut(usize) -> Box<dyn PlayerInterface> + 'static) {
+        self.replacement = Some(Box::new(replacement));
+    }
+
+    /// Seats another player, who is told what happens from now on
+    pub fn replace(&mut self, seat : usize, player : Box<dyn PlayerInterface>) {
+        self.players[seat] = player;
+    }
+
+    // Other
+
+    /// Asks the first player the table waits for to act, and tells everyone what happened.
+    /// Does nothing once the game is over.
+    pub fn step(&mut self) -> Result<(), TableError> {
+        let updates = self.table.take_updates();
+        self.broadcast(&updates);
+        let seat = match self.table.waiting_for().first() {
+            Some(seat) => *seat,
+            None => return Ok(()),
+        };
+        if !self.players[seat].is_connected() {
+            if let Some(replacement) = &mut self.replacement {
+                self.players[seat] = replacement(seat);
+            }
+        }
+        let observation = Observation::new(&self.table, seat);
+        // An illegal answer is played like a missing one, so that a faulty player cannot stop the game
+        let action = self.timed_action(seat, &observation)
+            .filter(|action| observation.legal_actions.contains(action))
+            .or_else(|| observation.default_action())
+            .ok_or(TableError::NotYourTurn(seat))?;
+        let updates = self.table.act(seat, action)?;
+        self.broadcast(&updates);
+        Ok(())
+    }
+
+    /// Plays the game until its end and returns its record
+    pub fn run(mut self) -> Result<GameRecord, TableError> {
+        while !self.table.is_over() {
+            self.step()?;
+        }
+        Ok(self.table.finish())
+    }
+
+    /// Asks a player to act within their time, the time beyond the base time being taken from their bank.
+    /// A player who takes too long is not interrupted : their answer is only ignored once it comes.
+    fn timed_action(&mut self, seat : usize, observation : &Observation) -> Option<Action> {
+        let player = &mut self.players[seat];
+        if !player.is_connected() {
+            return None;
+        }
+        let limit = self.time_control.base + self.banks[seat];
+        let start = self.clock.now();
+        let action = player.act(observation, limit);
+        let elapsed = self.clock.now().saturating_sub(start);
+        self.banks[seat] = self.banks[seat].saturating_sub(elapsed.saturating_sub(self.time_control.base));
+        action.filter(|_| elapsed <= limit)
+    }
+
+    fn broadcast(&mut self, updates : &[Update]) {
+        if updates.is_empty() {
+            return;
+        }
+        for (seat, player) in self.players.iter_mut().enumerate() {
+            player.observe(&visible_updates(updates, seat));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::rc::Rc;
+    use std::cell::RefCell;
+
+    use super::{TableHost, PlayerInterface, Observation, visible_updates};
+    use crate::game::clock::{ManualClock, TimeControl};
+    use crate::game::rules::RuleSet;
+    use crate::game::event::Event;
+    use crate::game::action::Action;
+    use crate::game::table::{Table, Update};
+
+    /// Plays the actions of a script while it lasts, then lets the host play for it,
+    /// and records what it is told
+    struct Scripted {
+        script : Vec<Action>,
+        observed : Rc<RefCell<Vec<Update>>>,
+    }
+
+    impl PlayerInterface for Scripted {
+        fn observe(&mut self, updates : &[Update]) {
+            self.observed.borrow_mut().extend_from_slice(updates);
+        }
+
+        fn act(&mut self, observation : &Observation, _timeout : Duration) -> Option<Action> {
+            assert!(!observation.legal_actions.is_empty());
+            if self.script.is_empty() {
+                None
+            } else {
+                Some(self.script.remove(0))
+            }
+        }
+    }
+
+    /// A player who left the table
+    struct Disconnected;
+
+    impl PlayerInterface for Disconnected {
+        fn act(&mut self, _observation : &Observation, _timeout : Duration) -> Option<Action> {
+            panic!("a disconnected player is asked to act");
+        }
+
+        fn is_connected(&self) -> bool {
+            false
+        }
+    }
+
+    fn instant() -> Box<dyn PlayerInterface> {
+        Box::new(|observation : &Observation, _ : Duration| observation.default_action())
+    }
+
+    /// A player who takes `thinking` to play the first discard that is not the drawn tile, and records their timeouts
+    fn slow(clock : &ManualClock, thinking : Duration, timeouts : &Rc<RefCell<Vec<Duration>>>) -> Box<dyn PlayerInterface> {
+        let (clock, timeouts) = (clock.clone(), timeouts.clone());
+        Box::new(move |observation : &Observation, timeout : Duration| {
+            clock.advance(thinking);
+            timeouts.borrow_mut().push(timeout);
+            let drawn = observation.round.as_ref().and_then(|round| round.drawn);
+            observation.legal_actions.iter()
+                .find(|action| matches!(action, Action::Discard { tile, riichi : false } if Some(*tile) != drawn))
+                .cloned()
+                .or_else(|| observation.default_action())
+        })
+    }
+
+    fn table() -> Table {
+        Table::new(5, vec!["A", "B", "C", "D"].into_iter().map(str::to_string).collect(), RuleSet::tenhou()).unwrap()
+    }
+
+    #[test]
+    fn test_default_players() {
+        let players = (0..4).map(|_| Box::new(|_ : &Observation, _ : Duration| None) as Box<dyn PlayerInterface>).collect();
+        let record = TableHost::new(table(), players).run().unwrap();
+        assert!(record.verify().is_ok());
+        assert!(!record.rounds.is_empty());
+    }
+
+    #[test]
+    fn test_observations() {
+        let observed : Vec<_> = (0..4).map(|_| Rc::new(RefCell::new(Vec::new()))).collect();
+        let players = observed.iter()
+            .map(|observed| Box::new(Scripted{script : Vec::new(), observed : observed.clone()}) as Box<dyn PlayerInterface>)
+            .collect();
+        let mut host = TableHost::new(table(), players);
+        for _ in 0..20 {
+            host.step().unwrap();
+        }
+        assert!(matches!(observed[0].borrow()[0], Update::RoundStarted { .. }));
+        for (seat, updates) in observed.iter().enumerate() {
+            // Each player only sees their own draws
+            assert!(updates.borrow().iter().any(|update| matches!(update, Update::Event(Event::Draw { player, .. }) if *player == seat)));
+            assert!(updates.borrow().iter().all(|update| !matches!(update, Update::Event(Event::Draw { player, .. }) if *player != seat)));
+            assert!(updates.borrow().iter().any(|update| matches!(update, Update::HiddenDraw { player } if *player != seat)));
+            match &updates.borrow()[0] {
+                Update::RoundStarted { hands, .. } => assert!(hands.iter().enumerate().all(|(player, hand)| hand.is_empty() != (player == seat))),
+                _ => panic!("expected the start of the round"),
+            }
+            assert!(updates.borrow().iter().any(|update| matches!(update, Update::Event(Event::Discard { .. }))));
+        }
+        assert_eq!(visible_updates(&observed[1].borrow(), 1), *observed[1].borrow());
+    }
+
+    #[test]
+    fn test_illegal_action() {
+        let mut players : Vec<Box<dyn PlayerInterface>> = (0..4).map(|_| Box::new(|_ : &Observation, _ : Duration| None) as Box<dyn PlayerInterface>).collect();
+        let observed = Rc::new(RefCell::new(Vec::new()));
+        players[0] = Box::new(Scripted{script : vec![Action::Ron], observed : observed.clone()});
+        let mut host = TableHost::new(table(), players);
+
+        // The dealer discards their drawn tile instead, and the game goes on
+        assert_eq!(Ok(()), host.step());
+        assert!(observed.borrow().iter().any(|update| matches!(update, Update::Event(Event::Discard { player : 0, tsumogiri : true, .. }))));
+        assert_eq!(Ok(()), host.step());
+        assert_ne!(Some(&0), host.table().waiting_for().first());
+    }
+
+    #[test]
+    fn test_time_bank() {
+        let clock = ManualClock::new();
+        let timeouts = Rc::new(RefCell::new(Vec::new()));
+        let players = vec![slow(&clock, Duration::from_secs(8), &timeouts), instant(), instant(), instant()];
+        let control = TimeControl::new(Duration::from_secs(5), Duration::from_secs(20));
+        let mut host = TableHost::with_clock(table(), players, Box::new(clock), control);
+        while timeouts.borrow().len() < 2 {
+            host.step().unwrap();
+        }
+        assert_eq!(vec![Duration::from_secs(25), Duration::from_secs(22)], *timeouts.borrow());
+        assert_eq!(Duration::from_secs(14), host.banks()[0]);
+        assert_eq!(Duration::from_secs(20), host.banks()[1]);
+    }
+
+    #[test]
+    fn test_late_answer() {
+        let clock = ManualClock::new();
+        let timeouts = Rc::new(RefCell::new(Vec::new()));
+        let observed = Rc::new(RefCell::new(Vec::new()));
+        let players = vec![
+            slow(&clock, Duration::from_secs(30), &timeouts),
+            Box::new(Scripted{script : Vec::new(), observed : observed.clone()}),
+            instant(),
+            instant(),
+        ];
+        let control = TimeControl::new(Duration::from_secs(5), Duration::from_secs(20));
+        let mut host = TableHost::with_clock(table(), players, Box::new(clock), control);
+        host.step().unwrap();
+        // The answer came after 25 seconds, so the drawn tile was discarded instead
+        assert!(observed.borrow().iter().any(|update| matches!(update, Update::Event(Event::Discard { player : 0, tsumogiri : true, .. }))));
+        assert_eq!(Duration::from_secs(0), host.banks()[0]);
+    }
+
+    #[test]
+    fn test_replacement() {
+        let observed = Rc::new(RefCell::new(Vec::new()));
+        let replaced = Rc::new(RefCell::new(Vec::new()));
+        let players = vec![instant(), Box::new(Disconnected), instant(), instant()];
+        let mut host = TableHost::new(table(), players);
+        let (replacement_observed, replacement_seats) = (observed.clone(), replaced.clone());
+        host.set_replacement(move |seat| {
+            replacement_seats.borrow_mut().push(seat);
+            Box::new(Scripted{script : Vec::new(), observed : replacement_observed.clone()})
+        });
+        for _ in 0..20 {
+            host.step().unwrap();
+        }
+        assert_eq!(vec![1], *replaced.borrow());
+        assert!(observed.borrow().iter().any(|update| matches!(update, Update::Event(Event::Draw { player : 1, .. }))));
+    }
+
+    #[test]
+    fn test_disconnected_without_replacement() {
+        let players = vec![instant(), Box::new(Disconnected), instant(), instant()];
+        let mut host = TableHost::new(table(), players);
+        for _ in 0..20 {
+            host.step().unwrap();
+        }
+        // The disconnected player discards the tiles they draw
+        let pond = host.table().round().unwrap().player(1).pond();
+        assert!(!pond.discards().is_empty());
+        assert!(pond.discards().iter().all(|discard| discard.tsumogiri));
+    }
+}
@@ -1 +1,11 @@
-pub mod wind;
\ No newline at end of file
+pub mod wind;
+pub mod rules;
+pub mod rng;
+pub mod wall;
+pub mod pond;
+pub mod event;
+pub mod action;
+pub mod round;
+pub mod table;
+pub mod clock;
+pub mod host;
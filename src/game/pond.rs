@@ -0,0 +1,50 @@
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+
+/// A tile discarded by a player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Discard {
+    pub tile : Tile,
+    /// Whether the discarded tile is the one that was just drawn
+    pub tsumogiri : bool,
+    /// Whether the tile was discarded to declare riichi, it is then displayed sideways
+    pub riichi : bool,
+    /// Whether another player called this tile, it is then removed from the pond
+    pub called : bool,
+}
+
+/// The discards of a player, in order
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pond {
+    discards : Vec<Discard>,
+}
+
+impl Pond {
+
+    pub fn new() -> Pond {
+        Pond{discards : Vec::new()}
+    }
+
+    /// Every tile discarded by the player, including the called ones
+    pub fn discards(&self) -> &Vec<Discard> {
+        &self.discards
+    }
+
+    pub fn push(&mut self, discard : Discard) {
+        self.discards.push(discard);
+    }
+
+    /// Marks the last discard as called by another player
+    pub fn mark_last_called(&mut self) {
+        if let Some(last) = self.discards.last_mut() {
+            last.called = true;
+        }
+    }
+
+    /// Returns whether the player discarded a tile of this value, which makes it safe against them
+    pub fn contains(&self, value : TileValue) -> bool {
+        self.discards.iter().any(|discard| discard.tile.value() == value)
+    }
+}
@@ -0,0 +1,57 @@
+/// Deterministic random number generator (SplitMix64).
+/// A game is fully determined by its seed, so the generator must give the same numbers
+/// on every platform and with every version of the crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state : u64,
+}
+
+impl Rng {
+
+    pub fn new(seed : u64) -> Rng {
+        Rng{state : seed}
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Gets a number in `0..bound`
+    pub fn below(&mut self, bound : usize) -> usize {
+        (((self.next_u64() >> 32) * bound as u64) >> 32) as usize
+    }
+
+    /// Shuffles the slice in place (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, items : &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_deterministic() {
+        let mut rng = Rng::new(1234);
+        // Reference values of SplitMix64
+        assert_eq!(0xe220_a839_7b1d_cdaf, Rng::new(0).next_u64());
+        let first : Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+        let mut again = Rng::new(1234);
+        assert_eq!(first, (0..4).map(|_| again.next_u64()).collect::<Vec<u64>>());
+
+        let mut items : Vec<usize> = (0..20).collect();
+        Rng::new(7).shuffle(&mut items);
+        let mut sorted = items.clone();
+        sorted.sort();
+        assert_eq!((0..20).collect::<Vec<usize>>(), sorted);
+        assert_ne!(sorted, items);
+    }
+}
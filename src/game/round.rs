@@ -0,0 +1,454 @@
+use std::fmt::{Display, Debug};
+
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::hand::Hand;
+use crate::hand::group::Group;
+use crate::hand::meld::Meld;
+use crate::game::wind::Wind;
+use crate::game::rules::RuleSet;
+use crate::game::wall::Wall;
+use crate::game::pond::{Pond, Discard};
+use crate::game::event::Event;
+
+/// Number of tiles dealt to each player at the start of a round
+pub const HAND_SIZE : usize = 13;
+
+/// What the round is waiting for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Phase {
+    /// The player has to draw, unless another player calls the last discard
+    Draw(usize),
+    /// The player holds an extra tile and has to discard it, or declare a kan or a nukidora
+    Discard(usize),
+    /// The player has to draw a replacement tile after a kan or a nukidora
+    Replacement(usize),
+}
+
+/// Reason why an event cannot be applied to a round
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RoundError {
+    /// The player cannot act at this point of the round
+    WrongTurn(usize),
+    TileNotInHand(Tile),
+    EmptyWall,
+    InvalidCall,
+    InvalidRiichi,
+    InvalidKan,
+    InvalidNuki,
+    /// A round cannot start without a dora indicator
+    MissingDoraIndicator,
+}
+
+impl Debug for RoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoundError::WrongTurn(player) => write!(f, "player {} cannot act now", player),
+            RoundError::TileNotInHand(tile) => write!(f, "{:?} is not in the hand", tile),
+            RoundError::EmptyWall => write!(f, "the wall is empty"),
+            RoundError::InvalidCall => write!(f, "invalid call"),
+            RoundError::InvalidRiichi => write!(f, "invalid riichi"),
+            RoundError::InvalidKan => write!(f, "invalid kan"),
+            RoundError::InvalidNuki => write!(f, "invalid nukidora"),
+            RoundError::MissingDoraIndicator => write!(f, "no dora indicator"),
+        }
+    }
+}
+
+impl Display for RoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for RoundError {}
+
+/// What a player has on the table during a round
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerState {
+    hand : Hand,
+    pond : Pond,
+    riichi : bool,
+    /// The tiles the other players discarded since the riichi of the player, who cannot win on them anymore
+    passed : Vec<TileValue>,
+    drawn : Option<Tile>,
+}
+
+impl PlayerState {
+
+    pub fn hand(&self) -> &Hand {
+        &self.hand
+    }
+
+    pub fn pond(&self) -> &Pond {
+        &self.pond
+    }
+
+    pub fn is_riichi(&self) -> bool {
+        self.riichi
+    }
+
+    /// The tiles the other players discarded since the riichi of the player, in order
+    pub fn passed(&self) -> &Vec<TileValue> {
+        &self.passed
+    }
+
+    /// The tile the player just drew, if they did not discard yet
+    pub fn drawn(&self) -> Option<Tile> {
+        self.drawn
+    }
+}
+
+/// State of a round, from the deal to the last discard.
+/// The round does not hold the wall : every drawn tile is given by a `Draw` event,
+/// which allows replaying a round from a log where the order of the wall is unknown.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Round {
+    rules : RuleSet,
+    round_wind : Wind,
+    dealer : usize,
+    honba : u8,
+    riichi_sticks : u8,
+    players : Vec<PlayerState>,
+    dora_indicators : Vec<Tile>,
+    remaining : usize,
+    phase : Phase,
+    last_discard : Option<(usize, Tile)>,
+}
+
+impl Round {
+
+    // Constructors
+
+    /// Starts a round with the dealt `hands`, indexed by player, and the first dora indicator
+    pub fn new(
+        rules : RuleSet,
+        round_wind : Wind,
+        dealer : usize,
+        honba : u8,
+        riichi_sticks : u8,
+        hands : Vec<Vec<Tile>>,
+        dora_indicator : Tile,
+    ) -> Self {
+        let remaining = Wall::tile_set(&rules).len() - 14 - HAND_SIZE * hands.len();
+        let players = hands.into_iter()
+            .map(|tiles| PlayerState {
+                hand : Hand::new(tiles),
+                pond : Pond::new(),
+                riichi : false,
+                passed : Vec::new(),
+                drawn : None,
+            })
+            .collect();
+        Round {
+            rules,
+            round_wind,
+            dealer,
+            honba,
+            riichi_sticks,
+            players,
+            dora_indicators : vec![dora_indicator],
+            remaining,
+            phase : Phase::Draw(dealer),
+            last_discard : None,
+        }
+    }
+
+    // Immutable getters
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn round_wind(&self) -> Wind {
+        self.round_wind
+    }
+
+    pub fn dealer(&self) -> usize {
+        self.dealer
+    }
+
+    pub fn honba(&self) -> u8 {
+        self.honba
+    }
+
+    pub fn riichi_sticks(&self) -> u8 {
+        self.riichi_sticks
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn player(&self, player : usize) -> &PlayerState {
+        &self.players[player]
+    }
+
+    pub fn dora_indicators(&self) -> &Vec<Tile> {
+        &self.dora_indicators
+    }
+
+    /// Number of tiles left in the live wall
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// The last discarded tile and the player who discarded it, as long as it can be called
+    pub fn last_discard(&self) -> Option<(usize, Tile)> {
+        self.last_discard
+    }
+
+    // Other
+
+    /// Gets the seat wind of a player in this round
+    pub fn seat_wind(&self, player : usize) -> Wind {
+        let count = self.players.len();
+        Wind::seats(count as u8)[(player + count - self.dealer) % count]
+    }
+
+    /// Gets the player that plays after `player`
+    pub fn next_player(&self, player : usize) -> usize {
+        (player + 1) % self.players.len()
+    }
+
+    /// Applies an event to the round, after checking that it is valid
+    pub fn apply(&mut self, event : &Event) -> Result<(), RoundError> {
+        if let Some(player) = event.player() {
+            if player >= self.players.len() {
+                return Err(RoundError::WrongTurn(player));
+            }
+        }
+        match *event {
+            Event::Draw { player, tile } => {
+                if self.phase != Phase::Draw(player) && self.phase != Phase::Replacement(player) {
+                    return Err(RoundError::WrongTurn(player));
+                }
+                if self.remaining == 0 {
+                    return Err(RoundError::EmptyWall);
+                }
+                self.remaining -= 1;
+                let state = &mut self.players[player];
+                state.hand.add_tile(tile);
+                state.drawn = Some(tile);
+                self.last_discard = None;
+                self.phase = Phase::Discard(player);
+            },
+            Event::Discard { player, tile, tsumogiri, riichi } => {
+                self.expect_discard_phase(player)?;
+                let state = &mut self.players[player];
+                if riichi && (state.riichi || !state.hand.is_closed()) {
+                    return Err(RoundError::InvalidRiichi);
+                }
+                if !state.hand.remove_tile(tile) {
+                    return Err(RoundError::TileNotInHand(tile));
+                }
+                if riichi {
+                    state.riichi = true;
+                    self.riichi_sticks += 1;
+                }
+                state.drawn = None;
+                state.pond.push(Discard{tile, tsumogiri, riichi, called : false});
+                for (other, state) in self.players.iter_mut().enumerate() {
+                    if other != player && state.riichi {
+                        state.passed.push(tile.value());
+                    }
+                }
+                self.last_discard = Some((player, tile));
+                self.phase = Phase::Draw(self.next_player(player));
+            },
+            Event::Chii { player, from, called, tiles } => {
+                if player != self.next_player(from) || !self.rules.players.allows_chii() {
+                    return Err(RoundError::InvalidCall);
+                }
+                let mut group = [called, tiles[0], tiles[1]];
+                group.sort();
+                if !group[0].followed_by(group[1]) || !group[1].followed_by(group[2]) {
+                    return Err(RoundError::InvalidCall);
+                }
+                self.call(player, from, called, &tiles, Group::Chii(group[0], group[1], group[2]))?;
+                self.phase = Phase::Discard(player);
+            },
+            Event::Pon { player, from, called, tiles } => {
+                if !tiles.iter().all(|tile| tile.value() == called.value()) {
+                    return Err(RoundError::InvalidCall);
+                }
+                self.call(player, from, called, &tiles, Group::Pon(tiles[0], tiles[1], called))?;
+                self.phase = Phase::Discard(player);
+            },
+            Event::OpenKan { player, from, called, tiles } => {
+                if !tiles.iter().all(|tile| tile.value() == called.value()) {
+                    return Err(RoundError::InvalidCall);
+                }
+                self.call(player, from, called, &tiles, Group::Kan(tiles[0], tiles[1], tiles[2], called))?;
+                self.phase = Phase::Replacement(player);
+            },
+            Event::ClosedKan { player, tiles } => {
+                self.expect_discard_phase(player)?;
+                if !tiles.iter().all(|tile| tile.value() == tiles[0].value()) {
+                    return Err(RoundError::InvalidKan);
+                }
+                self.remove_tiles(player, &tiles)?;
+                let state = &mut self.players[player];
+                state.hand.add_closed_kan(Group::Kan(tiles[0], tiles[1], tiles[2], tiles[3]));
+                state.drawn = None;
+                self.phase = Phase::Replacement(player);
+            },
+            Event::AddedKan { player, tile } => {
+                self.expect_discard_phase(player)?;
+                let state = &mut self.players[player];
+                if !state.hand.hidden_tiles().contains(&tile) {
+                    return Err(RoundError::TileNotInHand(tile));
+                }
+                if !state.hand.upgrade_pon(tile) {
+                    return Err(RoundError::InvalidKan);
+                }
+                state.hand.remove_tile(tile);
+                state.drawn = None;
+                self.phase = Phase::Replacement(player);
+            },
+            Event::Nuki { player, tile } => {
+                self.expect_discard_phase(player)?;
+                if !self.rules.players.is_sanma() || tile.value() != TileValue::new_wind(Wind::North) {
+                    return Err(RoundError::InvalidNuki);
+                }
+                let state = &mut self.players[player];
+                if !state.hand.remove_tile(tile) {
+                    return Err(RoundError::TileNotInHand(tile));
+                }
+                state.hand.add_nuki(tile);
+                state.drawn = None;
+                self.phase = Phase::Replacement(player);
+            },
+            Event::NewDora { indicator } => {
+                self.dora_indicators.push(indicator);
+            },
+        }
+        Ok(())
+    }
+
+    fn expect_discard_phase(&self, player : usize) -> Result<(), RoundError> {
+        if self.phase == Phase::Discard(player) {
+            Ok(())
+        } else {
+            Err(RoundError::WrongTurn(player))
+        }
+    }
+
+    fn remove_tiles(&mut self, player : usize, tiles : &[Tile]) -> Result<(), RoundError> {
+        let hand = &self.players[player].hand;
+        if let Some(missing) = tiles.iter().find(|tile| !hand.hidden_tiles().contains(tile)) {
+            return Err(RoundError::TileNotInHand(*missing));
+        }
+        for tile in tiles {
+            self.players[player].hand.remove_tile(*tile);
+        }
+        Ok(())
+    }
+
+    /// Moves the last discard and the given tiles of the hand to a new meld
+    fn call(&mut self, player : usize, from : usize, called : Tile, tiles : &[Tile], group : Group) -> Result<(), RoundError> {
+        if self.last_discard != Some((from, called)) || player == from {
+            return Err(RoundError::InvalidCall);
+        }
+        if self.phase != Phase::Draw(self.next_player(from)) {
+            return Err(RoundError::WrongTurn(player));
+        }
+        self.remove_tiles(player, tiles)?;
+        let source = self.seat_wind(from);
+        self.players[player].hand.add_meld(Meld::new(group, called, source));
+        self.players[from].pond.mark_last_called();
+        self.last_discard = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Round, RoundError, Phase};
+    use crate::tile::Tile;
+    use crate::tile::suit::Suit::{Man, Pin};
+    use crate::game::wind::Wind::{East, West};
+    use crate::game::rules::RuleSet;
+    use crate::game::event::Event;
+
+    fn round() -> Round {
+        let hands = (0..4)
+            .map(|player| (1..=9).map(|value| Tile::new_suited(Man, value, player)).chain(
+                (1..=4).map(|value| Tile::new_suited(Pin, value, player))
+            ).collect())
+            .collect();
+        Round::new(RuleSet::default(), East, 0, 0, 0, hands, Tile::new_suited(Pin, 9, 0))
+    }
+
+    #[test]
+    fn test_draw_and_discard() {
+        let mut round = round();
+        let tile = Tile::new_suited(Pin, 5, 0);
+        round.apply(&Event::Draw{player : 0, tile}).unwrap();
+        assert_eq!(69, round.remaining());
+        assert_eq!(Some(tile), round.player(0).drawn());
+
+        let wrong = Event::Discard{player : 1, tile, tsumogiri : true, riichi : false};
+        assert_eq!(Err(RoundError::WrongTurn(1)), round.apply(&wrong));
+
+        round.apply(&Event::Discard{player : 0, tile, tsumogiri : true, riichi : true}).unwrap();
+        assert!(round.player(0).is_riichi());
+        assert_eq!(1, round.riichi_sticks());
+        assert_eq!(Phase::Draw(1), round.phase());
+
+        // The riichi player cannot win anymore on the tiles the others discard
+        let passed = Tile::new_suited(Pin, 6, 1);
+        round.apply(&Event::Draw{player : 1, tile : passed}).unwrap();
+        round.apply(&Event::Discard{player : 1, tile : passed, tsumogiri : true, riichi : false}).unwrap();
+        assert_eq!(&vec![passed.value()], round.player(0).passed());
+        assert!(round.player(1).passed().is_empty());
+    }
+
+    #[test]
+    fn test_calls() {
+        let mut round = round();
+        let tile = Tile::new_suited(Pin, 3, 3);
+        round.apply(&Event::Draw{player : 0, tile : Tile::new_suited(Pin, 3, 2)}).unwrap();
+        round.apply(&Event::Discard{player : 0, tile : Tile::new_suited(Man, 9, 0), tsumogiri : false, riichi : false}).unwrap();
+
+        // Chii is only possible for the next player
+        let chii = Event::Chii{
+            player : 2,
+            from : 0,
+            called : Tile::new_suited(Man, 9, 0),
+            tiles : [Tile::new_suited(Man, 7, 2), Tile::new_suited(Man, 8, 2)],
+        };
+        assert_eq!(Err(RoundError::InvalidCall), round.apply(&chii));
+
+        let pon = Event::Pon{
+            player : 2,
+            from : 0,
+            called : Tile::new_suited(Man, 9, 0),
+            tiles : [Tile::new_suited(Man, 9, 2), Tile::new_suited(Man, 9, 1)],
+        };
+        assert_eq!(Err(RoundError::TileNotInHand(Tile::new_suited(Man, 9, 1))), round.apply(&pon));
+
+        let chii = Event::Chii{
+            player : 1,
+            from : 0,
+            called : Tile::new_suited(Man, 9, 0),
+            tiles : [Tile::new_suited(Man, 7, 1), Tile::new_suited(Man, 8, 1)],
+        };
+        round.apply(&chii).unwrap();
+        assert_eq!(1, round.player(1).hand().melds().len());
+        assert_eq!(East, round.player(1).hand().melds()[0].source());
+        assert!(round.player(0).pond().discards()[0].called);
+        assert_eq!(Phase::Discard(1), round.phase());
+
+        round.apply(&Event::Discard{player : 1, tile : Tile::new_suited(Pin, 4, 1), tsumogiri : false, riichi : false}).unwrap();
+        assert_eq!(West, round.seat_wind(2));
+        assert_eq!(Err(RoundError::WrongTurn(0)), round.apply(&Event::Draw{player : 0, tile}));
+    }
+}
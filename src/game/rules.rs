@@ -0,0 +1,323 @@
+use std::fmt::{Display, Debug};
+
+/// Number of red fives (aka dora) in each suit.
+/// The red fives are the copies of the five whose id is lower than the count of their suit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RedFives {
+    pub man : u8,
+    pub pin : u8,
+    pub sou : u8,
+}
+
+impl RedFives {
+
+    pub fn none() -> RedFives {
+        RedFives{man : 0, pin : 0, sou : 0}
+    }
+
+    /// One red five in each suit, as played online
+    pub fn one_each() -> RedFives {
+        RedFives{man : 1, pin : 1, sou : 1}
+    }
+
+    pub fn total(&self) -> u8 {
+        self.man + self.pin + self.sou
+    }
+}
+
+/// What happens when several players can ron on the same discard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MultipleRon {
+    /// Only the first player in turn order after the discarder wins (atamahane)
+    HeadBump,
+    /// Two players can win on the same tile, a third ron aborts the hand (sanchahou)
+    DoubleRon,
+    /// Every player that can ron wins
+    All,
+}
+
+/// Length of a game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RoundLength {
+    /// East round only
+    Tonpuusen,
+    /// East and south rounds
+    Hanchan,
+}
+
+/// How a tsumo is paid in a three player game, where the north seat is empty
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SanmaTsumo {
+    /// The share of the missing player is not paid (tsumo-son)
+    TsumoSon,
+    /// The share of the missing player is split between the two other players
+    Split,
+}
+
+/// Number of players at the table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Players {
+    Four,
+    /// Three player mahjong (sanma) : the manzu tiles from 2 to 8 are removed,
+    /// north winds are set aside as dora (nukidora) and chii is not allowed
+    Three(SanmaTsumo),
+}
+
+impl Players {
+
+    pub fn count(&self) -> u8 {
+        match self {
+            Players::Four     => 4,
+            Players::Three(_) => 3,
+        }
+    }
+
+    pub fn is_sanma(&self) -> bool {
+        matches!(self, Players::Three(_))
+    }
+
+    pub fn allows_chii(&self) -> bool {
+        !self.is_sanma()
+    }
+}
+
+/// Reason why a set of rules cannot be played
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RuleError {
+    /// The uma does not give exactly one bonus to each player
+    UmaLength(usize),
+}
+
+impl Debug for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::UmaLength(length) => write!(f, "the uma has {} placements instead of one per player", length),
+        }
+    }
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Set of rules the game is played with.
+/// Every club and online platform has its own variant of riichi mahjong,
+/// presets are given for the most common ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleSet {
+    pub players : Players,
+    pub red_fives : RedFives,
+    /// Whether tanyao is valid with an open hand
+    pub kuitan : bool,
+    /// Whether a player may win on a tile that gives a yaku when another one of their waits would give none.
+    /// Without atozuke, every wait of the hand needs a yaku
+    pub atozuke : bool,
+    pub multiple_ron : MultipleRon,
+    /// Whether 4 han 30 fu and 3 han 60 fu hands are rounded up to mangan
+    pub kiriage_mangan : bool,
+    /// Whether several yakuman (and double yakuman) can be cumulated
+    pub multiple_yakuman : bool,
+    pub starting_points : i32,
+    /// Points against which the final scores are compared; the difference with the starting points makes the oka
+    pub return_points : i32,
+    /// Placement bonus in points, from first to last player
+    pub uma : Vec<i32>,
+    /// Whether kyuushu kyuuhai, suufon renda, suucha riichi and suukaikan are used
+    pub abortive_draws : bool,
+    /// Whether the game ends when a player goes below zero points
+    pub tobi : bool,
+    pub round_length : RoundLength,
+}
+
+impl RuleSet {
+
+    // Presets
+
+    /// Rules of the World Riichi Championship
+    pub fn wrc() -> RuleSet {
+        RuleSet {
+            players : Players::Four,
+            red_fives : RedFives::none(),
+            kuitan : true,
+            atozuke : true,
+            multiple_ron : MultipleRon::HeadBump,
+            kiriage_mangan : true,
+            multiple_yakuman : false,
+            starting_points : 30000,
+            return_points : 30000,
+            uma : vec![15000, 5000, -5000, -15000],
+            abortive_draws : false,
+            tobi : false,
+            round_length : RoundLength::Hanchan,
+        }
+    }
+
+    /// Rules of the European Mahjong Association
+    pub fn ema() -> RuleSet {
+        RuleSet {
+            players : Players::Four,
+            red_fives : RedFives::none(),
+            kuitan : true,
+            atozuke : true,
+            multiple_ron : MultipleRon::All,
+            kiriage_mangan : false,
+            multiple_yakuman : false,
+            starting_points : 30000,
+            return_points : 30000,
+            uma : vec![15000, 5000, -5000, -15000],
+            abortive_draws : false,
+            tobi : false,
+            round_length : RoundLength::Hanchan,
+        }
+    }
+
+    /// Rules of the Mahjong Soul ranked games
+    pub fn mahjong_soul() -> RuleSet {
+        RuleSet {
+            players : Players::Four,
+            red_fives : RedFives::one_each(),
+            kuitan : true,
+            atozuke : true,
+            multiple_ron : MultipleRon::DoubleRon,
+            kiriage_mangan : false,
+            multiple_yakuman : true,
+            starting_points : 25000,
+            return_points : 25000,
+            uma : vec![15000, 5000, -5000, -15000],
+            abortive_draws : true,
+            tobi : true,
+            round_length : RoundLength::Hanchan,
+        }
+    }
+
+    /// Rules of the Tenhou ranked games
+    pub fn tenhou() -> RuleSet {
+        RuleSet {
+            players : Players::Four,
+            red_fives : RedFives::one_each(),
+            kuitan : true,
+            atozuke : true,
+            multiple_ron : MultipleRon::DoubleRon,
+            kiriage_mangan : false,
+            multiple_yakuman : true,
+            starting_points : 25000,
+            return_points : 30000,
+            uma : vec![20000, 10000, -10000, -20000],
+            abortive_draws : true,
+            tobi : true,
+            round_length : RoundLength::Hanchan,
+        }
+    }
+
+    /// Rules of the Mahjong Soul three player ranked games
+    pub fn mahjong_soul_sanma() -> RuleSet {
+        RuleSet {
+            players : Players::Three(SanmaTsumo::TsumoSon),
+            red_fives : RedFives{man : 0, pin : 1, sou : 1},
+            kuitan : true,
+            atozuke : true,
+            multiple_ron : MultipleRon::DoubleRon,
+            kiriage_mangan : false,
+            multiple_yakuman : true,
+            starting_points : 35000,
+            return_points : 35000,
+            uma : vec![15000, 0, -15000],
+            abortive_draws : true,
+            tobi : true,
+            round_length : RoundLength::Hanchan,
+        }
+    }
+
+    /// Names of the presets, see `preset`
+    pub const PRESETS : [&'static str; 5] = ["mahjongsoul", "tenhou", "sanma", "wrc", "ema"];
+
+    /// The preset of this name : `"mahjongsoul"`, `"tenhou"`, `"sanma"` (Mahjong Soul three player), `"wrc"` or `"ema"`
+    pub fn preset(name : &str) -> Option<RuleSet> {
+        match name {
+            "mahjongsoul" => Some(RuleSet::mahjong_soul()),
+            "tenhou" => Some(RuleSet::tenhou()),
+            "sanma" => Some(RuleSet::mahjong_soul_sanma()),
+            "wrc" => Some(RuleSet::wrc()),
+            "ema" => Some(RuleSet::ema()),
+            _ => None,
+        }
+    }
+
+    // Other
+
+    /// Checks that the rules can be played, the presets always being valid
+    pub fn validate(&self) -> Result<(), RuleError> {
+        if self.uma.len() != self.players.count() as usize {
+            return Err(RuleError::UmaLength(self.uma.len()));
+        }
+        Ok(())
+    }
+
+    /// Bonus given to the first player, paid by the difference between the starting and return points of every player
+    pub fn oka(&self) -> i32 {
+        (self.return_points - self.starting_points) * self.players.count() as i32
+    }
+
+    /// The result of a player with the final score `score` at the rank `rank`, from 0 for the first :
+    /// the score minus the return points, plus the uma of the rank and the oka for the first player
+    pub fn placement_points(&self, score : i32, rank : usize) -> i32 {
+        let oka = if rank == 0 {self.oka()} else {0};
+        score - self.return_points + self.uma.get(rank).copied().unwrap_or_default() + oka
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::mahjong_soul()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RuleSet, RuleError};
+
+    #[test]
+    fn test_oka() {
+        assert_eq!(0, RuleSet::wrc().oka());
+        assert_eq!(20000, RuleSet::tenhou().oka());
+        // 42000 points at the first place : 12000 above the return points, the uma and the oka
+        let tenhou = RuleSet::tenhou();
+        assert_eq!(12000 + 20000 + 20000, tenhou.placement_points(42000, 0));
+        assert_eq!(-12000 - 20000, tenhou.placement_points(18000, 3));
+    }
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(0, RuleSet::ema().red_fives.total());
+        assert_eq!(3, RuleSet::mahjong_soul().red_fives.total());
+        assert!(RuleSet::wrc().kiriage_mangan);
+        assert_ne!(RuleSet::tenhou(), RuleSet::mahjong_soul());
+        assert!(RuleSet::PRESETS.iter().all(|name| RuleSet::preset(name).is_some_and(|rules| rules.validate().is_ok())));
+        assert_eq!(Some(RuleSet::wrc()), RuleSet::preset("wrc"));
+        assert_eq!(None, RuleSet::preset("chess"));
+    }
+
+    #[test]
+    fn test_sanma() {
+        let rules = RuleSet::mahjong_soul_sanma();
+        assert_eq!(3, rules.players.count());
+        assert_eq!(3, rules.uma.len());
+        assert!(!rules.players.allows_chii());
+        assert!(RuleSet::wrc().players.allows_chii());
+
+        let mut rules = RuleSet::mahjong_soul_sanma();
+        assert_eq!(Ok(()), rules.validate());
+        rules.uma = RuleSet::mahjong_soul().uma;
+        assert_eq!(Err(RuleError::UmaLength(4)), rules.validate());
+    }
+}
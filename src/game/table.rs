@@ -0,0 +1,967 @@
+use std::fmt::{Display, Debug};
+
+use crate::tile::Tile;
+use crate::hand::Hand;
+use crate::hand::group::Group;
+use crate::hand::shanten::{is_tenpai, waits};
+use crate::game::wind::Wind;
+use crate::game::rules::{RuleSet, RuleError, MultipleRon, RoundLength};
+use crate::game::event::Event;
+use crate::game::action::Action;
+use crate::game::round::{Round, RoundError, Phase};
+use crate::record::{RoundResult, WinRecord, AbortiveDraw};
+use crate::record::game::{GameRecorder, GameRecord};
+use crate::scoring::yaku::WinContext;
+use crate::scoring::hand_value::{evaluate, HandValue};
+use crate::scoring::payment::{ron_payment, tsumo_payments};
+
+/// Points paid to declare riichi
+pub const RIICHI_DEPOSIT : i32 = 1000;
+
+/// Something the players of a table are told about
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Update {
+    /// A new round is dealt. `hands` are the dealt hands by seat, which are only meant to be shown to their owners
+    RoundStarted { round_wind : Wind, dealer : usize, honba : u8, riichi_sticks : u8, scores : Vec<i32>, dora_indicator : Tile, hands : Vec<Vec<Tile>> },
+    /// An event of the round. A drawn tile is only meant to be shown to the player who drew it
+    Event(Event),
+    /// Another player drew a tile, which is what the players see of a draw that is not theirs
+    HiddenDraw { player : usize },
+    /// A riichi declaration is accepted and its deposit paid
+    RiichiAccepted { player : usize },
+    /// The round is over, `scores` being the scores after the payments
+    RoundEnded { result : RoundResult, scores : Vec<i32> },
+    /// The game is over, the remaining riichi deposits going to the first player
+    GameEnded { scores : Vec<i32> },
+}
+
+/// Reason why a player cannot do an action at a table
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TableError {
+    /// The player does not have to decide anything now
+    NotYourTurn(usize),
+    /// The action is not one of the legal actions of the player
+    IllegalAction(Action),
+    GameOver,
+    /// The rules given to the table cannot be played
+    Rules(RuleError),
+    /// The round refused an event built by the table, which is a bug
+    Round(RoundError),
+}
+
+impl Debug for TableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableError::NotYourTurn(player) => write!(f, "player {} cannot act now", player),
+            TableError::IllegalAction(action) => write!(f, "{:?} is not allowed now", action),
+            TableError::GameOver => write!(f, "the game is over"),
+            TableError::Rules(error) => write!(f, "invalid rules : {}", error),
+            TableError::Round(error) => write!(f, "{:?}", error),
+        }
+    }
+}
+
+impl Display for TableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<RuleError> for TableError {
+    fn from(error : RuleError) -> Self {
+        TableError::Rules(error)
+    }
+}
+
+impl From<RoundError> for TableError {
+    fn from(error : RoundError) -> Self {
+        TableError::Round(error)
+    }
+}
+
+/// What a player has done during the round that matters for the yaku and the furiten
+#[derive(Clone, Copy, Debug, Default)]
+struct PlayerFlags {
+    double_riichi : bool,
+    ippatsu : bool,
+    /// A riichi declared on the tile being called, the deposit being paid if nobody wins on it
+    declaring : bool,
+    /// The player let a winning tile go since their last discard
+    temporary_furiten : bool,
+    /// The player let a winning tile go after declaring riichi
+    riichi_furiten : bool,
+}
+
+/// A tile the other players may call
+#[derive(Clone, Debug)]
+struct Calls {
+    from : usize,
+    tile : Tile,
+    /// Whether the tile is added to a pon, in which case it can only be robbed by a ron (chankan)
+    added_kan : bool,
+    /// The actions of each player, empty for the players that cannot call
+    options : Vec<Vec<Action>>,
+    responses : Vec<Option<Action>>,
+}
+
+/// What the table is waiting for
+#[derive(Clone, Debug)]
+enum Stage {
+    /// The player drew or called and has to discard or declare something
+    Turn(usize),
+    Calls(Calls),
+    Over,
+}
+
+/// Plays a whole game : deals the rounds, tells the players what they can do,
+/// settles the calls and the wins and keeps the scores.
+/// The table only moves forward when the players it waits for act, see `waiting_for` and `act`.
+pub struct Table {
+    recorder : GameRecorder,
+    rules : RuleSet,
+    scores : Vec<i32>,
+    /// Number of rounds played without counting the repeats, giving the dealer and the round wind
+    hand : usize,
+    honba : u8,
+    riichi_sticks : u8,
+    stage : Stage,
+    flags : Vec<PlayerFlags>,
+    /// Whether a call or a kan happened during the round, which ends the first turn
+    interrupted : bool,
+    /// The player who declared each kan of the round
+    kans : Vec<usize>,
+    /// Whether a kan dora is to be revealed after the next discard, for an open or added kan
+    pending_dora : bool,
+    /// Whether the last tile drawn comes from the dead wall
+    rinshan : bool,
+    updates : Vec<Update>,
+}
+
+impl Table {
+
+    // Constructors
+
+    /// Seats the players and deals the first round, the first player being the first dealer
+    pub fn new(seed : u64, names : Vec<String>, rules : RuleSet) -> Result<Table, TableError> {
+        rules.validate()?;
+        let players = names.len();
+        let mut table = Table {
+            recorder : GameRecorder::new(seed, names, rules.clone()),
+            scores : vec![rules.starting_points; players],
+            rules,
+            hand : 0,
+            honba : 0,
+            riichi_sticks : 0,
+            stage : Stage::Over,
+            flags : vec![PlayerFlags::default(); players],
+            interrupted : false,
+            kans : Vec::new(),
+            pending_dora : false,
+            rinshan : false,
+            updates : Vec::new(),
+        };
+        table.start_round();
+        Ok(table)
+    }
+
+    // Immutable getters
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn names(&self) -> &Vec<String> {
+        &self.recorder.record().names
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn scores(&self) -> &Vec<i32> {
+        &self.scores
+    }
+
+    pub fn honba(&self) -> u8 {
+        self.honba
+    }
+
+    /// Riichi deposits on the table
+    pub fn riichi_sticks(&self) -> u8 {
+        self.riichi_sticks
+    }
+
+    /// The round being played, `None` once the game is over
+    pub fn round(&self) -> Option<&Round> {
+        self.recorder.round()
+    }
+
+    /// Number of tiles left in the live wall
+    pub fn remaining(&self) -> usize {
+        self.recorder.remaining()
+    }
+
+    /// Everything played so far
+    pub fn record(&self) -> &GameRecord {
+        self.recorder.record()
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self.stage, Stage::Over)
+    }
+
+    /// The players that have to act before the game goes on
+    pub fn waiting_for(&self) -> Vec<usize> {
+        match &self.stage {
+            Stage::Turn(player) => vec![*player],
+            Stage::Calls(calls) => (0..self.player_count())
+                .filter(|player| !calls.options[*player].is_empty() && calls.responses[*player].is_none())
+                .collect(),
+            Stage::Over => Vec::new(),
+        }
+    }
+
+    /// Every action the player can do now, empty if the table is not waiting for them
+    pub fn legal_actions(&self, player : usize) -> Vec<Action> {
+        match &self.stage {
+            Stage::Turn(current) if *current == player => self.turn_actions(player),
+            Stage::Calls(calls) if calls.responses.get(player) == Some(&None) => calls.options[player].clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    // Other
+
+    /// Does the action of a player and goes on until some player has to decide something.
+    /// Returns everything that happened in the meantime.
+    pub fn act(&mut self, player : usize, action : Action) -> Result<Vec<Update>, TableError> {
+        if self.is_over() {
+            return Err(TableError::GameOver);
+        }
+        if !self.waiting_for().contains(&player) {
+            return Err(TableError::NotYourTurn(player));
+        }
+        if !self.legal_actions(player).contains(&action) {
+            return Err(TableError::IllegalAction(action));
+        }
+        match &mut self.stage {
+            Stage::Turn(_) => self.play_turn(player, action)?,
+            Stage::Calls(calls) => {
+                calls.responses[player] = Some(action);
+                if self.waiting_for().is_empty() {
+                    self.resolve_calls()?;
+                }
+            },
+            Stage::Over => (),
+        }
+        Ok(self.updates.drain(..).collect())
+    }
+
+    /// Everything that happened since the table was created or since the last action, like the first deal
+    pub fn take_updates(&mut self) -> Vec<Update> {
+        self.updates.drain(..).collect()
+    }
+
+    /// Ends the game and returns its record
+    pub fn finish(self) -> GameRecord {
+        self.recorder.finish()
+    }
+
+    /// The round, which exists as long as the game is not over
+    fn current(&self) -> &Round {
+        self.recorder.round().expect("no round is being played")
+    }
+
+    fn start_round(&mut self) {
+        let players = self.player_count();
+        let round_wind = Wind::seats(4)[(self.hand / players).min(3)];
+        let dealer = self.hand % players;
+        self.recorder.start_round(round_wind, dealer, self.honba, self.riichi_sticks, self.scores.clone());
+        self.flags = vec![PlayerFlags::default(); players];
+        self.interrupted = false;
+        self.kans.clear();
+        self.pending_dora = false;
+        self.rinshan = false;
+        let round = self.current();
+        let dora_indicator = round.dora_indicators()[0];
+        let hands = (0..players).map(|player| round.player(player).hand().hidden_tiles().clone()).collect();
+        self.updates.push(Update::RoundStarted {
+            round_wind,
+            dealer,
+            honba : self.honba,
+            riichi_sticks : self.riichi_sticks,
+            scores : self.scores.clone(),
+            dora_indicator,
+            hands,
+        });
+        self.draw(false);
+    }
+
+    /// Draws a tile for the player whose turn it is
+    fn draw(&mut self, replacement : bool) {
+        let round = self.current();
+        let player = match round.phase() {
+            Phase::Draw(player) | Phase::Replacement(player) => player,
+            Phase::Discard(player) => player,
+        };
+        let tile = self.recorder.draw().expect("the wall is not empty");
+        self.rinshan = replacement;
+        self.updates.push(Update::Event(Event::Draw{player, tile}));
+        self.stage = Stage::Turn(player);
+    }
+
+    fn reveal_dora(&mut self) {
+        if let Ok(indicator) = self.recorder.reveal_dora() {
+            self.updates.push(Update::Event(Event::NewDora{indicator}));
+        }
+    }
+
+    fn apply(&mut self, event : Event) -> Result<(), TableError> {
+        self.recorder.act(&event)?;
+        self.updates.push(Update::Event(event));
+        Ok(())
+    }
+
+    /// Keeps a tile of each value and color among the tiles
+    fn distinct(&self, tiles : impl IntoIterator<Item = Tile>) -> Vec<Tile> {
+        let red_fives = self.rules.red_fives;
+        let mut kept : Vec<Tile> = Vec::new();
+        for tile in tiles {
+            if !kept.iter().any(|other| other.value() == tile.value() && other.is_red(red_fives) == tile.is_red(red_fives)) {
+                kept.push(tile);
+            }
+        }
+        kept
+    }
+
+    /// The ways to take `size` tiles among the tiles, the copies of a value only differing by their color
+    fn combinations(&self, tiles : &[Tile], size : usize) -> Vec<Vec<Tile>> {
+        if size == 0 {
+            return vec![Vec::new()];
+        }
+        let red_fives = self.rules.red_fives;
+        let mut found : Vec<Vec<Tile>> = Vec::new();
+        for (i, tile) in tiles.iter().enumerate() {
+            for mut rest in self.combinations(&tiles[i + 1..], size - 1) {
+                rest.insert(0, *tile);
+                let colors = |tiles : &[Tile]| {
+                    let mut colors : Vec<bool> = tiles.iter().map(|tile| tile.is_red(red_fives)).collect();
+                    colors.sort();
+                    colors
+                };
+                if !found.iter().any(|other| colors(other) == colors(&rest)) {
+                    found.push(rest);
+                }
+            }
+        }
+        found
+    }
+
+    fn kan_count(&self) -> usize {
+        self.kans.len()
+    }
+
+    fn melds(hand : &Hand) -> usize {
+        hand.melds().len() + hand.closed_kans().len()
+    }
+
+    /// Whether the player has not discarded yet and nobody called or declared a kan
+    fn first_turn(&self, player : usize) -> bool {
+        !self.interrupted && self.current().player(player).pond().discards().is_empty()
+    }
+
+    /// Value of the hand of the player completed by the tile, if it is a valid win
+    fn win_value(&self, player : usize, tile : Tile, tsumo : bool, chankan : bool) -> Option<HandValue> {
+        let round = self.current();
+        let state = round.player(player);
+        let mut hand = state.hand().clone();
+        if !tsumo {
+            hand.add_tile(tile);
+        }
+        let flags = self.flags[player];
+        let context = WinContext {
+            winning_tile : tile,
+            tsumo,
+            riichi : state.is_riichi(),
+            double_riichi : flags.double_riichi,
+            ippatsu : flags.ippatsu,
+            seat_wind : round.seat_wind(player),
+            round_wind : round.round_wind(),
+            last_tile : round.remaining() == 0 && !self.rinshan,
+            rinshan : tsumo && self.rinshan,
+            chankan,
+            first_draw : tsumo && self.first_turn(player),
+        };
+        evaluate(&hand, &context, round.dora_indicators(), self.recorder.ura_dora_indicators(), &self.rules)
+    }
+
+    /// Whether the player cannot ron because they discarded or let go one of their waits
+    fn is_furiten(&self, player : usize) -> bool {
+        let flags = self.flags[player];
+        if flags.temporary_furiten || flags.riichi_furiten {
+            return true;
+        }
+        let state = self.current().player(player);
+        let hand = state.hand();
+        waits(&hand.tile_counts(), Table::melds(hand)).into_iter().any(|value| state.pond().contains(value))
+    }
+
+    /// The actions of the player whose turn it is
+    fn turn_actions(&self, player : usize) -> Vec<Action> {
+        let round = self.current();
+        let state = round.player(player);
+        let hand = state.hand();
+        let hidden = hand.hidden_tiles();
+        let mut actions = Vec::new();
+
+        if let Some(drawn) = state.drawn() {
+            if self.win_value(player, drawn, true, false).is_some() {
+                actions.push(Action::Tsumo);
+            }
+            if self.recorder.remaining() > 0 && self.kan_count() < 4 {
+                for tile in self.distinct(hidden.iter().cloned()) {
+                    let copies : Vec<Tile> = hidden.iter().filter(|other| other.value() == tile.value()).cloned().collect();
+                    if copies.len() == 4 && (!state.is_riichi() || self.keeps_waits(hand, drawn, &copies)) {
+                        actions.push(Action::ClosedKan{tiles : [copies[0], copies[1], copies[2], copies[3]]});
+                    }
+                }
+                for tile in self.distinct(hidden.iter().cloned()) {
+                    let pon = hand.melds().iter().any(|meld| matches!(meld.group(), Group::Pon(x, _, _) if x.value() == tile.value()));
+                    if pon && !state.is_riichi() {
+                        actions.push(Action::AddedKan{tile});
+                    }
+                }
+            }
+            if self.rules.players.is_sanma() && self.recorder.remaining() > 0 {
+                if let Some(north) = hidden.iter().find(|tile| tile.value() == crate::tile::tile_value::TileValue::new_wind(Wind::North)) {
+                    actions.push(Action::Nuki{tile : *north});
+                }
+            }
+        }
+
+        let discards = match state.drawn() {
+            Some(drawn) if state.is_riichi() => vec![drawn],
+            drawn => {
+                let mut tiles = self.distinct(drawn.into_iter().chain(hidden.iter().cloned()));
+                tiles.sort();
+                tiles
+            },
+        };
+        actions.extend(discards.iter().map(|tile| Action::Discard{tile : *tile, riichi : false}));
+
+        let can_riichi = !state.is_riichi()
+            && hand.is_closed()
+            && self.scores[player] >= RIICHI_DEPOSIT
+            && self.recorder.remaining() >= self.player_count();
+        if can_riichi {
+            for tile in discards.iter() {
+                let mut counts = hand.tile_counts();
+                counts.remove(tile.value());
+                if is_tenpai(&counts, Table::melds(hand)) {
+                    actions.push(Action::Discard{tile : *tile, riichi : true});
+                }
+            }
+        }
+
+        if self.rules.abortive_draws && state.drawn().is_some() && self.first_turn(player) {
+            let terminals = hand.tile_counts().distinct().filter(|value| value.is_yaochuu()).count();
+            if terminals >= 9 {
+                actions.push(Action::NineTerminals);
+            }
+        }
+        actions
+    }
+
+    /// Whether a closed kan declared in riichi keeps the waits of the hand, which is required
+    fn keeps_waits(&self, hand : &Hand, drawn : Tile, copies : &[Tile]) -> bool {
+        if !copies.contains(&drawn) {
+            return false;
+        }
+        let melds = Table::melds(hand);
+        let mut before = hand.tile_counts();
+        before.remove(drawn.value());
+        let mut after = hand.tile_counts();
+        copies.iter().for_each(|tile| { after.remove(tile.value()); });
+        waits(&before, melds) == waits(&after, melds + 1)
+    }
+
+    /// The calls every other player can make on the tile
+    fn call_options(&self, from : usize, tile : Tile, added_kan : bool) -> Vec<Vec<Action>> {
+        let round = self.current();
+        let mut options = vec![Vec::new(); self.player_count()];
+        for (player, actions) in options.iter_mut().enumerate().filter(|(player, _)| *player != from) {
+            let state = round.player(player);
+            if self.win_value(player, tile, false, added_kan).is_some() && !self.is_furiten(player) {
+                actions.push(Action::Ron);
+            }
+            if !added_kan && !state.is_riichi() && self.recorder.remaining() > 0 {
+                let hidden = state.hand().hidden_tiles();
+                let same : Vec<Tile> = hidden.iter().filter(|other| other.value() == tile.value()).cloned().collect();
+                for tiles in self.combinations(&same, 2) {
+                    actions.push(Action::Pon{tiles : [tiles[0], tiles[1]]});
+                }
+                if same.len() == 3 && self.kan_count() < 4 {
+                    actions.push(Action::OpenKan{tiles : [same[0], same[1], same[2]]});
+                }
+                if player == round.next_player(from) && self.rules.players.allows_chii() {
+                    actions.extend(self.chii_options(hidden, tile));
+                }
+            }
+            if !actions.is_empty() {
+                actions.push(Action::Pass);
+            }
+        }
+        options
+    }
+
+    fn chii_options(&self, hidden : &[Tile], tile : Tile) -> Vec<Action> {
+        let value = tile.value();
+        let shapes = [
+            (value.prev().and_then(|prev| prev.prev()), value.prev()),
+            (value.prev(), value.next()),
+            (value.next(), value.next().and_then(|next| next.next())),
+        ];
+        let mut actions = Vec::new();
+        for (first, second) in shapes.iter() {
+            let (first, second) = match (first, second) {
+                (Some(first), Some(second)) => (*first, *second),
+                _ => continue,
+            };
+            let firsts = self.distinct(hidden.iter().filter(|tile| tile.value() == first).cloned());
+            let seconds = self.distinct(hidden.iter().filter(|tile| tile.value() == second).cloned());
+            for x in firsts.iter() {
+                for y in seconds.iter() {
+                    actions.push(Action::Chii{tiles : [*x, *y]});
+                }
+            }
+        }
+        actions
+    }
+
+    fn play_turn(&mut self, player : usize, action : Action) -> Result<(), TableError> {
+        match action {
+            Action::Discard { tile, riichi } => {
+                let tsumogiri = self.current().player(player).drawn() == Some(tile);
+                self.apply(Event::Discard{player, tile, tsumogiri, riichi})?;
+                let first = !self.interrupted && self.current().player(player).pond().discards().len() == 1;
+                let flags = &mut self.flags[player];
+                flags.ippatsu = false;
+                flags.temporary_furiten = false;
+                if riichi {
+                    flags.declaring = true;
+                    flags.double_riichi = first;
+                }
+                self.rinshan = false;
+                if self.pending_dora {
+                    self.pending_dora = false;
+                    self.reveal_dora();
+                }
+                self.open_calls(player, tile, false)
+            },
+            Action::Tsumo => {
+                let tile = self.current().player(player).drawn().expect("a tsumo follows a draw");
+                self.win(&[player], None, tile, false);
+                Ok(())
+            },
+            Action::ClosedKan { tiles } => {
+                self.apply(Event::ClosedKan{player, tiles})?;
+                self.declared_kan(player);
+                self.reveal_dora();
+                self.draw(true);
+                Ok(())
+            },
+            Action::AddedKan { tile } => {
+                self.apply(Event::AddedKan{player, tile})?;
+                self.declared_kan(player);
+                if self.pending_dora {
+                    self.reveal_dora();
+                }
+                self.pending_dora = true;
+                self.open_calls(player, tile, true)
+            },
+            Action::Nuki { tile } => {
+                self.apply(Event::Nuki{player, tile})?;
+                self.draw(true);
+                Ok(())
+            },
+            Action::NineTerminals => {
+                self.end_round(RoundResult::AbortiveDraw(AbortiveDraw::NineTerminals));
+                Ok(())
+            },
+            _ => Err(TableError::IllegalAction(action)),
+        }
+    }
+
+    fn declared_kan(&mut self, player : usize) {
+        self.kans.push(player);
+        self.interrupt();
+    }
+
+    /// A call or a kan ends the first turn and every ippatsu
+    fn interrupt(&mut self) {
+        self.interrupted = true;
+        self.flags.iter_mut().for_each(|flags| flags.ippatsu = false);
+    }
+
+    /// Asks the other players whether they call the tile, or goes on if nobody can
+    fn open_calls(&mut self, from : usize, tile : Tile, added_kan : bool) -> Result<(), TableError> {
+        let options = self.call_options(from, tile, added_kan);
+        let responses = vec![None; self.player_count()];
+        self.stage = Stage::Calls(Calls{from, tile, added_kan, options, responses});
+        if self.waiting_for().is_empty() {
+            self.resolve_calls()?;
+        }
+        Ok(())
+    }
+
+    /// Settles the calls once every player answered, a ron taking precedence over a pon or a kan,
+    /// which take precedence over a chii
+    fn resolve_calls(&mut self) -> Result<(), TableError> {
+        let calls = match std::mem::replace(&mut self.stage, Stage::Over) {
+            Stage::Calls(calls) => calls,
+            stage => {
+                self.stage = stage;
+                return Ok(());
+            },
+        };
+        let players = self.player_count();
+        let order : Vec<usize> = (1..players).map(|offset| (calls.from + offset) % players).collect();
+        let response = |player : usize| calls.responses[player].unwrap_or(Action::Pass);
+
+        let mut winners : Vec<usize> = order.iter().cloned().filter(|player| response(*player) == Action::Ron).collect();
+        if !winners.is_empty() {
+            match self.rules.multiple_ron {
+                MultipleRon::HeadBump => winners.truncate(1),
+                MultipleRon::DoubleRon if winners.len() >= 3 => {
+                    self.end_round(RoundResult::AbortiveDraw(AbortiveDraw::TripleRon));
+                    return Ok(());
+                },
+                MultipleRon::DoubleRon | MultipleRon::All => (),
+            }
+            self.win(&winners, Some(calls.from), calls.tile, calls.added_kan);
+            return Ok(());
+        }
+
+        // Nobody won on the tile
+        for player in order.iter().cloned() {
+            let hand = self.current().player(player).hand();
+            if waits(&hand.tile_counts(), Table::melds(hand)).contains(&calls.tile.value()) {
+                let riichi = self.current().player(player).is_riichi();
+                let flags = &mut self.flags[player];
+                flags.temporary_furiten = true;
+                flags.riichi_furiten |= riichi;
+            }
+        }
+        if self.flags[calls.from].declaring {
+            self.flags[calls.from].declaring = false;
+            self.flags[calls.from].ippatsu = true;
+            self.scores[calls.from] -= RIICHI_DEPOSIT;
+            self.riichi_sticks += 1;
+            self.updates.push(Update::RiichiAccepted{player : calls.from});
+        }
+        if calls.added_kan {
+            self.draw(true);
+            return Ok(());
+        }
+
+        let call = order.iter().cloned()
+            .map(|player| (player, response(player)))
+            .find(|(_, action)| matches!(action, Action::Pon { .. } | Action::OpenKan { .. }))
+            .or_else(|| order.iter().cloned().map(|player| (player, response(player))).find(|(_, action)| matches!(action, Action::Chii { .. })));
+        if let Some((player, action)) = call {
+            let (from, called) = (calls.from, calls.tile);
+            match action {
+                Action::Pon { tiles } => self.apply(Event::Pon{player, from, called, tiles})?,
+                Action::Chii { tiles } => self.apply(Event::Chii{player, from, called, tiles})?,
+                Action::OpenKan { tiles } => self.apply(Event::OpenKan{player, from, called, tiles})?,
+                _ => (),
+            }
+            self.interrupt();
+            if let Action::OpenKan { .. } = action {
+                self.kans.push(player);
+                self.pending_dora = true;
+                self.draw(true);
+            } else {
+                self.stage = Stage::Turn(player);
+            }
+            return Ok(());
+        }
+
+        if let Some(abort) = self.abortive_draw() {
+            self.end_round(RoundResult::AbortiveDraw(abort));
+        } else if self.recorder.remaining() == 0 {
+            self.exhaustive_draw();
+        } else {
+            self.draw(false);
+        }
+        Ok(())
+    }
+
+    /// Abortive draw after a discard that nobody called
+    fn abortive_draw(&self) -> Option<AbortiveDraw> {
+        if !self.rules.abortive_draws || self.player_count() != 4 {
+            return None;
+        }
+        let round = self.current();
+        let ponds : Vec<_> = (0..4).map(|player| round.player(player).pond().discards()).collect();
+        let first = ponds[0].first().map(|discard| discard.tile.value());
+        let four_winds = !self.interrupted
+            && ponds.iter().all(|pond| pond.len() == 1 && Some(pond[0].tile.value()) == first)
+            && first.is_some_and(|value| value.is_wind());
+        if four_winds {
+            return Some(AbortiveDraw::FourWinds);
+        }
+        if (0..4).all(|player| round.player(player).is_riichi()) {
+            return Some(AbortiveDraw::FourRiichi);
+        }
+        if self.kans.len() == 4 && self.kans.iter().any(|player| *player != self.kans[0]) {
+            return Some(AbortiveDraw::FourKans);
+        }
+        None
+    }
+
+    /// Pays the winners, on a ron from the player `from` or on a tsumo.
+    /// The honba and the riichi deposits go to the first winner in turn order.
+    fn win(&mut self, winners : &[usize], from : Option<usize>, tile : Tile, chankan : bool) {
+        let players = self.player_count();
+        let round = self.current();
+        let dealer = round.dealer();
+        let mut records = Vec::new();
+        for (index, winner) in winners.iter().cloned().enumerate() {
+            let value = match self.win_value(winner, tile, from.is_none(), chankan) {
+                Some(value) => value,
+                None => continue,
+            };
+            let honba = if index == 0 {self.honba} else {0};
+            let mut deltas = vec![0; players];
+            match from {
+                Some(from) => {
+                    let payment = ron_payment(value.base_points, winner == dealer, honba, &self.rules) as i32;
+                    deltas[winner] += payment;
+                    deltas[from] -= payment;
+                },
+                None => {
+                    for (wind, payment) in tsumo_payments(value.base_points, round.seat_wind(winner), honba, &self.rules) {
+                        let payer = (0..players).find(|player| round.seat_wind(*player) == wind).unwrap_or(winner);
+                        deltas[payer] -= payment as i32;
+                        deltas[winner] += payment as i32;
+                    }
+                },
+            }
+            if index == 0 {
+                deltas[winner] += self.riichi_sticks as i32 * RIICHI_DEPOSIT;
+            }
+            records.push(WinRecord {
+                winner,
+                from,
+                pao : None,
+                han : value.han,
+                fu : value.fu,
+                yaku : value.yaku_list(),
+                deltas,
+            });
+        }
+        self.riichi_sticks = 0;
+        self.end_round(RoundResult::Win(records));
+    }
+
+    /// Ends the round when the wall is empty, the tenpai players being paid by the others
+    /// unless a player made nagashi mangan
+    fn exhaustive_draw(&mut self) {
+        let players = self.player_count();
+        let round = self.current();
+        let tenpai : Vec<bool> = (0..players)
+            .map(|player| {
+                let hand = round.player(player).hand();
+                is_tenpai(&hand.tile_counts(), Table::melds(hand))
+            })
+            .collect();
+
+        let nagashi : Vec<usize> = (0..players)
+            .filter(|player| {
+                let discards = round.player(*player).pond().discards();
+                !discards.is_empty() && discards.iter().all(|discard| discard.tile.is_yaochuu() && !discard.called)
+            })
+            .collect();
+        let mut deltas = vec![0; players];
+        if !nagashi.is_empty() {
+            for winner in nagashi {
+                for (wind, payment) in tsumo_payments(2000, round.seat_wind(winner), 0, &self.rules) {
+                    let payer = (0..players).find(|player| round.seat_wind(*player) == wind).unwrap_or(winner);
+                    deltas[payer] -= payment as i32;
+                    deltas[winner] += payment as i32;
+                }
+            }
+            self.end_round_with(RoundResult::NagashiMangan{deltas}, tenpai[round.dealer()]);
+            return;
+        }
+
+        let count = tenpai.iter().filter(|tenpai| **tenpai).count() as i32;
+        if count > 0 && count < players as i32 {
+            let total = 1000 * (players as i32 - 1);
+            for (delta, tenpai) in deltas.iter_mut().zip(tenpai.iter()) {
+                *delta = if *tenpai {total / count} else {-total / (players as i32 - count)};
+            }
+        }
+        let dealer_tenpai = tenpai[round.dealer()];
+        self.end_round_with(RoundResult::ExhaustiveDraw{deltas}, dealer_tenpai);
+    }
+
+    fn end_round(&mut self, result : RoundResult) {
+        let dealer = self.current().dealer();
+        let renchan = match &result {
+            RoundResult::Win(wins) => wins.iter().any(|win| win.winner == dealer),
+            _ => true,
+        };
+        self.end_round_with(result, renchan);
+    }
+
+    /// Pays the result of the round, then deals the next round or ends the game.
+    /// The dealer keeps their seat on `renchan`.
+    fn end_round_with(&mut self, result : RoundResult, renchan : bool) {
+        let players = self.player_count();
+        for (score, delta) in self.scores.iter_mut().zip(result.deltas(players)) {
+            *score += delta;
+        }
+        let win = matches!(result, RoundResult::Win(_));
+        self.recorder.finish_round(result.clone());
+        self.updates.push(Update::RoundEnded{result, scores : self.scores.clone()});
+
+        self.honba = if win && !renchan {0} else {self.honba + 1};
+        if !renchan {
+            self.hand += 1;
+        }
+        let winds = match self.rules.round_length {
+            RoundLength::Tonpuusen => 1,
+            RoundLength::Hanchan => 2,
+        };
+        let busted = self.rules.tobi && self.scores.iter().any(|score| *score < 0);
+        if busted || self.hand >= winds * players {
+            self.end_game();
+        } else {
+            self.start_round();
+        }
+    }
+
+    fn end_game(&mut self) {
+        let first = (0..self.player_count()).fold(0, |best, player| {
+            if self.scores[player] > self.scores[best] {player} else {best}
+        });
+        self.scores[first] += self.riichi_sticks as i32 * RIICHI_DEPOSIT;
+        self.riichi_sticks = 0;
+        self.stage = Stage::Over;
+        self.recorder.finish_game(self.scores.clone());
+        self.updates.push(Update::GameEnded{scores : self.scores.clone()});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Table, TableError, Update};
+    use crate::game::rules::{RuleSet, RuleError};
+    use crate::game::action::Action;
+    use crate::record::RoundResult;
+    use crate::hand::shanten::shanten;
+
+    fn names() -> Vec<String> {
+        ["A", "B", "C", "D"].iter().map(|name| name.to_string()).collect()
+    }
+
+    /// The discard leaving the hand of the player closest to a win
+    fn best_discard(table : &Table, player : usize, actions : &[Action]) -> Option<Action> {
+        let hand = table.round().unwrap().player(player).hand();
+        let melds = hand.melds().len() + hand.closed_kans().len();
+        actions.iter()
+            .filter(|action| matches!(action, Action::Discard { riichi : false, .. }))
+            .min_by_key(|action| match action {
+                Action::Discard { tile, .. } => {
+                    let mut counts = hand.tile_counts();
+                    counts.remove(tile.value());
+                    shanten(&counts, melds)
+                },
+                _ => 8,
+            })
+            .cloned()
+    }
+
+    /// Plays a game where every player wins when they can, calls according to `call`,
+    /// declares riichi when they can and otherwise discards toward a win
+    fn play(seed : u64, rules : RuleSet, call : bool) -> (Table, Vec<Update>) {
+        let players = rules.players.count() as usize;
+        let mut table = Table::new(seed, names().into_iter().take(players).collect(), rules).unwrap();
+        let mut updates = table.take_updates();
+        while !table.is_over() {
+            let player = table.waiting_for()[0];
+            let actions = table.legal_actions(player);
+            let action = actions.iter()
+                .find(|action| matches!(action, Action::Tsumo | Action::Ron))
+                .or_else(|| actions.iter().find(|action| call && matches!(action, Action::Pon { .. } | Action::Chii { .. } | Action::OpenKan { .. } | Action::AddedKan { .. })))
+                .or_else(|| actions.iter().find(|action| matches!(action, Action::Discard { riichi : true, .. })))
+                .cloned()
+                .or_else(|| best_discard(&table, player, &actions))
+                .unwrap_or(Action::Pass);
+            updates.extend(table.act(player, action).unwrap());
+        }
+        (table, updates)
+    }
+
+    #[test]
+    fn test_full_game() {
+        for (seed, call) in [(1, false), (2, true), (3, true)].iter() {
+            let rules = RuleSet::tenhou();
+            let (table, updates) = play(*seed, rules.clone(), *call);
+            assert_eq!(4 * rules.starting_points, table.scores().iter().sum::<i32>());
+            assert!(matches!(updates.last(), Some(Update::GameEnded { .. })));
+            let wins = updates.iter().filter(|update| matches!(update, Update::RoundEnded { result : RoundResult::Win(_), .. })).count();
+            assert!(wins > 0);
+            let record = table.finish();
+            assert_eq!(Ok(()), record.verify());
+        }
+    }
+
+    #[test]
+    fn test_final_scores() {
+        // The last round has a riichi and ends in a draw, its deposit going to the first player
+        let (table, updates) = play(0, RuleSet::tenhou(), false);
+        let start = updates.iter().rposition(|update| matches!(update, Update::RoundStarted { .. })).unwrap();
+        assert!(updates[start..].iter().any(|update| matches!(update, Update::RiichiAccepted { .. })));
+        assert!(updates[start..].iter().any(|update| matches!(update, Update::RoundEnded { result : RoundResult::ExhaustiveDraw { .. }, .. })));
+        let scores = match updates.last() {
+            Some(Update::GameEnded { scores }) => scores.clone(),
+            update => panic!("unexpected {:?}", update),
+        };
+        let record = table.finish();
+        assert_eq!(scores, record.final_scores());
+        assert_eq!(100_000, record.final_scores().iter().sum::<i32>());
+    }
+
+    #[test]
+    fn test_sanma_game() {
+        let rules = RuleSet::mahjong_soul_sanma();
+        let (table, _) = play(4, rules.clone(), true);
+        assert_eq!(3 * rules.starting_points, table.scores().iter().sum::<i32>());
+        assert_eq!(Ok(()), table.finish().verify());
+
+        let mut rules = RuleSet::mahjong_soul_sanma();
+        rules.uma = RuleSet::mahjong_soul().uma;
+        assert_eq!(Some(TableError::Rules(RuleError::UmaLength(4))), Table::new(4, names(), rules).err());
+    }
+
+    #[test]
+    fn test_turns() {
+        let mut table = Table::new(5, names(), RuleSet::wrc()).unwrap();
+        assert!(matches!(table.take_updates()[0], Update::RoundStarted { dealer : 0, .. }));
+        assert_eq!(vec![0], table.waiting_for());
+        assert!(table.legal_actions(1).is_empty());
+
+        let discard = table.legal_actions(0).into_iter().find(|action| matches!(action, Action::Discard { .. })).unwrap();
+        assert_eq!(Err(TableError::NotYourTurn(1)), table.act(1, discard));
+        assert_eq!(Err(TableError::IllegalAction(Action::Ron)), table.act(0, Action::Ron));
+        let updates = table.act(0, discard).unwrap();
+        assert!(!updates.is_empty());
+        assert_eq!(14, table.round().unwrap().player(0).hand().hidden_tiles().len() + 1);
+    }
+}
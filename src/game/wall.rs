@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Debug};
+
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::tile::suit::Suit;
+use crate::game::rules::RuleSet;
+use crate::game::rng::Rng;
+use crate::game::round::HAND_SIZE;
+
+/// Number of tiles of the dead wall
+pub const DEAD_WALL_SIZE : usize = 14;
+/// Number of replacement tiles drawn after a kan at the start of a round
+const REPLACEMENT_TILES : usize = 4;
+
+/// Reason why tiles cannot make a wall
+#[derive(Clone, PartialEq, Eq)]
+pub enum WallError {
+    /// Fewer tiles than the dead wall holds
+    TooShort(usize),
+}
+
+impl Debug for WallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WallError::TooShort(count) => write!(f, "a wall needs at least {} tiles, not {}", DEAD_WALL_SIZE, count),
+        }
+    }
+}
+
+impl Display for WallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for WallError {}
+
+/// The tiles that have not been drawn yet.
+/// The last 14 tiles form the dead wall, holding the replacement tiles drawn after a kan
+/// or a nukidora and the dora and ura dora indicators.
+#[derive(Clone, Debug)]
+pub struct Wall {
+    live_tiles : VecDeque<Tile>,
+    replacement_tiles : Vec<Tile>,
+    indicators : Vec<Tile>,
+    ura_indicators : Vec<Tile>,
+    revealed_indicators : usize,
+}
+
+impl Wall {
+
+    // Constructors
+
+    /// Builds a wall from already shuffled tiles, the last 14 making the dead wall
+    pub fn new(mut tiles : Vec<Tile>) -> Result<Self, WallError> {
+        if tiles.len() < DEAD_WALL_SIZE {
+            return Err(WallError::TooShort(tiles.len()));
+        }
+        let mut dead_wall = tiles.split_off(tiles.len() - DEAD_WALL_SIZE);
+        let ura_indicators = dead_wall.split_off(REPLACEMENT_TILES + 5);
+        let indicators = dead_wall.split_off(REPLACEMENT_TILES);
+        Ok(Wall {
+            live_tiles : tiles.into(),
+            replacement_tiles : dead_wall,
+            indicators,
+            ura_indicators,
+            revealed_indicators : 1,
+        })
+    }
+
+    /// Shuffles the tile set of the rules into a new wall
+    pub fn shuffled(rules : &RuleSet, rng : &mut Rng) -> Self {
+        let mut tiles = Wall::tile_set(rules);
+        rng.shuffle(&mut tiles);
+        Wall::new(tiles).expect("the tile set is larger than the dead wall")
+    }
+
+    /// Every tile the game is played with, unshuffled.
+    /// In a three player game, the manzu tiles from 2 to 8 are removed, which leaves 108 tiles.
+    pub fn tile_set(rules : &RuleSet) -> Vec<Tile> {
+        let removed = |tile : &Tile| match tile.value() {
+            TileValue::Suited(suited) => rules.players.is_sanma() && suited.suit() == Suit::Man && suited.is_simple(),
+            TileValue::Honor(_) => false,
+        };
+        let mut tiles : Vec<Tile> = Tile::full_set().filter(|tile| !removed(tile)).collect();
+        tiles.sort();
+        tiles
+    }
+
+    // Immutable getters
+
+    /// Number of tiles that can still be drawn
+    pub fn remaining(&self) -> usize {
+        self.live_tiles.len()
+    }
+
+    /// The dora indicators revealed so far
+    pub fn dora_indicators(&self) -> &[Tile] {
+        &self.indicators[..self.revealed_indicators]
+    }
+
+    /// The ura dora indicators under the revealed dora indicators
+    pub fn ura_dora_indicators(&self) -> &[Tile] {
+        &self.ura_indicators[..self.revealed_indicators]
+    }
+
+    // Other
+
+    /// Draws the next tile of the live wall
+    pub fn draw(&mut self) -> Option<Tile> {
+        self.live_tiles.pop_front()
+    }
+
+    /// Deals the starting hands, indexed by player : four tiles at a time three times, then one tile each,
+    /// starting with the dealer
+    pub fn deal(&mut self, dealer : usize, players : usize) -> Vec<Vec<Tile>> {
+        let mut hands = vec![Vec::new(); players];
+        for count in [4, 4, 4, 1].iter() {
+            for seat in 0..players {
+                let hand = &mut hands[(dealer + seat) % players];
+                hand.extend(self.live_tiles.drain(..*count));
+            }
+        }
+        debug_assert!(hands.iter().all(|hand| hand.len() == HAND_SIZE));
+        hands
+    }
+
+    /// Draws a replacement tile from the dead wall after a kan or a nukidora.
+    /// The last tile of the live wall is moved to the dead wall so that it keeps its size.
+    pub fn draw_replacement(&mut self) -> Option<Tile> {
+        let tile = self.replacement_tiles.pop()?;
+        if let Some(moved) = self.live_tiles.pop_back() {
+            self.replacement_tiles.insert(0, moved);
+        }
+        Some(tile)
+    }
+
+    /// Reveals a new dora indicator after a kan and returns it
+    pub fn reveal_kan_dora(&mut self) -> Option<Tile> {
+        let indicator = *self.indicators.get(self.revealed_indicators)?;
+        self.revealed_indicators += 1;
+        Some(indicator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Wall, WallError};
+    use crate::game::rng::Rng;
+    use crate::game::rules::RuleSet;
+
+    #[test]
+    fn test_tile_set() {
+        assert_eq!(136, Wall::tile_set(&RuleSet::default()).len());
+        assert_eq!(108, Wall::tile_set(&RuleSet::mahjong_soul_sanma()).len());
+    }
+
+    #[test]
+    fn test_dead_wall() {
+        let mut wall = Wall::new(Wall::tile_set(&RuleSet::default())).unwrap();
+        assert_eq!(122, wall.remaining());
+        assert_eq!(1, wall.dora_indicators().len());
+
+        wall.draw_replacement().unwrap();
+        assert_eq!(121, wall.remaining());
+        wall.reveal_kan_dora().unwrap();
+        assert_eq!(2, wall.dora_indicators().len());
+        assert_eq!(2, wall.ura_dora_indicators().len());
+
+        let tiles = Wall::tile_set(&RuleSet::default());
+        assert_eq!(Some(WallError::TooShort(13)), Wall::new(tiles[..13].to_vec()).err());
+        assert_eq!(0, Wall::new(tiles[..14].to_vec()).unwrap().remaining());
+    }
+
+    #[test]
+    fn test_deal() {
+        let rules = RuleSet::default();
+        let mut wall = Wall::shuffled(&rules, &mut Rng::new(42));
+        let first = wall.clone();
+        assert_eq!(first.live_tiles, Wall::shuffled(&rules, &mut Rng::new(42)).live_tiles);
+
+        let hands = wall.deal(1, 4);
+        assert_eq!(70, wall.remaining());
+        assert_eq!(first.live_tiles[0], hands[1][0]);
+        assert_eq!(first.live_tiles[4], hands[2][0]);
+        assert_eq!(first.live_tiles[48], hands[1][12]);
+    }
+}
@@ -2,6 +2,7 @@ use std::fmt::Debug;
 use Wind::{East, South, West, North};
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Wind {
     East,
     South,
@@ -27,6 +28,22 @@ impl Wind {
             North => None,
         }
     }
+
+    /// Gets the seat wind of the next player in turn order, at a table of `players` players.
+    /// In a three player game, the north seat is empty.
+    pub fn next_seat(&self, players : u8) -> Wind {
+        match self {
+            East  => South,
+            South => West,
+            West  => if players == 3 {East} else {North},
+            North => East,
+        }
+    }
+
+    /// Gets the seat winds of a table of `players` players, starting with the dealer
+    pub fn seats(players : u8) -> Vec<Wind> {
+        vec![East, South, West, North].into_iter().take(players as usize).collect()
+    }
 }
 
 impl Debug for Wind {
@@ -38,4 +55,26 @@ impl Debug for Wind {
             North => write!(f, "n"),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Wind::{East, South, West, North};
+    use super::Wind;
+
+    #[test]
+    fn test_next_seat() {
+        assert_eq!(North, West.next_seat(4));
+        assert_eq!(East, North.next_seat(4));
+        assert_eq!(East, West.next_seat(3));
+        assert_eq!(West, South.next_seat(3));
+        // Going around the table comes back to the dealer after each seat was visited once
+        for players in [3, 4] {
+            let seats = Wind::seats(players);
+            for (index, seat) in seats.iter().enumerate() {
+                assert_eq!(seats[(index + 1) % seats.len()], seat.next_seat(players));
+            }
+        }
+        assert_eq!(vec![East, South, West], Wind::seats(3));
+    }
+}
@@ -2,6 +2,7 @@ use crate::tile::Tile;
 
 /// Represents a closed group in a mahjong hand.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Group {
     Pon(Tile, Tile, Tile),
     Chii(Tile, Tile, Tile),
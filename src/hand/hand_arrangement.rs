@@ -1,315 +1,429 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
 use crate::hand::group::Group;
-use crate::hand::wait::Wait;
+use crate::hand::wait::{Wait, WaitKind};
+use crate::hand::shanten::regular_shanten;
 use crate::tile::Tile;
-use crate::tile::tile_value::TileValue;
+use crate::tile::tile_value::{TileValue, TILE_KINDS};
+use crate::tile::tile_counts::TileCounts;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HandArrangement {
     pub groups : Vec<Group>,
     pub wait : Option<Wait>,
 }
 
-type ExtractionFunction = fn(&HandArrangement, tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)>;
+/// Whether `HandArrangement::consume_with` tells apart the copies of a tile value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Copies {
+    /// The arrangements are unique by their tile values, each value giving its copies to the groups in order
+    Merged,
+    /// Every way to give the copies of a value to different groups is kept, to render the red fives for instance
+    Distinct,
+}
 
-impl HandArrangement {
+/// The tile values of an arrangement, regardless of the copies of the tiles and of the order of the groups
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ArrangementValues {
+    groups : Vec<Vec<TileValue>>,
+    wait : Option<(Vec<TileValue>, Vec<TileValue>)>,
+}
 
-    pub fn empty() -> HandArrangement {
-        HandArrangement {
-            groups : Vec::new(),
-            wait : None,
-        }
-    }
+impl ArrangementValues {
 
-    pub fn groups(&self) -> &Vec<Group> {
+    /// The values of each group, the groups being sorted
+    pub fn groups(&self) -> &Vec<Vec<TileValue>> {
         &self.groups
     }
 
-    pub fn wait(&self) -> &Option<Wait> {
+    /// The values of the tiles of the wait and the values it waits on
+    pub fn wait(&self) -> &Option<(Vec<TileValue>, Vec<TileValue>)> {
         &self.wait
     }
+}
 
-    /// Recursive function that determines the possible waiting arrangements that can be
-    /// made with the tiles contained in the closed part of a hand.
-    /// The `hidden_tiles` vector has to be sorted beforehand.
-    fn consume(&self, hidden_tiles : &Vec<Tile>) -> Vec<HandArrangement> {
-
-        let mut arrangements : Vec<HandArrangement> = Vec::new();
-        
-        if hidden_tiles.is_empty() {
-            if self.wait.is_some() {
-                arrangements.push(self.clone());
-            }
-            return arrangements;
-        }
-
-        self.extract(hidden_tiles, &mut arrangements, HandArrangement::extract_pon);
-        self.extract(hidden_tiles, &mut arrangements, HandArrangement::extract_pair);
-        self.extract(hidden_tiles, &mut arrangements, HandArrangement::extract_chii);
+/// A run of tile values searched independently of the others : the nine values of a suit, or a single honor.
+/// The blocks are sorted like the tiles, the red dragon coming before the green one.
+#[derive(Clone, Copy)]
+struct Block {
+    start : usize,
+    len : usize,
+    suited : bool,
+}
 
-        if self.wait.is_none() {
-            self.extract(hidden_tiles, &mut arrangements, HandArrangement::extract_pon_wait);
-            self.extract(hidden_tiles, &mut arrangements, HandArrangement::extract_chii_side_wait);
-            self.extract(hidden_tiles, &mut arrangements, HandArrangement::extract_chii_middle_wait);
-            self.extract(hidden_tiles, &mut arrangements, HandArrangement::extract_pair_wait);
-        }
+const BLOCKS : [Block; 10] = [
+    Block{start : 0, len : 9, suited : true},
+    Block{start : 9, len : 9, suited : true},
+    Block{start : 18, len : 9, suited : true},
+    Block{start : 27, len : 1, suited : false},
+    Block{start : 28, len : 1, suited : false},
+    Block{start : 29, len : 1, suited : false},
+    Block{start : 30, len : 1, suited : false},
+    Block{start : 31, len : 1, suited : false},
+    Block{start : 33, len : 1, suited : false},
+    Block{start : 32, len : 1, suited : false},
+];
+
+/// A group or a wait found by the search, given by the index of its lowest tile value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Shape {
+    Pon(usize),
+    Pair(usize),
+    Chii(usize),
+    PonWait(usize),
+    SideWait(usize),
+    MiddleWait(usize),
+    PairWait(usize),
+}
 
-        arrangements
-    }
-    
-    fn extract(
-        &self,
-        hidden_tiles : &Vec<Tile>,
-        arrangements : &mut Vec<HandArrangement>,
-        function : ExtractionFunction,
-    ) {
-        if let Some((new_arrangement, remaining_tiles)) = function(&self, hidden_tiles) {
-            arrangements.append(&mut new_arrangement.consume(&remaining_tiles));
+impl Shape {
+    fn shifted(self, offset : usize) -> Shape {
+        match self {
+            Shape::Pon(x) => Shape::Pon(x + offset),
+            Shape::Pair(x) => Shape::Pair(x + offset),
+            Shape::Chii(x) => Shape::Chii(x + offset),
+            Shape::PonWait(x) => Shape::PonWait(x + offset),
+            Shape::SideWait(x) => Shape::SideWait(x + offset),
+            Shape::MiddleWait(x) => Shape::MiddleWait(x + offset),
+            Shape::PairWait(x) => Shape::PairWait(x + offset),
         }
     }
+}
 
-    fn extract_pon(&self, hidden_tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)> {
-        if hidden_tiles.len() < 3 {
-            return None;
-        }
-
-        let mut hidden_tiles = hidden_tiles.clone();
-
-        let first  = hidden_tiles.remove(0);
-        let second = hidden_tiles.remove(0);
-        let third  = hidden_tiles.remove(0);
-
-        if first.value() == second.value() && first.value() == third.value() {
+/// What the groups found so far still allow
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct SearchState {
+    /// Whether the pair of the hand is found, a single tile wait standing for it
+    pair : bool,
+    wait : bool,
+}
 
-            let mut groups = self.groups.clone();
+/// The ways to split a block, with the state of the search after each of them
+type Decompositions = Rc<Vec<(Vec<Shape>, SearchState)>>;
 
-            groups.push(Group::Pon(first, second, third));
+thread_local! {
+    /// Decompositions of the blocks already met, by counts of the block and state of the search
+    static DECOMPOSITIONS : RefCell<HashMap<(u64, bool, SearchState), Decompositions>> = RefCell::new(HashMap::new());
+}
 
-            Some((
-                HandArrangement{
-                    groups,
-                    wait : self.wait.clone(),
-                },
-                hidden_tiles
-            ))
+impl HandArrangement {
 
-        } else {
-            None
+    pub fn empty() -> HandArrangement {
+        HandArrangement {
+            groups : Vec::new(),
+            wait : None,
         }
     }
 
-    fn extract_pair(&self, hidden_tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)> {
-        if hidden_tiles.len() < 2 {
-            return None;
-        }
-        // If the arrangement already contains a pair
-        if self.groups.iter().any(|group| {if let Group::Pair(_,_) = group {true} else {false}}) {
-            return None;
-        }
-
-        let mut hidden_tiles = hidden_tiles.clone();
-
-        let first  = hidden_tiles.remove(0);
-        let second = hidden_tiles.remove(0);
-
-        if first.value() == second.value() {
-
-            let mut groups = self.groups.clone();
-            groups.push(Group::Pair(first, second));
-
-            Some((
-                HandArrangement{
-                    groups,
-                    wait : self.wait.clone(),
-                },
-                hidden_tiles
-            ))
-
-        } else {
-            None
-        }
+    pub fn groups(&self) -> &Vec<Group> {
+        &self.groups
+    }
 
+    pub fn wait(&self) -> &Option<Wait> {
+        &self.wait
     }
 
-    fn extract_chii(&self, hidden_tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)> {
-        if hidden_tiles.len() < 3 {
-            return None;
-        }
-        let mut i = 0; // index of the removed tile
-        let mut hidden_tiles = hidden_tiles.clone();
-        // --- First tile
-        let first  = hidden_tiles.remove(0);
-        if let TileValue::Honor(_) = first.value() {
-            return None;
-        }
+    /// The tile values of this arrangement, equal for two arrangements that only differ by the copies of their tiles
+    pub fn values(&self) -> ArrangementValues {
+        let mut groups : Vec<Vec<TileValue>> = self.groups.iter()
+            .map(|group| group.tiles().iter().map(|tile| tile.value()).collect())
+            .collect();
+        groups.sort();
+        let wait = self.wait.as_ref().map(|wait| {
+            (wait.tiles().iter().map(|tile| tile.value()).collect(), wait.wait_symbols().clone())
+        });
+        ArrangementValues{groups, wait}
+    }
 
-        // --- Second tile
-        // Advances to the first different tile
-        while hidden_tiles[i].value() == first.value() {
-            i += 1;
-            // The -1 is because we need to be able to extract a third tile
-            if i == hidden_tiles.len() - 1 {
-                return None;
-            }
-        }
-        let second = hidden_tiles.remove(i);
+    /// The tiles of this arrangement, regardless of the order of the groups and of the tiles in a group
+    fn physical_tiles(&self) -> (Vec<Vec<Tile>>, Vec<Tile>) {
+        let sorted = |tiles : Vec<Tile>| {
+            let mut tiles = tiles;
+            tiles.sort();
+            tiles
+        };
+        let mut groups : Vec<Vec<Tile>> = self.groups.iter()
+            .map(|group| sorted(group.tiles().into_iter().cloned().collect()))
+            .collect();
+        groups.sort();
+        (groups, sorted(self.wait.iter().flat_map(|wait| wait.tiles().clone()).collect()))
+    }
 
-        if !first.followed_by(second) {
-            return None;
-        }
+    /// Determines the possible waiting arrangements that can be made with the tiles
+    /// contained in the closed part of a hand, completing this arrangement into groups and a pair.
+    /// A single tile wait stands for the pair, so it is only found when no other pair is made.
+    /// The arrangements are unique by their tile values (see `Copies::Merged`).
+    pub fn consume(&self, hidden_tiles : &[Tile]) -> Vec<HandArrangement> {
+        self.consume_with(hidden_tiles, Copies::Merged)
+    }
 
-        // --- Third tile
-        // Advances to the first different tile
-        while hidden_tiles[i].value() == second.value() {
-            i += 1;
-            if i == hidden_tiles.len() {
-                return None;
+    /// Same as `consume`, telling apart the copies of the tiles or not.
+    /// With `Copies::Merged`, the groups take the first copies of a value in the order of `hidden_tiles`, which is expected to be sorted.
+    pub fn consume_with(&self, hidden_tiles : &[Tile], copies_mode : Copies) -> Vec<HandArrangement> {
+        let tile_counts = TileCounts::from_tiles(hidden_tiles);
+        let counts = *tile_counts.counts();
+        // A hand of 3n + 1 tiles cannot wait on anything if it is not tenpai
+        let fresh = self.groups.is_empty() && self.wait.is_none();
+        if fresh && hidden_tiles.len() % 3 == 1 && hidden_tiles.len() <= 13 && counts.iter().all(|count| *count <= 4) {
+            let melds = 4 - hidden_tiles.len() / 3;
+            if regular_shanten(&tile_counts, melds) > 0 {
+                return Vec::new();
             }
         }
-        let third  = hidden_tiles.remove(i);
-
-        if !second.followed_by(third) {
-            return None;
-        }
-
-        let mut groups = self.groups.clone();
-        groups.push(Group::Chii(first, second, third));
-
-        Some ((
-            HandArrangement{
-                groups,
-                wait : self.wait.clone(),
+        // The copies of each value, in their order in the hand
+        let mut copies = hidden_tiles.to_vec();
+        copies.sort_by_key(|tile| tile.value().index());
+        let state = SearchState {
+            pair : self.groups.iter().any(|group| matches!(group, Group::Pair(_,_))),
+            wait : self.wait.is_some(),
+        };
+
+        let mut found = Vec::new();
+        self.search(&counts, 0, state, &mut Vec::new(), &mut found);
+        match copies_mode {
+            Copies::Merged => found.iter().map(|shapes| self.build(shapes, &counts, &copies)).collect(),
+            Copies::Distinct => {
+                let mut arrangements : Vec<HandArrangement> = Vec::new();
+                // Identical shapes still give the same arrangements with their copies swapped
+                let mut seen = BTreeSet::new();
+                for shapes in &found {
+                    for order in copy_orders(shapes, &counts, &copies) {
+                        let arrangement = self.build(shapes, &counts, &order);
+                        if seen.insert(arrangement.physical_tiles()) {
+                            arrangements.push(arrangement);
+                        }
+                    }
+                }
+                arrangements
             },
-            hidden_tiles,
-        ))
-
-    }
-
-    fn extract_pon_wait(&self, hidden_tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)> {
-        // TODO : consider mergeing it with extract_pair
-        if hidden_tiles.len() < 2 {
-            return None;
-        }
-
-        let mut hidden_tiles = hidden_tiles.clone();
-
-        let first  = hidden_tiles.remove(0);
-        let second = hidden_tiles.remove(0);
-
-        if first.value() == second.value() {
-            let wait = Some(Wait::new(vec![first, second], vec![first.value()]));
-            Some((
-                HandArrangement{
-                    groups : self.groups.clone(),
-                    wait,
-                },
-                hidden_tiles
-            ))
-        } else {
-            None
         }
     }
 
-    fn extract_pair_wait(&self, hidden_tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)> {
-        if hidden_tiles.len() < 1 {
-            return None;
-        }
-        // If the arrangement already contains a pair
-        if self.groups.iter().any(|group| {if let Group::Pair(_,_) = group {false} else {true}}) {
-            return None;
-        }
-
-        let mut hidden_tiles = hidden_tiles.clone();
-        let tile = hidden_tiles.remove(0);
-
-        let wait = Some(Wait::new(vec![tile], vec![tile.value()]));
-        Some((
-            HandArrangement {
-                groups: self.groups.clone(),
-                wait,
+    /// Goes through the decompositions of each block in turn, collecting the shapes of the complete ones
+    fn search(
+        &self,
+        counts : &[u8; TILE_KINDS],
+        block : usize,
+        state : SearchState,
+        shapes : &mut Vec<Shape>,
+        found : &mut Vec<Vec<Shape>>,
+    ) {
+        let Block { start, len, suited } = match BLOCKS.get(block) {
+            Some(block) => *block,
+            None => {
+                if state.wait {
+                    found.push(shapes.clone());
+                }
+                return;
             },
-            hidden_tiles,
-        ))
-    }
-
-    fn extract_chii_side_wait(&self, hidden_tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)> {
-        if hidden_tiles.len() < 2 {
-            return None;
+        };
+        let block_counts = &counts[start..start + len];
+        if block_counts.iter().all(|count| *count == 0) {
+            return self.search(counts, block + 1, state, shapes, found);
         }
-        let mut hidden_tiles = hidden_tiles.clone();
-
-        let mut i = 0;
-        let first = hidden_tiles.remove(0);
-
-        if let TileValue::Honor(_) = first.value() {
-            return None;
+        for (block_shapes, next_state) in decompositions(block_counts, suited, state).iter() {
+            let size = shapes.len();
+            shapes.extend(block_shapes.iter().map(|shape| shape.shifted(start)));
+            self.search(counts, block + 1, *next_state, shapes, found);
+            shapes.truncate(size);
         }
+    }
 
-        while hidden_tiles[i].value() == first.value() {
-            i += 1;
-            if i == hidden_tiles.len() {
-                return None;
+    /// Makes the arrangement given by the shapes, giving the copies of each value in order.
+    /// `copies` holds the tiles sorted by value, with the number of copies of each value given by `counts`.
+    fn build(&self, shapes : &[Shape], counts : &[u8; TILE_KINDS], copies : &[Tile]) -> HandArrangement {
+        let mut next = first_copies(counts);
+        let mut take = |index : usize| {
+            next[index] += 1;
+            copies[next[index] - 1]
+        };
+        let value = |index : usize| TileValue::from_index(index as u8).unwrap();
+
+        let mut arrangement = self.clone();
+        for shape in shapes {
+            match *shape {
+                Shape::Pon(x) => arrangement.groups.push(Group::Pon(take(x), take(x), take(x))),
+                Shape::Pair(x) => arrangement.groups.push(Group::Pair(take(x), take(x))),
+                Shape::Chii(x) => arrangement.groups.push(Group::Chii(take(x), take(x + 1), take(x + 2))),
+                Shape::PonWait(x) => {
+                    arrangement.wait = Some(Wait::new(vec![take(x), take(x)], vec![value(x)], WaitKind::Shanpon));
+                },
+                Shape::SideWait(x) => {
+                    let wait_symbols : Vec<TileValue> = vec![value(x).prev(), value(x + 1).next()].into_iter().flatten().collect();
+                    // A 1-2 or 8-9 wait can only be completed on one side
+                    let kind = if wait_symbols.len() == 2 {WaitKind::Ryanmen} else {WaitKind::Penchan};
+                    arrangement.wait = Some(Wait::new(vec![take(x), take(x + 1)], wait_symbols, kind));
+                },
+                Shape::MiddleWait(x) => {
+                    arrangement.wait = Some(Wait::new(vec![take(x), take(x + 2)], vec![value(x + 1)], WaitKind::Kanchan));
+                },
+                Shape::PairWait(x) => {
+                    arrangement.wait = Some(Wait::new(vec![take(x)], vec![value(x)], WaitKind::Tanki));
+                },
             }
         }
+        arrangement
+    }
+}
 
-        let second = hidden_tiles.remove(0);
-
-        if first.followed_by(second) {
-            let wait_symbols : Vec<TileValue> = vec![first.prev(), second.next()]
-                .into_iter()
-                .filter(|x| x.is_some())
-                .map(Option::unwrap)
-                .collect();
-            let wait = Some(Wait::new(vec![first, second], wait_symbols));
-
-            Some((
-                HandArrangement{
-                    groups : self.groups.clone(),
-                    wait,
-                },
-                hidden_tiles,
-            ))
-        } else {
-            None
+impl Shape {
+    /// The indices of the values this shape takes a copy of, once each
+    fn indices(self) -> Vec<usize> {
+        match self {
+            Shape::Pon(x) | Shape::Pair(x) | Shape::PonWait(x) | Shape::PairWait(x) => vec![x],
+            Shape::Chii(x) => vec![x, x + 1, x + 2],
+            Shape::SideWait(x) => vec![x, x + 1],
+            Shape::MiddleWait(x) => vec![x, x + 2],
         }
     }
 
-    fn extract_chii_middle_wait(&self, hidden_tiles : &Vec<Tile>) -> Option<(HandArrangement, Vec<Tile>)> {
-        if hidden_tiles.len() < 2 {
-            return None;
+    /// Number of copies the shape takes of each of its values
+    fn copies_taken(self) -> usize {
+        match self {
+            Shape::Pon(_) => 3,
+            Shape::Pair(_) | Shape::PonWait(_) => 2,
+            Shape::Chii(_) | Shape::SideWait(_) | Shape::MiddleWait(_) | Shape::PairWait(_) => 1,
         }
+    }
+}
 
-        let mut hidden_tiles = hidden_tiles.clone();
-        let first = hidden_tiles.remove(0);
+/// Position in the copies sorted by value of the first copy of each value
+fn first_copies(counts : &[u8; TILE_KINDS]) -> [usize; TILE_KINDS] {
+    let mut first = [0; TILE_KINDS];
+    for index in 1..TILE_KINDS {
+        first[index] = first[index - 1] + counts[index - 1] as usize;
+    }
+    first
+}
 
-        if let TileValue::Honor(_) = first.value() {
-            return None;
-        }
+/// The orders of the copies giving every way to share them between the shapes.
+/// Only the copies of the values used by several shapes are shared out, each choice of the copies taken by each shape
+/// being generated once.
+fn copy_orders(shapes : &[Shape], counts : &[u8; TILE_KINDS], copies : &[Tile]) -> Vec<Vec<Tile>> {
+    // The number of copies taken by each shape using a value, in the order `build` gives them
+    let mut takers : Vec<Vec<usize>> = vec![Vec::new(); TILE_KINDS];
+    shapes.iter().for_each(|shape| shape.indices().into_iter().for_each(|index| takers[index].push(shape.copies_taken())));
+    let first = first_copies(counts);
+    let mut orders = vec![copies.to_vec()];
+    for index in (0..TILE_KINDS).filter(|index| takers[*index].len() > 1) {
+        let (start, end) = (first[index], first[index] + counts[index] as usize);
+        let shares = shares(&copies[start..end], &takers[index]);
+        orders = orders.into_iter()
+            .flat_map(|order| shares.iter().map(move |share| {
+                let mut order = order.clone();
+                order[start..end].copy_from_slice(share);
+                order
+            }))
+            .collect();
+    }
+    orders
+}
 
-        let third_symbol = first.next()?.next()?;
+/// Every way to give the tiles to takers of `sizes` tiles each in turn, the tiles of each taker staying in order
+fn shares(tiles : &[Tile], sizes : &[usize]) -> Vec<Vec<Tile>> {
+    let (size, others) = match sizes.split_first() {
+        Some((size, others)) => (*size, others),
+        None => return vec![tiles.to_vec()],
+    };
+    subsets(tiles, size).into_iter()
+        .flat_map(|(taken, left)| shares(&left, others).into_iter().map(move |share| [taken.clone(), share].concat()))
+        .collect()
+}
 
-        let mut i = 0;
-        while hidden_tiles[i].value() < third_symbol {
-            i += 1;
-            if i == hidden_tiles.len() {
-                return None;
-            }
+/// Every way to take `size` of the tiles, with the tiles left
+fn subsets(tiles : &[Tile], size : usize) -> Vec<(Vec<Tile>, Vec<Tile>)> {
+    let (first, others) = match tiles.split_first() {
+        Some((first, others)) => (*first, others),
+        None if size == 0 => return vec![(Vec::new(), Vec::new())],
+        None => return Vec::new(),
+    };
+    let mut found = Vec::new();
+    if size > 0 {
+        for (mut taken, left) in subsets(others, size - 1) {
+            taken.insert(0, first);
+            found.push((taken, left));
         }
+    }
+    for (taken, mut left) in subsets(others, size) {
+        left.insert(0, first);
+        found.push((taken, left));
+    }
+    found
+}
 
-        if hidden_tiles[i].value() == third_symbol {
-            let third = hidden_tiles.remove(i);
-
-            let wait = Some(Wait::new(vec![first, third], vec![first.next()?]));
+/// The decompositions of a block, computed once per thread for each counts and state.
+/// A decomposition found several times with the groups in another order is only kept once.
+fn decompositions(counts : &[u8], suited : bool, state : SearchState) -> Decompositions {
+    let key = (counts.iter().fold(0, |key, count| key << 4 | *count as u64), suited, state);
+    if let Some(found) = DECOMPOSITIONS.with(|memo| memo.borrow().get(&key).cloned()) {
+        return found;
+    }
+    let mut block = [0; 9];
+    block[..counts.len()].copy_from_slice(counts);
+    let mut found = Vec::new();
+    decompose(&mut block[..counts.len()], suited, state, &mut Vec::new(), &mut found);
+    for (shapes, _) in found.iter_mut() {
+        shapes.sort();
+    }
+    found.sort();
+    found.dedup();
+    let found = Rc::new(found);
+    DECOMPOSITIONS.with(|memo| memo.borrow_mut().insert(key, found.clone()));
+    found
+}
 
-            Some((
-                HandArrangement{
-                    groups : self.groups.clone(),
-                    wait,
-                },
-                hidden_tiles
-            ))
-        } else {
-            None
-        }
+/// Backtracking search of the ways to use every tile of a block, always starting with its lowest tile.
+/// Groups are tried before waits, and only one wait can be made in the hand.
+fn decompose(
+    counts : &mut [u8],
+    suited : bool,
+    state : SearchState,
+    shapes : &mut Vec<Shape>,
+    found : &mut Vec<(Vec<Shape>, SearchState)>,
+) {
+    let first = match counts.iter().position(|count| *count > 0) {
+        Some(first) => first,
+        None => {
+            found.push((shapes.clone(), state));
+            return;
+        },
+    };
+    let has = |counts : &[u8], index : usize| counts.get(index).is_some_and(|count| *count > 0);
+    let mut try_shape = |counts : &mut [u8], shape : Shape, used : &[usize], state : SearchState| {
+        used.iter().for_each(|index| counts[*index] -= 1);
+        shapes.push(shape);
+        decompose(counts, suited, state, shapes, found);
+        shapes.pop();
+        used.iter().for_each(|index| counts[*index] += 1);
+    };
+
+    let waiting = SearchState{wait : true, ..state};
+    if counts[first] >= 3 {
+        try_shape(counts, Shape::Pon(first), &[first; 3], state);
+    }
+    if counts[first] >= 2 && !state.pair {
+        try_shape(counts, Shape::Pair(first), &[first; 2], SearchState{pair : true, ..state});
+    }
+    if suited && has(counts, first + 1) && has(counts, first + 2) {
+        try_shape(counts, Shape::Chii(first), &[first, first + 1, first + 2], state);
+    }
+    if state.wait {
+        return;
+    }
+    if counts[first] >= 2 {
+        try_shape(counts, Shape::PonWait(first), &[first; 2], waiting);
+    }
+    if suited && counts[first] == 1 && has(counts, first + 1) {
+        try_shape(counts, Shape::SideWait(first), &[first, first + 1], waiting);
+    }
+    if suited && has(counts, first + 2) {
+        try_shape(counts, Shape::MiddleWait(first), &[first, first + 2], waiting);
+    }
+    if !state.pair {
+        try_shape(counts, Shape::PairWait(first), &[first], SearchState{pair : true, wait : true});
     }
 }
 
@@ -319,19 +433,25 @@ mod tests {
     use crate::tile::Tile;
     use crate::tile::suit::Suit;
     use crate::tile::dragon::Dragon;
-    use crate::hand::hand_arrangement::HandArrangement;
-    // TODO : assertions instead of prints
-    
+    use crate::tile::notation::{parse_tiles, to_mpsz};
+    use crate::game::rules::RedFives;
+    use crate::hand::hand_arrangement::{HandArrangement, Copies, Shape, copy_orders};
+    use crate::hand::wait::WaitKind;
+    use crate::tile::tile_value::{TileValue, TILE_KINDS};
+    use crate::tile::tile_counts::TileCounts;
+    use proptest::prelude::*;
+    use proptest::sample::subsequence;
+
     #[test]
     fn test_pair_wait() {
         let arrangement = HandArrangement::empty();
         let tiles = vec![
             Tile::new_dragon(Dragon::White, 0),
         ];
-        println!("{:?}", arrangement.consume(&tiles));
+        assert_eq!(vec![(WaitKind::Tanki, "5z".to_string())], tile_waits(&tiles));
+        assert!(arrangement.consume(&tiles)[0].groups().is_empty());
     }
 
-
     #[test]
     fn test_pon_wait() {
         let arrangement = HandArrangement::empty();
@@ -339,7 +459,8 @@ mod tests {
             Tile::new_dragon(Dragon::White, 0),
             Tile::new_dragon(Dragon::White, 1),
         ];
-        println!("{:?}", arrangement.consume(&tiles));
+        assert_eq!(vec![(WaitKind::Shanpon, "5z".to_string())], tile_waits(&tiles));
+        assert!(arrangement.consume(&tiles)[0].groups().is_empty());
     }
 
     #[test]
@@ -352,7 +473,8 @@ mod tests {
             Tile::new_dragon(Dragon::White, 0),
             Tile::new_dragon(Dragon::White, 1),
         ];
-        println!("{:?}", arrangement.consume(&tiles));
+        assert_eq!(vec![(WaitKind::Shanpon, "5z".to_string())], tile_waits(&tiles));
+        assert_eq!(1, arrangement.consume(&tiles)[0].groups().len());
     }
 
     #[test]
@@ -365,7 +487,8 @@ mod tests {
             Tile::new_dragon(Dragon::White, 0),
             Tile::new_dragon(Dragon::White, 1),
         ];
-        println!("{:?}", arrangement.consume(&tiles));
+        assert_eq!(vec![(WaitKind::Shanpon, "5z".to_string())], tile_waits(&tiles));
+        assert_eq!(1, arrangement.consume(&tiles)[0].groups().len());
     }
 
     #[test]
@@ -380,7 +503,7 @@ mod tests {
             Tile::new_dragon(Dragon::White, 0),
             Tile::new_dragon(Dragon::White, 1),
         ];
-        println!("{:?}", arrangement.consume(&tiles).len());
+        assert_eq!(3, arrangement.consume(&tiles).len());
     }
 
     #[test]
@@ -399,7 +522,8 @@ mod tests {
             Tile::new_dragon(Dragon::White, 0),
             Tile::new_dragon(Dragon::White, 1),
         ];
-        println!("{:?}", arrangement.consume(&tiles4));
+        assert_eq!(vec![(WaitKind::Shanpon, "5z".to_string()); 2], tile_waits(&tiles4));
+        assert!(arrangement.consume(&tiles4).iter().all(|arrangement| arrangement.groups().len() == 3));
     }
 
     #[test]
@@ -421,6 +545,204 @@ mod tests {
             Tile::new_suited(Suit::Sou, 5, 0),
         ];
 
-        println!("{:?}", arrangement.consume(&tiles));
+        assert_eq!(vec![(WaitKind::Ryanmen, "25m".to_string())], tile_waits(&tiles));
+        assert_eq!(4, arrangement.consume(&tiles)[0].groups().len());
+    }
+
+    #[test]
+    fn test_copies() {
+        let tiles = parse_tiles("34445m55z", RedFives::none()).unwrap();
+        let merged = HandArrangement::empty().consume(&tiles);
+        let mut values : Vec<_> = merged.iter().map(|arrangement| arrangement.values()).collect();
+        values.sort();
+        values.dedup();
+        assert_eq!(merged.len(), values.len());
+        // The 4m of the chii can be any of the three copies
+        let distinct = HandArrangement::empty().consume_with(&tiles, Copies::Distinct);
+        assert_eq!(7, distinct.len());
+        let mut distinct_values : Vec<_> = distinct.iter().map(|arrangement| arrangement.values()).collect();
+        distinct_values.sort();
+        distinct_values.dedup();
+        assert_eq!(values, distinct_values);
+    }
+
+    #[test]
+    fn test_copy_orders() {
+        let copies = parse_tiles("2344445m", RedFives::none()).unwrap();
+        let counts = *TileCounts::from_tiles(&copies).counts();
+        // The chii takes one of the four 4m, the pon the three others
+        let orders = copy_orders(&[Shape::Chii(1), Shape::Pon(3)], &counts, &copies);
+        assert_eq!(4, orders.len());
+        let mut chii_copies : Vec<Tile> = orders.iter().map(|order| order[2]).collect();
+        chii_copies.sort();
+        chii_copies.dedup();
+        assert_eq!(4, chii_copies.len());
+    }
+
+    /// The kinds of the waits of a hand and the tiles they wait on
+    fn waits(text : &str) -> Vec<(WaitKind, String)> {
+        tile_waits(&parse_tiles(text, RedFives::none()).unwrap())
+    }
+
+    fn tile_waits(tiles : &[Tile]) -> Vec<(WaitKind, String)> {
+        let mut waits : Vec<(WaitKind, String)> = HandArrangement::empty().consume(tiles).iter()
+            .map(|arrangement| arrangement.wait().as_ref().unwrap())
+            .map(|wait| (wait.kind(), to_mpsz(wait.wait_symbols())))
+            .collect();
+        waits.sort_by_key(|(kind, symbols)| (kind.fu(), symbols.clone()));
+        waits
+    }
+
+    #[test]
+    fn test_wait_kinds() {
+        assert_eq!(vec![(WaitKind::Ryanmen, "25m".to_string())], waits("34m456p789s11122z"));
+        assert_eq!(vec![(WaitKind::Penchan, "3m".to_string())], waits("12m456p789s11122z"));
+        assert_eq!(vec![(WaitKind::Penchan, "7s".to_string())], waits("123m456p89s11122z"));
+        assert_eq!(vec![(WaitKind::Kanchan, "2m".to_string())], waits("13m456p789s11122z"));
+        assert_eq!(vec![(WaitKind::Tanki, "2z".to_string())], waits("123m456p789s1112z"));
+        let shanpon = waits("11m456p789s11122z");
+        assert_eq!(2, shanpon.len());
+        assert!(shanpon.iter().all(|(kind, _)| *kind == WaitKind::Shanpon));
+        // 1234m is waiting on a single 1m or 4m
+        assert_eq!(
+            vec![(WaitKind::Tanki, "1m".to_string()), (WaitKind::Tanki, "4m".to_string())],
+            waits("1234m456p789s111z"),
+        );
+    }
+
+    #[test]
+    fn test_every_tile_used_once() {
+        let tiles = parse_tiles("1112345678999m", RedFives::none()).unwrap();
+        let arrangements = HandArrangement::empty().consume(&tiles);
+        assert!(!arrangements.is_empty());
+        // The second search uses the memoized decompositions
+        assert_eq!(format!("{:?}", arrangements), format!("{:?}", HandArrangement::empty().consume(&tiles)));
+        for arrangement in arrangements {
+            let mut used : Vec<Tile> = arrangement.groups().iter().flat_map(|group| group.tiles()).cloned().collect();
+            used.extend(arrangement.wait().as_ref().unwrap().tiles());
+            used.sort();
+            assert_eq!(tiles, used);
+        }
+    }
+
+    /// Reference check of a complete hand of groups and a pair, by brute force :
+    /// the lowest tile left is either in a pon, in the pair or the first tile of a chii
+    fn is_complete(counts : &mut [u8; TILE_KINDS], pair : bool) -> bool {
+        let first = match counts.iter().position(|count| *count > 0) {
+            Some(first) => first,
+            None => return pair,
+        };
+        let mut options : Vec<(Vec<usize>, bool)> = Vec::new();
+        if counts[first] >= 3 {
+            options.push((vec![first; 3], pair));
+        }
+        if counts[first] >= 2 && !pair {
+            options.push((vec![first; 2], true));
+        }
+        if first < 27 && first % 9 < 7 && counts[first + 1] > 0 && counts[first + 2] > 0 {
+            options.push((vec![first, first + 1, first + 2], pair));
+        }
+        options.into_iter().any(|(used, pair)| {
+            used.iter().for_each(|index| counts[*index] -= 1);
+            let complete = is_complete(counts, pair);
+            used.iter().for_each(|index| counts[*index] += 1);
+            complete
+        })
+    }
+
+    /// Reference waits of a hand : every tile value that makes it complete
+    fn reference_waits(tiles : &[Tile]) -> String {
+        let counts = TileCounts::from_tiles(tiles);
+        let waits : Vec<TileValue> = TileValue::all()
+            .filter(|value| {
+                let mut counts = *counts.counts();
+                counts[value.index() as usize] += 1;
+                is_complete(&mut counts, false)
+            })
+            .collect();
+        to_mpsz(&waits)
+    }
+
+    /// Every tile value waited on by the arrangements of a hand
+    fn arrangement_waits(tiles : &[Tile]) -> String {
+        let mut waits : Vec<TileValue> = HandArrangement::empty().consume(tiles).iter()
+            .flat_map(|arrangement| arrangement.wait().as_ref().unwrap().wait_symbols().clone())
+            .collect();
+        waits.sort_by_key(|value| value.index());
+        waits.dedup();
+        to_mpsz(&waits)
+    }
+
+    #[test]
+    fn test_tricky_waits() {
+        let corpus = [
+            // Nobetan
+            ("1234m456p789s111z", "14m"),
+            // Sanmenchan
+            ("34567m456p789s11z", "258m"),
+            ("2345678m789s111z", "258m"),
+            // Nine-sided chuuren
+            ("1112345678999m", "123456789m"),
+            ("3334m456p789s111z", "245m"),
+            ("2223m456p789s111z", "134m"),
+            ("5556777m789s111z", "45678m"),
+            ("1112223334445m", "23456m"),
+            // Kokushi and seven pairs are not arrangements of groups
+            ("19m19p19s1234567z", ""),
+            ("1133m5577p99s112z", ""),
+        ];
+        for (text, expected) in corpus.iter() {
+            let mut tiles = parse_tiles(text, RedFives::none()).unwrap();
+            tiles.sort();
+            assert_eq!(*expected, reference_waits(&tiles), "reference waits of {}", text);
+            assert_eq!(*expected, arrangement_waits(&tiles), "waits of {}", text);
+        }
+    }
+
+    /// Hands of 3n + 1 tiles, often of a single suit to find many waits
+    fn hands() -> impl Strategy<Value = Vec<Tile>> {
+        let indices = prop_oneof![
+            Just((0..136).collect::<Vec<u8>>()),
+            Just((0..36).collect::<Vec<u8>>()),
+            Just((0..36).chain(108..136).collect::<Vec<u8>>()),
+        ];
+        (indices, 0..5usize)
+            .prop_flat_map(|(indices, groups)| subsequence(indices, 3 * groups + 1))
+            .prop_map(|indices| {
+                // The index order of the dragons is not the order of the tiles
+                let mut tiles : Vec<Tile> = indices.into_iter().filter_map(Tile::from_index).collect();
+                tiles.sort();
+                tiles
+            })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1000))]
+
+        #[test]
+        fn test_waits_match_reference(tiles in hands()) {
+            prop_assert_eq!(reference_waits(&tiles), arrangement_waits(&tiles));
+        }
+
+        #[test]
+        fn test_arrangements_are_complete(tiles in hands()) {
+            let arrangements = HandArrangement::empty().consume(&tiles);
+            let mut values : Vec<_> = arrangements.iter().map(|arrangement| arrangement.values()).collect();
+            values.sort();
+            values.dedup();
+            prop_assert_eq!(arrangements.len(), values.len());
+            for arrangement in arrangements {
+                let wait = arrangement.wait().as_ref().unwrap();
+                let mut used : Vec<Tile> = arrangement.groups().iter().flat_map(|group| group.tiles()).cloned().collect();
+                used.extend(wait.tiles());
+                used.sort();
+                prop_assert_eq!(&tiles, &used);
+                for value in wait.wait_symbols() {
+                    let mut counts = *TileCounts::from_tiles(&tiles).counts();
+                    counts[value.index() as usize] += 1;
+                    prop_assert!(is_complete(&mut counts, false));
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
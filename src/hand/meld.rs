@@ -4,7 +4,8 @@ use crate::game::wind::Wind;
 
 /// Represents an open group, where one of the tiles comes
 /// from another player's hand
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Meld {
     group : Group,
     completing_tile : Tile, // The tile that comes from another player's discord 
@@ -12,7 +13,24 @@ pub struct Meld {
 }
 
 impl Meld {
+
+    pub fn new(group : Group, completing_tile : Tile, source : Wind) -> Meld {
+        Meld{group, completing_tile, source}
+    }
+
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    pub fn completing_tile(&self) -> Tile {
+        self.completing_tile
+    }
+
+    pub fn source(&self) -> Wind {
+        self.source
+    }
+
     pub fn tiles(&self) -> Vec<&Tile> {
         self.group.tiles()
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,117 @@
 pub mod group;
 pub mod meld;
 pub mod wait;
-pub mod hand_arrangement;
\ No newline at end of file
+pub mod hand_arrangement;
+pub mod shanten;
+
+use crate::tile::Tile;
+use crate::tile::tile_counts::TileCounts;
+use group::Group;
+use meld::Meld;
+
+/// The tiles owned by a player : the hidden tiles, the closed kans and the open melds.
+/// In a three player game, the north winds set aside as dora (nukidora) are also kept in the hand.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hand {
+    hidden_tiles : Vec<Tile>,
+    closed_kans : Vec<Group>,
+    melds : Vec<Meld>,
+    nuki : Vec<Tile>,
+}
+
+impl Hand {
+
+    // Constructors
+
+    pub fn new(mut hidden_tiles : Vec<Tile>) -> Self {
+        hidden_tiles.sort();
+        Hand{hidden_tiles, closed_kans : Vec::new(), melds : Vec::new(), nuki : Vec::new()}
+    }
+
+    // Immutable getters
+
+    /// The tiles of the closed part of the hand, sorted
+    pub fn hidden_tiles(&self) -> &Vec<Tile> {
+        &self.hidden_tiles
+    }
+
+    pub fn closed_kans(&self) -> &Vec<Group> {
+        &self.closed_kans
+    }
+
+    pub fn melds(&self) -> &Vec<Meld> {
+        &self.melds
+    }
+
+    /// The north winds set aside as nukidora
+    pub fn nuki(&self) -> &Vec<Tile> {
+        &self.nuki
+    }
+
+    /// Number of copies of each value in the closed part of the hand
+    pub fn tile_counts(&self) -> TileCounts {
+        TileCounts::from_tiles(&self.hidden_tiles)
+    }
+
+    // Mutators
+
+    /// Adds a tile to the closed part of the hand, keeping it sorted
+    pub fn add_tile(&mut self, tile : Tile) {
+        let index = self.hidden_tiles.binary_search(&tile).unwrap_or_else(|index| index);
+        self.hidden_tiles.insert(index, tile);
+    }
+
+    /// Removes a tile from the closed part of the hand.
+    /// Returns `false` if the hand does not contain this tile.
+    pub fn remove_tile(&mut self, tile : Tile) -> bool {
+        match self.hidden_tiles.binary_search(&tile) {
+            Ok(index) => {
+                self.hidden_tiles.remove(index);
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    pub fn add_closed_kan(&mut self, kan : Group) {
+        self.closed_kans.push(kan);
+    }
+
+    pub fn add_meld(&mut self, meld : Meld) {
+        self.melds.push(meld);
+    }
+
+    pub fn add_nuki(&mut self, tile : Tile) {
+        self.nuki.push(tile);
+    }
+
+    /// Turns an open pon into a kan by adding the fourth tile (shouminkan).
+    /// Returns `false` if there is no pon of this tile among the melds.
+    pub fn upgrade_pon(&mut self, tile : Tile) -> bool {
+        for meld in self.melds.iter_mut() {
+            if let Group::Pon(x, y, z) = *meld.group() {
+                if x.value() == tile.value() {
+                    *meld = Meld::new(Group::Kan(x, y, z, tile), meld.completing_tile(), meld.source());
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Other
+
+    /// Returns every tile of the hand, including melds and kans but not the nukidora
+    pub fn tiles(&self) -> Vec<&Tile> {
+        self.hidden_tiles.iter()
+            .chain(self.closed_kans.iter().flat_map(Group::tiles))
+            .chain(self.melds.iter().flat_map(Meld::tiles))
+            .collect()
+    }
+
+    /// A hand is closed as long as it has no open meld. Closed kans do not open the hand.
+    pub fn is_closed(&self) -> bool {
+        self.melds.is_empty()
+    }
+}
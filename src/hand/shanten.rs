@@ -0,0 +1,267 @@
+//! Shanten, agari and tenpai checks with precomputed lookup tables.
+//! Every pattern of counts of a suit (or of the honors) is summarized once for all,
+//! so that a check only looks up the four blocks of a hand and combines them.
+
+use std::sync::OnceLock;
+
+use crate::tile::tile_value::TileValue;
+use crate::tile::tile_counts::TileCounts;
+
+/// Number of melds of a complete hand
+const HAND_MELDS : usize = 4;
+/// Blocks with more tiles than a hand are not summarized
+const MAX_BLOCK_TILES : usize = 14;
+/// Value of a summary entry when no decomposition is possible
+const IMPOSSIBLE : u32 = 7;
+/// Indices of the terminals and honors
+const YAOCHUU : [usize; 13] = [0, 8, 9, 17, 18, 26, 27, 28, 29, 30, 31, 32, 33];
+
+/// Best decompositions of a set of tiles : for each number of heads (0 or 1) and of melds (0 to 4),
+/// the largest number of taatsu (incomplete groups, capped to 4) that can be made with the other tiles,
+/// or -1 if there is no such decomposition
+type Best = [[i8; HAND_MELDS + 1]; 2];
+
+const NONE : Best = [[-1; HAND_MELDS + 1]; 2];
+const EMPTY : Best = [[0, -1, -1, -1, -1], [-1; HAND_MELDS + 1]];
+
+/// Best decompositions of two sets of tiles put together
+fn combine(first : &Best, second : &Best) -> Best {
+    let mut best = NONE;
+    for head in 0..2 {
+        for (melds, taatsu) in first[head].iter().enumerate() {
+            if *taatsu < 0 {
+                continue;
+            }
+            for other_head in 0..2 - head {
+                let entries = best[head + other_head][melds..].iter_mut();
+                for (entry, other_taatsu) in entries.zip(second[other_head].iter()) {
+                    if *other_taatsu >= 0 {
+                        *entry = (*entry).max((taatsu + other_taatsu).min(HAND_MELDS as i8));
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+/// The entries of `Best` packed in 3 bits each, `IMPOSSIBLE` standing for -1
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Summary(u32);
+
+impl Summary {
+
+    fn pack(best : &Best) -> Summary {
+        let entries = best.iter().flatten().rev();
+        Summary(entries.fold(0, |packed, taatsu| packed << 3 | if *taatsu < 0 {IMPOSSIBLE} else {*taatsu as u32}))
+    }
+
+    fn unpack(self) -> Best {
+        let mut best = NONE;
+        for (i, entry) in best.iter_mut().flatten().enumerate() {
+            let taatsu = (self.0 >> (3 * i)) & 7;
+            *entry = if taatsu == IMPOSSIBLE {-1} else {taatsu as i8};
+        }
+        best
+    }
+}
+
+struct Tables {
+    suits : Vec<Summary>,
+    honors : Vec<Summary>,
+}
+
+/// The tables are built on first use, which takes a fraction of a second
+fn tables() -> &'static Tables {
+    static TABLES : OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| Tables {
+        suits : build_table(9, true),
+        honors : build_table(7, false),
+    })
+}
+
+/// Summarizes every pattern of a block of `len` values, a pattern being indexed by its counts in base 5.
+/// Removing tiles from a pattern gives a lower index, so the patterns are built from the summaries of the previous ones.
+fn build_table(len : usize, suited : bool) -> Vec<Summary> {
+    let powers : Vec<usize> = (0..len).map(|i| 5usize.pow(i as u32)).collect();
+    let mut table = vec![Summary::pack(&NONE); 5usize.pow(len as u32)];
+    let mut counts = vec![0; len];
+    let mut total = 0;
+    for key in 0..table.len() {
+        if key > 0 {
+            // Counts of the next pattern, the lowest value being the lowest digit
+            let mut digit = 0;
+            while counts[digit] == 4 {
+                counts[digit] = 0;
+                total -= 4;
+                digit += 1;
+            }
+            counts[digit] += 1;
+            total += 1;
+        }
+        if total > MAX_BLOCK_TILES {
+            continue;
+        }
+        let first = match counts.iter().position(|count| *count > 0) {
+            Some(first) => first,
+            None => {
+                table[key] = Summary::pack(&EMPTY);
+                continue;
+            },
+        };
+        let has = |index : usize| suited && index < len && counts[index] > 0;
+
+        // The lowest tile is either left alone or the lowest tile of a group
+        let mut best = NONE;
+        let mut offer = |removed : &[usize], head : usize, melds : usize, taatsu : i8| {
+            let mut group = NONE;
+            group[head][melds] = taatsu;
+            let sub = table[key - removed.iter().map(|index| powers[*index]).sum::<usize>()].unpack();
+            let found = combine(&sub, &group);
+            for (entry, other) in best.iter_mut().flatten().zip(found.iter().flatten()) {
+                *entry = (*entry).max(*other);
+            }
+        };
+        offer(&[first], 0, 0, 0);
+        if counts[first] >= 3 {
+            offer(&[first; 3], 0, 1, 0);
+        }
+        if counts[first] >= 2 {
+            offer(&[first; 2], 1, 0, 0);
+            offer(&[first; 2], 0, 0, 1);
+        }
+        if has(first + 1) && has(first + 2) {
+            offer(&[first, first + 1, first + 2], 0, 1, 0);
+        }
+        if has(first + 1) {
+            offer(&[first, first + 1], 0, 0, 1);
+        }
+        if has(first + 2) {
+            offer(&[first, first + 2], 0, 0, 1);
+        }
+        table[key] = Summary::pack(&best);
+    }
+    table
+}
+
+fn key(counts : &[u8]) -> usize {
+    counts.iter().rev().fold(0, |key, count| key * 5 + *count as usize)
+}
+
+/// Best decompositions of a whole hand, or `None` if it holds more than 4 copies of a tile
+fn hand_best(counts : &TileCounts) -> Option<Best> {
+    let counts = counts.counts();
+    if counts.iter().any(|count| *count > 4) {
+        return None;
+    }
+    let tables = tables();
+    let mut best = tables.honors[key(&counts[27..])].unpack();
+    for suit in 0..3 {
+        best = combine(&best, &tables.suits[key(&counts[suit * 9..suit * 9 + 9])].unpack());
+    }
+    Some(best)
+}
+
+/// Shanten of a hand in the regular form of four melds and a pair, `melds` being the number of called melds and kans.
+/// Hands holding more than 14 tiles or more than 4 copies of a tile are counted as 8 shanten.
+/// A tenpai hand has a shanten of 0 and a complete hand a shanten of -1.
+pub fn regular_shanten(counts : &TileCounts, melds : usize) -> i8 {
+    let best = match hand_best(counts) {
+        Some(best) => best,
+        None => return 8,
+    };
+    let mut shanten = 8;
+    for (head, entries) in best.iter().enumerate() {
+        for (hand_melds, taatsu) in entries.iter().enumerate().take(HAND_MELDS + 1 - melds.min(HAND_MELDS)) {
+            if *taatsu >= 0 {
+                let melds = (hand_melds + melds) as i8;
+                let taatsu = (*taatsu).min(HAND_MELDS as i8 - melds);
+                shanten = shanten.min(8 - 2 * melds - taatsu - head as i8);
+            }
+        }
+    }
+    shanten
+}
+
+/// Shanten of a closed hand toward seven pairs (chiitoitsu)
+pub fn chiitoitsu_shanten(counts : &TileCounts) -> i8 {
+    let pairs = counts.counts().iter().filter(|count| **count >= 2).count() as i8;
+    let kinds = counts.counts().iter().filter(|count| **count >= 1).count() as i8;
+    6 - pairs + (7 - kinds).max(0)
+}
+
+/// Shanten of a closed hand toward thirteen orphans (kokushi musou)
+pub fn kokushi_shanten(counts : &TileCounts) -> i8 {
+    let counts = counts.counts();
+    let kinds = YAOCHUU.iter().filter(|index| counts[**index] >= 1).count() as i8;
+    let pair = YAOCHUU.iter().any(|index| counts[*index] >= 2);
+    13 - kinds - pair as i8
+}
+
+/// Smallest shanten of the regular form, and of seven pairs and thirteen orphans for a closed hand
+pub fn shanten(counts : &TileCounts, melds : usize) -> i8 {
+    let regular = regular_shanten(counts, melds);
+    if melds > 0 {
+        regular
+    } else {
+        regular.min(chiitoitsu_shanten(counts)).min(kokushi_shanten(counts))
+    }
+}
+
+/// Whether the hidden tiles and the `melds` called melds and kans make a complete hand
+pub fn is_agari(counts : &TileCounts, melds : usize) -> bool {
+    shanten(counts, melds) == -1
+}
+
+/// Whether the hand needs one tile to be complete
+pub fn is_tenpai(counts : &TileCounts, melds : usize) -> bool {
+    shanten(counts, melds) == 0
+}
+
+/// The tiles that would complete the hand, among the ones that are not all in the hand
+pub fn waits(counts : &TileCounts, melds : usize) -> Vec<TileValue> {
+    TileValue::all()
+        .filter(|value| {
+            let mut counts = *counts;
+            counts[*value] < 4 && {
+                counts.add(*value);
+                is_agari(&counts, melds)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shanten, regular_shanten, chiitoitsu_shanten, kokushi_shanten, waits, is_agari};
+    use crate::tile::tile_counts::TileCounts;
+    use crate::tile::notation::{parse_tiles, to_mpsz};
+    use crate::game::rules::RedFives;
+
+    fn counts(text : &str) -> TileCounts {
+        TileCounts::from_tiles(&parse_tiles(text, RedFives::none()).unwrap())
+    }
+
+    #[test]
+    fn test_shanten() {
+        assert!(is_agari(&counts("123m456p789s11122z"), 0));
+        assert_eq!(0, shanten(&counts("123m456p789s1112z"), 0));
+        assert_eq!(1, shanten(&counts("1233m456p789s117z"), 0));
+        assert_eq!(6, shanten(&counts("147m258p369s1234z"), 0));
+        assert_eq!(8, regular_shanten(&counts("147m258p369s1357z"), 0));
+        assert_eq!(0, chiitoitsu_shanten(&counts("1133m5577p99s112z")));
+        assert_eq!(0, kokushi_shanten(&counts("19m19p19s1234566z")));
+        // With three called melds
+        assert_eq!(0, regular_shanten(&counts("23m55p"), 3));
+        assert_eq!(-1, regular_shanten(&counts("234m55p"), 3));
+    }
+
+    #[test]
+    fn test_waits() {
+        assert_eq!("2z", to_mpsz(&waits(&counts("123m456p789s1112z"), 0)));
+        assert_eq!("123456789m", to_mpsz(&waits(&counts("1112345678999m"), 0)));
+        assert_eq!("2z", to_mpsz(&waits(&counts("1133m5577p99s112z"), 0)));
+        assert_eq!("19m19p19s1234567z", to_mpsz(&waits(&counts("19m19p19s1234567z"), 0)));
+        assert_eq!("14m", to_mpsz(&waits(&counts("23m55p"), 3)));
+    }
+}
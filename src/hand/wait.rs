@@ -1,18 +1,46 @@
 use crate::tile::Tile;
 use crate::tile::tile_value::TileValue;
 
+/// The shape of a wait, which matters for pinfu and for the fu of a hand
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WaitKind {
+    /// Two consecutive tiles waiting on both sides, like 34m waiting on 2m and 5m
+    Ryanmen,
+    /// Two tiles waiting on the one in between, like 35m waiting on 4m
+    Kanchan,
+    /// 12 waiting on 3 or 89 waiting on 7
+    Penchan,
+    /// A pair waiting to become a pon, the hand holding another pair
+    Shanpon,
+    /// A single tile waiting on its pair
+    Tanki,
+}
+
+impl WaitKind {
+    /// Fu given by the wait when winning
+    pub fn fu(&self) -> u32 {
+        match self {
+            WaitKind::Kanchan | WaitKind::Penchan | WaitKind::Tanki => 2,
+            WaitKind::Ryanmen | WaitKind::Shanpon => 0,
+        }
+    }
+}
+
 /// Represents a group of tiles where one tile is missing.
 /// It is used to know on what tiles a hand is waiting.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Wait {
     tiles : Vec<Tile>,
     wait_symbols : Vec<TileValue>,
+    kind : WaitKind,
 }
 
 impl Wait {
 
-    pub fn new(tiles : Vec<Tile>, wait_symbols : Vec<TileValue>) -> Wait {
-        Wait{tiles, wait_symbols}
+    pub fn new(tiles : Vec<Tile>, wait_symbols : Vec<TileValue>, kind : WaitKind) -> Wait {
+        Wait{tiles, wait_symbols, kind}
     }
 
     pub fn tiles(&self) -> &Vec<Tile> {
@@ -22,4 +50,8 @@ impl Wait {
     pub fn wait_symbols(&self) -> &Vec<TileValue> {
         &self.wait_symbols
     }
-}
\ No newline at end of file
+
+    pub fn kind(&self) -> WaitKind {
+        self.kind
+    }
+}
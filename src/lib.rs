@@ -0,0 +1,11 @@
+pub mod tile;
+pub mod game;
+pub mod hand;
+pub mod scoring;
+pub mod record;
+pub mod mjai;
+pub mod bot;
+#[cfg(feature = "discord")]
+pub mod discord;
+#[cfg(feature = "render")]
+pub mod render;
@@ -1,7 +0,0 @@
-pub mod tile;
-pub mod game;
-pub mod hand;
-
-fn main() {
-    println!("Hello, world!");
-}
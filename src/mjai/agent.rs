@@ -0,0 +1,512 @@
+use std::time::Duration;
+
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::tile::notation::TileAllocator;
+use crate::hand::Hand;
+use crate::hand::meld::Meld;
+use crate::hand::group::Group;
+use crate::hand::shanten::{is_tenpai, waits};
+use crate::game::wind::Wind;
+use crate::game::rules::RuleSet;
+use crate::game::wall::{Wall, DEAD_WALL_SIZE};
+use crate::game::round::HAND_SIZE;
+use crate::game::pond::{Pond, Discard};
+use crate::game::event::Event;
+use crate::game::action::Action;
+use crate::game::clock::TimeControl;
+use crate::game::table::{Update, RIICHI_DEPOSIT};
+use crate::game::host::{PlayerInterface, Observation, RoundView, PublicState};
+use crate::scoring::yaku::WinContext;
+use crate::scoring::hand_value::evaluate;
+use crate::mjai::{Message, MjaiError, Pai};
+use crate::mjai::client::MjaiAgent;
+use crate::mjai::server::find_tiles;
+
+/// Plays on an mjai server with one of our players, like the `RuleBot`.
+/// The agent follows the game from the messages of the server to tell the player about it, and asks the player
+/// to act with the legal actions it can work out, the server having the last word on them.
+/// The ends of the rounds are not told to the player, as mjai does not give the yaku of the wins.
+pub struct PlayerAgent<P> {
+    player : P,
+    rules : RuleSet,
+    /// The time given to the player for each action
+    timeout : Duration,
+    seat : usize,
+    scores : Vec<i32>,
+    allocator : TileAllocator,
+    round : Option<RoundView>,
+    /// The last discard, or the last tile added to a pon, and the player who played it
+    last_tile : Option<(usize, Tile)>,
+    /// The player whose next discard declares riichi
+    reaching : Option<usize>,
+    /// The discard chosen with a riichi declaration, sent once the server announces the declaration
+    riichi_discard : Option<Tile>,
+    /// Whether the next draw of the player is the replacement tile of a kan
+    rinshan : bool,
+}
+
+impl<P : PlayerInterface> PlayerAgent<P> {
+
+    // Constructors
+
+    /// Plays with `player`, the rules of the server being `rules`
+    pub fn new(player : P, rules : RuleSet) -> PlayerAgent<P> {
+        let allocator = TileAllocator::new(rules.red_fives);
+        PlayerAgent {
+            player,
+            rules,
+            timeout : TimeControl::online().base,
+            seat : 0,
+            scores : Vec::new(),
+            allocator,
+            round : None,
+            last_tile : None,
+            reaching : None,
+            riichi_discard : None,
+            rinshan : false,
+        }
+    }
+
+    // Immutable getters
+
+    pub fn player(&self) -> &P {
+        &self.player
+    }
+
+    // Mutators
+
+    pub fn set_timeout(&mut self, timeout : Duration) {
+        self.timeout = timeout;
+    }
+
+    // Other
+
+    fn round(&self) -> Result<&RoundView, MjaiError> {
+        self.round.as_ref().ok_or_else(|| MjaiError::Protocol("no round is being played".to_string()))
+    }
+
+    fn round_mut(&mut self) -> Result<&mut RoundView, MjaiError> {
+        self.round.as_mut().ok_or_else(|| MjaiError::Protocol("no round is being played".to_string()))
+    }
+
+    fn seat_wind(round : &RoundView, player : usize) -> Wind {
+        let count = round.players.len();
+        Wind::seats(count as u8)[(player + count - round.dealer) % count]
+    }
+
+    /// The tiles played by `actor` : found in the hand of the player, allocated for the others
+    fn tiles(&mut self, actor : usize, pais : &[Pai]) -> Result<Vec<Tile>, MjaiError> {
+        if actor == self.seat {
+            find_tiles(pais, &self.round()?.hand, &self.rules)
+        } else {
+            pais.iter().map(|pai| Ok(self.allocator.allocate(pai.value, pai.red)?)).collect()
+        }
+    }
+
+    /// The tile taken by a call, which is the last discard
+    fn called(&mut self, target : usize, pai : Pai) -> Result<Tile, MjaiError> {
+        match self.last_tile {
+            Some((player, tile)) if player == target && pai.matches(tile, &self.rules) => Ok(tile),
+            _ => Err(MjaiError::Protocol(format!("{} is not the last discard of player {}", pai.name(), target))),
+        }
+    }
+
+    fn start(&mut self, message : &Message) -> Result<Update, MjaiError> {
+        let (bakaze, oya, honba, kyotaku, dora_marker, scores, tehais) = match message {
+            Message::StartKyoku { bakaze, oya, honba, kyotaku, dora_marker, scores, tehais, .. } => (*bakaze, *oya, *honba, *kyotaku, *dora_marker, scores, tehais),
+            _ => return Err(MjaiError::Protocol(format!("{:?} does not start a round", message))),
+        };
+        self.allocator = TileAllocator::new(self.rules.red_fives);
+        let dora_indicator = self.allocator.allocate(dora_marker.value, dora_marker.red)?;
+        let own = tehais.get(self.seat).ok_or_else(|| MjaiError::Protocol("the hand of the player is missing".to_string()))?;
+        let mut hand = Vec::new();
+        for pai in own {
+            let pai = pai.ok_or_else(|| MjaiError::Protocol("the hand of the player is hidden".to_string()))?;
+            hand.push(self.allocator.allocate(pai.value, pai.red)?);
+        }
+        let players = tehais.len();
+        let public = PublicState{pond : Pond::new(), melds : Vec::new(), closed_kans : Vec::new(), nuki : Vec::new(), riichi : false, passed : Vec::new()};
+        let mut round = RoundView {
+            round_wind : bakaze,
+            seat_wind : Wind::East,
+            dealer : oya,
+            honba,
+            riichi_sticks : kyotaku,
+            remaining : Wall::tile_set(&self.rules).len() - DEAD_WALL_SIZE - HAND_SIZE * players,
+            dora_indicators : vec![dora_indicator],
+            hand : Hand::new(hand.clone()),
+            drawn : None,
+            players : vec![public; players],
+        };
+        round.seat_wind = PlayerAgent::<P>::seat_wind(&round, self.seat);
+        self.scores = scores.clone();
+        self.round = Some(round);
+        self.last_tile = None;
+        self.reaching = None;
+        self.riichi_discard = None;
+        self.rinshan = false;
+        let mut hands = vec![Vec::new(); players];
+        hands[self.seat] = hand;
+        Ok(Update::RoundStarted{round_wind : bakaze, dealer : oya, honba, riichi_sticks : kyotaku, scores : scores.clone(), dora_indicator, hands})
+    }
+
+    /// Follows an event in the view of the round
+    fn apply(&mut self, event : &Event) -> Result<(), MjaiError> {
+        let seat = self.seat;
+        let round = self.round.as_mut().ok_or_else(|| MjaiError::Protocol("no round is being played".to_string()))?;
+        match *event {
+            Event::Draw { tile, .. } => {
+                round.hand.add_tile(tile);
+                round.drawn = Some(tile);
+                round.remaining = round.remaining.saturating_sub(1);
+            },
+            Event::Discard { player, tile, tsumogiri, riichi } => {
+                if player == seat {
+                    round.hand.remove_tile(tile);
+                    round.drawn = None;
+                    self.rinshan = false;
+                }
+                let state = &mut round.players[player];
+                state.pond.push(Discard{tile, tsumogiri, riichi, called : false});
+                state.riichi |= riichi;
+                for (other, state) in round.players.iter_mut().enumerate() {
+                    if other != player && state.riichi {
+                        state.passed.push(tile.value());
+                    }
+                }
+                self.last_tile = Some((player, tile));
+            },
+            Event::Chii { player, from, called, tiles } | Event::Pon { player, from, called, tiles } => {
+                let group = match event {
+                    Event::Chii { .. } => {
+                        let mut group = [called, tiles[0], tiles[1]];
+                        group.sort();
+                        Group::Chii(group[0], group[1], group[2])
+                    },
+                    _ => Group::Pon(tiles[0], tiles[1], called),
+                };
+                let meld = Meld::new(group, called, PlayerAgent::<P>::seat_wind(round, from));
+                PlayerAgent::<P>::call(round, seat, player, from, &tiles, meld);
+            },
+            Event::OpenKan { player, from, called, tiles } => {
+                let meld = Meld::new(Group::Kan(tiles[0], tiles[1], tiles[2], called), called, PlayerAgent::<P>::seat_wind(round, from));
+                PlayerAgent::<P>::call(round, seat, player, from, &tiles, meld);
+                self.rinshan = player == seat;
+            },
+            Event::ClosedKan { player, tiles } => {
+                let kan = Group::Kan(tiles[0], tiles[1], tiles[2], tiles[3]);
+                if player == seat {
+                    tiles.iter().for_each(|tile| { round.hand.remove_tile(*tile); });
+                    round.hand.add_closed_kan(kan.clone());
+                    round.drawn = None;
+                    self.rinshan = true;
+                }
+                round.players[player].closed_kans.push(kan);
+            },
+            Event::AddedKan { player, tile } => {
+                if player == seat {
+                    round.hand.upgrade_pon(tile);
+                    round.hand.remove_tile(tile);
+                    round.drawn = None;
+                    self.rinshan = true;
+                }
+                for meld in round.players[player].melds.iter_mut() {
+                    if let Group::Pon(x, y, z) = *meld.group() {
+                        if x.value() == tile.value() {
+                            *meld = Meld::new(Group::Kan(x, y, z, tile), meld.completing_tile(), meld.source());
+                        }
+                    }
+                }
+                self.last_tile = Some((player, tile));
+            },
+            Event::Nuki { player, tile } => {
+                if player == seat {
+                    round.hand.remove_tile(tile);
+                    round.hand.add_nuki(tile);
+                    round.drawn = None;
+                    self.rinshan = true;
+                }
+                round.players[player].nuki.push(tile);
+            },
+            Event::NewDora { indicator } => round.dora_indicators.push(indicator),
+        }
+        Ok(())
+    }
+
+    fn call(round : &mut RoundView, seat : usize, player : usize, from : usize, tiles : &[Tile], meld : Meld) {
+        round.players[from].pond.mark_last_called();
+        if player == seat {
+            tiles.iter().for_each(|tile| { round.hand.remove_tile(*tile); });
+            round.hand.add_meld(meld.clone());
+        }
+        round.players[player].melds.push(meld);
+    }
+
+    /// Whether the hand of the player wins with the tile
+    fn wins(&self, round : &RoundView, tile : Tile, tsumo : bool, chankan : bool) -> bool {
+        let mut hand = round.hand.clone();
+        if !tsumo {
+            hand.add_tile(tile);
+            let melds = hand.melds().len() + hand.closed_kans().len();
+            if waits(&round.hand.tile_counts(), melds).into_iter().any(|value| round.players[self.seat].pond.contains(value)) {
+                return false;
+            }
+        }
+        let context = WinContext {
+            riichi : round.players[self.seat].riichi,
+            last_tile : round.remaining == 0 && !self.rinshan,
+            rinshan : tsumo && self.rinshan,
+            chankan,
+            ..WinContext::new(tile, tsumo, round.seat_wind, round.round_wind)
+        };
+        evaluate(&hand, &context, &round.dora_indicators, &[], &self.rules).is_some()
+    }
+
+    /// The actions of the player on their turn, after a draw or a call
+    fn turn_actions(&self, round : &RoundView) -> Vec<Action> {
+        let hand = &round.hand;
+        let hidden = hand.hidden_tiles();
+        let riichi = round.players[self.seat].riichi;
+        let mut actions = Vec::new();
+        if let Some(drawn) = round.drawn {
+            if self.wins(round, drawn, true, false) {
+                actions.push(Action::Tsumo);
+            }
+            if round.remaining > 0 && !riichi {
+                for tile in hidden.iter() {
+                    let copies : Vec<Tile> = hidden.iter().filter(|other| other.value() == tile.value()).cloned().collect();
+                    if copies.len() == 4 && copies[0] == *tile {
+                        actions.push(Action::ClosedKan{tiles : [copies[0], copies[1], copies[2], copies[3]]});
+                    }
+                    if hand.melds().iter().any(|meld| matches!(meld.group(), Group::Pon(x, _, _) if x.value() == tile.value())) {
+                        actions.push(Action::AddedKan{tile : *tile});
+                    }
+                }
+            }
+            if self.rules.players.is_sanma() && round.remaining > 0 {
+                if let Some(north) = hidden.iter().find(|tile| tile.value() == TileValue::new_wind(Wind::North)) {
+                    actions.push(Action::Nuki{tile : *north});
+                }
+            }
+        }
+
+        let mut discards : Vec<Tile> = match round.drawn {
+            Some(drawn) if riichi => vec![drawn],
+            _ => hidden.clone(),
+        };
+        discards.dedup_by(|x, y| Pai::from_tile(*x, &self.rules) == Pai::from_tile(*y, &self.rules));
+        actions.extend(discards.iter().map(|tile| Action::Discard{tile : *tile, riichi : false}));
+        let can_riichi = !riichi
+            && hand.is_closed()
+            && round.drawn.is_some()
+            && self.scores.get(self.seat).is_some_and(|score| *score >= RIICHI_DEPOSIT)
+            && round.remaining >= round.players.len();
+        if can_riichi {
+            for tile in discards.iter() {
+                let mut counts = hand.tile_counts();
+                counts.remove(tile.value());
+                if is_tenpai(&counts, hand.melds().len() + hand.closed_kans().len()) {
+                    actions.push(Action::Discard{tile : *tile, riichi : true});
+                }
+            }
+        }
+        actions
+    }
+
+    /// The calls of the player on a tile discarded, or added to a pon, by `from`
+    fn call_actions(&self, round : &RoundView, from : usize, tile : Tile, added_kan : bool) -> Vec<Action> {
+        let hidden = round.hand.hidden_tiles();
+        let mut actions = Vec::new();
+        if self.wins(round, tile, false, added_kan) {
+            actions.push(Action::Ron);
+        }
+        if !added_kan && !round.players[self.seat].riichi && round.remaining > 0 {
+            let same : Vec<Tile> = hidden.iter().filter(|other| other.value() == tile.value()).cloned().collect();
+            if same.len() >= 2 {
+                actions.push(Action::Pon{tiles : [same[0], same[1]]});
+            }
+            if same.len() == 3 {
+                actions.push(Action::OpenKan{tiles : [same[0], same[1], same[2]]});
+            }
+            if self.seat == (from + 1) % round.players.len() && self.rules.players.allows_chii() {
+                let value = tile.value();
+                let shapes = [
+                    (value.prev().and_then(|prev| prev.prev()), value.prev()),
+                    (value.prev(), value.next()),
+                    (value.next(), value.next().and_then(|next| next.next())),
+                ];
+                let find = |value : Option<_>| hidden.iter().find(|tile| Some(tile.value()) == value).cloned();
+                for (first, second) in shapes.iter() {
+                    if let (Some(x), Some(y)) = (find(*first), find(*second)) {
+                        actions.push(Action::Chii{tiles : [x, y]});
+                    }
+                }
+            }
+        }
+        if !actions.is_empty() {
+            actions.push(Action::Pass);
+        }
+        actions
+    }
+
+    /// Asks the player to choose one of the actions and writes it as an answer to the server
+    fn decide(&mut self, legal_actions : Vec<Action>) -> Result<Message, MjaiError> {
+        if legal_actions.is_empty() {
+            return Ok(Message::None);
+        }
+        let round = self.round()?.clone();
+        let observation = Observation{seat : self.seat, scores : self.scores.clone(), round : Some(round.clone()), legal_actions};
+        let action = self.player.act(&observation, self.timeout)
+            .filter(|action| observation.legal_actions.contains(action))
+            .or_else(|| observation.default_action())
+            .unwrap_or(Action::Pass);
+        let actor = self.seat;
+        let rules = &self.rules;
+        let pai = |tile : Tile| Pai::from_tile(tile, rules);
+        let pais = |tiles : &[Tile]| tiles.iter().map(|tile| pai(*tile)).collect::<Vec<Pai>>();
+        let last = self.last_tile.ok_or_else(|| MjaiError::Protocol("there is no tile to call".to_string()));
+        let response = match action {
+            Action::Discard { tile, riichi : false } => Message::Dahai{actor, pai : pai(tile), tsumogiri : Some(tile) == round.drawn},
+            Action::Discard { tile, riichi : true } => {
+                self.riichi_discard = Some(tile);
+                Message::Reach{actor}
+            },
+            Action::Chii { tiles } => {
+                let (target, called) = last?;
+                Message::Chi{actor, target, pai : pai(called), consumed : pais(&tiles)}
+            },
+            Action::Pon { tiles } => {
+                let (target, called) = last?;
+                Message::Pon{actor, target, pai : pai(called), consumed : pais(&tiles)}
+            },
+            Action::OpenKan { tiles } => {
+                let (target, called) = last?;
+                Message::Daiminkan{actor, target, pai : pai(called), consumed : pais(&tiles)}
+            },
+            Action::ClosedKan { tiles } => Message::Ankan{actor, consumed : pais(&tiles)},
+            Action::AddedKan { tile } => {
+                let consumed = round.hand.melds().iter()
+                    .find_map(|meld| match *meld.group() {
+                        Group::Pon(x, y, z) if x.value() == tile.value() => Some(pais(&[x, y, z])),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                Message::Kakan{actor, pai : pai(tile), consumed}
+            },
+            Action::Nuki { tile } => Message::Nukidora{actor, pai : pai(tile)},
+            Action::Tsumo => Message::Hora{actor, target : actor, deltas : Vec::new(), ura_markers : Vec::new()},
+            Action::Ron => Message::Hora{actor, target : last?.0, deltas : Vec::new(), ura_markers : Vec::new()},
+            Action::NineTerminals => Message::Ryukyoku{deltas : Vec::new()},
+            Action::Pass => Message::None,
+        };
+        Ok(response)
+    }
+
+    /// Follows a message of the server, returning the update told to the player and the answer to the server
+    fn follow(&mut self, message : &Message) -> Result<Message, MjaiError> {
+        let seat = self.seat;
+        let event = match message {
+            Message::StartGame { id, .. } => {
+                self.seat = *id;
+                return Ok(Message::None);
+            },
+            Message::StartKyoku { .. } => {
+                let update = self.start(message)?;
+                self.player.observe(&[update]);
+                return Ok(Message::None);
+            },
+            Message::Tsumo { actor, pai : Some(pai) } if *actor == seat => {
+                let tile = self.allocator.allocate(pai.value, pai.red)?;
+                Event::Draw{player : seat, tile}
+            },
+            Message::Tsumo { actor, .. } => {
+                let round = self.round_mut()?;
+                round.remaining = round.remaining.saturating_sub(1);
+                self.player.observe(&[Update::HiddenDraw{player : *actor}]);
+                return Ok(Message::None);
+            },
+            Message::Dahai { actor, pai, tsumogiri } => {
+                let tile = match self.round()?.drawn {
+                    Some(drawn) if *actor == seat && *tsumogiri => drawn,
+                    _ => self.tiles(*actor, &[*pai])?[0],
+                };
+                let riichi = self.reaching == Some(*actor);
+                if riichi {
+                    self.reaching = None;
+                }
+                Event::Discard{player : *actor, tile, tsumogiri : *tsumogiri, riichi}
+            },
+            Message::Reach { actor } => {
+                self.reaching = Some(*actor);
+                return match self.riichi_discard.take() {
+                    Some(tile) if *actor == seat => {
+                        let tsumogiri = Some(tile) == self.round()?.drawn;
+                        Ok(Message::Dahai{actor : seat, pai : Pai::from_tile(tile, &self.rules), tsumogiri})
+                    },
+                    _ => Ok(Message::None),
+                };
+            },
+            Message::ReachAccepted { actor } => {
+                if let Some(score) = self.scores.get_mut(*actor) {
+                    *score -= RIICHI_DEPOSIT;
+                }
+                self.round_mut()?.riichi_sticks += 1;
+                self.player.observe(&[Update::RiichiAccepted{player : *actor}]);
+                return Ok(Message::None);
+            },
+            Message::Chi { actor, target, pai, consumed } => {
+                let (called, tiles) = (self.called(*target, *pai)?, self.tiles(*actor, consumed)?);
+                Event::Chii{player : *actor, from : *target, called, tiles : [tiles[0], tiles[1]]}
+            },
+            Message::Pon { actor, target, pai, consumed } => {
+                let (called, tiles) = (self.called(*target, *pai)?, self.tiles(*actor, consumed)?);
+                Event::Pon{player : *actor, from : *target, called, tiles : [tiles[0], tiles[1]]}
+            },
+            Message::Daiminkan { actor, target, pai, consumed } => {
+                let (called, tiles) = (self.called(*target, *pai)?, self.tiles(*actor, consumed)?);
+                Event::OpenKan{player : *actor, from : *target, called, tiles : [tiles[0], tiles[1], tiles[2]]}
+            },
+            Message::Ankan { actor, consumed } => {
+                let tiles = self.tiles(*actor, consumed)?;
+                Event::ClosedKan{player : *actor, tiles : [tiles[0], tiles[1], tiles[2], tiles[3]]}
+            },
+            Message::Kakan { actor, pai, .. } => Event::AddedKan{player : *actor, tile : self.tiles(*actor, &[*pai])?[0]},
+            Message::Nukidora { actor, pai } => Event::Nuki{player : *actor, tile : self.tiles(*actor, &[*pai])?[0]},
+            Message::Dora { dora_marker } => Event::NewDora{indicator : self.allocator.allocate(dora_marker.value, dora_marker.red)?},
+            Message::Hora { deltas, .. } | Message::Ryukyoku { deltas } => {
+                if deltas.len() == self.scores.len() {
+                    self.scores.iter_mut().zip(deltas.iter()).for_each(|(score, delta)| *score += delta);
+                }
+                return Ok(Message::None);
+            },
+            Message::EndKyoku => {
+                self.round = None;
+                return Ok(Message::None);
+            },
+            Message::EndGame => {
+                self.player.observe(&[Update::GameEnded{scores : self.scores.clone()}]);
+                return Ok(Message::None);
+            },
+            _ => return Ok(Message::None),
+        };
+        self.apply(&event)?;
+        self.player.observe(&[Update::Event(event.clone())]);
+        let round = self.round()?;
+        let actions = match event {
+            Event::Draw { .. } => self.turn_actions(round),
+            Event::Chii { player, .. } | Event::Pon { player, .. } if player == seat => self.turn_actions(round),
+            Event::Discard { player, tile, .. } if player != seat => self.call_actions(round, player, tile, false),
+            Event::AddedKan { player, tile } if player != seat => self.call_actions(round, player, tile, true),
+            _ => Vec::new(),
+        };
+        self.decide(actions)
+    }
+}
+
+impl<P : PlayerInterface> MjaiAgent for PlayerAgent<P> {
+    /// A message the agent cannot follow is answered by `none`
+    fn respond(&mut self, message : &Message) -> Message {
+        self.follow(message).unwrap_or(Message::None)
+    }
+}
@@ -0,0 +1,52 @@
+use crate::mjai::{Message, MjaiError};
+use crate::mjai::connection::Connection;
+
+/// Decides the answers of one of our players connected to an mjai server
+pub trait MjaiAgent {
+    /// Answers a message of the server, with an action or with `Message::None`
+    fn respond(&mut self, message : &Message) -> Message;
+}
+
+impl<F> MjaiAgent for F where F : FnMut(&Message) -> Message {
+    fn respond(&mut self, message : &Message) -> Message {
+        self(message)
+    }
+}
+
+/// Plays a game on an mjai server : joins the room when greeted,
+/// then answers every message with the agent until the end of the game.
+pub fn run_client(connection : &mut Connection, name : &str, room : &str, agent : &mut dyn MjaiAgent) -> Result<(), MjaiError> {
+    while let Some(message) = connection.receive()? {
+        let response = match message {
+            Message::Hello => Message::Join{name : name.to_string(), room : room.to_string()},
+            _ => agent.respond(&message),
+        };
+        connection.send(&response)?;
+        if message == Message::EndGame {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, sink};
+
+    use super::run_client;
+    use crate::mjai::Message;
+    use crate::mjai::connection::Connection;
+
+    #[test]
+    fn test_client() {
+        let server = "{\"type\":\"hello\"}\n{\"type\":\"start_game\",\"id\":2,\"names\":[]}\n{\"type\":\"end_game\"}\n{\"type\":\"end_game\"}\n";
+        let mut connection = Connection::new(Cursor::new(server), sink());
+        let mut received = Vec::new();
+        let mut agent = |message : &Message| {
+            received.push(message.clone());
+            Message::None
+        };
+        run_client(&mut connection, "bot", "default", &mut agent).unwrap();
+        assert_eq!(vec![Message::StartGame{id : 2, names : Vec::new()}, Message::EndGame], received);
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::process::{Child, Command, Stdio};
+
+use crate::mjai::{Message, MjaiError};
+
+/// A line based channel to an mjai peer : a TCP socket, a child process or the standard input and output
+pub struct Connection {
+    reader : Box<dyn BufRead + Send>,
+    writer : Box<dyn Write + Send>,
+}
+
+impl Connection {
+
+    // Constructors
+
+    pub fn new(reader : impl Read + Send + 'static, writer : impl Write + Send + 'static) -> Connection {
+        Connection {
+            reader : Box::new(BufReader::new(reader)),
+            writer : Box::new(writer),
+        }
+    }
+
+    /// Talks through the standard input and output, when this program is run by an mjai server
+    pub fn stdio() -> Connection {
+        Connection::new(std::io::stdin(), std::io::stdout())
+    }
+
+    pub fn tcp(stream : TcpStream) -> std::io::Result<Connection> {
+        Ok(Connection::new(stream.try_clone()?, stream))
+    }
+
+    /// Connects to an mjai server
+    pub fn connect(address : impl ToSocketAddrs) -> std::io::Result<Connection> {
+        Connection::tcp(TcpStream::connect(address)?)
+    }
+
+    /// Waits for an AI to connect to our server
+    pub fn accept(listener : &TcpListener) -> std::io::Result<Connection> {
+        let (stream, _) = listener.accept()?;
+        Connection::tcp(stream)
+    }
+
+    /// Starts an AI as a child process talking through its standard input and output
+    pub fn spawn(command : &mut Command) -> std::io::Result<(Connection, Child)> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let missing = || std::io::Error::new(std::io::ErrorKind::BrokenPipe, "missing standard stream");
+        let reader = child.stdout.take().ok_or_else(missing)?;
+        let writer = child.stdin.take().ok_or_else(missing)?;
+        Ok((Connection::new(reader, writer), child))
+    }
+
+    // Other
+
+    pub fn send(&mut self, message : &Message) -> Result<(), MjaiError> {
+        writeln!(self.writer, "{}", message.to_json())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Waits for the next message, returns `None` if the peer closed the connection
+    pub fn receive(&mut self) -> Result<Option<Message>, MjaiError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                return Ok(Some(Message::parse(line.trim())?));
+            }
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! The mjai protocol, used by mahjong AIs to play on a server.
+//!
+//! Every message is a JSON object written on its own line, like `{"type":"dahai","actor":0,"pai":"5m","tsumogiri":true}`.
+//! The server sends the events of the game to each player, hiding the tiles they cannot see,
+//! and each player answers every message with an action or with `{"type":"none"}`.
+
+pub mod connection;
+pub mod server;
+pub mod client;
+pub mod agent;
+
+use std::fmt::{Display, Debug};
+
+use serde_json::{json, Map, Value};
+
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::tile::notation::{mjai_name, parse_mjai_name, NotationError};
+use crate::game::wind::Wind;
+use crate::game::rules::RuleSet;
+
+/// Reason why the communication with an mjai peer failed
+pub enum MjaiError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Notation(NotationError),
+    /// The message is valid JSON but does not follow the protocol
+    Protocol(String),
+}
+
+impl Debug for MjaiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MjaiError::Io(error) => write!(f, "connection error : {}", error),
+            MjaiError::Json(error) => write!(f, "invalid JSON : {}", error),
+            MjaiError::Notation(error) => write!(f, "invalid tile : {}", error),
+            MjaiError::Protocol(message) => write!(f, "invalid message : {}", message),
+        }
+    }
+}
+
+impl Display for MjaiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for MjaiError {}
+
+impl From<std::io::Error> for MjaiError {
+    fn from(error : std::io::Error) -> Self {
+        MjaiError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for MjaiError {
+    fn from(error : serde_json::Error) -> Self {
+        MjaiError::Json(error)
+    }
+}
+
+impl From<NotationError> for MjaiError {
+    fn from(error : NotationError) -> Self {
+        MjaiError::Notation(error)
+    }
+}
+
+/// A tile as written in the protocol, which only knows its value and whether it is a red five
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pai {
+    pub value : TileValue,
+    pub red : bool,
+}
+
+impl Pai {
+
+    pub fn from_tile(tile : Tile, rules : &RuleSet) -> Pai {
+        Pai{value : tile.value(), red : tile.is_red(rules.red_fives)}
+    }
+
+    /// Returns whether `tile` is written as this pai
+    pub fn matches(&self, tile : Tile, rules : &RuleSet) -> bool {
+        tile.value() == self.value && tile.is_red(rules.red_fives) == self.red
+    }
+
+    pub fn name(&self) -> String {
+        mjai_name(self.value, self.red)
+    }
+
+    pub fn parse(name : &str) -> Result<Pai, NotationError> {
+        let (value, red) = parse_mjai_name(name)?;
+        Ok(Pai{value, red})
+    }
+}
+
+/// A message of the protocol.
+/// Players are designated by their index at the table, the first dealer being the player 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// Sent by a TCP server to a newly connected client
+    Hello,
+    /// Answer of a client to `Hello`
+    Join { name : String, room : String },
+    /// `id` is the index of the player receiving the message
+    StartGame { id : usize, names : Vec<String> },
+    /// `kyoku` starts at 1 in each round wind, the tiles of the other players are hidden
+    StartKyoku {
+        bakaze : Wind,
+        kyoku : u8,
+        honba : u8,
+        kyotaku : u8,
+        oya : usize,
+        dora_marker : Pai,
+        scores : Vec<i32>,
+        tehais : Vec<Vec<Option<Pai>>>,
+    },
+    /// The drawn tile is hidden to the other players
+    Tsumo { actor : usize, pai : Option<Pai> },
+    Dahai { actor : usize, pai : Pai, tsumogiri : bool },
+    Chi { actor : usize, target : usize, pai : Pai, consumed : Vec<Pai> },
+    Pon { actor : usize, target : usize, pai : Pai, consumed : Vec<Pai> },
+    Daiminkan { actor : usize, target : usize, pai : Pai, consumed : Vec<Pai> },
+    Ankan { actor : usize, consumed : Vec<Pai> },
+    Kakan { actor : usize, pai : Pai, consumed : Vec<Pai> },
+    Nukidora { actor : usize, pai : Pai },
+    Dora { dora_marker : Pai },
+    /// Riichi declaration, followed by the discard
+    Reach { actor : usize },
+    /// The riichi is accepted once the discard did not deal in
+    ReachAccepted { actor : usize },
+    /// `target` is the player who dealt in, or the winner for a tsumo
+    Hora { actor : usize, target : usize, deltas : Vec<i32>, ura_markers : Vec<Pai> },
+    /// End of the round without winner. Sent by a player, it declares nine terminals and honors.
+    Ryukyoku { deltas : Vec<i32> },
+    EndKyoku,
+    EndGame,
+    /// Answer of a player who does nothing
+    None,
+}
+
+fn pai_list(pais : &[Pai]) -> Vec<String> {
+    pais.iter().map(Pai::name).collect()
+}
+
+fn wind_name(wind : Wind) -> &'static str {
+    match wind {
+        Wind::East => "E",
+        Wind::South => "S",
+        Wind::West => "W",
+        Wind::North => "N",
+    }
+}
+
+impl Message {
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Message::Hello => json!({"type" : "hello", "protocol" : "mjsonp", "protocol_version" : 3}),
+            Message::Join { name, room } => json!({"type" : "join", "name" : name, "room" : room}),
+            Message::StartGame { id, names } => json!({"type" : "start_game", "id" : id, "names" : names}),
+            Message::StartKyoku { bakaze, kyoku, honba, kyotaku, oya, dora_marker, scores, tehais } => json!({
+                "type" : "start_kyoku",
+                "bakaze" : wind_name(*bakaze),
+                "kyoku" : kyoku,
+                "honba" : honba,
+                "kyotaku" : kyotaku,
+                "oya" : oya,
+                "dora_marker" : dora_marker.name(),
+                "scores" : scores,
+                "tehais" : tehais.iter()
+                    .map(|tehai| tehai.iter().map(|pai| pai.map_or("?".to_string(), |pai| pai.name())).collect())
+                    .collect::<Vec<Vec<String>>>(),
+            }),
+            Message::Tsumo { actor, pai } => json!({
+                "type" : "tsumo",
+                "actor" : actor,
+                "pai" : pai.map_or("?".to_string(), |pai| pai.name()),
+            }),
+            Message::Dahai { actor, pai, tsumogiri } => json!({
+                "type" : "dahai", "actor" : actor, "pai" : pai.name(), "tsumogiri" : tsumogiri,
+            }),
+            Message::Chi { actor, target, pai, consumed } => json!({
+                "type" : "chi", "actor" : actor, "target" : target, "pai" : pai.name(), "consumed" : pai_list(consumed),
+            }),
+            Message::Pon { actor, target, pai, consumed } => json!({
+                "type" : "pon", "actor" : actor, "target" : target, "pai" : pai.name(), "consumed" : pai_list(consumed),
+            }),
+            Message::Daiminkan { actor, target, pai, consumed } => json!({
+                "type" : "daiminkan", "actor" : actor, "target" : target, "pai" : pai.name(), "consumed" : pai_list(consumed),
+            }),
+            Message::Ankan { actor, consumed } => json!({
+                "type" : "ankan", "actor" : actor, "consumed" : pai_list(consumed),
+            }),
+            Message::Kakan { actor, pai, consumed } => json!({
+                "type" : "kakan", "actor" : actor, "pai" : pai.name(), "consumed" : pai_list(consumed),
+            }),
+            Message::Nukidora { actor, pai } => json!({"type" : "nukidora", "actor" : actor, "pai" : pai.name()}),
+            Message::Dora { dora_marker } => json!({"type" : "dora", "dora_marker" : dora_marker.name()}),
+            Message::Reach { actor } => json!({"type" : "reach", "actor" : actor}),
+            Message::ReachAccepted { actor } => json!({"type" : "reach_accepted", "actor" : actor}),
+            Message::Hora { actor, target, deltas, ura_markers } => json!({
+                "type" : "hora", "actor" : actor, "target" : target, "deltas" : deltas, "ura_markers" : pai_list(ura_markers),
+            }),
+            Message::Ryukyoku { deltas } => json!({"type" : "ryukyoku", "deltas" : deltas}),
+            Message::EndKyoku => json!({"type" : "end_kyoku"}),
+            Message::EndGame => json!({"type" : "end_game"}),
+            Message::None => json!({"type" : "none"}),
+        }
+    }
+
+    pub fn parse(line : &str) -> Result<Message, MjaiError> {
+        let value : Value = serde_json::from_str(line)?;
+        let object = value.as_object().ok_or_else(|| MjaiError::Protocol(line.to_string()))?;
+        let fields = Fields{object, line};
+
+        Ok(match fields.string("type")? {
+            "hello" => Message::Hello,
+            "join" => Message::Join{name : fields.string("name")?.to_string(), room : fields.string("room")?.to_string()},
+            "start_game" => Message::StartGame{
+                id : fields.number("id").unwrap_or_default(),
+                names : fields.array("names").unwrap_or_default().iter()
+                    .map(|name| name.as_str().unwrap_or_default().to_string())
+                    .collect(),
+            },
+            "start_kyoku" => Message::StartKyoku{
+                bakaze : match fields.string("bakaze")? {
+                    "E" => Wind::East,
+                    "S" => Wind::South,
+                    "W" => Wind::West,
+                    "N" => Wind::North,
+                    _ => return Err(MjaiError::Protocol(line.to_string())),
+                },
+                kyoku : fields.number("kyoku")? as u8,
+                honba : fields.number("honba")? as u8,
+                kyotaku : fields.number("kyotaku")? as u8,
+                oya : fields.number("oya")?,
+                dora_marker : fields.pai("dora_marker")?,
+                scores : fields.array("scores").unwrap_or_default().iter()
+                    .map(|score| score.as_i64().unwrap_or_default() as i32)
+                    .collect(),
+                tehais : fields.array("tehais")?.iter()
+                    .map(|tehai| fields.hidden_pais(tehai))
+                    .collect::<Result<Vec<Vec<Option<Pai>>>, MjaiError>>()?,
+            },
+            "tsumo" => Message::Tsumo{actor : fields.number("actor")?, pai : fields.hidden_pai("pai")?},
+            "dahai" => Message::Dahai{
+                actor : fields.number("actor")?,
+                pai : fields.pai("pai")?,
+                tsumogiri : object.get("tsumogiri").and_then(Value::as_bool).unwrap_or_default(),
+            },
+            "chi" => Message::Chi{
+                actor : fields.number("actor")?,
+                target : fields.number("target")?,
+                pai : fields.pai("pai")?,
+                consumed : fields.pais("consumed")?,
+            },
+            "pon" => Message::Pon{
+                actor : fields.number("actor")?,
+                target : fields.number("target")?,
+                pai : fields.pai("pai")?,
+                consumed : fields.pais("consumed")?,
+            },
+            "daiminkan" => Message::Daiminkan{
+                actor : fields.number("actor")?,
+                target : fields.number("target")?,
+                pai : fields.pai("pai")?,
+                consumed : fields.pais("consumed")?,
+            },
+            "ankan" => Message::Ankan{actor : fields.number("actor")?, consumed : fields.pais("consumed")?},
+            "kakan" => Message::Kakan{
+                actor : fields.number("actor")?,
+                pai : fields.pai("pai")?,
+                consumed : fields.pais("consumed").unwrap_or_default(),
+            },
+            "nukidora" => Message::Nukidora{actor : fields.number("actor")?, pai : fields.pai("pai")?},
+            "dora" => Message::Dora{dora_marker : fields.pai("dora_marker")?},
+            "reach" => Message::Reach{actor : fields.number("actor")?},
+            "reach_accepted" => Message::ReachAccepted{actor : fields.number("actor")?},
+            "hora" => Message::Hora{
+                actor : fields.number("actor")?,
+                target : fields.number("target")?,
+                deltas : fields.deltas(),
+                ura_markers : fields.pais("ura_markers").unwrap_or_default(),
+            },
+            "ryukyoku" => Message::Ryukyoku{deltas : fields.deltas()},
+            "end_kyoku" => Message::EndKyoku,
+            "end_game" => Message::EndGame,
+            "none" => Message::None,
+            _ => return Err(MjaiError::Protocol(line.to_string())),
+        })
+    }
+}
+
+/// Access to the fields of a message, reporting the whole line on error
+struct Fields<'a> {
+    object : &'a Map<String, Value>,
+    line : &'a str,
+}
+
+impl<'a> Fields<'a> {
+
+    fn error(&self) -> MjaiError {
+        MjaiError::Protocol(self.line.to_string())
+    }
+
+    fn string(&self, key : &str) -> Result<&'a str, MjaiError> {
+        self.object.get(key).and_then(Value::as_str).ok_or_else(|| self.error())
+    }
+
+    fn number(&self, key : &str) -> Result<usize, MjaiError> {
+        self.object.get(key).and_then(Value::as_u64).map(|number| number as usize).ok_or_else(|| self.error())
+    }
+
+    fn array(&self, key : &str) -> Result<Vec<Value>, MjaiError> {
+        self.object.get(key).and_then(Value::as_array).cloned().ok_or_else(|| self.error())
+    }
+
+    fn pai(&self, key : &str) -> Result<Pai, MjaiError> {
+        Ok(Pai::parse(self.string(key)?)?)
+    }
+
+    fn hidden_pai(&self, key : &str) -> Result<Option<Pai>, MjaiError> {
+        match self.string(key)? {
+            "?" => Ok(None),
+            name => Ok(Some(Pai::parse(name)?)),
+        }
+    }
+
+    fn pais(&self, key : &str) -> Result<Vec<Pai>, MjaiError> {
+        self.array(key)?.iter()
+            .map(|name| Ok(Pai::parse(name.as_str().ok_or_else(|| self.error())?)?))
+            .collect()
+    }
+
+    fn hidden_pais(&self, value : &Value) -> Result<Vec<Option<Pai>>, MjaiError> {
+        value.as_array().ok_or_else(|| self.error())?.iter()
+            .map(|name| match name.as_str().ok_or_else(|| self.error())? {
+                "?" => Ok(None),
+                name => Ok(Some(Pai::parse(name)?)),
+            })
+            .collect()
+    }
+
+    fn deltas(&self) -> Vec<i32> {
+        self.array("deltas").unwrap_or_default().iter()
+            .map(|delta| delta.as_i64().unwrap_or_default() as i32)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Message, Pai};
+    use crate::tile::tile_value::TileValue;
+    use crate::tile::suit::Suit::{Man, Sou};
+    use crate::tile::dragon::Dragon::White;
+    use crate::game::wind::Wind::East;
+
+    #[test]
+    fn test_parse() {
+        let dahai = Message::parse(r#"{"type":"dahai","actor":2,"pai":"5sr","tsumogiri":false}"#).unwrap();
+        assert_eq!(Message::Dahai{actor : 2, pai : Pai{value : TileValue::new_suited(Sou, 5), red : true}, tsumogiri : false}, dahai);
+        let tsumo = Message::parse(r#"{"type":"tsumo","actor":1,"pai":"?"}"#).unwrap();
+        assert_eq!(Message::Tsumo{actor : 1, pai : None}, tsumo);
+        assert!(Message::parse(r#"{"type":"dahai","actor":2}"#).is_err());
+        assert!(Message::parse(r#"{"type":"unknown"}"#).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let messages = vec![
+            Message::StartKyoku{
+                bakaze : East,
+                kyoku : 1,
+                honba : 0,
+                kyotaku : 0,
+                oya : 0,
+                dora_marker : Pai{value : TileValue::new_dragon(White), red : false},
+                scores : vec![25000; 4],
+                tehais : vec![vec![Some(Pai{value : TileValue::new_suited(Man, 1), red : false}), None]],
+            },
+            Message::Pon{
+                actor : 1,
+                target : 3,
+                pai : Pai{value : TileValue::new_dragon(White), red : false},
+                consumed : vec![Pai{value : TileValue::new_dragon(White), red : false}; 2],
+            },
+            Message::Hora{actor : 1, target : 3, deltas : vec![0, 8000, 0, -8000], ura_markers : Vec::new()},
+            Message::None,
+        ];
+        for message in messages {
+            assert_eq!(message, Message::parse(&message.to_json().to_string()).unwrap());
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use std::time::Duration;
+
+use crate::tile::Tile;
+use crate::hand::Hand;
+use crate::game::rules::RuleSet;
+use crate::game::event::Event;
+use crate::game::action::Action;
+use crate::game::round::HAND_SIZE;
+use crate::game::table::Update;
+use crate::game::host::{PlayerInterface, Observation};
+use crate::record::RoundResult;
+use crate::mjai::{Message, MjaiError, Pai};
+use crate::mjai::connection::Connection;
+
+fn pais(tiles : &[Tile], rules : &RuleSet) -> Vec<Pai> {
+    tiles.iter().map(|tile| Pai::from_tile(*tile, rules)).collect()
+}
+
+/// What an mjai AI sees of one of our tables : converts the updates seen by the player at `seat`
+/// into the messages of the protocol.
+pub struct MjaiView {
+    seat : usize,
+    rules : RuleSet,
+    /// The pons of each player, which a `kakan` lists as its consumed tiles
+    pons : Vec<Vec<[Tile; 3]>>,
+}
+
+impl MjaiView {
+
+    pub fn new(seat : usize, rules : RuleSet) -> MjaiView {
+        MjaiView{seat, rules, pons : Vec::new()}
+    }
+
+    pub fn seat(&self) -> usize {
+        self.seat
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// Messages telling the player about an update, whose hidden tiles are already removed by `visible_updates`.
+    /// A riichi discard is announced by a `reach` and the `dahai`, the `reach_accepted` coming once nobody rons it.
+    /// The updates do not tell the ura dora indicators, so a `hora` has none.
+    pub fn messages(&mut self, update : &Update) -> Vec<Message> {
+        let rules = &self.rules;
+        let pai = |tile : Tile| Pai::from_tile(tile, rules);
+        match update {
+            Update::RoundStarted { round_wind, dealer, honba, riichi_sticks, scores, dora_indicator, hands } => {
+                self.pons = vec![Vec::new(); hands.len()];
+                vec![Message::StartKyoku {
+                    bakaze : *round_wind,
+                    kyoku : *dealer as u8 + 1,
+                    honba : *honba,
+                    kyotaku : *riichi_sticks,
+                    oya : *dealer,
+                    dora_marker : pai(*dora_indicator),
+                    scores : scores.clone(),
+                    tehais : hands.iter().enumerate()
+                        .map(|(player, hand)| if player == self.seat {
+                            hand.iter().map(|tile| Some(pai(*tile))).collect()
+                        } else {
+                            vec![None; HAND_SIZE]
+                        })
+                        .collect(),
+                }]
+            },
+            Update::Event(event) => self.event_messages(event),
+            Update::HiddenDraw { player } => vec![Message::Tsumo{actor : *player, pai : None}],
+            Update::RiichiAccepted { player } => vec![Message::ReachAccepted{actor : *player}],
+            Update::RoundEnded { result, .. } => result_messages(result, &[], self.pons.len(), rules),
+            Update::GameEnded { .. } => vec![Message::EndGame],
+        }
+    }
+
+    fn event_messages(&mut self, event : &Event) -> Vec<Message> {
+        let rules = &self.rules;
+        let pai = |tile : Tile| Pai::from_tile(tile, rules);
+        match *event {
+            Event::Draw { player, tile } => vec![Message::Tsumo{
+                actor : player,
+                pai : if player == self.seat {Some(pai(tile))} else {None},
+            }],
+            Event::Discard { player, tile, tsumogiri, riichi } => {
+                let dahai = Message::Dahai{actor : player, pai : pai(tile), tsumogiri};
+                if riichi {
+                    vec![Message::Reach{actor : player}, dahai]
+                } else {
+                    vec![dahai]
+                }
+            },
+            Event::Chii { player, from, called, tiles } => vec![Message::Chi{
+                actor : player, target : from, pai : pai(called), consumed : pais(&tiles, rules),
+            }],
+            Event::Pon { player, from, called, tiles } => {
+                let message = Message::Pon{actor : player, target : from, pai : pai(called), consumed : pais(&tiles, rules)};
+                self.pons[player].push([tiles[0], tiles[1], called]);
+                vec![message]
+            },
+            Event::OpenKan { player, from, called, tiles } => vec![Message::Daiminkan{
+                actor : player, target : from, pai : pai(called), consumed : pais(&tiles, rules),
+            }],
+            Event::ClosedKan { player, tiles } => vec![Message::Ankan{actor : player, consumed : pais(&tiles, rules)}],
+            Event::AddedKan { player, tile } => {
+                let pons = &mut self.pons[player];
+                let consumed = match pons.iter().position(|pon| pon[0].value() == tile.value()) {
+                    Some(position) => pais(&pons.remove(position), rules),
+                    None => Vec::new(),
+                };
+                vec![Message::Kakan{actor : player, pai : pai(tile), consumed}]
+            },
+            Event::Nuki { player, tile } => vec![Message::Nukidora{actor : player, pai : pai(tile)}],
+            Event::NewDora { indicator } => vec![Message::Dora{dora_marker : pai(indicator)}],
+        }
+    }
+}
+
+/// Messages announcing the end of a round
+pub fn result_messages(result : &RoundResult, ura_indicators : &[Tile], players : usize, rules : &RuleSet) -> Vec<Message> {
+    let mut messages : Vec<Message> = match result {
+        RoundResult::Win(wins) => wins.iter()
+            .map(|win| Message::Hora{
+                actor : win.winner,
+                target : win.from.unwrap_or(win.winner),
+                deltas : win.deltas.clone(),
+                ura_markers : pais(ura_indicators, rules),
+            })
+            .collect(),
+        _ => vec![Message::Ryukyoku{deltas : result.deltas(players)}],
+    };
+    messages.push(Message::EndKyoku);
+    messages
+}
+
+/// Finds in a hand the tiles written as `pais`
+pub(crate) fn find_tiles(pais : &[Pai], hand : &Hand, rules : &RuleSet) -> Result<Vec<Tile>, MjaiError> {
+    let mut found : Vec<Tile> = Vec::new();
+    for pai in pais {
+        let tile = hand.hidden_tiles().iter()
+            .find(|tile| pai.matches(**tile, rules) && !found.contains(tile))
+            .ok_or_else(|| MjaiError::Protocol(format!("{} is not in the hand", pai.name())))?;
+        found.push(*tile);
+    }
+    Ok(found)
+}
+
+/// Converts the answer of `player` into an action, finding the tiles in their hand, `drawn` being the tile they just drew
+pub fn action_from_response(response : &Message, player : usize, hand : &Hand, drawn : Option<Tile>, rules : &RuleSet) -> Result<Action, MjaiError> {
+    let wrong_actor = || MjaiError::Protocol(format!("{:?} is not an action of player {}", response, player));
+    let action = match response {
+        Message::None => return Ok(Action::Pass),
+        Message::Ryukyoku { .. } => return Ok(Action::NineTerminals),
+        Message::Dahai { actor, pai, tsumogiri } if *actor == player => {
+            let tile = match drawn {
+                Some(drawn) if *tsumogiri => drawn,
+                _ => find_tiles(&[*pai], hand, rules)?[0],
+            };
+            Action::Discard{tile, riichi : false}
+        },
+        Message::Chi { actor, consumed, .. } if *actor == player && consumed.len() == 2 => {
+            let tiles = find_tiles(consumed, hand, rules)?;
+            Action::Chii{tiles : [tiles[0], tiles[1]]}
+        },
+        Message::Pon { actor, consumed, .. } if *actor == player && consumed.len() == 2 => {
+            let tiles = find_tiles(consumed, hand, rules)?;
+            Action::Pon{tiles : [tiles[0], tiles[1]]}
+        },
+        Message::Daiminkan { actor, consumed, .. } if *actor == player && consumed.len() == 3 => {
+            let tiles = find_tiles(consumed, hand, rules)?;
+            Action::OpenKan{tiles : [tiles[0], tiles[1], tiles[2]]}
+        },
+        Message::Ankan { actor, consumed } if *actor == player && consumed.len() == 4 => {
+            let tiles = find_tiles(consumed, hand, rules)?;
+            Action::ClosedKan{tiles : [tiles[0], tiles[1], tiles[2], tiles[3]]}
+        },
+        Message::Kakan { actor, pai, .. } if *actor == player => {
+            Action::AddedKan{tile : find_tiles(&[*pai], hand, rules)?[0]}
+        },
+        Message::Nukidora { actor, pai } if *actor == player => {
+            Action::Nuki{tile : find_tiles(&[*pai], hand, rules)?[0]}
+        },
+        Message::Hora { actor, target, .. } if *actor == player => {
+            if target == actor {Action::Tsumo} else {Action::Ron}
+        },
+        _ => return Err(wrong_actor()),
+    };
+    Ok(action)
+}
+
+/// Greets an AI that just connected to our server and returns the name it joins with
+pub fn greet(connection : &mut Connection) -> Result<String, MjaiError> {
+    connection.send(&Message::Hello)?;
+    match connection.receive()? {
+        Some(Message::Join { name, .. }) => Ok(name),
+        Some(message) => Err(MjaiError::Protocol(format!("expected join, got {:?}", message))),
+        None => Err(MjaiError::Protocol("connection closed".to_string())),
+    }
+}
+
+/// An AI playing at one of our tables through the mjai protocol.
+/// Every message sent to the AI is answered, either by an action or by `none` : the answers to its draws and calls,
+/// and to the discards and added kans of the others, are kept until the host asks the player to act.
+pub struct MjaiPlayer {
+    connection : Connection,
+    view : MjaiView,
+    names : Vec<String>,
+    /// The answer of the AI to the last message on which it could act
+    response : Message,
+    /// Whether the AI already sent its riichi declaration, which is then not announced to it again
+    reached : bool,
+    started : bool,
+    connected : bool,
+}
+
+impl MjaiPlayer {
+
+    // Constructors
+
+    /// The AI at `seat`, `names` being the names of every player of the table
+    pub fn new(connection : Connection, seat : usize, rules : RuleSet, names : Vec<String>) -> MjaiPlayer {
+        MjaiPlayer {
+            connection,
+            view : MjaiView::new(seat, rules),
+            names,
+            response : Message::None,
+            reached : false,
+            started : false,
+            connected : true,
+        }
+    }
+
+    // Immutable getters
+
+    /// Index of the player at the table
+    pub fn id(&self) -> usize {
+        self.view.seat()
+    }
+
+    // Other
+
+    /// Sends a message and waits for the answer of the AI
+    pub fn send(&mut self, message : &Message) -> Result<Message, MjaiError> {
+        self.connection.send(message)?;
+        self.connection.receive()?.ok_or_else(|| MjaiError::Protocol("connection closed".to_string()))
+    }
+
+    /// Whether the AI can act on the message : on its own draws and calls, on the discards and added kans of the others
+    fn can_act(&self, message : &Message) -> bool {
+        match message {
+            Message::Tsumo { actor, .. } | Message::Chi { actor, .. } | Message::Pon { actor, .. } => *actor == self.id(),
+            Message::Dahai { actor, .. } | Message::Kakan { actor, .. } => *actor != self.id(),
+            _ => false,
+        }
+    }
+
+    fn tell(&mut self, updates : &[Update]) -> Result<(), MjaiError> {
+        for update in updates {
+            if !self.started && matches!(update, Update::RoundStarted { .. }) {
+                self.started = true;
+                self.send(&Message::StartGame{id : self.id(), names : self.names.clone()})?;
+            }
+            for message in self.view.messages(update) {
+                if self.reached && message == (Message::Reach{actor : self.id()}) {
+                    self.reached = false;
+                    continue;
+                }
+                let response = self.send(&message)?;
+                if self.can_act(&message) {
+                    self.response = response;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the answer of the AI into an action.
+    /// When the AI declares riichi, its declaration is sent back and it answers with its discard.
+    pub fn decide(&mut self, response : &Message, hand : &Hand, drawn : Option<Tile>) -> Result<Action, MjaiError> {
+        let rules = self.view.rules().clone();
+        if *response == (Message::Reach{actor : self.id()}) {
+            let discard = self.send(response)?;
+            self.reached = true;
+            return match action_from_response(&discard, self.id(), hand, drawn, &rules)? {
+                Action::Discard { tile, .. } => Ok(Action::Discard{tile, riichi : true}),
+                _ => Err(MjaiError::Protocol("riichi without discard".to_string())),
+            };
+        }
+        action_from_response(response, self.id(), hand, drawn, &rules)
+    }
+}
+
+impl PlayerInterface for MjaiPlayer {
+    fn observe(&mut self, updates : &[Update]) {
+        if self.connected && self.tell(updates).is_err() {
+            self.connected = false;
+        }
+    }
+
+    /// The AI already answered when it was told about the update, so the timeout is only spent on a riichi discard.
+    /// An answer that is not one of the legal actions is ignored.
+    fn act(&mut self, observation : &Observation, _timeout : Duration) -> Option<Action> {
+        let round = observation.round.as_ref()?;
+        let response = std::mem::replace(&mut self.response, Message::None);
+        let action = match self.decide(&response, &round.hand, round.drawn) {
+            Ok(action) => action,
+            Err(MjaiError::Io(_)) => {
+                self.connected = false;
+                return None;
+            },
+            Err(_) => return None,
+        };
+        let red_fives = self.view.rules().red_fives;
+        observation.legal_actions.iter().find(|legal| legal.is_equivalent(&action, red_fives)).cloned()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, sink};
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use super::{MjaiPlayer, MjaiView, greet};
+    use crate::tile::Tile;
+    use crate::tile::suit::Suit::{Man, Pin};
+    use crate::hand::Hand;
+    use crate::game::wind::Wind::East;
+    use crate::game::rules::RuleSet;
+    use crate::game::event::Event;
+    use crate::game::action::Action;
+    use crate::game::table::{Table, Update};
+    use crate::game::host::{TableHost, PlayerInterface, Observation, visible_updates};
+    use crate::bot::RuleBot;
+    use crate::mjai::{Message, Pai};
+    use crate::mjai::connection::Connection;
+    use crate::mjai::client::run_client;
+    use crate::mjai::agent::PlayerAgent;
+
+    fn hand(player : u8) -> Vec<Tile> {
+        (1..=9).map(|value| Tile::new_suited(Man, value, player)).chain(
+            (1..=4).map(|value| Tile::new_suited(Pin, value, player))
+        ).collect()
+    }
+
+    #[test]
+    fn test_hidden_tiles() {
+        let start = Update::RoundStarted {
+            round_wind : East,
+            dealer : 0,
+            honba : 0,
+            riichi_sticks : 0,
+            scores : vec![25000; 4],
+            dora_indicator : Tile::new_suited(Pin, 9, 0),
+            hands : (0..4).map(hand).collect(),
+        };
+        let draw = Update::Event(Event::Draw{player : 0, tile : Tile::new_suited(Pin, 5, 0)});
+        let mut view = MjaiView::new(1, RuleSet::default());
+        let messages : Vec<Message> = visible_updates(&[start, draw], 1).iter().flat_map(|update| view.messages(update)).collect();
+        match &messages[0] {
+            Message::StartKyoku { tehais, .. } => {
+                assert!(tehais[0].iter().all(Option::is_none));
+                assert_eq!(13, tehais[0].len());
+                assert!(tehais[1].iter().all(Option::is_some));
+            },
+            _ => panic!("expected start_kyoku"),
+        }
+        assert_eq!(Message::Tsumo{actor : 0, pai : None}, messages[1]);
+    }
+
+    #[test]
+    fn test_riichi() {
+        let rules = RuleSet::default();
+        let drawn = Tile::new_suited(Pin, 5, 1);
+        let mut tiles = hand(0);
+        tiles.push(drawn);
+        let answers = "{\"type\":\"dahai\",\"actor\":0,\"pai\":\"5p\",\"tsumogiri\":true}\n";
+        let mut player = MjaiPlayer::new(Connection::new(Cursor::new(answers), sink()), 0, rules.clone(), Vec::new());
+        let action = player.decide(&Message::Reach{actor : 0}, &Hand::new(tiles), Some(drawn)).unwrap();
+        assert_eq!(Action::Discard{tile : drawn, riichi : true}, action);
+        assert_eq!(Some(Pai::from_tile(drawn, &rules)), Pai::parse("5p").ok());
+    }
+
+    /// Counts the actions of the AI that were not legal
+    struct Counted {
+        player : MjaiPlayer,
+        refused : Rc<Cell<usize>>,
+        decisions : Rc<Cell<usize>>,
+    }
+
+    impl PlayerInterface for Counted {
+        fn observe(&mut self, updates : &[Update]) {
+            self.player.observe(updates);
+        }
+
+        fn act(&mut self, observation : &Observation, timeout : Duration) -> Option<Action> {
+            let action = self.player.act(observation, timeout);
+            self.decisions.set(self.decisions.get() + 1);
+            if action.is_none() {
+                self.refused.set(self.refused.get() + 1);
+            }
+            action
+        }
+
+        fn is_connected(&self) -> bool {
+            self.player.is_connected()
+        }
+    }
+
+    #[test]
+    fn test_game() {
+        let rules = RuleSet::default();
+        let (server_reader, client_writer) = std::io::pipe().unwrap();
+        let (client_reader, server_writer) = std::io::pipe().unwrap();
+        let client_rules = rules.clone();
+        let client = std::thread::spawn(move || {
+            let mut connection = Connection::new(client_reader, client_writer);
+            let mut agent = PlayerAgent::new(RuleBot::new(), client_rules);
+            run_client(&mut connection, "rule", "default", &mut agent).is_ok()
+        });
+
+        let mut connection = Connection::new(server_reader, server_writer);
+        let mut names = vec![greet(&mut connection).unwrap()];
+        names.extend((1..4).map(|seat| format!("Bot {}", seat)));
+        assert_eq!("rule", names[0]);
+        let (refused, decisions) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let ai = MjaiPlayer::new(connection, 0, rules.clone(), names.clone());
+        let mut players : Vec<Box<dyn PlayerInterface>> = vec![Box::new(Counted{player : ai, refused : refused.clone(), decisions : decisions.clone()})];
+        players.extend((1..4).map(|_| Box::new(RuleBot::new()) as Box<dyn PlayerInterface>));
+        let record = TableHost::new(Table::new(3, names, rules).unwrap(), players).run().unwrap();
+        assert!(client.join().unwrap());
+
+        assert!(record.verify().is_ok());
+        assert!(decisions.get() > 0);
+        assert_eq!(0, refused.get());
+        // The AI chose its discards rather than getting the default action
+        let chosen = record.rounds.iter()
+            .flat_map(|round| round.events.iter())
+            .filter(|event| matches!(event, Event::Discard { player : 0, tsumogiri : false, .. }))
+            .count();
+        assert!(chosen > 0);
+    }
+}
@@ -0,0 +1,409 @@
+use std::fmt::{Display, Debug};
+
+use crate::tile::Tile;
+use crate::game::wind::Wind;
+use crate::game::rules::RuleSet;
+use crate::game::rng::Rng;
+use crate::game::wall::Wall;
+use crate::game::event::Event;
+use crate::game::round::{Round, RoundError, Phase};
+use crate::record::{RoundRecord, RoundResult, placements};
+
+/// Reason why a game record cannot be replayed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// An event of the record is not valid
+    Round { round : usize, event : usize, error : RoundError },
+    /// The deal, a drawn tile or a dora indicator is not the one given by the seed of the game.
+    /// `event` is `None` for the deal and the ura dora indicators.
+    WallMismatch { round : usize, event : Option<usize> },
+    NoSuchRound(usize),
+}
+
+impl Debug for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Round { round, event, error } => write!(f, "round {}, event {} : {:?}", round, event, error),
+            ReplayError::WallMismatch { round, event : Some(event) } => write!(f, "round {}, event {} : the tile does not come from the wall", round, event),
+            ReplayError::WallMismatch { round, event : None } => write!(f, "round {} : the dead wall or the deal does not come from the wall", round),
+            ReplayError::NoSuchRound(round) => write!(f, "there is no round {}", round),
+        }
+    }
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Everything that happened during a game.
+/// The walls are not stored : they are shuffled again from the seed, which allows checking
+/// that every deal and every draw of the record is genuine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    pub seed : u64,
+    pub names : Vec<String>,
+    pub rules : RuleSet,
+    pub rounds : Vec<RoundRecord>,
+    /// Scores of the players at the end of the game, after the leftover riichi deposits went to the first player.
+    /// The starting points while the game goes on
+    pub scores : Vec<i32>,
+}
+
+impl GameRecord {
+
+    // Constructors
+
+    pub fn new(seed : u64, names : Vec<String>, rules : RuleSet) -> GameRecord {
+        let scores = vec![rules.starting_points; names.len()];
+        GameRecord{seed, names, rules, rounds : Vec::new(), scores}
+    }
+
+    // Other
+
+    /// The wall of the round `index`, before the deal
+    pub fn wall(&self, index : usize) -> Wall {
+        let mut seeds = Rng::new(self.seed);
+        let seed = (0..=index).map(|_| seeds.next_u64()).last().unwrap_or(self.seed);
+        Wall::shuffled(&self.rules, &mut Rng::new(seed))
+    }
+
+    /// Replays the round `index` up to the event `count`, checking every tile against the wall
+    pub fn replay_round(&self, index : usize, count : usize) -> Result<Round, ReplayError> {
+        let (mut round, mut wall) = self.start_round(index)?;
+        let events = &self.rounds[index].events;
+        for i in 0..count.min(events.len()) {
+            self.replay_event(index, i, &mut round, &mut wall)?;
+        }
+        Ok(round)
+    }
+
+    /// Deals the round `index` and checks the deal against the wall
+    fn start_round(&self, index : usize) -> Result<(Round, Wall), ReplayError> {
+        let record = self.rounds.get(index).ok_or(ReplayError::NoSuchRound(index))?;
+        let mut wall = self.wall(index);
+        let mut hands = wall.deal(record.dealer, self.names.len());
+        let mut recorded = record.hands.clone();
+        hands.iter_mut().chain(recorded.iter_mut()).for_each(|hand| hand.sort());
+        if hands != recorded || wall.dora_indicators().first() != record.dora_indicators.first() {
+            return Err(ReplayError::WallMismatch{round : index, event : None});
+        }
+        let round = record.start(&self.rules).map_err(|error| ReplayError::Round{round : index, event : 0, error})?;
+        Ok((round, wall))
+    }
+
+    /// Applies the event `i` of the round `index`, checking drawn tiles and dora indicators against the wall.
+    /// After the last event, the ura dora indicators are checked too.
+    fn replay_event(&self, index : usize, i : usize, round : &mut Round, wall : &mut Wall) -> Result<(), ReplayError> {
+        let record = &self.rounds[index];
+        let event = &record.events[i];
+        let expected = match event {
+            Event::Draw { .. } if matches!(round.phase(), Phase::Replacement(_)) => wall.draw_replacement(),
+            Event::Draw { .. } => wall.draw(),
+            Event::NewDora { .. } => wall.reveal_kan_dora(),
+            _ => None,
+        };
+        match *event {
+            Event::Draw { tile, .. } | Event::NewDora { indicator : tile } if expected != Some(tile) => {
+                return Err(ReplayError::WallMismatch{round : index, event : Some(i)});
+            },
+            _ => (),
+        }
+        round.apply(event).map_err(|error| ReplayError::Round{round : index, event : i, error})?;
+        if i + 1 == record.events.len() && wall.ura_dora_indicators() != &record.ura_indicators[..] {
+            return Err(ReplayError::WallMismatch{round : index, event : None});
+        }
+        Ok(())
+    }
+
+    /// Replays every round, checking that the record is consistent with the seed
+    pub fn verify(&self) -> Result<(), ReplayError> {
+        for (index, record) in self.rounds.iter().enumerate() {
+            self.replay_round(index, record.events.len())?;
+        }
+        Ok(())
+    }
+
+    /// Scores of the players at the end of the game
+    pub fn final_scores(&self) -> Vec<i32> {
+        self.scores.clone()
+    }
+
+    /// Rank of each player at the end of the game, from 0 for the first. Tied players are ranked by seat
+    pub fn placements(&self) -> Vec<usize> {
+        placements(&self.scores)
+    }
+
+    /// Result of each player with the uma and the oka of the rules, see `RuleSet::placement_points`
+    pub fn placement_points(&self) -> Vec<i32> {
+        self.scores.iter().zip(self.placements()).map(|(score, rank)| self.rules.placement_points(*score, rank)).collect()
+    }
+
+    /// Starts a step by step replay of the game
+    pub fn replay(&self) -> GameReplay<'_> {
+        GameReplay{record : self, round : 0, event : 0, state : None}
+    }
+}
+
+/// Step by step replay of a game record
+pub struct GameReplay<'a> {
+    record : &'a GameRecord,
+    round : usize,
+    event : usize,
+    state : Option<(Round, Wall)>,
+}
+
+impl<'a> GameReplay<'a> {
+
+    // Immutable getters
+
+    /// The round and the number of events applied in this round
+    pub fn position(&self) -> (usize, usize) {
+        (self.round, self.event)
+    }
+
+    /// State of the current round, `None` once the game is over
+    pub fn round(&self) -> Option<&Round> {
+        self.state.as_ref().map(|(round, _)| round)
+    }
+
+    // Other
+
+    /// Goes to the state of the round `round` after `event` events
+    pub fn seek(&mut self, round : usize, event : usize) -> Result<&Round, ReplayError> {
+        let (mut state, mut wall) = self.record.start_round(round)?;
+        let event = event.min(self.record.rounds[round].events.len());
+        for i in 0..event {
+            self.record.replay_event(round, i, &mut state, &mut wall)?;
+        }
+        self.round = round;
+        self.event = event;
+        let (state, _) = self.state.insert((state, wall));
+        Ok(state)
+    }
+
+    /// Applies the next event and returns it, going to the start of the next round at the end of a round.
+    /// Returns `None` at the end of the game.
+    pub fn step(&mut self) -> Result<Option<&'a Event>, ReplayError> {
+        loop {
+            if self.state.is_none() {
+                if self.round >= self.record.rounds.len() {
+                    return Ok(None);
+                }
+                self.seek(self.round, 0)?;
+            }
+            let events = &self.record.rounds[self.round].events;
+            if self.event < events.len() {
+                break;
+            }
+            self.state = None;
+            self.round += 1;
+            self.event = 0;
+        }
+        let record = self.record;
+        if let Some((round, wall)) = self.state.as_mut() {
+            record.replay_event(self.round, self.event, round, wall)?;
+        }
+        self.event += 1;
+        Ok(Some(&record.rounds[self.round].events[self.event - 1]))
+    }
+}
+
+/// The round being recorded
+struct Recording {
+    round : Round,
+    wall : Wall,
+    start : Round,
+    scores : Vec<i32>,
+    events : Vec<Event>,
+}
+
+/// Plays the rounds of a game on the walls given by its seed and records everything
+pub struct GameRecorder {
+    record : GameRecord,
+    current : Option<Recording>,
+}
+
+impl GameRecorder {
+
+    // Constructors
+
+    pub fn new(seed : u64, names : Vec<String>, rules : RuleSet) -> GameRecorder {
+        GameRecorder{record : GameRecord::new(seed, names, rules), current : None}
+    }
+
+    // Immutable getters
+
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// The round being played
+    pub fn round(&self) -> Option<&Round> {
+        self.current.as_ref().map(|current| &current.round)
+    }
+
+    /// Number of tiles left in the live wall of the round being played
+    pub fn remaining(&self) -> usize {
+        self.current.as_ref().map_or(0, |current| current.wall.remaining())
+    }
+
+    /// The ura dora indicators of the round being played, under the revealed dora indicators
+    pub fn ura_dora_indicators(&self) -> &[Tile] {
+        self.current.as_ref().map_or(&[], |current| current.wall.ura_dora_indicators())
+    }
+
+    // Other
+
+    /// Deals a new round from the next wall, `scores` being the scores of the players at its start
+    pub fn start_round(&mut self, round_wind : Wind, dealer : usize, honba : u8, riichi_sticks : u8, scores : Vec<i32>) -> &Round {
+        let mut wall = self.record.wall(self.record.rounds.len());
+        let hands = wall.deal(dealer, self.record.names.len());
+        let indicator = wall.dora_indicators()[0];
+        let start = Round::new(self.record.rules.clone(), round_wind, dealer, honba, riichi_sticks, hands, indicator);
+        let current = self.current.insert(Recording{round : start.clone(), wall, start, scores, events : Vec::new()});
+        &current.round
+    }
+
+    /// Draws the next tile for the player whose turn it is, from the dead wall after a kan or a nukidora
+    pub fn draw(&mut self) -> Result<Tile, RoundError> {
+        let current = self.current.as_mut().ok_or(RoundError::EmptyWall)?;
+        let (player, tile) = match current.round.phase() {
+            Phase::Draw(player) => (player, current.wall.draw()),
+            Phase::Replacement(player) => (player, current.wall.draw_replacement()),
+            Phase::Discard(player) => return Err(RoundError::WrongTurn(player)),
+        };
+        let tile = tile.ok_or(RoundError::EmptyWall)?;
+        current.apply(Event::Draw{player, tile})?;
+        Ok(tile)
+    }
+
+    /// Reveals a new dora indicator after a kan
+    pub fn reveal_dora(&mut self) -> Result<Tile, RoundError> {
+        let current = self.current.as_mut().ok_or(RoundError::InvalidKan)?;
+        let indicator = current.wall.reveal_kan_dora().ok_or(RoundError::InvalidKan)?;
+        current.apply(Event::NewDora{indicator})?;
+        Ok(indicator)
+    }
+
+    /// Applies the action of a player. Drawn tiles and dora indicators come from the wall,
+    /// see `draw` and `reveal_dora`.
+    pub fn act(&mut self, event : &Event) -> Result<(), RoundError> {
+        let current = self.current.as_mut().ok_or(RoundError::EmptyWall)?;
+        match *event {
+            Event::Draw { player, .. } => Err(RoundError::WrongTurn(player)),
+            Event::NewDora { .. } => Err(RoundError::InvalidKan),
+            _ => current.apply(event.clone()),
+        }
+    }
+
+    /// Ends the round being played and adds it to the record
+    pub fn finish_round(&mut self, result : RoundResult) {
+        if let Some(current) = self.current.take() {
+            let start = &current.start;
+            self.record.rounds.push(RoundRecord {
+                round_wind : start.round_wind(),
+                dealer : start.dealer(),
+                honba : start.honba(),
+                riichi_sticks : start.riichi_sticks(),
+                scores : current.scores,
+                hands : (0..start.player_count()).map(|player| start.player(player).hand().hidden_tiles().clone()).collect(),
+                dora_indicators : current.round.dora_indicators().clone(),
+                ura_indicators : current.wall.ura_dora_indicators().to_vec(),
+                events : current.events,
+                result,
+            });
+        }
+    }
+
+    /// Ends the game, `scores` being the final scores of the players
+    pub fn finish_game(&mut self, scores : Vec<i32>) {
+        self.record.scores = scores;
+    }
+
+    pub fn finish(self) -> GameRecord {
+        self.record
+    }
+}
+
+impl Recording {
+
+    fn apply(&mut self, event : Event) -> Result<(), RoundError> {
+        self.round.apply(&event)?;
+        self.events.push(event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameRecorder, GameRecord, ReplayError};
+    use crate::game::wind::Wind::East;
+    use crate::game::rules::RuleSet;
+    use crate::game::event::Event;
+    use crate::game::round::Phase;
+    use crate::record::RoundResult;
+
+    /// Every player discards what they draw until the wall is empty
+    fn record(seed : u64, rounds : usize) -> GameRecord {
+        let names = ["A", "B", "C", "D"].iter().map(|name| name.to_string()).collect();
+        let mut recorder = GameRecorder::new(seed, names, RuleSet::default());
+        for dealer in 0..rounds {
+            recorder.start_round(East, dealer, 0, 0, vec![25000; 4]);
+            while recorder.remaining() > 0 {
+                let tile = recorder.draw().unwrap();
+                let player = match recorder.round().unwrap().phase() {
+                    Phase::Discard(player) => player,
+                    phase => panic!("unexpected {:?}", phase),
+                };
+                recorder.act(&Event::Discard{player, tile, tsumogiri : true, riichi : false}).unwrap();
+            }
+            recorder.finish_round(RoundResult::ExhaustiveDraw{deltas : vec![0; 4]});
+        }
+        recorder.finish()
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let game = record(7, 2);
+        assert_eq!(game, record(7, 2));
+        assert_ne!(game.rounds[0].hands, record(8, 1).rounds[0].hands);
+        assert_ne!(game.rounds[0].hands, game.rounds[1].hands);
+        assert_eq!(Ok(()), game.verify());
+        assert_eq!(vec![25000; 4], game.final_scores());
+        assert_eq!(vec![0, 1, 2, 3], game.placements());
+        // Tied at 25000, the players are ranked by seat for the uma
+        assert_eq!(vec![15000, 5000, -5000, -15000], game.placement_points());
+    }
+
+    #[test]
+    fn test_tampered() {
+        let mut game = record(7, 1);
+        let fake = game.rounds[0].hands[1][0];
+        if let Event::Draw { tile, .. } = &mut game.rounds[0].events[10] {
+            *tile = fake;
+        }
+        assert!(matches!(game.verify(), Err(ReplayError::WallMismatch{round : 0, event : Some(10)})));
+    }
+
+    #[test]
+    fn test_replay() {
+        let game = record(3, 2);
+        let mut replay = game.replay();
+        let mut count = 0;
+        while replay.step().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(game.rounds.iter().map(|round| round.events.len()).sum::<usize>(), count);
+        assert!(replay.round().is_none());
+
+        let round = replay.seek(1, 9).unwrap();
+        assert_eq!(122 - 52 - 5, round.remaining());
+        assert_eq!((1, 9), replay.position());
+        assert!(matches!(replay.seek(2, 0), Err(ReplayError::NoSuchRound(2))));
+    }
+}
@@ -0,0 +1,163 @@
+pub mod tenhou;
+pub mod game;
+
+use crate::tile::Tile;
+use crate::game::wind::Wind;
+use crate::game::rules::RuleSet;
+use crate::game::event::Event;
+use crate::game::round::{Round, RoundError};
+use crate::game::table::RIICHI_DEPOSIT;
+
+/// Rank of each player from their final score, from 0 for the first. Tied players are ranked by seat
+pub fn placements(scores : &[i32]) -> Vec<usize> {
+    let mut ranking : Vec<usize> = (0..scores.len()).collect();
+    ranking.sort_by_key(|player| -scores[*player]);
+    let mut placements = vec![0; scores.len()];
+    for (rank, player) in ranking.into_iter().enumerate() {
+        placements[player] = rank;
+    }
+    placements
+}
+
+/// Kind of abortive draw ending a round
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AbortiveDraw {
+    /// A player declared nine different terminals or honors on their first draw (kyuushu kyuuhai)
+    NineTerminals,
+    /// The four players discarded the same wind on the first turn (suufon renda)
+    FourWinds,
+    /// The four players declared riichi (suucha riichi)
+    FourRiichi,
+    /// Four kans were declared by several players (suukaikan)
+    FourKans,
+    /// Three players called ron on the same tile (sanchahou)
+    TripleRon,
+}
+
+/// A win as displayed on the score screen
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WinRecord {
+    pub winner : usize,
+    /// The player who dealt in, `None` for a tsumo
+    pub from : Option<usize>,
+    /// The player liable for a yakuman (pao), if any
+    pub pao : Option<usize>,
+    pub han : u8,
+    pub fu : u8,
+    /// Name and value in han of each yaku, yakuman being counted as 13 han
+    pub yaku : Vec<(String, u8)>,
+    /// Score change of each player
+    pub deltas : Vec<i32>,
+}
+
+/// How a round ended
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RoundResult {
+    /// One or several players won
+    Win(Vec<WinRecord>),
+    /// The wall is empty, tenpai players are paid by noten players
+    ExhaustiveDraw { deltas : Vec<i32> },
+    /// The wall is empty and a player only discarded terminals and honors that were not called
+    NagashiMangan { deltas : Vec<i32> },
+    AbortiveDraw(AbortiveDraw),
+}
+
+impl RoundResult {
+
+    /// Score change of each player at the end of the round
+    pub fn deltas(&self, players : usize) -> Vec<i32> {
+        let mut total = vec![0; players];
+        let mut add = |deltas : &Vec<i32>| {
+            for (sum, delta) in total.iter_mut().zip(deltas) {
+                *sum += delta;
+            }
+        };
+        match self {
+            RoundResult::Win(wins) => wins.iter().for_each(|win| add(&win.deltas)),
+            | RoundResult::ExhaustiveDraw { deltas }
+            | RoundResult::NagashiMangan { deltas } => add(deltas),
+            RoundResult::AbortiveDraw(_) => (),
+        }
+        total
+    }
+}
+
+/// Everything needed to replay a round : the starting state, the events and the result
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoundRecord {
+    pub round_wind : Wind,
+    pub dealer : usize,
+    pub honba : u8,
+    pub riichi_sticks : u8,
+    /// Scores of the players at the start of the round
+    pub scores : Vec<i32>,
+    /// The hands dealt to each player
+    pub hands : Vec<Vec<Tile>>,
+    /// Every dora indicator revealed during the round, the first one being revealed at the start
+    pub dora_indicators : Vec<Tile>,
+    pub ura_indicators : Vec<Tile>,
+    pub events : Vec<Event>,
+    pub result : RoundResult,
+}
+
+impl RoundRecord {
+
+    /// State of the round at its start, before the first draw
+    pub fn start(&self, rules : &RuleSet) -> Result<Round, RoundError> {
+        let indicator = *self.dora_indicators.first().ok_or(RoundError::MissingDoraIndicator)?;
+        Ok(Round::new(
+            rules.clone(),
+            self.round_wind,
+            self.dealer,
+            self.honba,
+            self.riichi_sticks,
+            self.hands.clone(),
+            indicator,
+        ))
+    }
+
+    /// Replays the `count` first events of the round
+    pub fn replay(&self, rules : &RuleSet, count : usize) -> Result<Round, RoundError> {
+        let mut round = self.start(rules)?;
+        for event in self.events.iter().take(count) {
+            round.apply(event)?;
+        }
+        Ok(round)
+    }
+
+    /// The riichi deposit paid by each player during the round : a riichi discard that is dealt into pays none
+    pub fn deposits(&self) -> Vec<i32> {
+        let mut deposits = vec![0; self.scores.len()];
+        for (index, event) in self.events.iter().enumerate() {
+            if let Event::Discard { player, riichi : true, .. } = *event {
+                let dealt_in = index + 1 == self.events.len()
+                    && matches!(&self.result, RoundResult::Win(wins) if wins.iter().any(|win| win.from == Some(player)));
+                if !dealt_in {
+                    deposits[player] += RIICHI_DEPOSIT;
+                }
+            }
+        }
+        deposits
+    }
+
+    /// Scores of the players at the end of a game ending with this round : the deposits and the payments of the round
+    /// are counted, and the riichi sticks left on the table go to the first player, the first seat winning ties
+    pub fn game_end_scores(&self) -> Vec<i32> {
+        let players = self.scores.len();
+        let deposits = self.deposits();
+        let mut scores : Vec<i32> = (0..players)
+            .map(|player| self.scores[player] - deposits[player])
+            .zip(self.result.deltas(players))
+            .map(|(score, delta)| score + delta)
+            .collect();
+        if !matches!(self.result, RoundResult::Win(_)) {
+            let first = (0..players).fold(0, |best, player| if scores[player] > scores[best] {player} else {best});
+            scores[first] += self.riichi_sticks as i32 * RIICHI_DEPOSIT + deposits.iter().sum::<i32>();
+        }
+        scores
+    }
+}
@@ -0,0 +1,920 @@
+//! Import and export of the JSON logs of Tenhou, as used by its replay viewer.
+//!
+//! Tiles are written as two digit numbers : 11 to 19 for manzu, 21 to 29 for pinzu, 31 to 39 for souzu,
+//! 41 to 47 for east, south, west, north, white, green and red, and 51 to 53 for the red fives.
+//! Each round lists the draws and the discards of every player separately, calls and kans being written
+//! as strings whose letter gives the kind of call and whose position gives the player the tile comes from.
+
+use std::collections::VecDeque;
+use std::fmt::{Display, Debug};
+
+use serde_json::{json, Value};
+
+use crate::tile::{Tile, TileError};
+use crate::tile::tile_value::TileValue;
+use crate::tile::suit::Suit;
+use crate::tile::dragon::Dragon;
+use crate::tile::notation::{TileAllocator, NotationError};
+use crate::game::wind::Wind;
+use crate::game::rules::{RuleSet, RedFives, RoundLength};
+use crate::game::event::Event;
+use crate::game::round::{Round, RoundError};
+use crate::record::{RoundRecord, RoundResult, WinRecord, AbortiveDraw, placements};
+use crate::record::game::GameRecord;
+use crate::scoring::payment::{base_points, ron_payment, tsumo_payments, YAKUMAN_BASE_POINTS};
+
+/// Code of a discard of the tile that was just drawn
+const TSUMOGIRI : u64 = 60;
+
+/// Reason why a log cannot be imported
+pub enum TenhouError {
+    Json(serde_json::Error),
+    /// The log does not follow the expected format
+    Format(&'static str),
+    /// The log describes an impossible sequence of events
+    Replay(RoundError),
+    Notation(NotationError),
+}
+
+impl Debug for TenhouError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TenhouError::Json(error) => write!(f, "invalid JSON : {}", error),
+            TenhouError::Format(message) => write!(f, "invalid log : {}", message),
+            TenhouError::Replay(error) => write!(f, "invalid round : {}", error),
+            TenhouError::Notation(error) => write!(f, "invalid tiles : {}", error),
+        }
+    }
+}
+
+impl Display for TenhouError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for TenhouError {}
+
+impl From<TileError> for TenhouError {
+    fn from(error : TileError) -> Self {
+        TenhouError::Notation(NotationError::from(error))
+    }
+}
+
+impl From<serde_json::Error> for TenhouError {
+    fn from(error : serde_json::Error) -> Self {
+        TenhouError::Json(error)
+    }
+}
+
+impl From<NotationError> for TenhouError {
+    fn from(error : NotationError) -> Self {
+        TenhouError::Notation(error)
+    }
+}
+
+impl From<RoundError> for TenhouError {
+    fn from(error : RoundError) -> Self {
+        TenhouError::Replay(error)
+    }
+}
+
+/// A game in the Tenhou log format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TenhouLog {
+    pub names : Vec<String>,
+    pub rules : RuleSet,
+    pub rounds : Vec<RoundRecord>,
+    /// Scores of the players at the end of the game
+    pub scores : Vec<i32>,
+}
+
+impl From<&GameRecord> for TenhouLog {
+    fn from(record : &GameRecord) -> Self {
+        TenhouLog{names : record.names.clone(), rules : record.rules.clone(), rounds : record.rounds.clone(), scores : record.final_scores()}
+    }
+}
+
+impl TenhouLog {
+
+    /// Parses a JSON log, as downloaded from Tenhou
+    pub fn parse(json : &str) -> Result<TenhouLog, TenhouError> {
+        let log : Value = serde_json::from_str(json)?;
+
+        let rules = import_rules(&log)?;
+        let players = rules.players.count() as usize;
+        let names = array(&log["name"])?
+            .iter()
+            .take(players)
+            .map(|name| name.as_str().unwrap_or_default().to_string())
+            .collect();
+        let rounds = array(&log["log"])?
+            .iter()
+            .map(|round| import_round(array(round)?, &rules))
+            .collect::<Result<Vec<RoundRecord>, TenhouError>>()?;
+
+        // The final scores are given with the points of each player, and rebuilt from the last round when missing
+        let scores = match log["sc"].as_array() {
+            Some(sc) if sc.len() == 2 * players => sc.iter().step_by(2)
+                .map(|score| score.as_i64().map(|score| score as i32).ok_or(TenhouError::Format("a final score is not a number")))
+                .collect::<Result<Vec<i32>, TenhouError>>()?,
+            _ => rounds.last().map(RoundRecord::game_end_scores).unwrap_or_else(|| vec![rules.starting_points; players]),
+        };
+
+        Ok(TenhouLog{names, rules, rounds, scores})
+    }
+
+    /// Writes the game as a JSON log that can be opened in the Tenhou viewer
+    pub fn to_json(&self) -> String {
+        let log = json!({
+            "title" : ["", ""],
+            "name" : self.names,
+            "rule" : {
+                "disp" : rules_display(&self.rules),
+                "aka" : if self.rules.red_fives.total() > 0 {1} else {0},
+            },
+            "log" : self.rounds.iter().map(|round| export_round(round, &self.rules)).collect::<Vec<Value>>(),
+            "sc" : self.final_scores(),
+        });
+        log.to_string()
+    }
+
+    /// Final score and points after uma and oka of each player
+    fn final_scores(&self) -> Vec<Value> {
+        self.scores.iter().zip(placements(&self.scores))
+            .flat_map(|(score, rank)| vec![json!(score), json!(self.rules.placement_points(*score, rank) as f64 / 1000.0)])
+            .collect()
+    }
+}
+
+// --- Tiles
+
+fn decode_tile(code : u64) -> Result<(TileValue, bool), TenhouError> {
+    let suit = match code / 10 {
+        1 => Suit::Man,
+        2 => Suit::Pin,
+        3 => Suit::Sou,
+        4 => {
+            return match code {
+                41 => Ok((TileValue::new_wind(Wind::East), false)),
+                42 => Ok((TileValue::new_wind(Wind::South), false)),
+                43 => Ok((TileValue::new_wind(Wind::West), false)),
+                44 => Ok((TileValue::new_wind(Wind::North), false)),
+                45 => Ok((TileValue::new_dragon(Dragon::White), false)),
+                46 => Ok((TileValue::new_dragon(Dragon::Green), false)),
+                47 => Ok((TileValue::new_dragon(Dragon::Red), false)),
+                _ => Err(TenhouError::Format("invalid tile")),
+            };
+        },
+        5 => {
+            let suit = match code {
+                51 => Suit::Man,
+                52 => Suit::Pin,
+                53 => Suit::Sou,
+                _ => return Err(TenhouError::Format("invalid tile")),
+            };
+            return Ok((TileValue::try_new_suited(suit, 5)?, true));
+        },
+        _ => return Err(TenhouError::Format("invalid tile")),
+    };
+    Ok((TileValue::try_new_suited(suit, (code % 10) as u8)?, false))
+}
+
+fn encode_tile(tile : Tile, rules : &RuleSet) -> u64 {
+    match tile.value() {
+        TileValue::Suited(suited) => {
+            let suit = match suited.suit() {
+                Suit::Man => 1,
+                Suit::Pin => 2,
+                Suit::Sou => 3,
+            };
+            if tile.is_red(rules.red_fives) {
+                50 + suit
+            } else {
+                suit * 10 + suited.value() as u64
+            }
+        },
+        TileValue::Honor(_) => {
+            let honors = [
+                TileValue::new_wind(Wind::East),
+                TileValue::new_wind(Wind::South),
+                TileValue::new_wind(Wind::West),
+                TileValue::new_wind(Wind::North),
+                TileValue::new_dragon(Dragon::White),
+                TileValue::new_dragon(Dragon::Green),
+                TileValue::new_dragon(Dragon::Red),
+            ];
+            41 + honors.iter().position(|honor| *honor == tile.value()).unwrap_or_default() as u64
+        },
+    }
+}
+
+/// Finds in the hand a tile written as `code`, which has not been picked yet
+fn find_tile(hand : &[Tile], code : u64, picked : &[Tile], rules : &RuleSet) -> Result<Tile, TenhouError> {
+    let (value, red) = decode_tile(code)?;
+    hand.iter()
+        .find(|tile| tile.value() == value && tile.is_red(rules.red_fives) == red && !picked.contains(tile))
+        .copied()
+        .ok_or(TenhouError::Replay(RoundError::TileNotInHand(Tile::new(value, 0))))
+}
+
+/// Gives an id to a tile written as `code`
+fn allocate(allocator : &mut TileAllocator, code : u64) -> Result<Tile, TenhouError> {
+    let (value, red) = decode_tile(code)?;
+    Ok(allocator.allocate(value, red)?)
+}
+
+fn allocate_all(allocator : &mut TileAllocator, codes : &[Value]) -> Result<Vec<Tile>, TenhouError> {
+    codes.iter().map(|code| allocate(allocator, number(code)?)).collect()
+}
+
+// --- Import
+
+fn array(value : &Value) -> Result<&Vec<Value>, TenhouError> {
+    value.as_array().ok_or(TenhouError::Format("expected an array"))
+}
+
+fn number(value : &Value) -> Result<u64, TenhouError> {
+    value.as_u64().ok_or(TenhouError::Format("expected a number"))
+}
+
+fn import_rules(log : &Value) -> Result<RuleSet, TenhouError> {
+    let display = log["rule"]["disp"].as_str().unwrap_or_default();
+    let mut rules = if display.contains('三') {
+        RuleSet::mahjong_soul_sanma()
+    } else {
+        RuleSet::tenhou()
+    };
+    rules.kuitan = display.is_empty() || display.contains('喰');
+    rules.round_length = if display.contains('東') {RoundLength::Tonpuusen} else {RoundLength::Hanchan};
+    rules.red_fives = match log["rule"]["aka"].as_u64() {
+        Some(0) => RedFives::none(),
+        _ => rules.red_fives,
+    };
+    if let Some(first_round) = array(&log["log"])?.first() {
+        let scores = array(first_round)?.get(1).ok_or(TenhouError::Format("missing round data"))?;
+        rules.starting_points = number(&scores[0])? as i32;
+    }
+    Ok(rules)
+}
+
+/// Replays a round from the draws and discards of each player, to recover the order of the events
+struct RoundImporter<'a> {
+    rules : &'a RuleSet,
+    round : Round,
+    events : Vec<Event>,
+    allocator : TileAllocator,
+    kan_indicators : VecDeque<Tile>,
+}
+
+impl<'a> RoundImporter<'a> {
+
+    fn push(&mut self, event : Event) -> Result<(), TenhouError> {
+        self.round.apply(&event)?;
+        self.events.push(event);
+        Ok(())
+    }
+
+    fn reveal_kan_dora(&mut self) -> Result<(), TenhouError> {
+        if let Some(indicator) = self.kan_indicators.pop_front() {
+            self.push(Event::NewDora{indicator})?;
+        }
+        Ok(())
+    }
+
+    fn hand(&self, player : usize) -> Vec<Tile> {
+        self.round.player(player).hand().hidden_tiles().clone()
+    }
+
+    fn find_tiles(&self, player : usize, codes : &[u64]) -> Result<Vec<Tile>, TenhouError> {
+        let hand = self.hand(player);
+        let mut picked = Vec::new();
+        for code in codes {
+            picked.push(find_tile(&hand, *code, &picked, self.rules)?);
+        }
+        Ok(picked)
+    }
+
+    /// Draw from the wall or call of the last discard.
+    /// Returns whether the player has to draw a replacement tile after an open kan.
+    fn take(&mut self, player : usize, take : &Value) -> Result<bool, TenhouError> {
+        let call = match take.as_str() {
+            Some(call) => Call::parse(call)?,
+            None => {
+                let tile = allocate(&mut self.allocator, number(take)?)?;
+                self.push(Event::Draw{player, tile})?;
+                return Ok(false);
+            },
+        };
+        let (from, called) = self.round.last_discard().ok_or(TenhouError::Format("call without discard"))?;
+        if from != call.source(player, self.round.player_count()) {
+            return Err(TenhouError::Format("call from the wrong player"));
+        }
+        let tiles = self.find_tiles(player, &call.other_codes())?;
+        match (call.letter, tiles.len()) {
+            ('c', 2) => self.push(Event::Chii{player, from, called, tiles : [tiles[0], tiles[1]]})?,
+            ('p', 2) => self.push(Event::Pon{player, from, called, tiles : [tiles[0], tiles[1]]})?,
+            ('m', 3) => {
+                self.push(Event::OpenKan{player, from, called, tiles : [tiles[0], tiles[1], tiles[2]]})?;
+                self.reveal_kan_dora()?;
+                return Ok(true);
+            },
+            _ => return Err(TenhouError::Format("invalid call")),
+        }
+        Ok(false)
+    }
+
+    /// Discard, kan or nukidora. Returns whether the player has to draw a replacement tile.
+    fn discard(&mut self, player : usize, discard : &Value) -> Result<bool, TenhouError> {
+        let drawn = self.round.player(player).drawn();
+        let (code, riichi) = match discard.as_str() {
+            Some(riichi) if riichi.starts_with('r') => {
+                (riichi[1..].parse().map_err(|_| TenhouError::Format("invalid riichi"))?, true)
+            },
+            Some(kan) => {
+                let call = Call::parse(kan)?;
+                match call.letter {
+                    'a' => {
+                        let tiles = self.find_tiles(player, &call.codes)?;
+                        if tiles.len() != 4 {
+                            return Err(TenhouError::Format("invalid closed kan"));
+                        }
+                        self.push(Event::ClosedKan{player, tiles : [tiles[0], tiles[1], tiles[2], tiles[3]]})?;
+                        self.reveal_kan_dora()?;
+                    },
+                    'k' => {
+                        let tile = self.find_tiles(player, &[call.called_code()])?[0];
+                        self.push(Event::AddedKan{player, tile})?;
+                        self.reveal_kan_dora()?;
+                    },
+                    'f' => {
+                        let tile = self.find_tiles(player, &[call.called_code()])?[0];
+                        self.push(Event::Nuki{player, tile})?;
+                    },
+                    _ => return Err(TenhouError::Format("invalid discard")),
+                }
+                return Ok(true);
+            },
+            None => (number(discard)?, false),
+        };
+        let tsumogiri = code == TSUMOGIRI;
+        let tile = if tsumogiri {
+            drawn.ok_or(TenhouError::Format("tsumogiri without draw"))?
+        } else {
+            self.find_tiles(player, &[code])?[0]
+        };
+        self.push(Event::Discard{player, tile, tsumogiri, riichi})?;
+        Ok(false)
+    }
+}
+
+/// A call, kan or nukidora written as a string, like `"p454545"` or `"4242a4242"`
+struct Call {
+    letter : char,
+    /// Position of the letter among the tiles
+    position : usize,
+    codes : Vec<u64>,
+}
+
+impl Call {
+
+    fn parse(call : &str) -> Result<Call, TenhouError> {
+        let index = call.find(|c : char| c.is_ascii_alphabetic()).ok_or(TenhouError::Format("invalid call"))?;
+        let letter = call[index..].chars().next().unwrap_or_default();
+        let digits : Vec<u64> = call.chars().filter_map(|c| c.to_digit(10)).map(u64::from).collect();
+        if digits.len() % 2 == 1 || index % 2 == 1 {
+            return Err(TenhouError::Format("invalid call"));
+        }
+        let codes : Vec<u64> = digits.chunks(2).map(|pair| pair[0] * 10 + pair[1]).collect();
+        // The letter is always followed by the called tile
+        if index / 2 >= codes.len() {
+            return Err(TenhouError::Format("no tile after the call letter"));
+        }
+        Ok(Call{letter, position : index / 2, codes})
+    }
+
+    /// The tile written just after the letter : the called tile, or the added tile of a kan
+    fn called_code(&self) -> u64 {
+        self.codes[self.position]
+    }
+
+    fn other_codes(&self) -> Vec<u64> {
+        let mut codes = self.codes.clone();
+        codes.remove(self.position);
+        codes
+    }
+
+    /// The player who discarded the called tile
+    fn source(&self, player : usize, players : usize) -> usize {
+        let left = (player + players - 1) % players;
+        match (self.letter, self.position) {
+            ('c', _) | (_, 0) => left,
+            (_, 1) => (player + 2) % players,
+            _ => (player + 1) % players,
+        }
+    }
+}
+
+fn import_round(round : &[Value], rules : &RuleSet) -> Result<RoundRecord, TenhouError> {
+    let players = rules.players.count() as usize;
+    if round.len() < 5 + 3 * players {
+        return Err(TenhouError::Format("missing round data"));
+    }
+    let header = array(&round[0])?;
+    if header.len() < 3 {
+        return Err(TenhouError::Format("missing round header"));
+    }
+    let kyoku = number(&header[0])? as usize;
+    let honba = number(&header[1])? as u8;
+    let riichi_sticks = number(&header[2])? as u8;
+    let round_wind = [Wind::East, Wind::South, Wind::West, Wind::North][(kyoku / 4) % 4];
+    let dealer = kyoku % 4;
+    if dealer >= players {
+        return Err(TenhouError::Format("dealer without a seat"));
+    }
+
+    let scores = array(&round[1])?.iter()
+        .take(players)
+        .map(|score| score.as_i64().map(|score| score as i32).ok_or(TenhouError::Format("invalid score")))
+        .collect::<Result<Vec<i32>, TenhouError>>()?;
+    if scores.len() < players {
+        return Err(TenhouError::Format("missing score"));
+    }
+
+    let mut allocator = TileAllocator::new(rules.red_fives);
+    let mut hands = Vec::new();
+    for player in 0..players {
+        hands.push(allocate_all(&mut allocator, array(&round[4 + 3 * player])?)?);
+    }
+    let dora_indicators = allocate_all(&mut allocator, array(&round[2])?)?;
+    let ura_indicators = allocate_all(&mut allocator, array(&round[3])?)?;
+    let first_indicator = *dora_indicators.first().ok_or(TenhouError::Format("missing dora indicator"))?;
+
+    let mut takes : Vec<VecDeque<Value>> = Vec::new();
+    let mut discards : Vec<VecDeque<Value>> = Vec::new();
+    for player in 0..players {
+        takes.push(array(&round[5 + 3 * player])?.iter().cloned().collect());
+        discards.push(array(&round[6 + 3 * player])?.iter().cloned().collect());
+    }
+
+    let mut importer = RoundImporter {
+        rules,
+        round : Round::new(rules.clone(), round_wind, dealer, honba, riichi_sticks, hands.clone(), first_indicator),
+        events : Vec::new(),
+        allocator,
+        kan_indicators : dora_indicators.iter().skip(1).copied().collect(),
+    };
+
+    let mut player = dealer;
+    while let Some(take) = takes[player].pop_front() {
+        if importer.take(player, &take)? {
+            // The discard following an open kan is written as a 0, the player draws a replacement tile instead
+            discards[player].pop_front();
+            continue;
+        }
+        let discard = match discards[player].pop_front() {
+            Some(discard) => discard,
+            None => break,
+        };
+        if importer.discard(player, &discard)? {
+            continue;
+        }
+
+        // The next player to take a tile is the one calling the discard if any, pon and kan having priority
+        let (_, discarded) = importer.round.last_discard().ok_or(TenhouError::Format("missing discard"))?;
+        let code = encode_tile(discarded, rules);
+        let callers : Vec<(usize, char)> = (1..players)
+            .map(|offset| (player + offset) % players)
+            .filter_map(|caller| {
+                let call = Call::parse(takes[caller].front()?.as_str()?).ok()?;
+                if call.called_code() == code && call.source(caller, players) == player {
+                    Some((caller, call.letter))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        player = callers.iter()
+            .find(|(_, letter)| *letter != 'c')
+            .or_else(|| callers.first())
+            .map_or((player + 1) % players, |(caller, _)| *caller);
+    }
+
+    Ok(RoundRecord {
+        round_wind,
+        dealer,
+        honba,
+        riichi_sticks,
+        scores,
+        hands,
+        dora_indicators,
+        ura_indicators,
+        events : importer.events,
+        result : import_result(array(round.last().unwrap_or(&Value::Null))?, players)?,
+    })
+}
+
+fn deltas(value : &Value, players : usize) -> Result<Vec<i32>, TenhouError> {
+    let deltas = match value {
+        Value::Null => return Ok(vec![0; players]),
+        _ => array(value)?.iter()
+            .take(players)
+            .map(|delta| delta.as_i64().map(|delta| delta as i32).ok_or(TenhouError::Format("invalid score change")))
+            .collect::<Result<Vec<i32>, TenhouError>>()?,
+    };
+    if deltas.len() < players {
+        return Err(TenhouError::Format("missing score change"));
+    }
+    Ok(deltas)
+}
+
+fn import_result(result : &[Value], players : usize) -> Result<RoundResult, TenhouError> {
+    let name = result.first().and_then(Value::as_str).ok_or(TenhouError::Format("missing result"))?;
+    let changes = deltas(result.get(1).unwrap_or(&Value::Null), players)?;
+    Ok(match name {
+        "和了" => {
+            let wins = result[1..].chunks(2)
+                .map(|win| import_win(win, players))
+                .collect::<Result<Vec<WinRecord>, TenhouError>>()?;
+            RoundResult::Win(wins)
+        },
+        "流局" | "全員聴牌" | "全員不聴" => RoundResult::ExhaustiveDraw{deltas : changes},
+        "流し満貫" => RoundResult::NagashiMangan{deltas : changes},
+        "九種九牌" => RoundResult::AbortiveDraw(AbortiveDraw::NineTerminals),
+        "四風連打" => RoundResult::AbortiveDraw(AbortiveDraw::FourWinds),
+        "四家立直" => RoundResult::AbortiveDraw(AbortiveDraw::FourRiichi),
+        "四槓散了" => RoundResult::AbortiveDraw(AbortiveDraw::FourKans),
+        "三家和了" => RoundResult::AbortiveDraw(AbortiveDraw::TripleRon),
+        _ => return Err(TenhouError::Format("unknown result")),
+    })
+}
+
+/// Parses the score change and the details of a win, like `[3, 1, 3, "30符2飜2000点", "立直(1飜)", "ドラ(1飜)"]`
+fn import_win(win : &[Value], players : usize) -> Result<WinRecord, TenhouError> {
+    if win.len() != 2 {
+        return Err(TenhouError::Format("invalid win"));
+    }
+    let details = array(&win[1])?;
+    if details.len() < 4 {
+        return Err(TenhouError::Format("invalid win"));
+    }
+    let winner = number(&details[0])? as usize;
+    let from = number(&details[1])? as usize;
+    let pao = number(&details[2])? as usize;
+    if [winner, from, pao].iter().any(|player| *player >= players) {
+        return Err(TenhouError::Format("win of a player without a seat"));
+    }
+    let text = details[3].as_str().unwrap_or_default();
+
+    let yaku = details[4..].iter()
+        .filter_map(Value::as_str)
+        .map(|yaku| {
+            let (name, value) = yaku.split_at(yaku.find('(').unwrap_or(yaku.len()));
+            let han = if value.contains("役満") {
+                13
+            } else {
+                value.trim_start_matches('(').trim_end_matches(")").trim_end_matches('飜').parse().unwrap_or_default()
+            };
+            (name.to_string(), han)
+        })
+        .collect::<Vec<(String, u8)>>();
+
+    // The fu are only written for hands below mangan
+    let fu = match text.find('符') {
+        Some(index) => text[..index].parse().unwrap_or_default(),
+        None => 0,
+    };
+
+    Ok(WinRecord {
+        winner,
+        from : if from == winner {None} else {Some(from)},
+        pao : if pao == winner {None} else {Some(pao)},
+        han : yaku.iter().map(|(_, han)| *han).sum(),
+        fu,
+        yaku,
+        deltas : deltas(&win[0], players)?,
+    })
+}
+
+// --- Export
+
+fn rules_display(rules : &RuleSet) -> String {
+    let mut display = String::new();
+    if rules.players.is_sanma() {
+        display.push('三');
+    }
+    display.push('般');
+    display.push(match rules.round_length {
+        RoundLength::Tonpuusen => '東',
+        RoundLength::Hanchan => '南',
+    });
+    if rules.kuitan {
+        display.push('喰');
+    }
+    if rules.red_fives.total() > 0 {
+        display.push('赤');
+    }
+    display
+}
+
+/// Writes a call, putting its letter and the called tile at the position matching the player it comes from
+fn call_string(letter : char, called : u64, others : &[u64], player : usize, from : usize, players : usize) -> String {
+    let position = if letter == 'c' || from == (player + players - 1) % players {
+        0
+    } else if from == (player + 1) % players {
+        others.len()
+    } else {
+        1
+    };
+    let mut call = String::new();
+    for (index, code) in others.iter().enumerate() {
+        if index == position {
+            call.push(letter);
+            call.push_str(&called.to_string());
+        }
+        call.push_str(&code.to_string());
+    }
+    if position == others.len() {
+        call.push(letter);
+        call.push_str(&called.to_string());
+    }
+    call
+}
+
+fn export_round(record : &RoundRecord, rules : &RuleSet) -> Value {
+    let players = record.hands.len();
+    let encode = |tiles : &[Tile]| tiles.iter().map(|tile| json!(encode_tile(*tile, rules))).collect::<Vec<Value>>();
+    let mut takes : Vec<Vec<Value>> = vec![Vec::new(); players];
+    let mut discards : Vec<Vec<Value>> = vec![Vec::new(); players];
+    // Call string of each pon, to write the added kans at the same position
+    let mut pons : Vec<(usize, TileValue, String)> = Vec::new();
+
+    for event in &record.events {
+        match *event {
+            Event::Draw { player, tile } => takes[player].push(json!(encode_tile(tile, rules))),
+            Event::Discard { player, tile, tsumogiri, riichi } => {
+                let code = if tsumogiri {TSUMOGIRI} else {encode_tile(tile, rules)};
+                discards[player].push(if riichi {json!(format!("r{}", code))} else {json!(code)});
+            },
+            Event::Chii { player, from, called, tiles } => {
+                let call = call_string('c', encode_tile(called, rules), &encode_codes(&tiles, rules), player, from, players);
+                takes[player].push(json!(call));
+            },
+            Event::Pon { player, from, called, tiles } => {
+                let call = call_string('p', encode_tile(called, rules), &encode_codes(&tiles, rules), player, from, players);
+                pons.push((player, called.value(), call.clone()));
+                takes[player].push(json!(call));
+            },
+            Event::OpenKan { player, from, called, tiles } => {
+                let call = call_string('m', encode_tile(called, rules), &encode_codes(&tiles, rules), player, from, players);
+                takes[player].push(json!(call));
+                discards[player].push(json!(0));
+            },
+            Event::ClosedKan { player, tiles } => {
+                let codes = encode_codes(&tiles, rules);
+                let call = format!("{}{}{}a{}", codes[0], codes[1], codes[2], codes[3]);
+                discards[player].push(json!(call));
+            },
+            Event::AddedKan { player, tile } => {
+                let added = format!("k{}", encode_tile(tile, rules));
+                let call = pons.iter()
+                    .find(|(pon_player, value, _)| *pon_player == player && *value == tile.value())
+                    .map_or(added.clone(), |(_, _, pon)| {
+                        let index = pon.find('p').unwrap_or_default();
+                        format!("{}{}{}", &pon[..index], added, &pon[index + 1..])
+                    });
+                discards[player].push(json!(call));
+            },
+            Event::Nuki { player, tile } => {
+                discards[player].push(json!(format!("f{}", encode_tile(tile, rules))));
+            },
+            Event::NewDora { .. } => (),
+        }
+    }
+
+    let round_number = Wind::seats(4).iter().position(|wind| *wind == record.round_wind).unwrap_or_default();
+    let mut round = vec![
+        json!([round_number * 4 + record.dealer, record.honba, record.riichi_sticks]),
+        json!(record.scores),
+        json!(encode(&record.dora_indicators)),
+        json!(encode(&record.ura_indicators)),
+    ];
+    for player in 0..players {
+        round.push(json!(encode(&record.hands[player])));
+        round.push(json!(takes[player]));
+        round.push(json!(discards[player]));
+    }
+    round.push(export_result(record, rules));
+    json!(round)
+}
+
+fn encode_codes(tiles : &[Tile], rules : &RuleSet) -> Vec<u64> {
+    tiles.iter().map(|tile| encode_tile(*tile, rules)).collect()
+}
+
+fn export_result(record : &RoundRecord, rules : &RuleSet) -> Value {
+    match &record.result {
+        RoundResult::Win(wins) => {
+            let mut result = vec![json!("和了")];
+            for win in wins {
+                result.push(json!(win.deltas));
+                let mut details = vec![
+                    json!(win.winner),
+                    json!(win.from.unwrap_or(win.winner)),
+                    json!(win.pao.unwrap_or(win.winner)),
+                    json!(win_text(win, record, rules)),
+                ];
+                for (name, han) in &win.yaku {
+                    details.push(json!(if *han >= 13 {format!("{}(役満)", name)} else {format!("{}({}飜)", name, han)}));
+                }
+                result.push(json!(details));
+            }
+            json!(result)
+        },
+        RoundResult::ExhaustiveDraw { deltas } => json!(["流局", deltas]),
+        RoundResult::NagashiMangan { deltas } => json!(["流し満貫", deltas]),
+        RoundResult::AbortiveDraw(draw) => json!([match draw {
+            AbortiveDraw::NineTerminals => "九種九牌",
+            AbortiveDraw::FourWinds => "四風連打",
+            AbortiveDraw::FourRiichi => "四家立直",
+            AbortiveDraw::FourKans => "四槓散了",
+            AbortiveDraw::TripleRon => "三家和了",
+        }]),
+    }
+}
+
+/// Value of a win as displayed by Tenhou, like `"30符2飜2000点"` or `"満貫2000-4000点"`
+fn win_text(win : &WinRecord, record : &RoundRecord, rules : &RuleSet) -> String {
+    let base = if win.han >= 13 && rules.multiple_yakuman {
+        YAKUMAN_BASE_POINTS * (win.han / 13) as u32
+    } else if win.fu == 0 {
+        // Limit hand whose fu were not recorded
+        base_points(win.han, win.fu, rules).max(2000)
+    } else {
+        base_points(win.han, win.fu, rules)
+    };
+    let name = match base {
+        0..=1999 => format!("{}符{}飜", win.fu, win.han),
+        2000 => "満貫".to_string(),
+        2001..=3000 => "跳満".to_string(),
+        3001..=4000 => "倍満".to_string(),
+        4001..=6000 => "三倍満".to_string(),
+        _ => "役満".to_string(),
+    };
+
+    let players = record.hands.len();
+    let seat = Wind::seats(players as u8)[(win.winner + players - record.dealer) % players];
+    let points = match win.from {
+        Some(_) => format!("{}点", ron_payment(base, seat == Wind::East, 0, rules)),
+        None => {
+            let payments = tsumo_payments(base, seat, 0, rules);
+            let lowest = payments.iter().map(|(_, points)| *points).min().unwrap_or_default();
+            if seat == Wind::East {
+                format!("{}点∀", lowest)
+            } else {
+                let highest = payments.iter().map(|(_, points)| *points).max().unwrap_or_default();
+                format!("{}-{}点", lowest, highest)
+            }
+        },
+    };
+    format!("{}{}", name, points)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{TenhouLog, TenhouError};
+    use crate::tile::TileError;
+    use crate::tile::notation::NotationError;
+    use crate::game::rules::RuleSet;
+    use crate::game::event::Event;
+    use crate::game::round::RoundError;
+    use crate::game::table::Table;
+    use crate::game::host::{TableHost, PlayerInterface};
+    use crate::record::RoundResult;
+    use crate::bot::RuleBot;
+
+    const LOG : &str = r#"{
+        "title" : ["", ""],
+        "name" : ["A", "B", "C", "D"],
+        "rule" : {"disp" : "般南喰赤", "aka" : 1},
+        "log" : [[
+            [0, 0, 0],
+            [25000, 25000, 25000, 25000],
+            [41],
+            [42],
+            [11, 11, 12, 13, 14, 15, 16, 17, 18, 19, 21, 22, 23],
+            [44, 38],
+            [60, 60],
+            [31, 32, 33, 34, 35, 36, 37, 38, 39, 41, 41, 42, 43],
+            [36],
+            [41],
+            [24, 25, 26, 27, 28, 29, 44, 44, 45, 45, 46, 46, 47],
+            ["44p4444"],
+            [47],
+            [21, 22, 23, 24, 52, 26, 27, 28, 29, 31, 32, 33, 47],
+            [11],
+            ["r60"],
+            ["和了", [0, -2000, 0, 3000], [3, 1, 3, "30符2飜2000点", "立直(1飜)", "赤ドラ(1飜)"]]
+        ]]
+    }"#;
+
+    #[test]
+    fn test_import() {
+        let log = TenhouLog::parse(LOG).unwrap();
+        assert_eq!(vec!["A", "B", "C", "D"], log.names);
+        assert_eq!(1, log.rounds.len());
+
+        let round = &log.rounds[0];
+        assert_eq!(10, round.events.len());
+        assert!(matches!(round.events[2], Event::Pon{player : 2, from : 0, ..}));
+        assert!(matches!(round.events[5], Event::Discard{player : 3, riichi : true, tsumogiri : true, ..}));
+
+        let state = round.replay(&log.rules, round.events.len()).unwrap();
+        assert_eq!(1, state.player(2).hand().melds().len());
+        assert!(state.player(3).is_riichi());
+
+        match &round.result {
+            RoundResult::Win(wins) => {
+                assert_eq!(Some(1), wins[0].from);
+                assert_eq!(2, wins[0].han);
+                assert_eq!(30, wins[0].fu);
+            },
+            _ => panic!("expected a win"),
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let log = TenhouLog::parse(LOG).unwrap();
+        let json = log.to_json();
+        assert!(json.contains("\"44p4444\""));
+        assert!(json.contains("\"r60\""));
+        assert!(json.contains("30符2飜2000点"));
+        assert_eq!(log, TenhouLog::parse(&json).unwrap());
+    }
+
+    /// The error of parsing the test log after a change
+    fn malformed(change : impl Fn(&mut Value)) -> TenhouError {
+        let mut log : Value = serde_json::from_str(LOG).unwrap();
+        change(&mut log);
+        TenhouLog::parse(&log.to_string()).unwrap_err()
+    }
+
+    #[test]
+    fn test_malformed() {
+        let format = |error : TenhouError| match error {
+            TenhouError::Format(message) => message,
+            other => panic!("expected a format error, got {:?}", other),
+        };
+        assert_eq!("missing round data", format(malformed(|log| log["log"][0] = json!([[0, 0, 0]]))));
+        assert_eq!("missing round header", format(malformed(|log| log["log"][0][0] = json!([0]))));
+        // The fourth seat is empty in a three player game
+        assert_eq!("dealer without a seat", format(malformed(|log| {
+            log["rule"]["disp"] = json!("三般南喰赤");
+            log["log"][0][0] = json!([3, 0, 0]);
+        })));
+        assert_eq!("no tile after the call letter", format(malformed(|log| log["log"][0][11] = json!(["123456p"]))));
+        assert_eq!("missing dora indicator", format(malformed(|log| log["log"][0][2] = json!([]))));
+        assert_eq!("win of a player without a seat", format(malformed(|log| log["log"][0][16][2][0] = json!(7))));
+        assert_eq!("missing score change", format(malformed(|log| log["log"][0][16][1] = json!([0, -2000]))));
+        assert_eq!("invalid tile", format(malformed(|log| log["log"][0][2] = json!([48]))));
+        // A suited code ending with 0 has no number
+        match malformed(|log| log["log"][0][2] = json!([20])) {
+            TenhouError::Notation(NotationError::Tile(error)) => assert_eq!(TileError::InvalidNumber(0), error),
+            other => panic!("expected a tile error, got {:?}", other),
+        }
+
+        // A record without dora indicator cannot be replayed
+        let log = TenhouLog::parse(LOG).unwrap();
+        let mut round = log.rounds[0].clone();
+        round.dora_indicators.clear();
+        assert_eq!(Some(RoundError::MissingDoraIndicator), round.replay(&log.rules, 0).err());
+    }
+
+    #[test]
+    fn test_export_scores() {
+        // Without "sc", the final scores are rebuilt : the riichi deposit of D comes back with their win
+        let log = TenhouLog::parse(LOG).unwrap();
+        assert_eq!(vec![25000, 23000, 25000, 27000], log.scores);
+        let json : Value = serde_json::from_str(&log.to_json()).unwrap();
+        assert_eq!(json!([25000, 5.0, 23000, -27.0, 25000, -15.0, 27000, 37.0]), json["sc"]);
+
+        // A game of bots whose last round has a riichi
+        let names : Vec<String> = (0..4).map(|seat| seat.to_string()).collect();
+        let table = Table::new(0, names, RuleSet::tenhou()).unwrap();
+        let players : Vec<Box<dyn PlayerInterface>> = (0..4).map(|_| Box::new(RuleBot::new()) as Box<dyn PlayerInterface>).collect();
+        let record = TableHost::new(table, players).run().unwrap();
+        let last = record.rounds.last().unwrap();
+        assert!(last.events.iter().any(|event| matches!(event, Event::Discard { riichi : true, .. })));
+        assert_eq!(record.final_scores(), last.game_end_scores());
+
+        let log = TenhouLog::from(&record);
+        let json : Value = serde_json::from_str(&log.to_json()).unwrap();
+        let exported : Vec<i32> = json["sc"].as_array().unwrap().iter().step_by(2).map(|score| score.as_i64().unwrap() as i32).collect();
+        assert_eq!(record.final_scores(), exported);
+        assert_eq!(100_000, exported.iter().sum::<i32>());
+        assert_eq!(record.final_scores(), TenhouLog::parse(&log.to_json()).unwrap().scores);
+    }
+}
@@ -0,0 +1,405 @@
+//! Draws hands, ponds and tables as PNG images, from the tile sprites bundled in `assets/tiles`.
+//!
+//! The sprites are drawn by the `tile_sprites` example, which can be run again to change them.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Debug};
+
+use tiny_skia::{Pixmap, PixmapPaint, Transform};
+
+use crate::tile::Tile;
+use crate::tile::notation::tiles_to_mpsz;
+use crate::hand::Hand;
+use crate::hand::meld::Meld;
+use crate::hand::group::Group;
+use crate::game::wind::Wind;
+use crate::game::pond::Pond;
+use crate::game::round::Round;
+use crate::game::rules::RuleSet;
+
+/// Size of an upright tile, in pixels
+pub const TILE_WIDTH : u32 = 48;
+pub const TILE_HEIGHT : u32 = 64;
+
+/// Space between the groups of tiles, in pixels
+const GAP : u32 = 8;
+
+/// Number of tiles of a row of a pond, except for the last row which has no limit
+const POND_ROW : usize = 6;
+
+macro_rules! sprites {
+    ($($name : literal),*) => {
+        &[$(($name, include_bytes!(concat!("../../assets/tiles/", $name, ".png")))),*]
+    };
+}
+
+const SPRITES : &[(&str, &[u8])] = sprites!(
+    "back",
+    "1m", "2m", "3m", "4m", "5m", "6m", "7m", "8m", "9m", "0m",
+    "1p", "2p", "3p", "4p", "5p", "6p", "7p", "8p", "9p", "0p",
+    "1s", "2s", "3s", "4s", "5s", "6s", "7s", "8s", "9s", "0s",
+    "1z", "2z", "3z", "4z", "5z", "6z", "7z"
+);
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum RenderError {
+    /// The image could not be encoded to PNG
+    Encoding(String),
+}
+
+impl Debug for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Encoding(error) => write!(f, "cannot encode the image : {}", error),
+        }
+    }
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// A sprite drawn at a position, `sideways` sprites being turned a quarter counterclockwise
+#[derive(Clone, Debug)]
+struct Item {
+    sprite : String,
+    x : u32,
+    y : u32,
+    sideways : bool,
+}
+
+/// Sprites placed in a rectangle
+#[derive(Clone, Debug, Default)]
+struct Layout {
+    items : Vec<Item>,
+    width : u32,
+    height : u32,
+}
+
+impl Layout {
+
+    fn tile(sprite : String, sideways : bool) -> Layout {
+        let (width, height) = if sideways {(TILE_HEIGHT, TILE_WIDTH)} else {(TILE_WIDTH, TILE_HEIGHT)};
+        Layout{items : vec![Item{sprite, x : 0, y : 0, sideways}], width, height}
+    }
+
+    fn shifted(mut self, x : u32, y : u32) -> Layout {
+        for item in self.items.iter_mut() {
+            item.x += x;
+            item.y += y;
+        }
+        self
+    }
+
+    /// Puts a layout on the right, the bottoms of both being aligned
+    fn beside(self, other : Layout, gap : u32) -> Layout {
+        if other.items.is_empty() {
+            return self;
+        }
+        if self.items.is_empty() {
+            return other;
+        }
+        let height = self.height.max(other.height);
+        let x = self.width + gap;
+        let width = x + other.width;
+        let (left_y, right_y) = (height - self.height, height - other.height);
+        let mut items = self.shifted(0, left_y).items;
+        items.extend(other.shifted(x, right_y).items);
+        Layout{items, width, height}
+    }
+
+    /// Puts a layout below, the left sides of both being aligned
+    fn below(self, other : Layout, gap : u32) -> Layout {
+        if other.items.is_empty() {
+            return self;
+        }
+        if self.items.is_empty() {
+            return other;
+        }
+        let y = self.height + gap;
+        let (width, height) = (self.width.max(other.width), y + other.height);
+        let mut items = self.items;
+        items.extend(other.shifted(0, y).items);
+        Layout{items, width, height}
+    }
+
+    /// A layout on top of another, with the bottom one's width
+    fn stacked(self, top : Layout) -> Layout {
+        let height = self.height + top.height;
+        let width = self.width.max(top.width);
+        let mut items = top.items;
+        items.extend(self.shifted(0, top.height).items);
+        Layout{items, width, height}
+    }
+
+    fn row(tiles : impl IntoIterator<Item = Layout>) -> Layout {
+        tiles.into_iter().fold(Layout::default(), |row, tile| row.beside(tile, 0))
+    }
+}
+
+/// Draws the tiles with the bundled sprites
+pub struct Renderer {
+    sprites : HashMap<&'static str, Pixmap>,
+    rules : RuleSet,
+}
+
+impl Renderer {
+
+    // Constructors
+
+    /// Loads the sprites, the red fives being the ones of `rules`
+    pub fn new(rules : &RuleSet) -> Renderer {
+        let sprites = SPRITES.iter()
+            .map(|(name, bytes)| (*name, Pixmap::decode_png(bytes).expect("the bundled sprites are valid PNG images")))
+            .collect();
+        Renderer{sprites, rules : rules.clone()}
+    }
+
+    // Other
+
+    /// Name of the sprite of a tile : its MPSZ notation, with a 0 for a red five
+    pub fn sprite_name(&self, tile : Tile) -> String {
+        tiles_to_mpsz(&[tile], self.rules.red_fives)
+    }
+
+    /// Tiles side by side
+    pub fn tiles(&self, tiles : &[Tile]) -> Result<Vec<u8>, RenderError> {
+        self.encode(&self.tile_row(tiles))
+    }
+
+    /// A hand as its player sees it : the hidden tiles, the drawn tile apart, then the melds with the called tiles
+    /// turned toward the player who discarded them, the closed kans and the nukidora.
+    /// `seat` is the seat wind of the player, which tells where the called tiles come from.
+    pub fn hand(&self, hand : &Hand, drawn : Option<Tile>, seat : Wind) -> Result<Vec<u8>, RenderError> {
+        self.encode(&self.hand_layout(hand, drawn, seat))
+    }
+
+    /// The discards of a pond in rows of 6, the riichi discard sideways and the called discards removed
+    pub fn pond(&self, pond : &Pond) -> Result<Vec<u8>, RenderError> {
+        self.encode(&self.pond_layout(pond))
+    }
+
+    /// The dora indicators, followed by face down tiles for the indicators not revealed yet
+    pub fn dora_indicators(&self, indicators : &[Tile]) -> Result<Vec<u8>, RenderError> {
+        self.encode(&self.dora_layout(indicators))
+    }
+
+    /// The table as seen by `viewer` : the dora indicators, the pond and the melds of the other players
+    /// in turn order after the viewer, then the pond and the hand of the viewer
+    pub fn table(&self, round : &Round, viewer : usize) -> Result<Vec<u8>, RenderError> {
+        let players = round.player_count();
+        let mut layout = self.dora_layout(round.dora_indicators());
+        for offset in 1..players {
+            let player = (viewer + offset) % players;
+            let state = round.player(player);
+            let open = self.melds_layout(state.hand(), round.seat_wind(player));
+            layout = layout.below(self.pond_layout(state.pond()).beside(open, 2 * GAP), 2 * GAP);
+        }
+        let state = round.player(viewer);
+        layout = layout
+            .below(self.pond_layout(state.pond()), 2 * GAP)
+            .below(self.hand_layout(state.hand(), state.drawn(), round.seat_wind(viewer)), 2 * GAP);
+        self.encode(&layout)
+    }
+
+    fn tile_layout(&self, tile : Tile, sideways : bool) -> Layout {
+        Layout::tile(self.sprite_name(tile), sideways)
+    }
+
+    fn back() -> Layout {
+        Layout::tile("back".to_string(), false)
+    }
+
+    fn tile_row(&self, tiles : &[Tile]) -> Layout {
+        Layout::row(tiles.iter().map(|tile| self.tile_layout(*tile, false)))
+    }
+
+    fn hand_layout(&self, hand : &Hand, drawn : Option<Tile>, seat : Wind) -> Layout {
+        let mut hidden = hand.hidden_tiles().clone();
+        if let Some(position) = drawn.and_then(|drawn| hidden.iter().position(|tile| *tile == drawn)) {
+            hidden.remove(position);
+        }
+        hidden.sort();
+        let drawn = Layout::row(drawn.map(|tile| self.tile_layout(tile, false)));
+        self.tile_row(&hidden).beside(drawn, GAP).beside(self.melds_layout(hand, seat), 2 * GAP)
+    }
+
+    /// The melds, closed kans and nukidora of a hand
+    fn melds_layout(&self, hand : &Hand, seat : Wind) -> Layout {
+        let melds = hand.melds().iter().map(|meld| self.meld_layout(meld, seat));
+        let kans = hand.closed_kans().iter().map(|kan| {
+            let tiles = kan.tiles();
+            Layout::row(tiles.iter().enumerate().map(|(index, tile)| {
+                if index == 0 || index == tiles.len() - 1 {Renderer::back()} else {self.tile_layout(**tile, false)}
+            }))
+        });
+        let nuki = Layout::row(hand.nuki().iter().map(|tile| self.tile_layout(*tile, false)));
+        melds.chain(kans).chain(std::iter::once(nuki)).fold(Layout::default(), |layout, group| layout.beside(group, GAP))
+    }
+
+    /// A meld with the called tile sideways : on the left when called from the player on the left,
+    /// in the middle from the player across, on the right from the player on the right.
+    /// The tile added to a pon lies on top of the called tile.
+    fn meld_layout(&self, meld : &Meld, seat : Wind) -> Layout {
+        let called = meld.completing_tile();
+        let mut tiles : Vec<Tile> = meld.tiles().into_iter().cloned().collect();
+        let added = match meld.group() {
+            Group::Kan(_, _, _, fourth) if *fourth != called => tiles.pop(),
+            _ => None,
+        };
+        if let Some(position) = tiles.iter().position(|tile| *tile == called) {
+            tiles.remove(position);
+        }
+        let players = self.rules.players.count() as usize;
+        let relative = (seat_index(meld.source()) + players - seat_index(seat)) % players;
+        let position = if relative == players - 1 {0} else if relative == 1 {tiles.len()} else {1};
+        let mut sideways = self.tile_layout(called, true);
+        if let Some(added) = added {
+            sideways = sideways.stacked(self.tile_layout(added, true));
+        }
+        let mut layouts : Vec<Layout> = tiles.iter().map(|tile| self.tile_layout(*tile, false)).collect();
+        layouts.insert(position, sideways);
+        Layout::row(layouts)
+    }
+
+    fn pond_layout(&self, pond : &Pond) -> Layout {
+        let mut rows : Vec<Vec<Layout>> = vec![Vec::new()];
+        let mut sideways = false;
+        for discard in pond.discards() {
+            sideways |= discard.riichi;
+            if discard.called {
+                continue;
+            }
+            if rows.len() < 3 && rows.last().map(Vec::len) == Some(POND_ROW) {
+                rows.push(Vec::new());
+            }
+            rows.last_mut().unwrap().push(self.tile_layout(discard.tile, sideways));
+            sideways = false;
+        }
+        rows.into_iter().fold(Layout::default(), |layout, row| layout.below(Layout::row(row), 0))
+    }
+
+    fn dora_layout(&self, indicators : &[Tile]) -> Layout {
+        let hidden = 5usize.saturating_sub(indicators.len());
+        self.tile_row(indicators).beside(Layout::row((0..hidden).map(|_| Renderer::back())), 0)
+    }
+
+    fn draw(&self, layout : &Layout) -> Pixmap {
+        let mut pixmap = Pixmap::new(layout.width.max(1), layout.height.max(1)).expect("the image has a valid size");
+        let paint = PixmapPaint::default();
+        for item in layout.items.iter() {
+            let sprite = self.sprites[item.sprite.as_str()].as_ref();
+            let (x, y) = (item.x as f32, item.y as f32);
+            let transform = if item.sideways {
+                Transform::from_row(0.0, -1.0, 1.0, 0.0, x, y + TILE_WIDTH as f32)
+            } else {
+                Transform::from_translate(x, y)
+            };
+            pixmap.draw_pixmap(0, 0, sprite, &paint, transform, None);
+        }
+        pixmap
+    }
+
+    fn encode(&self, layout : &Layout) -> Result<Vec<u8>, RenderError> {
+        self.draw(layout).encode_png().map_err(|error| RenderError::Encoding(error.to_string()))
+    }
+}
+
+fn seat_index(wind : Wind) -> usize {
+    match wind {
+        Wind::East => 0,
+        Wind::South => 1,
+        Wind::West => 2,
+        Wind::North => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_skia::Pixmap;
+
+    use super::{Renderer, TILE_WIDTH, TILE_HEIGHT, GAP, SPRITES};
+    use crate::tile::notation::parse_tiles;
+    use crate::hand::Hand;
+    use crate::hand::meld::Meld;
+    use crate::hand::group::Group;
+    use crate::game::wind::Wind;
+    use crate::game::pond::{Pond, Discard};
+    use crate::game::rules::RuleSet;
+    use crate::game::table::Table;
+
+    fn size(png : &[u8]) -> (u32, u32) {
+        let pixmap = Pixmap::decode_png(png).unwrap();
+        (pixmap.width(), pixmap.height())
+    }
+
+    #[test]
+    fn test_sprites() {
+        let renderer = Renderer::new(&RuleSet::mahjong_soul());
+        assert_eq!(38, SPRITES.len());
+        for sprite in renderer.sprites.values() {
+            assert_eq!((TILE_WIDTH, TILE_HEIGHT), (sprite.width(), sprite.height()));
+        }
+        let rules = RuleSet::mahjong_soul();
+        let tiles = parse_tiles("055m5p5s1234567z", rules.red_fives).unwrap();
+        let names : Vec<String> = tiles.iter().map(|tile| renderer.sprite_name(*tile)).collect();
+        assert_eq!(vec!["0m", "5m", "5m", "5p", "5s", "1z", "2z", "3z", "4z", "5z", "6z", "7z"], names);
+        assert_eq!((12 * TILE_WIDTH, TILE_HEIGHT), size(&renderer.tiles(&tiles).unwrap()));
+    }
+
+    #[test]
+    fn test_hand() {
+        let rules = RuleSet::tenhou();
+        let renderer = Renderer::new(&rules);
+        let tiles = parse_tiles("123m456p78s2z1111z", rules.red_fives).unwrap();
+        let mut hand = Hand::new(tiles[..9].to_vec());
+        hand.add_meld(Meld::new(Group::Pon(tiles[9], tiles[10], tiles[11]), tiles[11], Wind::West));
+        let drawn = tiles[8];
+        let width = 8 * TILE_WIDTH + GAP + TILE_WIDTH + 2 * GAP + 2 * TILE_WIDTH + TILE_HEIGHT;
+        assert_eq!((width, TILE_HEIGHT), size(&renderer.hand(&hand, Some(drawn), Wind::East).unwrap()));
+
+        // The added tile lies on the called one
+        assert!(hand.upgrade_pon(tiles[12]));
+        assert_eq!((width, 2 * TILE_WIDTH), size(&renderer.hand(&hand, Some(drawn), Wind::East).unwrap()));
+
+        let kan = Group::Kan(tiles[0], tiles[1], tiles[2], tiles[3]);
+        let mut hand = Hand::new(Vec::new());
+        hand.add_closed_kan(kan);
+        assert_eq!((4 * TILE_WIDTH, TILE_HEIGHT), size(&renderer.hand(&hand, None, Wind::East).unwrap()));
+    }
+
+    #[test]
+    fn test_pond() {
+        let rules = RuleSet::tenhou();
+        let renderer = Renderer::new(&rules);
+        let tiles = parse_tiles("123456789m", rules.red_fives).unwrap();
+        let mut pond = Pond::new();
+        for (index, tile) in tiles.iter().enumerate() {
+            pond.push(Discard{tile : *tile, tsumogiri : false, riichi : index == 1, called : false});
+            if index == 1 {
+                pond.mark_last_called();
+            }
+        }
+        // The riichi discard was called, so the next discard is sideways
+        let width = 5 * TILE_WIDTH + TILE_HEIGHT;
+        assert_eq!((width, 2 * TILE_HEIGHT), size(&renderer.pond(&pond).unwrap()));
+        assert_eq!((1, 1), size(&renderer.pond(&Pond::new()).unwrap()));
+    }
+
+    #[test]
+    fn test_table() {
+        let rules = RuleSet::tenhou();
+        let renderer = Renderer::new(&rules);
+        let table = Table::new(1, vec!["A", "B", "C", "D"].into_iter().map(str::to_string).collect(), rules).unwrap();
+        let round = table.round().unwrap();
+        assert_eq!((5 * TILE_WIDTH, TILE_HEIGHT), size(&renderer.dora_indicators(round.dora_indicators()).unwrap()));
+        let (width, height) = size(&renderer.table(round, 0).unwrap());
+        assert!(width >= 13 * TILE_WIDTH);
+        // No discards yet : the dora indicators and the hand
+        assert_eq!(TILE_HEIGHT + 2 * GAP + TILE_HEIGHT, height);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::hand::Hand;
+use crate::game::rules::RuleSet;
+
+/// Number of dora of each kind contained in a winning hand
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DoraCount {
+    /// Dora given by the first indicator
+    pub dora : u8,
+    /// Dora given by the indicators revealed after a kan
+    pub kan_dora : u8,
+    /// Dora given by the indicators under the dead wall, only counted for a riichi hand
+    pub ura_dora : u8,
+    /// Red fives
+    pub aka_dora : u8,
+    /// North winds set aside in a three player game
+    pub nukidora : u8,
+}
+
+impl DoraCount {
+
+    /// Total number of han given by the dora
+    pub fn total(&self) -> u8 {
+        self.dora + self.kan_dora + self.ura_dora + self.aka_dora + self.nukidora
+    }
+}
+
+/// Counts the dora contained in a winning hand, including its melds and kans.
+/// The first tile of `indicators` is the dora indicator, the following ones are the kan dora indicators.
+/// The `ura_indicators` are only taken into account if the winner declared riichi.
+/// In a three player game, the nukidora are counted and can also be designated by the indicators.
+pub fn count_dora(
+    hand : &Hand,
+    indicators : &[Tile],
+    ura_indicators : &[Tile],
+    riichi : bool,
+    rules : &RuleSet,
+) -> DoraCount {
+    let mut tiles = hand.tiles();
+    tiles.extend(hand.nuki().iter());
+
+    let sanma = rules.players.is_sanma();
+    let mut count = DoraCount::default();
+
+    if let Some((first, kan_indicators)) = indicators.split_first() {
+        count.dora = matching(&tiles, &[*first], sanma);
+        count.kan_dora = matching(&tiles, kan_indicators, sanma);
+    }
+    if riichi {
+        count.ura_dora = matching(&tiles, ura_indicators, sanma);
+    }
+    count.aka_dora = tiles.iter().filter(|tile| tile.is_red(rules.red_fives)).count() as u8;
+    count.nukidora = hand.nuki().len() as u8;
+
+    count
+}
+
+/// Counts the tiles designated as dora by the `indicators`.
+/// A tile is counted once per indicator pointing to it.
+fn matching(tiles : &[&Tile], indicators : &[Tile], sanma : bool) -> u8 {
+    let next_dora = if sanma {Tile::next_dora_sanma} else {Tile::next_dora};
+    let dora : Vec<TileValue> = indicators.iter().map(next_dora).collect();
+    tiles.iter()
+        .map(|tile| dora.iter().filter(|value| **value == tile.value()).count() as u8)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_dora, DoraCount};
+    use crate::tile::Tile;
+    use crate::tile::suit::Suit::{Man, Pin, Sou};
+    use crate::tile::dragon::Dragon::{White, Red};
+    use crate::game::wind::Wind::{East, South, West, North};
+    use crate::game::rules::RuleSet;
+    use crate::hand::Hand;
+    use crate::hand::group::Group;
+    use crate::hand::meld::Meld;
+
+    fn winning_hand() -> Hand {
+        let mut hand = Hand::new(vec![
+            Tile::new_suited(Man, 2, 1),
+            Tile::new_suited(Man, 3, 1),
+            Tile::new_suited(Man, 4, 1),
+            Tile::new_suited(Pin, 5, 0),
+            Tile::new_suited(Pin, 6, 0),
+            Tile::new_suited(Pin, 7, 0),
+            Tile::new_wind(East, 0),
+            Tile::new_wind(East, 1),
+        ]);
+        hand.add_meld(Meld::new(
+            Group::Pon(Tile::new_dragon(Red, 0), Tile::new_dragon(Red, 1), Tile::new_dragon(Red, 2)),
+            Tile::new_dragon(Red, 2),
+            South,
+        ));
+        hand.add_closed_kan(Group::Kan(
+            Tile::new_suited(Sou, 9, 0),
+            Tile::new_suited(Sou, 9, 1),
+            Tile::new_suited(Sou, 9, 2),
+            Tile::new_suited(Sou, 9, 3),
+        ));
+        hand
+    }
+
+    #[test]
+    fn test_no_dora() {
+        let indicators = vec![Tile::new_suited(Man, 7, 0)];
+        let count = count_dora(&winning_hand(), &indicators, &[], false, &RuleSet::wrc());
+        assert_eq!(DoraCount::default(), count);
+    }
+
+    #[test]
+    fn test_dora_in_melds() {
+        let indicators = vec![
+            Tile::new_dragon(White, 0),     // Red dragon, in the pon
+            Tile::new_suited(Sou, 8, 0),    // 9 sou, in the closed kan
+            Tile::new_suited(Man, 1, 0),    // 2 man
+        ];
+        let count = count_dora(&winning_hand(), &indicators, &[], false, &RuleSet::wrc());
+        assert_eq!(3, count.dora);
+        assert_eq!(5, count.kan_dora);
+        assert_eq!(8, count.total());
+    }
+
+    #[test]
+    fn test_ura_dora_only_with_riichi() {
+        let indicators = vec![Tile::new_suited(Man, 7, 0)];
+        let ura_indicators = vec![Tile::new_wind(North, 0)];
+        let hand = winning_hand();
+
+        let count = count_dora(&hand, &indicators, &ura_indicators, false, &RuleSet::wrc());
+        assert_eq!(0, count.ura_dora);
+        let count = count_dora(&hand, &indicators, &ura_indicators, true, &RuleSet::wrc());
+        assert_eq!(2, count.ura_dora);
+    }
+
+    #[test]
+    fn test_aka_dora() {
+        let indicators = vec![Tile::new_suited(Pin, 4, 0)];
+        let hand = winning_hand();
+
+        let count = count_dora(&hand, &indicators, &[], false, &RuleSet::wrc());
+        assert_eq!(0, count.aka_dora);
+        let count = count_dora(&hand, &indicators, &[], false, &RuleSet::tenhou());
+        assert_eq!(1, count.dora);
+        assert_eq!(1, count.aka_dora);
+    }
+
+    #[test]
+    fn test_nukidora() {
+        let mut hand = winning_hand();
+        hand.add_nuki(Tile::new_wind(North, 0));
+        hand.add_nuki(Tile::new_wind(North, 1));
+        let indicators = vec![Tile::new_wind(West, 0)];
+
+        let count = count_dora(&hand, &indicators, &[], false, &RuleSet::mahjong_soul_sanma());
+        assert_eq!(2, count.nukidora);
+        assert_eq!(2, count.dora);
+        assert_eq!(5, count.total()); // The 5 pin is red
+    }
+
+    #[test]
+    fn test_sanma_manzu_indicator() {
+        let indicators = vec![Tile::new_suited(Man, 1, 0)];
+        let mut hand = Hand::new(vec![Tile::new_suited(Man, 9, 0), Tile::new_suited(Man, 9, 1)]);
+        hand.add_nuki(Tile::new_wind(North, 0));
+
+        let count = count_dora(&hand, &indicators, &[], false, &RuleSet::mahjong_soul_sanma());
+        assert_eq!(2, count.dora);
+    }
+}
@@ -0,0 +1,369 @@
+use crate::tile::Tile;
+use crate::tile::tile_counts::TileCounts;
+use crate::hand::Hand;
+use crate::hand::group::Group;
+use crate::hand::wait::WaitKind;
+use crate::hand::hand_arrangement::HandArrangement;
+use crate::hand::shanten::{kokushi_shanten, chiitoitsu_shanten, waits};
+use crate::game::rules::RuleSet;
+use crate::scoring::yaku::{Yaku, WinContext, Set, regular_yaku, situation_yaku, color_yaku, yakuhai_count};
+use crate::scoring::dora::{count_dora, DoraCount};
+use crate::scoring::payment::{base_points, YAKUMAN_BASE_POINTS};
+
+/// Value of a winning hand, for its most valuable interpretation
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandValue {
+    /// Every yaku of the hand with its value in han
+    pub yaku : Vec<(Yaku, u8)>,
+    pub dora : DoraCount,
+    /// Total of the han, dora included. A yakuman counts as 13 han and its dora are ignored
+    pub han : u8,
+    pub fu : u8,
+    pub base_points : u32,
+}
+
+impl HandValue {
+
+    pub fn is_yakuman(&self) -> bool {
+        self.yaku.iter().any(|(yaku, _)| yaku.is_yakuman())
+    }
+
+    /// Names and han of the yaku and of the dora, as displayed on the score screen
+    pub fn yaku_list(&self) -> Vec<(String, u8)> {
+        let mut list : Vec<(String, u8)> = self.yaku.iter().map(|(yaku, han)| (yaku.name(), *han)).collect();
+        if !self.is_yakuman() {
+            let dora = [
+                ("Dora", self.dora.dora + self.dora.kan_dora),
+                ("Aka dora", self.dora.aka_dora),
+                ("Ura dora", self.dora.ura_dora),
+                ("Nukidora", self.dora.nukidora),
+            ];
+            list.extend(dora.iter().filter(|(_, han)| *han > 0).map(|(name, han)| (name.to_string(), *han)));
+        }
+        list
+    }
+}
+
+/// Evaluates a winning hand, whose hidden tiles include the winning tile of the `context`.
+/// The `indicators` are the dora indicators revealed during the round, the `ura_indicators`
+/// only counting for a riichi hand.
+/// Returns `None` if the hand is not complete or has no yaku, or without atozuke if another wait of the hand has no yaku.
+pub fn evaluate(
+    hand : &Hand,
+    context : &WinContext,
+    indicators : &[Tile],
+    ura_indicators : &[Tile],
+    rules : &RuleSet,
+) -> Option<HandValue> {
+    let winning = context.winning_tile;
+    let mut waiting : Vec<Tile> = hand.hidden_tiles().clone();
+    let position = waiting.iter().position(|tile| *tile == winning)?;
+    waiting.remove(position);
+
+    let closed = hand.is_closed();
+    let riichi = context.riichi || context.double_riichi;
+    let dora = count_dora(hand, indicators, ura_indicators, riichi, rules);
+
+    let mut candidates : Vec<(Vec<Yaku>, u8)> = Vec::new();
+    if let Some(yaku) = special_yaku(hand, &waiting, context, rules) {
+        candidates.push(yaku);
+    }
+    for (sets, wait) in regular_interpretations(hand, &waiting, context) {
+        let mut closed_counts = [0; 9];
+        waiting.iter().filter_map(Tile::number).for_each(|number| closed_counts[number as usize - 1] += 1);
+        let yaku = regular_yaku(&sets, wait, context, closed, &closed_counts, rules);
+        let fu = regular_fu(&sets, wait, context, closed, yaku.contains(&Yaku::Pinfu));
+        candidates.push((yaku, fu));
+    }
+    if !rules.atozuke && !every_wait_has_yaku(hand, &waiting, context, rules) {
+        return None;
+    }
+
+    candidates.into_iter()
+        .filter_map(|(yaku, fu)| value(yaku, fu, dora, closed, rules))
+        .max_by_key(|value| (value.base_points, value.han, value.fu))
+}
+
+/// Whether the hand has a yaku whatever the tile it wins on among its waits, dora not being yaku
+fn every_wait_has_yaku(hand : &Hand, waiting : &[Tile], context : &WinContext, rules : &RuleSet) -> bool {
+    let melds = hand.melds().len() + hand.closed_kans().len();
+    let rules = RuleSet{atozuke : true, ..rules.clone()};
+    waits(&TileCounts::from_tiles(waiting), melds).into_iter().all(|value| {
+        // A copy of the wait that is not in the hand, there is none when the hand holds all four
+        let tile = match (0..4).map(|id| Tile::new(value, id)).find(|tile| !waiting.contains(tile)) {
+            Some(tile) => tile,
+            None => return true,
+        };
+        let mut other = hand.clone();
+        other.remove_tile(context.winning_tile);
+        other.add_tile(tile);
+        evaluate(&other, &WinContext{winning_tile : tile, ..*context}, &[], &[], &rules).is_some()
+    })
+}
+
+/// Computes the han and the points of an interpretation, `None` if it has no yaku
+fn value(yaku : Vec<Yaku>, fu : u8, dora : DoraCount, closed : bool, rules : &RuleSet) -> Option<HandValue> {
+    let yaku : Vec<(Yaku, u8)> = yaku.into_iter()
+        .map(|yaku| (yaku, yaku.han(closed, rules)))
+        .filter(|(_, han)| *han > 0)
+        .collect();
+    if yaku.is_empty() {
+        return None;
+    }
+    let han : u8 = yaku.iter().map(|(_, han)| han).sum();
+    if yaku.iter().any(|(yaku, _)| yaku.is_yakuman()) {
+        let han = if rules.multiple_yakuman {han} else {13};
+        return Some(HandValue{yaku, dora, han, fu, base_points : YAKUMAN_BASE_POINTS * (han / 13) as u32});
+    }
+    let han = han + dora.total();
+    Some(HandValue{yaku, dora, han, fu, base_points : base_points(han, fu, rules)})
+}
+
+/// Yaku of the seven pairs and thirteen orphans hands, with their fu
+fn special_yaku(hand : &Hand, waiting : &[Tile], context : &WinContext, rules : &RuleSet) -> Option<(Vec<Yaku>, u8)> {
+    if !hand.is_closed() || !hand.closed_kans().is_empty() {
+        return None;
+    }
+    let counts = TileCounts::from_tiles(hand.hidden_tiles());
+    let first_draw = context.first_draw && context.tsumo;
+    let heavenly = if context.seat_wind == crate::game::wind::Wind::East {Yaku::Tenhou} else {Yaku::Chiihou};
+    if kokushi_shanten(&counts) == -1 {
+        let waiting_counts = TileCounts::from_tiles(waiting);
+        let juusanmen = waiting_counts.distinct().count() == 13;
+        let mut yaku = vec![if juusanmen {Yaku::KokushiMusouJuusanmen} else {Yaku::KokushiMusou}];
+        if first_draw {
+            yaku.push(heavenly);
+        }
+        return Some((yaku, 0));
+    }
+    if chiitoitsu_shanten(&counts) == -1 {
+        let mut yaku = situation_yaku(context, true);
+        yaku.extend(color_yaku(&counts.values(), true, rules));
+        yaku.push(Yaku::Chiitoitsu);
+        if first_draw {
+            yaku.push(heavenly);
+        }
+        if yaku.iter().any(Yaku::is_yakuman) {
+            yaku.retain(Yaku::is_yakuman);
+        }
+        return Some((yaku, 25));
+    }
+    None
+}
+
+/// Every way to read the hand as four groups and a pair, with the wait completed by the winning tile
+fn regular_interpretations(hand : &Hand, waiting : &[Tile], context : &WinContext) -> Vec<(Vec<Set>, WaitKind)> {
+    let winning = context.winning_tile.value();
+    let lowest = |tiles : Vec<&Tile>| tiles.iter().map(|tile| tile.value()).min().unwrap();
+    let mut fixed = Vec::new();
+    for kan in hand.closed_kans() {
+        fixed.push(Set::Quad(lowest(kan.tiles()), true));
+    }
+    for meld in hand.melds() {
+        fixed.push(match meld.group() {
+            Group::Chii(..) => Set::Sequence(lowest(meld.tiles())),
+            Group::Pon(..) => Set::Triplet(lowest(meld.tiles()), false),
+            Group::Kan(..) => Set::Quad(lowest(meld.tiles()), false),
+            Group::Pair(..) => Set::Pair(lowest(meld.tiles())),
+        });
+    }
+
+    let mut interpretations = Vec::new();
+    for arrangement in HandArrangement::empty().consume(waiting) {
+        let wait = match arrangement.wait() {
+            Some(wait) if wait.wait_symbols().contains(&winning) => wait,
+            _ => continue,
+        };
+        let mut sets = fixed.clone();
+        for group in arrangement.groups() {
+            sets.push(match group {
+                Group::Chii(..) => Set::Sequence(lowest(group.tiles())),
+                Group::Pon(..) => Set::Triplet(lowest(group.tiles()), true),
+                Group::Kan(..) => Set::Quad(lowest(group.tiles()), true),
+                Group::Pair(..) => Set::Pair(lowest(group.tiles())),
+            });
+        }
+        let completed = wait.tiles().iter().map(Tile::value).chain(std::iter::once(winning)).min().unwrap();
+        sets.push(match wait.kind() {
+            WaitKind::Ryanmen | WaitKind::Kanchan | WaitKind::Penchan => Set::Sequence(completed),
+            // A triplet completed by a ron counts as open
+            WaitKind::Shanpon => Set::Triplet(winning, context.tsumo),
+            WaitKind::Tanki => Set::Pair(winning),
+        });
+        interpretations.push((sets, wait.kind()));
+    }
+    interpretations
+}
+
+/// Fu of a hand made of four groups and a pair, rounded up to the next ten
+fn regular_fu(sets : &[Set], wait : WaitKind, context : &WinContext, closed : bool, pinfu : bool) -> u8 {
+    if pinfu {
+        return if context.tsumo {20} else {30};
+    }
+    let mut fu = 20 + wait.fu();
+    if closed && !context.tsumo {
+        fu += 10;
+    }
+    if context.tsumo {
+        fu += 2;
+    }
+    for set in sets {
+        let value = set.value();
+        let yaochuu = if value.is_yaochuu() {2} else {1};
+        fu += match *set {
+            Set::Sequence(_) => 0,
+            Set::Pair(value) => 2 * yakuhai_count(value, context) as u32,
+            Set::Triplet(_, concealed) => 2 * yaochuu * if concealed {2} else {1},
+            Set::Quad(_, concealed) => 8 * yaochuu * if concealed {2} else {1},
+        };
+    }
+    let fu = fu.div_ceil(10) * 10;
+    // An open hand without fu is rounded up to 30 fu
+    if fu == 20 {30} else {fu as u8}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+    use crate::tile::Tile;
+    use crate::tile::notation::parse_tiles;
+    use crate::game::rules::{RuleSet, RedFives};
+    use crate::tile::dragon::Dragon::{White, Green};
+    use crate::game::wind::Wind::{East, South};
+    use crate::hand::Hand;
+    use crate::hand::group::Group;
+    use crate::hand::meld::Meld;
+    use crate::scoring::yaku::{Yaku, WinContext};
+
+    /// A closed hand from its notation, the winning tile being the last one
+    fn win(text : &str, tsumo : bool) -> (Hand, WinContext) {
+        let tiles = parse_tiles(text, RedFives::none()).unwrap();
+        let winning = *tiles.last().unwrap();
+        (Hand::new(tiles), WinContext::new(winning, tsumo, South, East))
+    }
+
+    fn yaku(hand : &Hand, context : &WinContext) -> Vec<Yaku> {
+        let value = evaluate(hand, context, &[], &[], &RuleSet::wrc()).unwrap();
+        value.yaku.iter().map(|(yaku, _)| *yaku).collect()
+    }
+
+    #[test]
+    fn test_pinfu() {
+        let (hand, context) = win("23456m234p678s55p7m", false);
+        let value = evaluate(&hand, &context, &[], &[], &RuleSet::wrc()).unwrap();
+        assert_eq!(vec![(Yaku::Tanyao, 1), (Yaku::Pinfu, 1)], value.yaku);
+        assert_eq!((2, 30, 480), (value.han, value.fu, value.base_points));
+
+        let (hand, context) = win("23456m234p678s55p7m", true);
+        let value = evaluate(&hand, &context, &[], &[], &RuleSet::wrc()).unwrap();
+        assert_eq!((3, 20), (value.han, value.fu));
+    }
+
+    #[test]
+    fn test_no_yaku() {
+        let (hand, context) = win("12345m234p678s11z6m", false);
+        assert_eq!(None, evaluate(&hand, &context, &[], &[], &RuleSet::wrc()));
+        // Not complete
+        let (hand, context) = win("12345m234p678s11z9m", false);
+        assert_eq!(None, evaluate(&hand, &context, &[], &[], &RuleSet::wrc()));
+    }
+
+    #[test]
+    fn test_best_interpretation() {
+        // Sanankou on a tsumo, ryanpeikou otherwise
+        let (hand, context) = win("222333444m678s55p", true);
+        assert!(yaku(&hand, &context).contains(&Yaku::Sanankou));
+        let (hand, context) = win("223344m667788s5p5p", false);
+        assert_eq!(vec![Yaku::Tanyao, Yaku::Ryanpeikou], yaku(&hand, &context));
+
+        // A shanpon ron does not make a concealed triplet
+        let (hand, context) = win("111m222p789m33s55z5z", false);
+        let value = evaluate(&hand, &context, &[], &[], &RuleSet::wrc()).unwrap();
+        assert!(!value.yaku.iter().any(|(yaku, _)| *yaku == Yaku::Sanankou));
+        assert!(value.yaku.contains(&(Yaku::Yakuhai(crate::tile::dragon::Dragon::White), 1)));
+        // 20 + 10 (closed ron) + 8 + 4 + 4 (open triplet of dragons) = 46
+        assert_eq!(50, value.fu);
+    }
+
+    #[test]
+    fn test_special_hands() {
+        let (hand, context) = win("1199m1199p2255s7z7z", false);
+        let value = evaluate(&hand, &context, &[], &[], &RuleSet::wrc()).unwrap();
+        assert_eq!(vec![(Yaku::Chiitoitsu, 2)], value.yaku);
+        assert_eq!(25, value.fu);
+
+        let (hand, context) = win("19m19p19s1234567z1m", false);
+        let value = evaluate(&hand, &context, &[], &[], &RuleSet::wrc()).unwrap();
+        assert_eq!(vec![(Yaku::KokushiMusouJuusanmen, 13)], value.yaku);
+        assert_eq!(8000, value.base_points);
+
+        let value = evaluate(&hand, &context, &[], &[], &RuleSet::tenhou()).unwrap();
+        assert_eq!(16000, value.base_points);
+    }
+
+    #[test]
+    fn test_open_hand() {
+        let tiles = parse_tiles("234m567p11z88s8s", RedFives::none()).unwrap();
+        let winning = *tiles.last().unwrap();
+        let mut hand = Hand::new(tiles);
+        let pon = parse_tiles("777z", RedFives::none()).unwrap();
+        hand.add_meld(Meld::new(Group::Pon(pon[0], pon[1], pon[2]), pon[2], East));
+        let context = WinContext::new(winning, false, South, East);
+        let indicator = Tile::new_suited(crate::tile::suit::Suit::Sou, 7, 0);
+        let value = evaluate(&hand, &context, &[indicator], &[], &RuleSet::wrc()).unwrap();
+        assert_eq!(vec!["Yakuhai chun".to_string(), "Dora".to_string()], value.yaku_list().into_iter().map(|(name, _)| name).collect::<Vec<_>>());
+        // 20 + 4 (open dragons) + 2 (pair of the round wind) + 2 (simples completed by a ron) = 28
+        assert_eq!(30, value.fu);
+        assert_eq!(1 + 3, value.han);
+        assert_eq!(2000, value.base_points);
+    }
+
+    #[test]
+    fn test_chuuren() {
+        let (hand, context) = win("1112345678999m5m", true);
+        assert_eq!(vec![Yaku::JunseiChuurenPoutou], yaku(&hand, &context));
+        let (hand, context) = win("1112345678999m1m", true);
+        assert_eq!(vec![Yaku::JunseiChuurenPoutou], yaku(&hand, &context));
+        let (hand, context) = win("1112335678999m4m", true);
+        assert_eq!(vec![Yaku::ChuurenPoutou], yaku(&hand, &context));
+    }
+
+    #[test]
+    fn test_yaku() {
+        let sorted = |mut yaku : Vec<Yaku>| {
+            yaku.sort_by_key(|yaku| yaku.name());
+            yaku
+        };
+        let cases : Vec<(&str, Vec<Yaku>)> = vec![
+            ("223344m567p678s5s5s", vec![Yaku::Tanyao, Yaku::Iipeikou]),
+            ("123456789m555z1z1z", vec![Yaku::Ittsu, Yaku::Honitsu, Yaku::Yakuhai(White)]),
+            ("123456789p234p1p1p", vec![Yaku::Pinfu, Yaku::Ittsu, Yaku::Chinitsu]),
+            ("123m789p123999s1z1z", vec![Yaku::Chanta]),
+            ("1123m789p123999s1m", vec![Yaku::Junchan]),
+            ("123m123p23456s77s1s", vec![Yaku::Pinfu, Yaku::SanshokuDoujun]),
+            ("23m567p555666z77z4m", vec![Yaku::Yakuhai(White), Yaku::Yakuhai(Green), Yaku::Shousangen]),
+            ("555666777z234m5m5m", vec![Yaku::Daisangen]),
+            ("111m999p111s11z22z2z", vec![Yaku::Toitoi, Yaku::Sanankou, Yaku::Honroutou, Yaku::SeatWind(South)]),
+        ];
+        for (text, expected) in cases {
+            let (hand, context) = win(text, false);
+            assert_eq!(sorted(expected), sorted(yaku(&hand, &context)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_atozuke() {
+        // Waiting on 1p and 4p, with the triplet ruling out pinfu the 1p gives no yaku
+        let mut rules = RuleSet::wrc();
+        rules.atozuke = false;
+        let (hand, context) = win("222567m234s2388p4p", false);
+        assert!(evaluate(&hand, &context, &[], &[], &RuleSet::wrc()).is_some());
+        assert_eq!(None, evaluate(&hand, &context, &[], &[], &rules));
+        // A riichi is a yaku whatever the wait
+        let riichi = WinContext{riichi : true, ..context};
+        assert!(evaluate(&hand, &riichi, &[], &[], &rules).is_some());
+        let (hand, context) = win("222567m234s3488p5p", false);
+        assert!(evaluate(&hand, &context, &[], &[], &rules).is_some());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod dora;
+pub mod payment;
+pub mod yaku;
+pub mod hand_value;
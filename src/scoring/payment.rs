@@ -0,0 +1,113 @@
+use crate::game::wind::Wind;
+use crate::game::rules::{RuleSet, Players, SanmaTsumo};
+
+/// Base points of a yakuman, a mangan is worth a quarter of it
+pub const YAKUMAN_BASE_POINTS : u32 = 8000;
+
+/// Computes the base points of a hand from its han and fu.
+/// Every payment is a multiple of the base points.
+/// A hand of 13 han or more is a counted yakuman (kazoe yakuman).
+pub fn base_points(han : u8, fu : u8, rules : &RuleSet) -> u32 {
+    match han {
+        0..=4 => {
+            let base = fu as u32 * 2u32.pow(2 + han as u32);
+            let kiriage = rules.kiriage_mangan && ((han == 4 && fu == 30) || (han == 3 && fu == 60));
+            if base >= 2000 || kiriage {2000} else {base}
+        },
+        5        => 2000,
+        6..=7    => 3000,
+        8..=10   => 4000,
+        11..=12  => 6000,
+        _        => YAKUMAN_BASE_POINTS,
+    }
+}
+
+/// Rounds a payment up to the next hundred points
+fn round_up(points : u32) -> u32 {
+    points.div_ceil(100) * 100
+}
+
+/// Points paid by the discarder on a ron.
+/// Each honba adds 100 points per other player at the table.
+pub fn ron_payment(base_points : u32, dealer : bool, honba : u8, rules : &RuleSet) -> u32 {
+    let multiplier = if dealer {6} else {4};
+    round_up(base_points * multiplier) + 100 * honba as u32 * (rules.players.count() as u32 - 1)
+}
+
+/// Points paid by each other player on a tsumo by the player seated at `winner`.
+/// Each honba adds 100 points per payer.
+/// In a three player game, the share of the empty north seat is either not paid
+/// or split between the two payers, depending on the rules.
+pub fn tsumo_payments(base_points : u32, winner : Wind, honba : u8, rules : &RuleSet) -> Vec<(Wind, u32)> {
+    let share = |payer : Wind| {
+        if winner == Wind::East || payer == Wind::East {base_points * 2} else {base_points}
+    };
+    // Share of the missing north player split between the two payers
+    let split = match rules.players {
+        Players::Three(SanmaTsumo::Split) => share(Wind::North) / 2,
+        Players::Three(SanmaTsumo::TsumoSon) | Players::Four => 0,
+    };
+
+    Wind::seats(rules.players.count())
+        .into_iter()
+        .filter(|payer| *payer != winner)
+        .map(|payer| (payer, round_up(share(payer) + split) + 100 * honba as u32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base_points, ron_payment, tsumo_payments};
+    use crate::game::wind::Wind::{East, South, West, North};
+    use crate::game::rules::{RuleSet, Players, SanmaTsumo};
+
+    #[test]
+    fn test_base_points() {
+        let rules = RuleSet::tenhou();
+        assert_eq!(240, base_points(1, 30, &rules));
+        assert_eq!(1920, base_points(4, 30, &rules));
+        assert_eq!(2000, base_points(3, 70, &rules));
+        assert_eq!(3000, base_points(6, 30, &rules));
+        assert_eq!(8000, base_points(13, 30, &rules));
+        assert_eq!(2000, base_points(4, 30, &RuleSet::wrc()));
+    }
+
+    #[test]
+    fn test_ron() {
+        let rules = RuleSet::tenhou();
+        assert_eq!(1000, ron_payment(base_points(1, 30, &rules), false, 0, &rules));
+        assert_eq!(1500, ron_payment(base_points(1, 30, &rules), true, 0, &rules));
+        assert_eq!(8600, ron_payment(2000, false, 2, &rules));
+    }
+
+    #[test]
+    fn test_tsumo() {
+        let rules = RuleSet::tenhou();
+        assert_eq!(
+            vec![(East, 1000), (West, 500), (North, 500)],
+            tsumo_payments(base_points(2, 30, &rules), South, 0, &rules),
+        );
+        assert_eq!(
+            vec![(South, 4100), (West, 4100), (North, 4100)],
+            tsumo_payments(2000, East, 1, &rules),
+        );
+    }
+
+    #[test]
+    fn test_sanma_tsumo() {
+        let mut rules = RuleSet::mahjong_soul_sanma();
+        assert_eq!(
+            vec![(East, 4000), (West, 2000)],
+            tsumo_payments(2000, South, 0, &rules),
+        );
+        rules.players = Players::Three(SanmaTsumo::Split);
+        assert_eq!(
+            vec![(East, 5000), (West, 3000)],
+            tsumo_payments(2000, South, 0, &rules),
+        );
+        assert_eq!(
+            vec![(South, 6000), (West, 6000)],
+            tsumo_payments(2000, East, 0, &rules),
+        );
+    }
+}
@@ -0,0 +1,484 @@
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::tile::honor::HonorTile;
+use crate::tile::dragon::Dragon;
+use crate::tile::suit::Suit;
+use crate::game::wind::Wind;
+use crate::game::rules::RuleSet;
+use crate::hand::wait::WaitKind;
+
+/// A pattern giving value to a winning hand.
+/// A hand needs at least one yaku to win, the dora do not count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Yaku {
+    Riichi,
+    DoubleRiichi,
+    Ippatsu,
+    MenzenTsumo,
+    Pinfu,
+    Iipeikou,
+    Tanyao,
+    /// A triplet of the seat wind
+    SeatWind(Wind),
+    /// A triplet of the round wind
+    RoundWind(Wind),
+    /// A triplet of dragons
+    Yakuhai(Dragon),
+    Haitei,
+    Houtei,
+    Rinshan,
+    Chankan,
+    Chiitoitsu,
+    Toitoi,
+    Sanankou,
+    SanshokuDoukou,
+    Sankantsu,
+    Shousangen,
+    Honroutou,
+    Chanta,
+    Ittsu,
+    SanshokuDoujun,
+    Honitsu,
+    Junchan,
+    Ryanpeikou,
+    Chinitsu,
+    // Yakuman
+    KokushiMusou,
+    /// Thirteen orphans waiting on any of the 13 tiles
+    KokushiMusouJuusanmen,
+    Suuankou,
+    /// Four concealed triplets waiting on the pair
+    SuuankouTanki,
+    Daisangen,
+    Shousuushii,
+    Daisuushii,
+    Tsuuiisou,
+    Chinroutou,
+    Ryuuiisou,
+    ChuurenPoutou,
+    /// Nine gates waiting on any of the 9 tiles
+    JunseiChuurenPoutou,
+    Suukantsu,
+    Tenhou,
+    Chiihou,
+}
+
+impl Yaku {
+
+    pub fn name(&self) -> String {
+        match self {
+            Yaku::SeatWind(wind) | Yaku::RoundWind(wind) => format!("Yakuhai {}", wind_name(*wind)),
+            Yaku::Yakuhai(dragon) => format!("Yakuhai {}", match dragon {
+                Dragon::White => "haku",
+                Dragon::Green => "hatsu",
+                Dragon::Red => "chun",
+            }),
+            Yaku::KokushiMusouJuusanmen => "Kokushi musou juusanmen".to_string(),
+            Yaku::SuuankouTanki => "Suuankou tanki".to_string(),
+            Yaku::JunseiChuurenPoutou => "Junsei chuuren poutou".to_string(),
+            Yaku::MenzenTsumo => "Menzen tsumo".to_string(),
+            Yaku::DoubleRiichi => "Double riichi".to_string(),
+            Yaku::SanshokuDoukou => "Sanshoku doukou".to_string(),
+            Yaku::SanshokuDoujun => "Sanshoku doujun".to_string(),
+            Yaku::KokushiMusou => "Kokushi musou".to_string(),
+            Yaku::ChuurenPoutou => "Chuuren poutou".to_string(),
+            _ => format!("{:?}", self),
+        }
+    }
+
+    /// Value of the yaku in han, 13 for a yakuman and 26 for a double yakuman.
+    /// Some yaku lose a han in an open hand, and some are only valid in a closed hand (0 han).
+    pub fn han(&self, closed : bool, rules : &RuleSet) -> u8 {
+        let open_penalty = if closed {0} else {1};
+        match self {
+            | Yaku::Riichi
+            | Yaku::Ippatsu
+            | Yaku::MenzenTsumo
+            | Yaku::Pinfu
+            | Yaku::Iipeikou => if closed {1} else {0},
+            | Yaku::Tanyao
+            | Yaku::SeatWind(_)
+            | Yaku::RoundWind(_)
+            | Yaku::Yakuhai(_)
+            | Yaku::Haitei
+            | Yaku::Houtei
+            | Yaku::Rinshan
+            | Yaku::Chankan => 1,
+            Yaku::DoubleRiichi | Yaku::Chiitoitsu => if closed {2} else {0},
+            | Yaku::Toitoi
+            | Yaku::Sanankou
+            | Yaku::SanshokuDoukou
+            | Yaku::Sankantsu
+            | Yaku::Shousangen
+            | Yaku::Honroutou => 2,
+            Yaku::Chanta | Yaku::Ittsu | Yaku::SanshokuDoujun => 2 - open_penalty,
+            Yaku::Honitsu | Yaku::Junchan => 3 - open_penalty,
+            Yaku::Ryanpeikou => if closed {3} else {0},
+            Yaku::Chinitsu => 6 - open_penalty,
+            | Yaku::KokushiMusouJuusanmen
+            | Yaku::SuuankouTanki
+            | Yaku::Daisuushii
+            | Yaku::JunseiChuurenPoutou if rules.multiple_yakuman => 26,
+            _ => 13,
+        }
+    }
+
+    pub fn is_yakuman(&self) -> bool {
+        matches!(self,
+            | Yaku::KokushiMusou
+            | Yaku::KokushiMusouJuusanmen
+            | Yaku::Suuankou
+            | Yaku::SuuankouTanki
+            | Yaku::Daisangen
+            | Yaku::Shousuushii
+            | Yaku::Daisuushii
+            | Yaku::Tsuuiisou
+            | Yaku::Chinroutou
+            | Yaku::Ryuuiisou
+            | Yaku::ChuurenPoutou
+            | Yaku::JunseiChuurenPoutou
+            | Yaku::Suukantsu
+            | Yaku::Tenhou
+            | Yaku::Chiihou
+        )
+    }
+}
+
+fn wind_name(wind : Wind) -> &'static str {
+    match wind {
+        Wind::East => "ton",
+        Wind::South => "nan",
+        Wind::West => "shaa",
+        Wind::North => "pei",
+    }
+}
+
+/// How a hand was won, besides its tiles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WinContext {
+    pub winning_tile : Tile,
+    /// Whether the winning tile was drawn, rather than discarded by another player
+    pub tsumo : bool,
+    pub riichi : bool,
+    /// Riichi declared on the first discard, before any call
+    pub double_riichi : bool,
+    /// Win in the turn following the riichi declaration, before any call
+    pub ippatsu : bool,
+    pub seat_wind : Wind,
+    pub round_wind : Wind,
+    /// Win on the last tile of the wall (haitei or houtei)
+    pub last_tile : bool,
+    /// Tsumo on the replacement tile of a kan
+    pub rinshan : bool,
+    /// Ron on a tile added to a pon
+    pub chankan : bool,
+    /// Tsumo on the first draw of the player, before any call (tenhou or chiihou)
+    pub first_draw : bool,
+}
+
+impl WinContext {
+
+    /// A win with none of the special conditions
+    pub fn new(winning_tile : Tile, tsumo : bool, seat_wind : Wind, round_wind : Wind) -> WinContext {
+        WinContext {
+            winning_tile,
+            tsumo,
+            riichi : false,
+            double_riichi : false,
+            ippatsu : false,
+            seat_wind,
+            round_wind,
+            last_tile : false,
+            rinshan : false,
+            chankan : false,
+            first_draw : false,
+        }
+    }
+}
+
+/// A group of a complete hand, given by its lowest value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Set {
+    Sequence(TileValue),
+    /// A triplet and whether it is concealed : a triplet completed by a ron is not
+    Triplet(TileValue, bool),
+    /// A quad and whether it is closed
+    Quad(TileValue, bool),
+    Pair(TileValue),
+}
+
+impl Set {
+
+    pub(crate) fn value(&self) -> TileValue {
+        match *self {
+            Set::Sequence(value) | Set::Triplet(value, _) | Set::Quad(value, _) | Set::Pair(value) => value,
+        }
+    }
+
+    /// The value of every tile of the set
+    pub(crate) fn tiles(&self) -> Vec<TileValue> {
+        match *self {
+            Set::Sequence(value) => vec![Some(value), value.next(), value.next().and_then(|next| next.next())].into_iter().flatten().collect(),
+            Set::Triplet(value, _) => vec![value; 3],
+            Set::Quad(value, _) => vec![value; 4],
+            Set::Pair(value) => vec![value; 2],
+        }
+    }
+
+    fn is_triplet(&self) -> bool {
+        matches!(self, Set::Triplet(_, _) | Set::Quad(_, _))
+    }
+
+    fn is_concealed_triplet(&self) -> bool {
+        matches!(self, Set::Triplet(_, true) | Set::Quad(_, true))
+    }
+
+    fn has_yaochuu(&self) -> bool {
+        self.tiles().iter().any(TileValue::is_yaochuu)
+    }
+}
+
+/// Whether a honor gives a han when it makes a triplet, or fu when it makes the pair
+pub(crate) fn yakuhai_count(value : TileValue, context : &WinContext) -> u8 {
+    match value {
+        TileValue::Honor(HonorTile::Dragon(_)) => 1,
+        TileValue::Honor(HonorTile::Wind(wind)) => (wind == context.seat_wind) as u8 + (wind == context.round_wind) as u8,
+        TileValue::Suited(_) => 0,
+    }
+}
+
+fn suit(value : TileValue) -> Option<Suit> {
+    match value {
+        TileValue::Suited(suited) => Some(suited.suit()),
+        TileValue::Honor(_) => None,
+    }
+}
+
+fn number(value : TileValue) -> u8 {
+    match value {
+        TileValue::Suited(suited) => suited.value(),
+        TileValue::Honor(_) => 0,
+    }
+}
+
+/// Yaku given by the situation of the win, common to every shape of hand
+pub(crate) fn situation_yaku(context : &WinContext, closed : bool) -> Vec<Yaku> {
+    let mut yaku = Vec::new();
+    if context.double_riichi {
+        yaku.push(Yaku::DoubleRiichi);
+    } else if context.riichi {
+        yaku.push(Yaku::Riichi);
+    }
+    if context.ippatsu {
+        yaku.push(Yaku::Ippatsu);
+    }
+    if context.tsumo && closed {
+        yaku.push(Yaku::MenzenTsumo);
+    }
+    if context.last_tile {
+        yaku.push(if context.tsumo {Yaku::Haitei} else {Yaku::Houtei});
+    }
+    if context.rinshan {
+        yaku.push(Yaku::Rinshan);
+    }
+    if context.chankan {
+        yaku.push(Yaku::Chankan);
+    }
+    yaku
+}
+
+/// Yaku given by the tiles of a hand, whatever its shape
+pub(crate) fn color_yaku(values : &[TileValue], closed : bool, rules : &RuleSet) -> Vec<Yaku> {
+    let mut yaku = Vec::new();
+    if values.iter().all(TileValue::is_simple) && (closed || rules.kuitan) {
+        yaku.push(Yaku::Tanyao);
+    }
+    let suits : Vec<Suit> = values.iter().filter_map(|value| suit(*value)).collect();
+    let one_suit = suits.windows(2).all(|pair| pair[0] == pair[1]);
+    if !suits.is_empty() && one_suit {
+        yaku.push(if suits.len() == values.len() {Yaku::Chinitsu} else {Yaku::Honitsu});
+    }
+    if values.iter().all(TileValue::is_yaochuu) {
+        if suits.is_empty() {
+            yaku.push(Yaku::Tsuuiisou);
+        } else if suits.len() == values.len() {
+            yaku.push(Yaku::Chinroutou);
+        } else {
+            yaku.push(Yaku::Honroutou);
+        }
+    }
+    if values.iter().all(TileValue::is_green) {
+        yaku.push(Yaku::Ryuuiisou);
+    }
+    yaku
+}
+
+/// Whether three of the values are of the same number in the three suits
+fn three_suits(values : &[TileValue]) -> bool {
+    values.iter().any(|first| {
+        [Suit::Man, Suit::Pin, Suit::Sou].iter().all(|wanted| {
+            values.iter().any(|value| suit(*value) == Some(*wanted) && number(*value) == number(*first))
+        })
+    })
+}
+
+/// Yaku of a hand made of four groups and a pair.
+/// `wait` is the wait completed by the winning tile, `closed_counts` the counts by number of the tiles
+/// of the closed hand before the win, which tell apart the nine gates waiting on any of the nine tiles.
+pub(crate) fn regular_yaku(
+    sets : &[Set],
+    wait : WaitKind,
+    context : &WinContext,
+    closed : bool,
+    closed_counts : &[u8; 9],
+    rules : &RuleSet,
+) -> Vec<Yaku> {
+    let mut yaku = situation_yaku(context, closed);
+    let values : Vec<TileValue> = sets.iter().flat_map(Set::tiles).collect();
+    yaku.extend(color_yaku(&values, closed, rules));
+
+    let pair = sets.iter().find(|set| matches!(set, Set::Pair(_))).map(Set::value);
+    let groups : Vec<Set> = sets.iter().filter(|set| !matches!(set, Set::Pair(_))).cloned().collect();
+    let sequences : Vec<TileValue> = groups.iter().filter(|set| matches!(set, Set::Sequence(_))).map(Set::value).collect();
+    let triplets : Vec<TileValue> = groups.iter().filter(|set| set.is_triplet()).map(Set::value).collect();
+    let quads = groups.iter().filter(|set| matches!(set, Set::Quad(_, _))).count();
+    let concealed = groups.iter().filter(|set| set.is_concealed_triplet()).count();
+
+    // Yakuman
+    if concealed == 4 {
+        yaku.push(if wait == WaitKind::Tanki {Yaku::SuuankouTanki} else {Yaku::Suuankou});
+    }
+    let dragons = triplets.iter().filter(|value| value.is_dragon()).count();
+    let winds = triplets.iter().filter(|value| value.is_wind()).count();
+    let wind_pair = pair.is_some_and(|pair| pair.is_wind());
+    let dragon_pair = pair.is_some_and(|pair| pair.is_dragon());
+    if dragons == 3 {
+        yaku.push(Yaku::Daisangen);
+    }
+    if winds == 4 {
+        yaku.push(Yaku::Daisuushii);
+    } else if winds == 3 && wind_pair {
+        yaku.push(Yaku::Shousuushii);
+    }
+    if quads == 4 {
+        yaku.push(Yaku::Suukantsu);
+    }
+    if yaku.contains(&Yaku::Chinitsu) && closed && quads == 0 {
+        let mut counts = [0; 9];
+        values.iter().for_each(|value| counts[number(*value) as usize - 1] += 1);
+        let gates = [3, 1, 1, 1, 1, 1, 1, 1, 3];
+        if counts.iter().zip(gates.iter()).all(|(count, gate)| count >= gate) {
+            yaku.push(if *closed_counts == gates {Yaku::JunseiChuurenPoutou} else {Yaku::ChuurenPoutou});
+        }
+    }
+    if context.first_draw && context.tsumo {
+        yaku.push(if context.seat_wind == Wind::East {Yaku::Tenhou} else {Yaku::Chiihou});
+    }
+    if yaku.iter().any(Yaku::is_yakuman) {
+        yaku.retain(Yaku::is_yakuman);
+        return yaku;
+    }
+
+    // Closed hands
+    let yakuhai_pair = pair.is_some_and(|pair| yakuhai_count(pair, context) > 0);
+    if closed && sequences.len() == 4 && !yakuhai_pair && wait == WaitKind::Ryanmen {
+        yaku.push(Yaku::Pinfu);
+    }
+    if closed {
+        let mut sorted = sequences.clone();
+        sorted.sort();
+        let mut identical = 0;
+        let mut i = 0;
+        while i + 1 < sorted.len() {
+            if sorted[i] == sorted[i + 1] {
+                identical += 1;
+                i += 2;
+            } else {
+                i += 1;
+            }
+        }
+        match identical {
+            2 => yaku.push(Yaku::Ryanpeikou),
+            1 => yaku.push(Yaku::Iipeikou),
+            _ => (),
+        }
+    }
+
+    // Honors
+    for value in triplets.iter() {
+        if let TileValue::Honor(honor) = value {
+            match honor {
+                HonorTile::Dragon(dragon) => yaku.push(Yaku::Yakuhai(*dragon)),
+                HonorTile::Wind(wind) => {
+                    if *wind == context.seat_wind {
+                        yaku.push(Yaku::SeatWind(*wind));
+                    }
+                    if *wind == context.round_wind {
+                        yaku.push(Yaku::RoundWind(*wind));
+                    }
+                },
+            }
+        }
+    }
+    if dragons == 2 && dragon_pair {
+        yaku.push(Yaku::Shousangen);
+    }
+
+    // Triplets
+    if triplets.len() == 4 {
+        yaku.push(Yaku::Toitoi);
+    }
+    if concealed == 3 {
+        yaku.push(Yaku::Sanankou);
+    }
+    if quads == 3 {
+        yaku.push(Yaku::Sankantsu);
+    }
+    if three_suits(&triplets) {
+        yaku.push(Yaku::SanshokuDoukou);
+    }
+
+    // Sequences
+    if three_suits(&sequences) {
+        yaku.push(Yaku::SanshokuDoujun);
+    }
+    let ittsu = [Suit::Man, Suit::Pin, Suit::Sou].iter().any(|wanted| {
+        [1, 4, 7].iter().all(|start| sequences.iter().any(|value| suit(*value) == Some(*wanted) && number(*value) == *start))
+    });
+    if ittsu {
+        yaku.push(Yaku::Ittsu);
+    }
+    let outside = sets.iter().all(Set::has_yaochuu) && !sequences.is_empty();
+    if outside {
+        yaku.push(if values.iter().any(TileValue::is_honor) {Yaku::Chanta} else {Yaku::Junchan});
+    }
+    yaku
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Yaku, WinContext};
+    use crate::tile::Tile;
+    use crate::tile::suit::Suit::Man;
+    use crate::tile::dragon::Dragon::Green;
+    use crate::game::wind::Wind::{East, South};
+    use crate::game::rules::RuleSet;
+
+    #[test]
+    fn test_han() {
+        let rules = RuleSet::wrc();
+        assert_eq!(2, Yaku::Ittsu.han(true, &rules));
+        assert_eq!(1, Yaku::Ittsu.han(false, &rules));
+        assert_eq!(0, Yaku::Pinfu.han(false, &rules));
+        assert_eq!(13, Yaku::SuuankouTanki.han(true, &rules));
+        assert_eq!(26, Yaku::SuuankouTanki.han(true, &RuleSet::tenhou()));
+        assert_eq!("Yakuhai hatsu", Yaku::Yakuhai(Green).name());
+        assert_eq!("Yakuhai nan", Yaku::RoundWind(South).name());
+        assert_eq!("Pinfu", Yaku::Pinfu.name());
+
+        let context = WinContext::new(Tile::new_suited(Man, 1, 0), true, East, East);
+        assert!(!context.riichi && !context.first_draw);
+    }
+}
@@ -0,0 +1,216 @@
+//! Compact ways to write tiles in text messages : the MPSZ notation, the characters of the
+//! Unicode Mahjong Tiles block (🀇 to 🀡 for the suits, 🀀 to 🀆 for the honors) or custom emoji.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::tile::suit::Suit;
+use crate::tile::honor::HonorTile;
+use crate::tile::dragon::Dragon;
+use crate::tile::notation::tiles_to_mpsz;
+use crate::hand::Hand;
+use crate::hand::group::Group;
+use crate::game::wind::Wind;
+use crate::game::rules::RedFives;
+
+/// Character of a face down tile
+pub const UNICODE_BACK : char = '\u{1F02B}';
+
+/// Character of a tile value in the Unicode Mahjong Tiles block
+pub fn unicode_char(value : TileValue) -> char {
+    let code = match value {
+        TileValue::Suited(suited) => {
+            let first = match suited.suit() {
+                Suit::Man => 0x1F007,
+                Suit::Sou => 0x1F010,
+                Suit::Pin => 0x1F019,
+            };
+            first + suited.value() as u32 - 1
+        },
+        TileValue::Honor(HonorTile::Wind(wind)) => match wind {
+            Wind::East => 0x1F000,
+            Wind::South => 0x1F001,
+            Wind::West => 0x1F002,
+            Wind::North => 0x1F003,
+        },
+        TileValue::Honor(HonorTile::Dragon(dragon)) => match dragon {
+            Dragon::Red => 0x1F004,
+            Dragon::Green => 0x1F005,
+            Dragon::White => 0x1F006,
+        },
+    };
+    std::char::from_u32(code).expect("the Mahjong Tiles block only holds valid characters")
+}
+
+/// Custom emoji of a chat server, like `<:man1:112233>` on Discord, for each tile.
+/// The tiles are named in the MPSZ notation (`"5m"`, `"0m"` for a red five) and `"back"` names a face down tile.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EmojiSet {
+    emoji : HashMap<String, String>,
+}
+
+impl EmojiSet {
+
+    pub fn new() -> EmojiSet {
+        EmojiSet::default()
+    }
+
+    /// Uses the Discord emoji of this name and id for a tile
+    pub fn insert(&mut self, tile : &str, name : &str, id : u64) {
+        self.emoji.insert(tile.to_string(), format!("<:{}:{}>", name, id));
+    }
+
+    pub fn get(&self, tile : &str) -> Option<&str> {
+        self.emoji.get(tile).map(String::as_str)
+    }
+}
+
+/// How tiles are written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TileStyle {
+    /// The MPSZ notation, like `"406m"`
+    Mpsz,
+    /// The characters of the Unicode Mahjong Tiles block, which has no red fives
+    Unicode,
+    /// Custom emoji, the tiles without an emoji being written with their Unicode character,
+    /// and the red fives without an emoji with the emoji of the other fives
+    Emoji(EmojiSet),
+}
+
+impl TileStyle {
+
+    /// Something written with this style, `red_fives` telling which tiles are red
+    pub fn apply<'a, T : ?Sized>(&'a self, value : &'a T, red_fives : RedFives) -> Styled<'a, T> {
+        Styled{value, style : self, red_fives}
+    }
+
+    fn write_tile(&self, f : &mut Formatter<'_>, tile : Tile, red_fives : RedFives) -> std::fmt::Result {
+        match self {
+            TileStyle::Mpsz => write!(f, "{}", tiles_to_mpsz(&[tile], red_fives)),
+            TileStyle::Unicode => write!(f, "{}", unicode_char(tile.value())),
+            TileStyle::Emoji(emoji) => {
+                let name = tiles_to_mpsz(&[tile], red_fives);
+                let plain = tiles_to_mpsz(&[tile], RedFives::none());
+                match emoji.get(&name).or_else(|| emoji.get(&plain)) {
+                    Some(emoji) => write!(f, "{}", emoji),
+                    None => write!(f, "{}", unicode_char(tile.value())),
+                }
+            },
+        }
+    }
+
+    fn write_tiles(&self, f : &mut Formatter<'_>, tiles : &[Tile], red_fives : RedFives) -> std::fmt::Result {
+        match self {
+            TileStyle::Mpsz => write!(f, "{}", tiles_to_mpsz(tiles, red_fives)),
+            _ => tiles.iter().try_for_each(|tile| self.write_tile(f, *tile, red_fives)),
+        }
+    }
+
+    fn write_back(&self, f : &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileStyle::Emoji(emoji) if emoji.get("back").is_some() => write!(f, "{}", emoji.get("back").unwrap_or_default()),
+            _ => write!(f, "{}", UNICODE_BACK),
+        }
+    }
+}
+
+/// Tiles written with a `TileStyle`, see `TileStyle::apply`
+pub struct Styled<'a, T : ?Sized> {
+    value : &'a T,
+    style : &'a TileStyle,
+    red_fives : RedFives,
+}
+
+impl Display for Styled<'_, TileValue> {
+    fn fmt(&self, f : &mut Formatter<'_>) -> std::fmt::Result {
+        self.style.write_tile(f, Tile::new(*self.value, 0), RedFives::none())
+    }
+}
+
+impl Display for Styled<'_, Tile> {
+    fn fmt(&self, f : &mut Formatter<'_>) -> std::fmt::Result {
+        self.style.write_tile(f, *self.value, self.red_fives)
+    }
+}
+
+impl Display for Styled<'_, [Tile]> {
+    fn fmt(&self, f : &mut Formatter<'_>) -> std::fmt::Result {
+        self.style.write_tiles(f, self.value, self.red_fives)
+    }
+}
+
+/// The hidden tiles, then each meld and closed kan after a space, then the nukidora.
+/// The closed kans show their ends face down, except in the MPSZ notation.
+impl Display for Styled<'_, Hand> {
+    fn fmt(&self, f : &mut Formatter<'_>) -> std::fmt::Result {
+        let hand = self.value;
+        self.style.write_tiles(f, hand.hidden_tiles(), self.red_fives)?;
+        for meld in hand.melds() {
+            let tiles : Vec<Tile> = meld.tiles().into_iter().cloned().collect();
+            write!(f, " ")?;
+            self.style.write_tiles(f, &tiles, self.red_fives)?;
+        }
+        for kan in hand.closed_kans() {
+            write!(f, " ")?;
+            if let (Group::Kan(_, second, third, _), false) = (kan, self.style == &TileStyle::Mpsz) {
+                self.style.write_back(f)?;
+                self.style.write_tiles(f, &[*second, *third], self.red_fives)?;
+                self.style.write_back(f)?;
+            } else {
+                let tiles : Vec<Tile> = kan.tiles().into_iter().cloned().collect();
+                self.style.write_tiles(f, &tiles, self.red_fives)?;
+            }
+        }
+        if !hand.nuki().is_empty() {
+            write!(f, " ")?;
+            self.style.write_tiles(f, hand.nuki(), self.red_fives)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unicode_char, EmojiSet, TileStyle};
+    use crate::tile::tile_value::TileValue;
+    use crate::tile::suit::Suit::Man;
+    use crate::tile::notation::parse_tiles;
+    use crate::hand::Hand;
+    use crate::hand::group::Group;
+    use crate::game::rules::RedFives;
+
+    #[test]
+    fn test_unicode() {
+        let tiles = parse_tiles("19m19p19s1234567z", RedFives::none()).unwrap();
+        let text : String = tiles.iter().map(|tile| unicode_char(tile.value())).collect();
+        assert_eq!("🀇🀏🀙🀡🀐🀘🀀🀁🀂🀃🀆🀅🀄", text);
+        assert_eq!("🀇🀏🀙🀡🀐🀘🀀🀁🀂🀃🀆🀅🀄", TileStyle::Unicode.apply(&tiles[..], RedFives::none()).to_string());
+        let value = TileValue::new_suited(Man, 5);
+        assert_eq!("🀋", TileStyle::Unicode.apply(&value, RedFives::none()).to_string());
+        assert_eq!("5m 🀋", format!("{} {:#}", value, value));
+    }
+
+    #[test]
+    fn test_emoji() {
+        let mut emoji = EmojiSet::new();
+        emoji.insert("5m", "man5", 1);
+        emoji.insert("0p", "pin5red", 2);
+        emoji.insert("back", "back", 3);
+        let style = TileStyle::Emoji(emoji);
+        let tiles = parse_tiles("05m05p1z", RedFives::one_each()).unwrap();
+        assert_eq!("<:man5:1><:man5:1><:pin5red:2>🀝🀀", style.apply(&tiles[..], RedFives::one_each()).to_string());
+        assert_eq!("<:pin5red:2>", style.apply(&tiles[2], RedFives::one_each()).to_string());
+    }
+
+    #[test]
+    fn test_hand() {
+        let red_fives = RedFives::one_each();
+        let tiles = parse_tiles("406m11z2222p", red_fives).unwrap();
+        let mut hand = Hand::new(tiles[..5].to_vec());
+        hand.add_closed_kan(Group::Kan(tiles[5], tiles[6], tiles[7], tiles[8]));
+        assert_eq!("406m11z 2222p", TileStyle::Mpsz.apply(&hand, red_fives).to_string());
+        assert_eq!("🀊🀋🀌🀀🀀 🀫🀚🀚🀫", TileStyle::Unicode.apply(&hand, red_fives).to_string());
+    }
+}
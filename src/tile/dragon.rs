@@ -2,6 +2,7 @@ use std::fmt::Debug;
 use Dragon::{White, Red, Green};
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Dragon {
     White,
     Red,
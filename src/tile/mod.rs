@@ -2,13 +2,46 @@ pub mod suit;
 pub mod dragon;
 pub mod honor;
 pub mod tile_value;
+pub mod notation;
+pub mod display;
+pub mod tile_counts;
+#[cfg(feature = "serde")]
+mod serialization;
 
-use std::fmt::Debug;
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display};
 
 use tile_value::TileValue;
 use suit::Suit;
 use dragon::Dragon;
 use crate::game::wind::Wind;
+use crate::game::rules::RedFives;
+
+/// Reason why a tile cannot be created
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TileError {
+    /// The number of a suited tile is not between 1 and 9
+    InvalidNumber(u8),
+    /// The id of a tile is not between 0 and 3
+    InvalidId(u8),
+}
+
+impl Debug for TileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileError::InvalidNumber(number) => write!(f, "{} is not the number of a suited tile", number),
+            TileError::InvalidId(id) => write!(f, "{} is not the id of a tile", id),
+        }
+    }
+}
+
+impl Display for TileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for TileError {}
 
 /// One of the 134 possible riichi mahjong tiles.
 /// The variant is stored in the `value` field and two tiles with the same value are separated by their id (there are four of each tile so id is between 0 and 3 included).
@@ -28,20 +61,46 @@ impl Tile {
 
     // Constructors
 
+    /// # Panics
+    /// If `id` is greater than 3, see `try_new`
     pub fn new(value : TileValue, id : u8) -> Self {
-        Tile{value, id}
+        Tile::try_new(value, id).unwrap_or_else(|error| panic!("{}", error))
     }
 
+    pub fn try_new(value : TileValue, id : u8) -> Result<Self, TileError> {
+        if id < 4 {
+            Ok(Tile{value, id})
+        } else {
+            Err(TileError::InvalidId(id))
+        }
+    }
+
+    /// # Panics
+    /// If `value` is not between 1 and 9 or `id` is greater than 3, see `try_new_suited`
     pub fn new_suited(suit : Suit, value : u8, id : u8) -> Self {
-        Tile{value : TileValue::new_suited(suit, value), id}
+        Tile::new(TileValue::new_suited(suit, value), id)
+    }
+
+    pub fn try_new_suited(suit : Suit, value : u8, id : u8) -> Result<Self, TileError> {
+        Tile::try_new(TileValue::try_new_suited(suit, value)?, id)
     }
 
     pub fn new_wind(wind : Wind, id : u8) -> Self {
-        Tile{value : TileValue::new_wind(wind), id}
+        Tile::new(TileValue::new_wind(wind), id)
     }
 
     pub fn new_dragon(dragon : Dragon, id : u8) -> Self {
-        Tile{value : TileValue::new_dragon(dragon), id}
+        Tile::new(TileValue::new_dragon(dragon), id)
+    }
+
+    /// The 136 tiles of a game with four players, in the order of their index
+    pub fn full_set() -> impl Iterator<Item = Tile> {
+        (0..136).filter_map(Tile::from_index)
+    }
+
+    /// The tile whose index is `value * 4 + id`, `None` past 135 (see `Tile::index`)
+    pub fn from_index(index : u8) -> Option<Self> {
+        TileValue::from_index(index / 4).map(|value| Tile{value, id : index % 4})
     }
 
     // Immutable getters
@@ -54,6 +113,43 @@ impl Tile {
         self.id
     }
 
+    /// Index of the tile between 0 and 135, the four copies of a value following each other (see `TileValue::index`)
+    pub fn index(&self) -> u8 {
+        self.value.index() * 4 + self.id
+    }
+
+    /// Whether this is a 1 or a 9
+    pub fn is_terminal(&self) -> bool {
+        self.value.is_terminal()
+    }
+
+    pub fn is_honor(&self) -> bool {
+        self.value.is_honor()
+    }
+
+    /// Whether this is a terminal or an honor
+    pub fn is_yaochuu(&self) -> bool {
+        self.value.is_yaochuu()
+    }
+
+    /// Whether this is a suited tile from 2 to 8
+    pub fn is_simple(&self) -> bool {
+        self.value.is_simple()
+    }
+
+    /// Whether this tile can be part of an all green hand (ryuuiisou)
+    pub fn is_green(&self) -> bool {
+        self.value.is_green()
+    }
+
+    pub fn is_dragon(&self) -> bool {
+        self.value.is_dragon()
+    }
+
+    pub fn is_wind(&self) -> bool {
+        self.value.is_wind()
+    }
+
     // Other
 
     /// Gets the dora tile if this tile is the dora indicator
@@ -61,6 +157,11 @@ impl Tile {
         self.value.next_dora()
     }
 
+    /// Gets the dora tile if this tile is the dora indicator in a three player game
+    pub fn next_dora_sanma(&self) -> TileValue {
+        self.value.next_dora_sanma()
+    }
+
     /// If the tile is a suited tile and its value is lower than 9, gets the value of the next tile
     pub fn next(&self) -> Option<TileValue> {
         self.value.next()
@@ -85,4 +186,27 @@ impl Tile {
         }
     }
 
+    /// Returns whether this tile is one of the red fives (aka dora) given by `red_fives`
+    pub fn is_red(&self, red_fives : RedFives) -> bool {
+        match self.value {
+            TileValue::Suited(suited) if suited.value() == 5 => {
+                let count = match suited.suit() {
+                    Suit::Man => red_fives.man,
+                    Suit::Pin => red_fives.pin,
+                    Suit::Sou => red_fives.sou,
+                };
+                self.id < count
+            },
+            _ => false,
+        }
+    }
+
+}
+
+impl TryFrom<(TileValue, u8)> for Tile {
+    type Error = TileError;
+
+    fn try_from((value, id) : (TileValue, u8)) -> Result<Self, TileError> {
+        Tile::try_new(value, id)
+    }
 }
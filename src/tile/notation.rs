@@ -0,0 +1,273 @@
+//! Text notations of tiles.
+//!
+//! The MPSZ notation writes the numbers of a suit followed by its letter : `m` for manzu, `p` for pinzu,
+//! `s` for souzu and `z` for honors, numbered from 1 to 7 as east, south, west, north, white, green and red.
+//! A red five is written as a 0, so `"123m406p11z"` holds a red 5 pin and two east winds.
+//!
+//! The mjai protocol names each tile separately : `"5m"`, `"5mr"` for a red five,
+//! `"E"`, `"S"`, `"W"`, `"N"` for the winds and `"P"`, `"F"`, `"C"` for the white, green and red dragons.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Debug};
+
+use crate::tile::{Tile, TileError};
+use crate::tile::tile_value::TileValue;
+use crate::tile::honor::HonorTile;
+use crate::tile::suit::Suit;
+use crate::tile::dragon::Dragon;
+use crate::game::wind::Wind;
+use crate::game::rules::RedFives;
+
+/// The honors in the order of the MPSZ notation
+const HONORS : [TileValue; 7] = [
+    TileValue::Honor(HonorTile::Wind(Wind::East)),
+    TileValue::Honor(HonorTile::Wind(Wind::South)),
+    TileValue::Honor(HonorTile::Wind(Wind::West)),
+    TileValue::Honor(HonorTile::Wind(Wind::North)),
+    TileValue::Honor(HonorTile::Dragon(Dragon::White)),
+    TileValue::Honor(HonorTile::Dragon(Dragon::Green)),
+    TileValue::Honor(HonorTile::Dragon(Dragon::Red)),
+];
+
+const MJAI_HONORS : [&str; 7] = ["E", "S", "W", "N", "P", "F", "C"];
+
+/// Reason why a text cannot be read as tiles
+#[derive(Clone, PartialEq, Eq)]
+pub enum NotationError {
+    InvalidTile(String),
+    /// The text holds more than four copies of a tile
+    TooManyCopies(TileValue),
+    /// The text names a tile that does not exist, like a 0 of an honor
+    Tile(TileError),
+}
+
+impl Debug for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotationError::InvalidTile(text) => write!(f, "invalid tile \"{}\"", text),
+            NotationError::TooManyCopies(value) => write!(f, "more than four copies of {:?}", value),
+            NotationError::Tile(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Display for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl From<TileError> for NotationError {
+    fn from(error : TileError) -> Self {
+        NotationError::Tile(error)
+    }
+}
+
+fn suit_letter(suit : Suit) -> char {
+    match suit {
+        Suit::Man => 'm',
+        Suit::Pin => 'p',
+        Suit::Sou => 's',
+    }
+}
+
+/// Digit and letter of a tile value in the MPSZ notation
+fn mpsz_parts(value : TileValue) -> (u8, char) {
+    match value {
+        TileValue::Suited(suited) => (suited.value(), suit_letter(suited.suit())),
+        TileValue::Honor(_) => (HONORS.iter().position(|honor| *honor == value).unwrap_or_default() as u8 + 1, 'z'),
+    }
+}
+
+/// Writes tile values in the MPSZ notation, like `"123m456p11z"`.
+/// Consecutive tiles of the same suit share their letter.
+pub fn to_mpsz(values : &[TileValue]) -> String {
+    write_mpsz(values.iter().map(|value| mpsz_parts(*value)))
+}
+
+/// Writes tiles in the MPSZ notation, red fives being written as a 0 like in `"406m"`
+pub fn tiles_to_mpsz(tiles : &[Tile], red_fives : RedFives) -> String {
+    write_mpsz(tiles.iter().map(|tile| {
+        let (digit, letter) = mpsz_parts(tile.value());
+        (if tile.is_red(red_fives) {0} else {digit}, letter)
+    }))
+}
+
+fn write_mpsz(parts : impl Iterator<Item = (u8, char)>) -> String {
+    let mut text = String::new();
+    let mut current = None;
+    for (digit, letter) in parts {
+        if let Some(previous) = current {
+            if previous != letter {
+                text.push(previous);
+            }
+        }
+        text.push_str(&digit.to_string());
+        current = Some(letter);
+    }
+    if let Some(letter) = current {
+        text.push(letter);
+    }
+    text
+}
+
+/// Reads tiles written in the MPSZ notation, returning their values and whether they are red fives
+pub fn parse_mpsz(text : &str) -> Result<Vec<(TileValue, bool)>, NotationError> {
+    let mut values = Vec::new();
+    let mut digits = Vec::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if let Some(digit) = c.to_digit(10) {
+            digits.push(digit as u8);
+            continue;
+        }
+        if digits.is_empty() {
+            return Err(NotationError::InvalidTile(text.to_string()));
+        }
+        for digit in digits.drain(..) {
+            let invalid = || NotationError::InvalidTile(format!("{}{}", digit, c));
+            let value = match c {
+                'm' | 'p' | 's' => {
+                    let suit = match c {
+                        'm' => Suit::Man,
+                        'p' => Suit::Pin,
+                        _ => Suit::Sou,
+                    };
+                    if digit == 0 {
+                        values.push((TileValue::try_new_suited(suit, 5)?, true));
+                        continue;
+                    }
+                    TileValue::try_new_suited(suit, digit)?
+                },
+                'z' if (1..=7).contains(&digit) => HONORS[digit as usize - 1],
+                _ => return Err(invalid()),
+            };
+            values.push((value, false));
+        }
+    }
+    if !digits.is_empty() {
+        return Err(NotationError::InvalidTile(text.to_string()));
+    }
+    Ok(values)
+}
+
+/// Reads tiles written in the MPSZ notation and gives them ids, see `TileAllocator`
+pub fn parse_tiles(text : &str, red_fives : RedFives) -> Result<Vec<Tile>, NotationError> {
+    let mut allocator = TileAllocator::new(red_fives);
+    parse_mpsz(text)?
+        .into_iter()
+        .map(|(value, red)| allocator.allocate(value, red))
+        .collect()
+}
+
+/// Name of a tile in the mjai protocol
+pub fn mjai_name(value : TileValue, red : bool) -> String {
+    match value {
+        TileValue::Suited(suited) => {
+            format!("{}{}{}", suited.value(), suit_letter(suited.suit()), if red {"r"} else {""})
+        },
+        TileValue::Honor(_) => {
+            MJAI_HONORS[HONORS.iter().position(|honor| *honor == value).unwrap_or_default()].to_string()
+        },
+    }
+}
+
+/// Reads the name of a tile in the mjai protocol, returning its value and whether it is a red five
+pub fn parse_mjai_name(name : &str) -> Result<(TileValue, bool), NotationError> {
+    let invalid = || NotationError::InvalidTile(name.to_string());
+    if let Some(index) = MJAI_HONORS.iter().position(|honor| *honor == name) {
+        return Ok((HONORS[index], false));
+    }
+    let (value, red) = match name.strip_suffix('r') {
+        Some(five) => (five, true),
+        None => (name, false),
+    };
+    match parse_mpsz(value) {
+        Ok(values) if values.len() == 1 && !values[0].1 && (!red || value.starts_with('5')) && !value.ends_with('z') => {
+            Ok((values[0].0, red))
+        },
+        _ => Err(invalid()),
+    }
+}
+
+/// Gives an id to tiles only known by their value, making sure no id is used twice.
+/// The red fives get the lowest ids, as expected by `Tile::is_red`.
+pub struct TileAllocator {
+    red_fives : RedFives,
+    used : BTreeMap<TileValue, [bool; 4]>,
+}
+
+impl TileAllocator {
+
+    pub fn new(red_fives : RedFives) -> TileAllocator {
+        TileAllocator{red_fives, used : BTreeMap::new()}
+    }
+
+    pub fn allocate(&mut self, value : TileValue, red : bool) -> Result<Tile, NotationError> {
+        let red_count = (0..4).filter(|id| Tile::new(value, *id).is_red(self.red_fives)).count();
+        let used = self.used.entry(value).or_insert([false; 4]);
+        // Without red fives in the rules, a red five is an ordinary five
+        let candidates : Vec<usize> = if red && red_count > 0 {
+            (0..red_count).collect()
+        } else {
+            (red_count..4).chain(0..red_count).collect()
+        };
+        let id = candidates.into_iter()
+            .find(|id| !used[*id])
+            .ok_or(NotationError::TooManyCopies(value))?;
+        used[id] = true;
+        Ok(Tile::try_new(value, id as u8)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_mpsz, tiles_to_mpsz, parse_mpsz, parse_tiles, mjai_name, parse_mjai_name, NotationError};
+    use crate::tile::TileError;
+    use crate::tile::tile_value::TileValue;
+    use crate::tile::suit::Suit::{Man, Pin};
+    use crate::tile::dragon::Dragon::Red;
+    use crate::game::wind::Wind::East;
+    use crate::game::rules::RedFives;
+
+    #[test]
+    fn test_tile_errors() {
+        assert_eq!(NotationError::Tile(TileError::InvalidId(4)), NotationError::from(TileError::InvalidId(4)));
+        assert_eq!(Err(NotationError::InvalidTile("8z".to_string())), parse_mpsz("8z"));
+        assert_eq!(Err(NotationError::TooManyCopies(TileValue::new_wind(East))), parse_tiles("11111z", RedFives::none()));
+    }
+
+    #[test]
+    fn test_mpsz() {
+        let values : Vec<TileValue> = parse_mpsz("123m406p17z").unwrap().into_iter().map(|(value, _)| value).collect();
+        assert_eq!(TileValue::new_suited(Pin, 5), values[4]);
+        assert_eq!(TileValue::new_wind(East), values[6]);
+        assert_eq!(TileValue::new_dragon(Red), values[7]);
+        assert_eq!("123m456p17z", to_mpsz(&values));
+
+        assert!(parse_mpsz("123").is_err());
+        assert!(parse_mpsz("8z").is_err());
+    }
+
+    #[test]
+    fn test_parse_tiles() {
+        let tiles = parse_tiles("5550m", RedFives::one_each()).unwrap();
+        assert!(tiles.iter().all(|tile| tile.value() == TileValue::new_suited(Man, 5)));
+        assert_eq!(1, tiles.iter().filter(|tile| tile.is_red(RedFives::one_each())).count());
+        assert!(parse_tiles("55555m", RedFives::none()).is_err());
+        assert_eq!("5550m", tiles_to_mpsz(&tiles, RedFives::one_each()));
+        assert_eq!("5555m", tiles_to_mpsz(&tiles, RedFives::none()));
+    }
+
+    #[test]
+    fn test_mjai_names() {
+        assert_eq!("5pr", mjai_name(TileValue::new_suited(Pin, 5), true));
+        assert_eq!("C", mjai_name(TileValue::new_dragon(Red), false));
+        assert_eq!(Ok((TileValue::new_suited(Man, 5), true)), parse_mjai_name("5mr"));
+        assert_eq!(Ok((TileValue::new_wind(East), false)), parse_mjai_name("E"));
+        assert!(parse_mjai_name("4mr").is_err());
+        assert!(parse_mjai_name("1z").is_err());
+    }
+
+}
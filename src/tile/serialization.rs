@@ -0,0 +1,122 @@
+//! Compact encodings of the tiles with serde.
+//! A `Tile` is written as its index between 0 and 135 (see `Tile::index`),
+//! a tile value as its MPSZ notation, like `"5m"` or `"7z"`, and tile counts as `"123m55p"`.
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+
+use crate::tile::Tile;
+use crate::tile::tile_value::TileValue;
+use crate::tile::suit::SuitedTile;
+use crate::tile::honor::HonorTile;
+use crate::tile::tile_counts::TileCounts;
+use crate::tile::notation::{to_mpsz, parse_mpsz};
+
+impl Serialize for Tile {
+    fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.index())
+    }
+}
+
+impl<'de> Deserialize<'de> for Tile {
+    fn deserialize<D : Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        let index = u8::deserialize(deserializer)?;
+        Tile::from_index(index).ok_or_else(|| D::Error::custom(format!("{} is not a tile index", index)))
+    }
+}
+
+impl Serialize for TileValue {
+    fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_mpsz(&[*self]))
+    }
+}
+
+impl<'de> Deserialize<'de> for TileValue {
+    fn deserialize<D : Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        match parse_mpsz(&text).map_err(D::Error::custom)?[..] {
+            [(value, false)] => Ok(value),
+            _ => Err(D::Error::custom(format!("{} is not a single tile", text))),
+        }
+    }
+}
+
+impl Serialize for SuitedTile {
+    fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        TileValue::from_suited(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SuitedTile {
+    fn deserialize<D : Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        match TileValue::deserialize(deserializer)? {
+            TileValue::Suited(suited) => Ok(suited),
+            value => Err(D::Error::custom(format!("{:?} is not a suited tile", value))),
+        }
+    }
+}
+
+impl Serialize for HonorTile {
+    fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        TileValue::from_honor(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for HonorTile {
+    fn deserialize<D : Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        match TileValue::deserialize(deserializer)? {
+            TileValue::Honor(honor) => Ok(honor),
+            value => Err(D::Error::custom(format!("{:?} is not an honor", value))),
+        }
+    }
+}
+
+impl Serialize for TileCounts {
+    fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_mpsz(&self.values()))
+    }
+}
+
+impl<'de> Deserialize<'de> for TileCounts {
+    fn deserialize<D : Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Ok(parse_mpsz(&text).map_err(D::Error::custom)?.into_iter().map(|(value, _)| value).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tile::Tile;
+    use crate::tile::tile_value::TileValue;
+    use crate::tile::suit::Suit::Pin;
+    use crate::tile::dragon::Dragon::Red;
+    use crate::hand::group::Group;
+    use crate::game::rules::RuleSet;
+    use crate::game::event::Event;
+
+    #[test]
+    fn test_tiles() {
+        let five_pin = Tile::new_suited(Pin, 5, 2);
+        assert_eq!("54", serde_json::to_string(&five_pin).unwrap());
+        assert_eq!(five_pin, serde_json::from_str("54").unwrap());
+        assert!(serde_json::from_str::<Tile>("136").is_err());
+
+        assert_eq!("\"7z\"", serde_json::to_string(&TileValue::new_dragon(Red)).unwrap());
+        assert_eq!(TileValue::new_suited(Pin, 5), serde_json::from_str("\"5p\"").unwrap());
+        assert!(serde_json::from_str::<TileValue>("\"55p\"").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let tiles = [Tile::new_suited(Pin, 1, 0), Tile::new_suited(Pin, 2, 3), Tile::new_suited(Pin, 3, 1)];
+        let chii = Group::Chii(tiles[0], tiles[1], tiles[2]);
+        assert_eq!("{\"Chii\":[36,43,45]}", serde_json::to_string(&chii).unwrap());
+
+        let event = Event::Chii{player : 1, from : 0, called : tiles[0], tiles : [tiles[1], tiles[2]]};
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(event, serde_json::from_str(&json).unwrap());
+
+        let rules = RuleSet::mahjong_soul_sanma();
+        assert_eq!(rules, serde_json::from_str(&serde_json::to_string(&rules).unwrap()).unwrap());
+    }
+}
@@ -1,6 +1,10 @@
+use std::convert::TryFrom;
 use std::fmt::{Display,Debug};
 
+use crate::tile::TileError;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Suit {
     Man,
     Pin,
@@ -15,12 +19,17 @@ pub struct SuitedTile {
 
 impl SuitedTile {
 
+    /// # Panics
+    /// If `value` is not between 1 and 9, see `try_new`
     pub fn new(suit : Suit, value : u8) -> SuitedTile {
-        if value == 0 || value > 9 {
-            println!("Error : creating a SuitedTile with invalid value");
-            SuitedTile{suit : Suit::Man, value : 0}
+        SuitedTile::try_new(suit, value).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(suit : Suit, value : u8) -> Result<SuitedTile, TileError> {
+        if (1..=9).contains(&value) {
+            Ok(SuitedTile{suit, value})
         } else {
-            SuitedTile{suit, value}
+            Err(TileError::InvalidNumber(value))
         }
     }
 
@@ -33,7 +42,7 @@ impl SuitedTile {
     }
 
     pub fn is_valid(&self) -> bool {
-        self.value > 0 && self.value < 9
+        (1..=9).contains(&self.value)
     }
 
     pub fn is_terminal(&self) -> bool {
@@ -96,17 +105,29 @@ impl Display for SuitedTile {
     }
 }
 
+impl TryFrom<(Suit, u8)> for SuitedTile {
+    type Error = TileError;
+
+    fn try_from((suit, value) : (Suit, u8)) -> Result<Self, TileError> {
+        SuitedTile::try_new(suit, value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::convert::TryFrom;
+
     use crate::tile::suit::Suit::{Man, Pin, Sou};
     use crate::tile::suit::SuitedTile;
+    use crate::tile::{Tile, TileError};
+    use crate::tile::tile_value::TileValue;
+    use crate::tile::dragon::Dragon::Red;
 
     #[test]
     fn test_debug() {
         let seven_sou = SuitedTile::new(Sou, 7);
         assert_eq!("7s", &format!("{:?}", seven_sou));
-        let invalid = SuitedTile::new(Man, 10);
-        assert_eq!("Invalid", &format!("{:?}", invalid));
+        assert_eq!("9m", &format!("{:?}", SuitedTile::new(Man, 9)));
         let three_pin = SuitedTile::new(Pin, 3);
         assert_eq!("3p", &format!("{:?}", three_pin));
     }
@@ -119,10 +140,21 @@ mod tests {
 
     #[test]
     fn test_invalid_tile() {
-        let not_valid = SuitedTile::new(Man, 12);
-        assert!(!not_valid.is_valid());
-        let not_valid_neither = SuitedTile::new(Sou, 0);
-        assert!(!not_valid_neither.is_valid());
+        assert_eq!(Err(TileError::InvalidNumber(12)), SuitedTile::try_new(Man, 12));
+        assert_eq!(Err(TileError::InvalidNumber(0)), SuitedTile::try_from((Sou, 0)));
+        assert!(SuitedTile::try_new(Sou, 9).unwrap().is_valid());
+
+        assert_eq!(Err(TileError::InvalidNumber(10)), TileValue::try_new_suited(Pin, 10));
+        assert_eq!(Ok(TileValue::new_suited(Pin, 1)), TileValue::try_from((Pin, 1)));
+        assert_eq!(Err(TileError::InvalidId(4)), Tile::try_new_suited(Pin, 1, 4));
+        assert_eq!(Err(TileError::InvalidNumber(0)), Tile::try_new_suited(Pin, 0, 4));
+        assert_eq!(Ok(Tile::new_dragon(Red, 3)), Tile::try_from((TileValue::new_dragon(Red), 3)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_invalid_tile() {
+        SuitedTile::new(Man, 10);
     }
 
     #[test]
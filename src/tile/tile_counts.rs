@@ -0,0 +1,135 @@
+use std::fmt::Debug;
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut};
+
+use crate::tile::Tile;
+use crate::tile::tile_value::{TileValue, TILE_KINDS};
+use crate::tile::notation::to_mpsz;
+
+/// Number of copies of each tile value, indexed by `TileValue::index`.
+/// Cheap to copy and compare, it is meant for the algorithms that do not care about the ids of the tiles.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileCounts([u8; TILE_KINDS]);
+
+impl Debug for TileCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", to_mpsz(&self.values()))
+    }
+}
+
+impl Default for TileCounts {
+    fn default() -> Self {
+        TileCounts::new()
+    }
+}
+
+impl TileCounts {
+
+    // Constructors
+
+    pub fn new() -> Self {
+        TileCounts([0; TILE_KINDS])
+    }
+
+    pub fn from_tiles(tiles : &[Tile]) -> Self {
+        tiles.iter().map(|tile| tile.value()).collect()
+    }
+
+    // Immutable getters
+
+    /// Number of copies of each tile value, indexed by `TileValue::index`
+    pub fn counts(&self) -> &[u8; TILE_KINDS] {
+        &self.0
+    }
+
+    /// Total number of tiles
+    pub fn total(&self) -> usize {
+        self.0.iter().map(|count| *count as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|count| *count == 0)
+    }
+
+    /// The values of the tiles, sorted by index and repeated as many times as they are counted
+    pub fn values(&self) -> Vec<TileValue> {
+        self.distinct()
+            .flat_map(|value| std::iter::repeat_n(value, self[value] as usize))
+            .collect()
+    }
+
+    /// The values counted at least once, sorted by index
+    pub fn distinct(&self) -> impl Iterator<Item = TileValue> + '_ {
+        self.0.iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .filter_map(|(index, _)| TileValue::from_index(index as u8))
+    }
+
+    // Mutators
+
+    pub fn add(&mut self, value : TileValue) {
+        self[value] += 1;
+    }
+
+    /// Removes one copy of a value, returns `false` if there is none
+    pub fn remove(&mut self, value : TileValue) -> bool {
+        let count = &mut self[value];
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+}
+
+impl Index<TileValue> for TileCounts {
+    type Output = u8;
+
+    fn index(&self, value : TileValue) -> &u8 {
+        &self.0[value.index() as usize]
+    }
+}
+
+impl IndexMut<TileValue> for TileCounts {
+    fn index_mut(&mut self, value : TileValue) -> &mut u8 {
+        &mut self.0[value.index() as usize]
+    }
+}
+
+impl FromIterator<TileValue> for TileCounts {
+    fn from_iter<I : IntoIterator<Item = TileValue>>(values : I) -> Self {
+        let mut counts = TileCounts::new();
+        for value in values {
+            counts.add(value);
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TileCounts;
+    use crate::tile::tile_value::TileValue;
+    use crate::tile::suit::Suit::Pin;
+    use crate::tile::dragon::Dragon::Red;
+    use crate::tile::notation::parse_tiles;
+    use crate::game::rules::RedFives;
+
+    #[test]
+    fn test_counts() {
+        let tiles = parse_tiles("1m555p77z", RedFives::none()).unwrap();
+        let mut counts = TileCounts::from_tiles(&tiles);
+        assert_eq!(6, counts.total());
+        assert_eq!(3, counts[TileValue::new_suited(Pin, 5)]);
+        assert_eq!(2, counts.counts()[33]);
+        assert_eq!("1m555p77z", format!("{:?}", counts));
+
+        assert!(counts.remove(TileValue::new_dragon(Red)));
+        assert!(counts.remove(TileValue::new_dragon(Red)));
+        assert!(!counts.remove(TileValue::new_dragon(Red)));
+        assert_eq!(2, counts.distinct().count());
+        assert!(!counts.is_empty());
+        assert!(TileCounts::new().is_empty());
+    }
+}
@@ -1,12 +1,21 @@
-use std::fmt::Debug;
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display};
 
 use super::suit::SuitedTile;
 use super::suit::Suit;
 use super::honor::HonorTile;
 use super::dragon::Dragon;
 use crate::game::wind::Wind;
+use crate::tile::TileError;
 use TileValue::{Suited, Honor};
 
+/// Number of different tile values
+pub const TILE_KINDS : usize = 34;
+
+const SUITS : [Suit; 3] = [Suit::Man, Suit::Pin, Suit::Sou];
+const WINDS : [Wind; 4] = [Wind::East, Wind::South, Wind::West, Wind::North];
+const DRAGONS : [Dragon; 3] = [Dragon::White, Dragon::Green, Dragon::Red];
+
 /// In Riichi Mahjong, a tile can be on one of two types : a suited tile or an honor.
 /// A suited tile is from one of three groups : `Man`, `Pin` or `Sou` and holds a number.
 /// A honor tile is either one of the three dragons or one of the four winds.
@@ -25,6 +34,17 @@ impl Debug for TileValue {
     }
 }
 
+/// Writes the value in the MPSZ notation, or as a Unicode character with the alternate flag (`{:#}`)
+impl Display for TileValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", crate::tile::display::unicode_char(*self))
+        } else {
+            write!(f, "{}", crate::tile::notation::to_mpsz(&[*self]))
+        }
+    }
+}
+
 impl TileValue {
 
     // Constructors
@@ -37,10 +57,16 @@ impl TileValue {
         Honor(tile)
     }
 
+    /// # Panics
+    /// If `value` is not between 1 and 9, see `try_new_suited`
     pub fn new_suited(suit : Suit, value : u8) -> Self {
         Suited(SuitedTile::new(suit, value))
     }
 
+    pub fn try_new_suited(suit : Suit, value : u8) -> Result<Self, TileError> {
+        SuitedTile::try_new(suit, value).map(Suited)
+    }
+
     pub fn new_dragon(dragon : Dragon) -> Self {
         Honor(HonorTile::Dragon(dragon))
     }
@@ -49,6 +75,70 @@ impl TileValue {
         Honor(HonorTile::Wind(wind))
     }
 
+    /// The 34 tile values, in the order of their index
+    pub fn all() -> impl Iterator<Item = TileValue> {
+        (0..TILE_KINDS as u8).filter_map(TileValue::from_index)
+    }
+
+    /// The value at an index between 0 and 33, in MPSZ order (see `TileValue::index`), `None` past 33
+    pub fn from_index(index : u8) -> Option<TileValue> {
+        let index = index as usize;
+        match index {
+            0..=26 => Some(TileValue::new_suited(SUITS[index / 9], (index % 9) as u8 + 1)),
+            27..=30 => Some(TileValue::new_wind(WINDS[index - 27])),
+            31..=33 => Some(TileValue::new_dragon(DRAGONS[index - 31])),
+            _ => None,
+        }
+    }
+
+    // Immutable getters
+
+    /// Index of the value between 0 and 33 : 1m to 9m, 1p to 9p, 1s to 9s, the winds from east to north
+    /// then the white, green and red dragons, in the order of the MPSZ notation
+    pub fn index(&self) -> u8 {
+        let index = match self {
+            Suited(suited) => SUITS.iter().position(|suit| *suit == suited.suit()).unwrap_or_default() * 9 + suited.value() as usize - 1,
+            Honor(HonorTile::Wind(wind)) => 27 + WINDS.iter().position(|other| other == wind).unwrap_or_default(),
+            Honor(HonorTile::Dragon(dragon)) => 31 + DRAGONS.iter().position(|other| other == dragon).unwrap_or_default(),
+        };
+        index as u8
+    }
+
+    /// Whether this is a 1 or a 9
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Suited(suited) if suited.is_terminal())
+    }
+
+    pub fn is_honor(&self) -> bool {
+        matches!(self, Honor(_))
+    }
+
+    /// Whether this is a terminal or an honor
+    pub fn is_yaochuu(&self) -> bool {
+        self.is_terminal() || self.is_honor()
+    }
+
+    /// Whether this is a suited tile from 2 to 8
+    pub fn is_simple(&self) -> bool {
+        matches!(self, Suited(suited) if suited.is_simple())
+    }
+
+    /// Whether this tile can be part of an all green hand (ryuuiisou) : 2, 3, 4, 6 and 8 sou and the green dragon
+    pub fn is_green(&self) -> bool {
+        match self {
+            Suited(suited) => suited.suit() == Suit::Sou && [2, 3, 4, 6, 8].contains(&suited.value()),
+            Honor(honor) => *honor == HonorTile::Dragon(Dragon::Green),
+        }
+    }
+
+    pub fn is_dragon(&self) -> bool {
+        matches!(self, Honor(HonorTile::Dragon(_)))
+    }
+
+    pub fn is_wind(&self) -> bool {
+        matches!(self, Honor(HonorTile::Wind(_)))
+    }
+
     // Other
 
     /// Gets the dora tile if this tile is the dora indicator
@@ -64,6 +154,17 @@ impl TileValue {
         }
     }
 
+    /// Gets the dora tile if this tile is the dora indicator in a three player game.
+    /// As the manzu tiles from 2 to 8 are removed, 1m indicates 9m and 9m indicates 1m.
+    pub fn next_dora_sanma(&self) -> TileValue {
+        match self {
+            Suited(suited) if suited.suit() == Suit::Man => {
+                TileValue::new_suited(Suit::Man, if suited.value() == 1 {9} else {1})
+            },
+            _ => self.next_dora(),
+        }
+    }
+
     /// Gets the suit with the next value if it exists
     pub fn next(&self) -> Option<TileValue> {
         match self {
@@ -90,11 +191,20 @@ impl TileValue {
     }
 }
 
+impl TryFrom<(Suit, u8)> for TileValue {
+    type Error = TileError;
+
+    fn try_from((suit, value) : (Suit, u8)) -> Result<Self, TileError> {
+        TileValue::try_new_suited(suit, value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::TileValue;
-    use crate::tile::suit::Suit::{Man, Sou};
+    use crate::tile::Tile;
+    use crate::tile::suit::Suit::{Man, Pin, Sou};
     use crate::tile::dragon::Dragon::{White, Red};
     use crate::game::wind::Wind::{East, South, West, North};
 
@@ -107,4 +217,53 @@ mod tests {
         assert_eq!(TileValue::new_suited(Sou, 1), TileValue::new_suited(Sou, 9).next_dora());
         assert_eq!(TileValue::new_wind(East), TileValue::new_wind(North).next_dora())
     }
+
+    #[test]
+    fn test_index() {
+        assert_eq!(0, TileValue::new_suited(Man, 1).index());
+        assert_eq!(26, TileValue::new_suited(Sou, 9).index());
+        assert_eq!(30, TileValue::new_wind(North).index());
+        assert_eq!(33, TileValue::new_dragon(Red).index());
+        for index in 0..34 {
+            assert_eq!(index, TileValue::from_index(index).unwrap().index());
+        }
+        assert_eq!(None, TileValue::from_index(34));
+
+        let tile = Tile::new_suited(Pin, 5, 2);
+        assert_eq!(54, tile.index());
+        assert_eq!(Some(tile), Tile::from_index(54));
+        assert_eq!(None, Tile::from_index(136));
+    }
+
+    #[test]
+    fn test_categories() {
+        let values : Vec<TileValue> = TileValue::all().collect();
+        assert_eq!(34, values.len());
+        assert_eq!(13, values.iter().filter(|value| value.is_yaochuu()).count());
+        assert_eq!(21, values.iter().filter(|value| value.is_simple()).count());
+        assert_eq!(6, values.iter().filter(|value| value.is_green()).count());
+        assert_eq!(3, values.iter().filter(|value| value.is_dragon()).count());
+        assert_eq!(4, values.iter().filter(|value| value.is_wind()).count());
+
+        assert!(TileValue::new_suited(Pin, 9).is_terminal());
+        assert!(!TileValue::new_suited(Pin, 9).is_honor());
+        assert!(TileValue::new_dragon(White).is_honor());
+        assert!(!TileValue::new_dragon(White).is_terminal());
+        assert!(!TileValue::new_dragon(White).is_green());
+        assert!(TileValue::new_suited(Sou, 6).is_green());
+        assert!(!TileValue::new_suited(Sou, 5).is_green());
+
+        let tiles : Vec<Tile> = Tile::full_set().collect();
+        assert_eq!(136, tiles.len());
+        assert!(tiles.windows(2).all(|pair| pair[0] != pair[1]));
+        assert_eq!(4, tiles.iter().filter(|tile| tile.is_green() && tile.is_dragon()).count());
+    }
+
+    #[test]
+    fn test_next_dora_sanma() {
+        assert_eq!(TileValue::new_suited(Man, 9), TileValue::new_suited(Man, 1).next_dora_sanma());
+        assert_eq!(TileValue::new_suited(Man, 1), TileValue::new_suited(Man, 9).next_dora_sanma());
+        assert_eq!(TileValue::new_suited(Sou, 1), TileValue::new_suited(Sou, 9).next_dora_sanma());
+        assert_eq!(TileValue::new_wind(East), TileValue::new_wind(North).next_dora_sanma());
+    }
 }
\ No newline at end of file